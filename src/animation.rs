@@ -0,0 +1,70 @@
+// Animated blocks, whose color changes over time: magma glowing brighter and dimmer, water
+// shimmering, beacons blinking, and blocks from packs stepping through colors like a flipbook.
+// Nothing about the voxels changes; the color is worked out from the time each frame is drawn.
+
+use glam::*;
+#[cfg(feature = "graphics")]
+use crate::World;
+#[cfg(feature = "graphics")]
+use crate::block;
+#[cfg(feature = "graphics")]
+use crate::rng::Rng;
+
+#[cfg(feature = "graphics")]
+const TAU: f32 = std::f32::consts::TAU;
+// how far a pulse brightens and dims, how much a shimmer glints, and how dark a blinking
+// block is while it's off
+#[cfg(feature = "graphics")]
+const PULSE: f32 = 0.25;
+#[cfg(feature = "graphics")]
+const SHIMMER: f32 = 0.12;
+#[cfg(feature = "graphics")]
+const BLINK_OFF: f32 = 0.3;
+
+// how a block's color changes, each over a period of that many seconds
+#[derive(Clone, Copy, PartialEq)]
+pub enum Animation {
+	Still,
+	// brighter and dimmer all together
+	Pulse(f32),
+	// glints passing over it, not quite together
+	Shimmer(f32),
+	// on for half of each period and dark for the other half
+	Blink(f32),
+	// each of the colors in turn, for a period each, in place of its own
+	Cycle(&'static [Vec4], f32),
+}
+
+// seconds a color is shown for when blocks from packs cycle without saying
+pub const FRAME: f32 = 0.5;
+
+impl Animation {
+	// `color` as it is at `time` seconds, for a voxel `phase` of the way through the animation,
+	// from 0 to 1, so neighbors don't all shimmer at once
+	#[cfg(feature = "graphics")]
+	pub fn color(self, color: Vec4, phase: f32, time: f32) -> Vec4 {
+		let bright = match self {
+			Animation::Still => return color,
+			Animation::Pulse(period) => 1.0 + PULSE * (TAU * time / period).sin(),
+			Animation::Shimmer(period) => 1.0 + SHIMMER * (TAU * (time / period + phase)).sin(),
+			Animation::Blink(period) => if (time / period).fract() < 0.5 { 1.0 } else { BLINK_OFF },
+			Animation::Cycle(frames, period) => {
+				let Some(&frame) = frames.get((time / period) as usize % frames.len().max(1)) else { return color };
+				return frame;
+			},
+		};
+		(color * bright).min(Vec4::ONE).with_w(color.w)
+	}
+}
+
+// how far through its animation voxel i is at `hit` on its surface, so it changes across a face
+#[cfg(feature = "graphics")]
+pub fn phase(i: usize, hit: Vec3) -> f32 {
+	Rng::new(i as u64).f32() + hit.element_sum() / 3.0
+}
+
+// `color`, the color of voxel i seen at `hit` on its surface, as it is at `time` seconds
+#[cfg(feature = "graphics")]
+pub fn animate(world: &World, i: usize, hit: Vec3, color: Vec4, time: f32) -> Vec4 {
+	block::def(world.voxel(i).block).animation.color(color, phase(i, hit), time)
+}
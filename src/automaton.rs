@@ -0,0 +1,162 @@
+// 3D cellular automata. Voxels of the cell block are alive; each generation a live cell
+// survives if the number of live cells among its 26 neighbors is one of the rule's survival
+// counts, and air with a birth count of live neighbors comes alive. Neighbors are found by
+// walking the graph, so patterns wrap around the torus and through any other links the same way.
+
+use std::collections::{HashMap, HashSet};
+use rayon::prelude::*;
+use crate::{World, Voxel, Progress, offset, link_world, furl};
+use crate::block;
+use crate::rng::Rng;
+
+// ticks between generations while running
+const GENERATION_INTERVAL: u32 = 6;
+
+// some well known rules, by name
+const PRESETS: [(&str, &str); 5] = [
+	("amoeba", "B5-7,12-13,15/S9-26"),
+	("clouds", "B13-14,17-19/S13-26"),
+	("builder", "B4,6,8-9/S2,6,9"),
+	("pyroclastic", "B6-8/S4-7"),
+	("445", "B4/S4"),
+];
+
+#[derive(Clone)]
+pub struct Rule {
+	// shown by the game
+	#[cfg_attr(not(feature = "graphics"), allow(dead_code))]
+	name: String,
+	// indexed by the number of live neighbors
+	birth: [bool; 27],
+	survival: [bool; 27],
+}
+
+impl Rule {
+	// A rule in B/S notation, like "B6-8/S4-7": neighbor counts, or ranges of them, at which
+	// cells are born and survive. Or the name of one of the presets.
+	pub fn parse(text: &str) -> Result<Rule, String> {
+		let notation = PRESETS.iter().find(|(name, _)| *name == text).map_or(text, |(_, rule)| rule);
+		let mut rule = Rule { name: text.to_string(), birth: [false; 27], survival: [false; 27] };
+		for part in notation.split('/') {
+			let (counts, list) = match part.chars().next().map(|c| c.to_ascii_uppercase()) {
+				Some('B') => (&mut rule.birth, &part[1..]),
+				Some('S') => (&mut rule.survival, &part[1..]),
+				_ => return Err(format!("expected B... or S... in '{}'", part)),
+			};
+			for range in list.split(',').filter(|r| !r.is_empty()) {
+				let (lo, hi) = range.split_once('-').unwrap_or((range, range));
+				let count = |s: &str| s.parse::<usize>().ok().filter(|&n| n <= 26).ok_or(format!("bad neighbor count '{}'", s));
+				let (lo, hi) = (count(lo)?, count(hi)?);
+				for c in counts.iter_mut().take(hi + 1).skip(lo) {
+					*c = true;
+				}
+			}
+		}
+		if rule.birth[0] {
+			return Err("cells born with no neighbors would fill the world".to_string());
+		}
+		Ok(rule)
+	}
+}
+
+pub struct Automaton {
+	rule: Rule,
+	pub running: bool,
+	pub generation: u64,
+	cells: HashSet<usize>,
+	ticks: u32,
+}
+
+// the 26 voxels around voxel i
+fn around(world: &World, i: usize) -> Vec<usize> {
+	let mut found = Vec::with_capacity(26);
+	for dx in -1..=1 {
+		for dy in -1..=1 {
+			for dz in -1..=1 {
+				if [dx, dy, dz] != [0, 0, 0] {
+					found.push(offset(world, i, [dx, dy, dz]));
+				}
+			}
+		}
+	}
+	found
+}
+
+impl Automaton {
+	// start out with the cells already in the world, paused
+	pub fn new(rule: Rule, world: &World) -> Automaton {
+		let [nx, ny, nz] = world.size();
+		let cells = (0..nx*ny*nz).into_par_iter().filter(|&i| world.voxel(i).block == block::CELL).collect();
+		Automaton { rule, running: false, generation: 0, cells, ticks: 0 }
+	}
+
+	#[cfg(feature = "graphics")]
+	pub fn rule(&self) -> &str {
+		&self.rule.name
+	}
+
+	// keep track of cells placed or broken
+	pub fn notice(&mut self, world: &World, changed: &[usize]) {
+		for &i in changed {
+			if world.voxel(i).block == block::CELL {
+				self.cells.insert(i);
+			} else {
+				self.cells.remove(&i);
+			}
+		}
+	}
+
+	pub fn tick(&mut self, world: &mut World) {
+		if !self.running {
+			return;
+		}
+		self.ticks += 1;
+		if self.ticks >= GENERATION_INTERVAL {
+			self.ticks = 0;
+			self.step(world);
+		}
+	}
+
+	// Advance one generation. Only live cells and the voxels around them can change, so those
+	// are all that's counted.
+	pub fn step(&mut self, world: &mut World) {
+		let neighborhoods: Vec<Vec<usize>> = self.cells.par_iter().map(|&i| around(world, i)).collect();
+		let mut counts: HashMap<usize, usize> = HashMap::new();
+		for j in neighborhoods.into_iter().flatten() {
+			*counts.entry(j).or_default() += 1;
+		}
+		let deaths: Vec<usize> = self.cells.iter().copied().filter(|i| !self.rule.survival[counts.get(i).copied().unwrap_or(0)]).collect();
+		let births: Vec<usize> = counts.into_iter()
+			.filter(|&(j, n)| self.rule.birth[n] && !self.cells.contains(&j) && world.voxel(j).block == block::AIR)
+			.map(|(j, _)| j)
+			.collect();
+		for i in deaths {
+			world.set(i, Voxel::of(block::AIR));
+			self.cells.remove(&i);
+		}
+		for j in births {
+			world.set(j, Voxel::of(block::CELL));
+			self.cells.insert(j);
+		}
+		self.generation += 1;
+	}
+}
+
+// A world of air with a cube of random cells in the middle, `size` voxels across, each alive
+// with chance `density`.
+pub fn build_soup_world(nx: usize, ny: usize, nz: usize, size: usize, density: f32, rng: &mut Rng, progress: &Progress) -> World {
+	let mut world = link_world(nx, ny, nz, progress);
+	let mut voxels = vec![Voxel::of(block::AIR); nx*ny*nz];
+	let start = |n: usize| n.saturating_sub(size) / 2;
+	for x in start(nx)..(start(nx) + size).min(nx) {
+		for y in start(ny)..(start(ny) + size).min(ny) {
+			for z in start(nz)..(start(nz) + size).min(nz) {
+				if rng.f32() < density {
+					voxels[furl(x, y, z, ny, nz)] = Voxel::of(block::CELL);
+				}
+			}
+		}
+	}
+	world.set_voxels(&voxels);
+	world
+}
@@ -0,0 +1,6 @@
+// The dedicated server on its own, built without graphics by
+// `cargo build --release --no-default-features --features server`.
+
+fn main() {
+	voxeltorus::serve();
+}
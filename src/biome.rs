@@ -0,0 +1,117 @@
+// Biomes: a periodic temperature map over the (x, z) torus picks each column's palette and
+// how rough its terrain is
+
+use glam::*;
+use crate::block::{self, BlockId};
+use crate::noise::{Noise, NoiseParams};
+use crate::rng::Rng;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Biome {
+	Snow,
+	Grassland,
+	Desert,
+}
+
+pub struct Palette {
+	// top voxel of each solid run
+	pub surface: BlockId,
+	// the few voxels beneath the surface
+	pub subsurface: BlockId,
+	pub stone: BlockId,
+}
+
+pub const SUBSURFACE_DEPTH: usize = 3;
+
+impl Biome {
+	pub fn palette(self) -> Palette {
+		match self {
+			Biome::Snow => Palette {
+				surface: block::SNOW,
+				subsurface: block::DIRT,
+				stone: block::STONE,
+			},
+			Biome::Grassland => Palette {
+				surface: block::GRASS,
+				subsurface: block::DIRT,
+				stone: block::STONE,
+			},
+			Biome::Desert => Palette {
+				surface: block::SAND,
+				subsurface: block::SANDSTONE,
+				stone: block::STONE,
+			},
+		}
+	}
+}
+
+const BIOME_NOISE: NoiseParams = NoiseParams { amplitude: 1.0, frequency: 2, octaves: 2 };
+// temperature below COLD is snow, above HOT is desert
+const COLD: f32 = -0.15;
+const HOT: f32 = 0.15;
+// terrain noise multiplier for snow, grassland and desert, interpolated by temperature
+// so neighboring biomes join without cliffs
+const ROUGHNESS: [f32; 3] = [1.4, 0.8, 0.35];
+
+impl Biome {
+	pub fn from_temperature(t: f32) -> Biome {
+		if t < COLD {
+			Biome::Snow
+		} else if t > HOT {
+			Biome::Desert
+		} else {
+			Biome::Grassland
+		}
+	}
+}
+
+pub fn roughness(t: f32) -> f32 {
+	let blend = |a: f32, b: f32, t: f32| a + (b - a) * t.clamp(0.0, 1.0);
+	if t < 0.0 {
+		blend(ROUGHNESS[1], ROUGHNESS[0], t / (2.0 * COLD))
+	} else {
+		blend(ROUGHNESS[1], ROUGHNESS[2], t / (2.0 * HOT))
+	}
+}
+
+// temperature anywhere on the (x, z) torus, given as fractions of the world size
+pub struct Climate {
+	noise: Noise,
+}
+
+impl Climate {
+	pub fn new(rng: &mut Rng) -> Climate {
+		Climate { noise: Noise::new(rng) }
+	}
+
+	pub fn temperature(&self, u: f32, v: f32) -> f32 {
+		self.noise.fbm(vec3(u, 0.0, v), &BIOME_NOISE)
+	}
+}
+
+// temperature of every column of a world, worked out once up front
+pub struct BiomeMap {
+	nz: usize,
+	temperature: Vec<f32>,
+}
+
+impl BiomeMap {
+	pub fn generate(nx: usize, nz: usize, rng: &mut Rng) -> BiomeMap {
+		let climate = Climate::new(rng);
+		let mut temperature = vec![0.0; nx*nz];
+		for x in 0..nx {
+			for z in 0..nz {
+				temperature[x*nz + z] = climate.temperature(x as f32 / nx as f32, z as f32 / nz as f32);
+			}
+		}
+		BiomeMap { nz, temperature }
+	}
+
+	pub fn biome(&self, x: usize, z: usize) -> Biome {
+		Biome::from_temperature(self.temperature[x*self.nz + z])
+	}
+
+	pub fn roughness(&self, x: usize, z: usize) -> f32 {
+		roughness(self.temperature[x*self.nz + z])
+	}
+}
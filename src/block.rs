@@ -0,0 +1,260 @@
+// Block types. Every voxel records which block it is; its color starts from the block's base
+// color but may be changed per voxel.
+//
+// Besides the built-in blocks, packs in assets/blocks add more: TOML files with a [table] per
+// block, named after it, like
+//
+//     [glass]
+//     color = [0.8, 0.9, 1.0]
+//     transparent = true
+//     hardness = 0.3
+//
+// Leaving out color makes it white and hardness makes it 1. The flags transparent, liquid,
+// falls, flammable and emits are false unless set. A block can be animated with `pulse`,
+// `shimmer` or `blink` set to the seconds each takes, or step through colors listed like
+// `frames = [[1, 0.3, 0], [1, 0.6, 0]]`, each shown for the seconds `cycle` gives. Packs are
+// only ever TOML, and blocks only ever drawn in colors, so a pack giving any a texture is refused.
+
+use glam::*;
+use std::sync::OnceLock;
+use crate::animation::{self, Animation};
+use crate::config::numbers;
+
+pub type BlockId = u16;
+
+#[derive(Clone)]
+pub struct BlockDef {
+	pub name: &'static str,
+	pub color: Vec4,
+	pub transparent: bool,
+	// can be swum through
+	pub liquid: bool,
+	// drops when there's nothing under it
+	pub falls: bool,
+	// catches fire from burning neighbors
+	pub flammable: bool,
+	// seconds of holding the button down it takes to break; 0 breaks at a click
+	pub hardness: f32,
+	// gives off its own light, so it shows through the dark of distance
+	pub emits: bool,
+	// how its color changes over time
+	pub animation: Animation,
+}
+
+pub const AIR: BlockId = 0;
+pub const STONE: BlockId = 1;
+pub const DIRT: BlockId = 2;
+pub const GRASS: BlockId = 3;
+pub const SAND: BlockId = 4;
+pub const SANDSTONE: BlockId = 5;
+pub const SNOW: BlockId = 6;
+pub const WOOD: BlockId = 7;
+pub const LEAVES: BlockId = 8;
+pub const PINE_NEEDLES: BlockId = 9;
+pub const CACTUS: BlockId = 10;
+pub const BOULDER: BlockId = 11;
+#[cfg(feature = "graphics")]
+pub const PLANKS: BlockId = 12;
+// imported images and models, whose colors come from the source
+pub const CUSTOM: BlockId = 13;
+pub const COAL: BlockId = 14;
+pub const IRON: BlockId = 15;
+pub const GOLD: BlockId = 16;
+pub const DIAMOND: BlockId = 17;
+pub const WATER: BlockId = 18;
+// lit by breaking it, blowing up shortly after
+pub const TNT: BlockId = 19;
+// spreads to flammable neighbors and burns out; its state counts up as it burns
+pub const FIRE: BlockId = 20;
+// a live cell of the cellular automaton
+pub const CELL: BlockId = 21;
+// signal blocks; see the circuit module
+pub const WIRE: BlockId = 22;
+pub const SWITCH: BlockId = 23;
+pub const LAMP: BlockId = 24;
+// opened and closed with `E`; open (state 1) it can be seen through and walked through
+pub const DOOR: BlockId = 25;
+// carries a line of text, written when it's placed or used with `E`
+#[cfg(feature = "graphics")]
+pub const SIGN: BlockId = 26;
+// glowing molten rock, in pockets deep down; it doesn't flow
+pub const MAGMA: BlockId = 27;
+// grows into a tree on the ground, given room
+pub const SAPLING: BlockId = 29;
+
+pub const BLOCKS: [BlockDef; 30] = [
+	BlockDef { name: "air", color: vec4(0.0, 0.0, 0.0, 1.0), transparent: true, liquid: false, falls: false, flammable: false, hardness: 0.0, emits: false, animation: Animation::Still },
+	BlockDef { name: "stone", color: vec4(0.5, 0.5, 0.5, 1.0), transparent: false, liquid: false, falls: false, flammable: false, hardness: 1.5, emits: false, animation: Animation::Still },
+	BlockDef { name: "dirt", color: vec4(0.45, 0.32, 0.2, 1.0), transparent: false, liquid: false, falls: false, flammable: false, hardness: 0.5, emits: false, animation: Animation::Still },
+	BlockDef { name: "grass", color: vec4(0.3, 0.58, 0.22, 1.0), transparent: false, liquid: false, falls: false, flammable: false, hardness: 0.6, emits: false, animation: Animation::Still },
+	BlockDef { name: "sand", color: vec4(0.86, 0.78, 0.52, 1.0), transparent: false, liquid: false, falls: true, flammable: false, hardness: 0.5, emits: false, animation: Animation::Still },
+	BlockDef { name: "sandstone", color: vec4(0.78, 0.66, 0.42, 1.0), transparent: false, liquid: false, falls: false, flammable: false, hardness: 0.8, emits: false, animation: Animation::Still },
+	BlockDef { name: "snow", color: vec4(0.93, 0.94, 0.97, 1.0), transparent: false, liquid: false, falls: false, flammable: false, hardness: 0.2, emits: false, animation: Animation::Still },
+	BlockDef { name: "wood", color: vec4(0.4, 0.27, 0.15, 1.0), transparent: false, liquid: false, falls: false, flammable: true, hardness: 1.0, emits: false, animation: Animation::Still },
+	BlockDef { name: "leaves", color: vec4(0.2, 0.48, 0.16, 1.0), transparent: false, liquid: false, falls: false, flammable: true, hardness: 0.2, emits: false, animation: Animation::Still },
+	BlockDef { name: "pine_needles", color: vec4(0.14, 0.32, 0.2, 1.0), transparent: false, liquid: false, falls: false, flammable: true, hardness: 0.2, emits: false, animation: Animation::Still },
+	BlockDef { name: "cactus", color: vec4(0.3, 0.55, 0.25, 1.0), transparent: false, liquid: false, falls: false, flammable: true, hardness: 0.4, emits: false, animation: Animation::Still },
+	BlockDef { name: "boulder", color: vec4(0.45, 0.44, 0.43, 1.0), transparent: false, liquid: false, falls: false, flammable: false, hardness: 2.0, emits: false, animation: Animation::Still },
+	BlockDef { name: "planks", color: vec4(0.5, 0.4, 0.3, 1.0), transparent: false, liquid: false, falls: false, flammable: true, hardness: 0.8, emits: false, animation: Animation::Still },
+	BlockDef { name: "custom", color: vec4(1.0, 1.0, 1.0, 1.0), transparent: false, liquid: false, falls: false, flammable: false, hardness: 1.0, emits: false, animation: Animation::Still },
+	BlockDef { name: "coal", color: vec4(0.16, 0.16, 0.17, 1.0), transparent: false, liquid: false, falls: false, flammable: false, hardness: 1.5, emits: false, animation: Animation::Still },
+	BlockDef { name: "iron", color: vec4(0.72, 0.55, 0.45, 1.0), transparent: false, liquid: false, falls: false, flammable: false, hardness: 2.0, emits: false, animation: Animation::Still },
+	BlockDef { name: "gold", color: vec4(0.93, 0.78, 0.22, 1.0), transparent: false, liquid: false, falls: false, flammable: false, hardness: 2.0, emits: false, animation: Animation::Still },
+	BlockDef { name: "diamond", color: vec4(0.42, 0.87, 0.92, 1.0), transparent: false, liquid: false, falls: false, flammable: false, hardness: 3.0, emits: false, animation: Animation::Still },
+	BlockDef { name: "water", color: vec4(0.16, 0.35, 0.62, 1.0), transparent: true, liquid: true, falls: false, flammable: false, hardness: 0.0, emits: false, animation: Animation::Shimmer(2.5) },
+	BlockDef { name: "tnt", color: vec4(0.8, 0.18, 0.14, 1.0), transparent: false, liquid: false, falls: false, flammable: true, hardness: 0.0, emits: false, animation: Animation::Still },
+	BlockDef { name: "fire", color: vec4(1.0, 0.55, 0.1, 1.0), transparent: false, liquid: false, falls: false, flammable: false, hardness: 0.0, emits: true, animation: Animation::Still },
+	BlockDef { name: "cell", color: vec4(0.55, 0.9, 0.35, 1.0), transparent: false, liquid: false, falls: false, flammable: false, hardness: 0.0, emits: false, animation: Animation::Still },
+	BlockDef { name: "wire", color: vec4(0.35, 0.08, 0.06, 1.0), transparent: false, liquid: false, falls: false, flammable: false, hardness: 0.0, emits: false, animation: Animation::Still },
+	BlockDef { name: "switch", color: vec4(0.35, 0.35, 0.38, 1.0), transparent: false, liquid: false, falls: false, flammable: false, hardness: 0.2, emits: false, animation: Animation::Still },
+	BlockDef { name: "lamp", color: vec4(0.4, 0.33, 0.2, 1.0), transparent: false, liquid: false, falls: false, flammable: false, hardness: 0.3, emits: false, animation: Animation::Still },
+	BlockDef { name: "door", color: vec4(0.55, 0.38, 0.22, 1.0), transparent: false, liquid: false, falls: false, flammable: true, hardness: 0.8, emits: false, animation: Animation::Still },
+	BlockDef { name: "sign", color: vec4(0.62, 0.5, 0.32, 1.0), transparent: false, liquid: false, falls: false, flammable: true, hardness: 0.5, emits: false, animation: Animation::Still },
+	BlockDef { name: "magma", color: vec4(0.85, 0.3, 0.06, 1.0), transparent: false, liquid: false, falls: false, flammable: false, hardness: 1.0, emits: true, animation: Animation::Pulse(3.0) },
+	BlockDef { name: "beacon", color: vec4(0.95, 0.25, 0.2, 1.0), transparent: false, liquid: false, falls: false, flammable: false, hardness: 0.5, emits: true, animation: Animation::Blink(1.0) },
+	BlockDef { name: "sapling", color: vec4(0.36, 0.62, 0.22, 1.0), transparent: false, liquid: false, falls: false, flammable: true, hardness: 0.0, emits: false, animation: Animation::Still },
+];
+
+pub const PACK_FOLDER: &str = "assets/blocks";
+
+// the built-in blocks followed by the ones from packs, once they're loaded
+static REGISTRY: OnceLock<Vec<BlockDef>> = OnceLock::new();
+
+// every block there is, indexed by id
+pub fn all() -> &'static [BlockDef] {
+	REGISTRY.get().map_or(&BLOCKS, |blocks| blocks)
+}
+
+pub fn def(block: BlockId) -> &'static BlockDef {
+	&all()[block as usize]
+}
+
+pub fn by_name(name: &str) -> Option<BlockId> {
+	all().iter().position(|b| b.name == name).map(|i| i as BlockId)
+}
+
+// Add the blocks from every pack in `folder`, in order of their names, handing what's wrong with
+// them to `warn`. Only the first call does anything, and it has to come before anything keeps
+// the number of blocks.
+pub fn load_packs(folder: &str, mut warn: impl FnMut(String)) {
+	let mut blocks = BLOCKS.to_vec();
+	let mut paths: Vec<_> = std::fs::read_dir(folder).into_iter().flatten().filter_map(|e| e.ok()).map(|e| e.path()).collect();
+	paths.sort();
+	for path in paths {
+		match path.extension().and_then(|x| x.to_str()) {
+			Some("toml") => match std::fs::read_to_string(&path) {
+				Ok(text) => parse(&text, &mut blocks, |e| warn(format!("{}: {}", path.display(), e))),
+				Err(e) => warn(format!("{}: {}", path.display(), e)),
+			},
+			Some("json") => warn(format!("{}: only TOML block packs can be read", path.display())),
+			_ => {},
+		}
+	}
+	let _ = REGISTRY.set(blocks);
+}
+
+// Add the blocks in the text of a pack to `blocks`. A pack giving any block a texture adds none
+// of them, since blocks can only be drawn in their colors.
+fn parse(text: &str, blocks: &mut Vec<BlockDef>, mut warn: impl FnMut(String)) {
+	let before = blocks.len();
+	// the block the lines are about, if it's one being added
+	let mut block: Option<usize> = None;
+	// the line giving a block a texture, if one does
+	let mut textured: Option<usize> = None;
+	for (n, line) in text.lines().enumerate() {
+		let line = line.split('#').next().unwrap_or("").trim();
+		if line.is_empty() {
+			continue;
+		}
+		if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+			let name = name.trim();
+			block = None;
+			if name.is_empty() || !name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_') {
+				warn(format!("line {}: block names are lowercase letters, digits and _, not '{}'", n + 1, name));
+			} else if blocks.iter().any(|b| b.name == name) {
+				warn(format!("line {}: there's already a block called '{}'", n + 1, name));
+			} else if blocks.len() > BlockId::MAX as usize {
+				warn(format!("line {}: there's no room for more blocks", n + 1));
+			} else {
+				let name = Box::leak(name.to_string().into_boxed_str());
+				blocks.push(BlockDef { name, color: Vec4::ONE, transparent: false, liquid: false, falls: false, flammable: false, hardness: 1.0, emits: false, animation: Animation::Still });
+				block = Some(blocks.len() - 1);
+			}
+			continue;
+		}
+		let Some(def) = block.map(|k| &mut blocks[k]) else { continue };
+		let Some((key, value)) = line.split_once('=') else {
+			warn(format!("line {}: expected 'key = value'", n + 1));
+			continue;
+		};
+		let value = value.trim();
+		let flag = match value {
+			"true" => Some(true),
+			"false" => Some(false),
+			_ => None,
+		};
+		let wrong = match (key.trim(), numbers(value).as_deref(), flag) {
+			("color", Some(&[r, g, b]), _) => { def.color = vec4(r, g, b, 1.0); false },
+			("color", Some(&[r, g, b, a]), _) => { def.color = vec4(r, g, b, a); false },
+			("hardness", Some(&[h]), _) if h >= 0.0 => { def.hardness = h; false },
+			("transparent", _, Some(f)) => { def.transparent = f; false },
+			("liquid", _, Some(f)) => { def.liquid = f; false },
+			("falls", _, Some(f)) => { def.falls = f; false },
+			("flammable", _, Some(f)) => { def.flammable = f; false },
+			("emits", _, Some(f)) => { def.emits = f; false },
+			("pulse", Some(&[p]), _) if p > 0.0 => { def.animation = Animation::Pulse(p); false },
+			("shimmer", Some(&[p]), _) if p > 0.0 => { def.animation = Animation::Shimmer(p); false },
+			("blink", Some(&[p]), _) if p > 0.0 => { def.animation = Animation::Blink(p); false },
+			("cycle", Some(&[p]), _) if p > 0.0 => {
+				let frames = match def.animation { Animation::Cycle(frames, _) => frames, _ => &[] };
+				def.animation = Animation::Cycle(frames, p);
+				false
+			},
+			("frames", _, _) if colors(value).is_some_and(|c| !c.is_empty()) => {
+				let period = match def.animation { Animation::Cycle(_, period) => period, _ => animation::FRAME };
+				def.animation = Animation::Cycle(colors(value).unwrap_or_default().leak(), period);
+				false
+			},
+			("texture", _, _) => {
+				textured = textured.or(Some(n + 1));
+				false
+			},
+			_ => true,
+		};
+		if wrong {
+			warn(format!("line {}: can't use '{}'", n + 1, line));
+		}
+	}
+	if let Some(n) = textured {
+		blocks.truncate(before);
+		warn(format!("line {}: blocks can't have textures, only colors, so none of this pack's blocks were added", n));
+	}
+}
+
+// a list of colors like [[1, 0.3, 0], [1, 0.6, 0]]
+fn colors(value: &str) -> Option<Vec<Vec4>> {
+	let list = value.trim().strip_prefix('[')?.strip_suffix(']')?;
+	list.split(']').map(|c| c.trim().trim_start_matches(',').trim()).filter(|c| !c.is_empty()).map(|c| {
+		match numbers(&format!("{}]", c)).as_deref() {
+			Some(&[r, g, b]) if c.starts_with('[') => Some(vec4(r, g, b, 1.0)),
+			_ => None,
+		}
+	}).collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn a_pack_with_a_texture_adds_none_of_its_blocks() {
+		let mut blocks = BLOCKS.to_vec();
+		let mut warnings = vec![];
+		parse("[glass]\ncolor = [0.8, 0.9, 1.0]\n[mossy]\ntexture = \"moss.png\"\n", &mut blocks, |e| warnings.push(e));
+		assert_eq!(blocks.len(), BLOCKS.len());
+		assert_eq!(warnings.len(), 1);
+		assert!(warnings[0].starts_with("line 4"));
+		parse("[glass]\ncolor = [0.8, 0.9, 1.0]\n", &mut blocks, |e| warnings.push(e));
+		assert_eq!(blocks.last().map(|b| b.name), Some("glass"));
+	}
+}
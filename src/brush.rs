@@ -0,0 +1,86 @@
+// Brushes: shapes that placing and breaking work on instead of a single voxel. A sphere or cube
+// of a chosen radius spreads out over the neighbor graph from the clicked voxel, so it keeps
+// its shape across seams, and a line runs from the voxel clicked last to the one clicked now.
+
+#[cfg(feature = "graphics")]
+use crate::{World, offset};
+
+pub const MAX_RADIUS: usize = 16;
+
+#[cfg(feature = "graphics")]
+#[derive(Clone, Copy, PartialEq)]
+pub enum Shape {
+	Block,
+	Sphere,
+	Cube,
+	Line,
+}
+
+#[cfg(feature = "graphics")]
+pub struct Brush {
+	pub shape: Shape,
+	pub radius: usize,
+	// voxel clicked last, where the next line starts
+	anchor: Option<usize>,
+}
+
+#[cfg(feature = "graphics")]
+impl Brush {
+	pub fn new() -> Brush {
+		Brush { shape: Shape::Block, radius: 2, anchor: None }
+	}
+
+	pub fn name(&self) -> String {
+		match self.shape {
+			Shape::Block => "block".to_string(),
+			Shape::Sphere => format!("sphere {}", self.radius),
+			Shape::Cube => format!("cube {}", self.radius),
+			Shape::Line => "line".to_string(),
+		}
+	}
+
+	pub fn next_shape(&mut self) {
+		self.shape = match self.shape {
+			Shape::Block => Shape::Sphere,
+			Shape::Sphere => Shape::Cube,
+			Shape::Cube => Shape::Line,
+			Shape::Line => Shape::Block,
+		};
+		self.anchor = None;
+	}
+
+	pub fn resize(&mut self, change: i32) {
+		self.radius = (self.radius as i32 + change).clamp(1, MAX_RADIUS as i32) as usize;
+	}
+
+	// The voxels covered by clicking voxel i.
+	pub fn voxels(&mut self, world: &World, i: usize) -> Vec<usize> {
+		let r = self.radius as f32;
+		let covered = match self.shape {
+			Shape::Block => vec![i],
+			// half a voxel more rounds off the flat spots at the poles
+			Shape::Sphere => world.iter_region(i, r + 0.5).map(|(j, _)| j).collect(),
+			Shape::Cube => world.walk(i, |d| d.abs().max_element() <= r).map(|(j, _)| j).collect(),
+			Shape::Line => self.anchor.map_or(vec![i], |a| line(world, a, i)),
+		};
+		self.anchor = Some(i);
+		covered
+	}
+}
+
+// The voxels on a straight line from a to b, the shorter way around the world, stepping from
+// each to the next over the graph.
+#[cfg(feature = "graphics")]
+fn line(world: &World, a: usize, b: usize) -> Vec<usize> {
+	let d = world.displacement(a, b);
+	let n = d.iter().map(|c| c.abs()).max().unwrap_or(0);
+	let mut found = vec![a];
+	let (mut i, mut at) = (a, [0; 3]);
+	for t in 1..=n {
+		let next = d.map(|c| (c as f32 * t as f32 / n as f32).round() as i32);
+		i = offset(world, i, [0, 1, 2].map(|k| next[k] - at[k]));
+		at = next;
+		found.push(i);
+	}
+	found
+}
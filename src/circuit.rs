@@ -0,0 +1,90 @@
+// Signal blocks for building simple circuits. A switch that's on powers the blocks next to it;
+// wire carries power on, losing a step of it with every voxel it passes through; and a lamp
+// lights up while anything next to it is powered. Power moves one voxel per tick: each tick the
+// voxels next to the ones that changed are worked out again, and any that change in turn pass
+// it on the tick after.
+
+use std::collections::HashSet;
+use glam::*;
+use crate::{World, Voxel, step};
+use crate::block;
+
+// power of a switch that's on, and so the furthest wire carries a signal
+pub const MAX_POWER: u8 = 15;
+
+pub struct Circuit {
+	// voxels to work out again this tick
+	pending: HashSet<usize>,
+}
+
+fn neighbors(world: &World, i: usize) -> [usize; 6] {
+	[(0, -1), (0, 1), (1, -1), (1, 1), (2, -1), (2, 1)].map(|(axis, sign)| step(world, i, axis, sign))
+}
+
+// power voxel i gives the voxels next to it
+fn power(world: &World, i: usize) -> u8 {
+	let voxel = world.voxel(i);
+	match voxel.block {
+		block::SWITCH if voxel.state > 0 => MAX_POWER,
+		block::WIRE => voxel.state,
+		_ => 0,
+	}
+}
+
+// the state voxel i should have given its neighbors, if it takes part in circuits
+fn wanted(world: &World, i: usize) -> Option<u8> {
+	let strongest = || neighbors(world, i).iter().map(|&j| power(world, j)).max().unwrap_or(0);
+	match world.voxel(i).block {
+		block::WIRE => Some(strongest().saturating_sub(1)),
+		block::LAMP => Some((strongest() > 0) as u8),
+		_ => None,
+	}
+}
+
+// Color of the signal blocks, which show their state: wire glows brighter the more power it
+// carries, a switch that's on turns green, and a lit lamp shines.
+pub fn color(voxel: &Voxel) -> Vec4 {
+	let on = voxel.state as f32 / match voxel.block {
+		block::WIRE => MAX_POWER as f32,
+		_ => 1.0,
+	};
+	let lit = match voxel.block {
+		block::WIRE => vec4(1.0, 0.15, 0.1, 1.0),
+		block::SWITCH => vec4(0.3, 0.8, 0.3, 1.0),
+		_ => vec4(1.0, 0.95, 0.6, 1.0),
+	};
+	voxel.color.lerp(lit, on)
+}
+
+// flip the switch in voxel i
+#[cfg(feature = "graphics")]
+pub fn toggle(world: &mut World, i: usize) {
+	let voxel = world.voxel(i);
+	let flipped = voxel.clone().with_state(1 - voxel.state.min(1));
+	world.set(i, flipped);
+}
+
+impl Circuit {
+	pub fn new() -> Circuit {
+		Circuit { pending: HashSet::new() }
+	}
+
+	// work out voxels that changed, and the ones next to them, again
+	pub fn notice(&mut self, world: &World, changed: &[usize]) {
+		for &i in changed {
+			self.pending.insert(i);
+			self.pending.extend(neighbors(world, i));
+		}
+	}
+
+	pub fn tick(&mut self, world: &mut World) {
+		let updates: Vec<(usize, u8)> = std::mem::take(&mut self.pending).into_iter().filter_map(|i| {
+			let state = wanted(world, i)?;
+			(state != world.voxel(i).state).then_some((i, state))
+		}).collect();
+		for (i, state) in updates {
+			let voxel = world.voxel(i).clone().with_state(state);
+			world.set(i, voxel);
+		}
+	}
+}
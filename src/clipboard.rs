@@ -0,0 +1,100 @@
+// The clipboard: a copy of the voxels in a selected box, which can be pasted back into the world
+// elsewhere, turned a quarter turn at a time, and written out as a prefab schematic.
+
+use crate::{World, Voxel, step, offset};
+use crate::block;
+use crate::selection::Region;
+
+// characters given to blocks in written schematics, after the first letter of their name
+const SPARE_KEYS: &str = "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
+
+pub struct Clipboard {
+	pub size: [usize; 3],
+	// indexed [x][y][z] like `furl`
+	voxels: Vec<Voxel>,
+}
+
+impl Clipboard {
+	pub fn copy(world: &World, region: &Region) -> Clipboard {
+		let voxels = region.voxels(world).into_iter().map(|i| world.voxel(i).clone()).collect();
+		Clipboard { size: region.size, voxels }
+	}
+
+	fn index(&self, x: usize, y: usize, z: usize) -> usize {
+		x*self.size[1]*self.size[2] + y*self.size[2] + z
+	}
+
+	// turned a quarter turn about +y, taking the +x direction to +z
+	pub fn rotated(&self) -> Clipboard {
+		let [sx, sy, sz] = self.size;
+		let mut turned = Clipboard { size: [sz, sy, sx], voxels: self.voxels.clone() };
+		for x in 0..sx {
+			for y in 0..sy {
+				for z in 0..sz {
+					let k = turned.index(sz - 1 - z, y, x);
+					turned.voxels[k] = self.voxels[self.index(x, y, z)].clone();
+				}
+			}
+		}
+		turned
+	}
+
+	// paste with the bottom of the box centered on voxel `origin`, air and all
+	pub fn paste(&self, world: &mut World, origin: usize) {
+		let [sx, sy, sz] = self.size;
+		let corner = offset(world, origin, [-(sx as i32 / 2), 0, -(sz as i32 / 2)]);
+		let mut i_x = corner;
+		for x in 0..sx {
+			let mut i_y = i_x;
+			for y in 0..sy {
+				let mut i = i_y;
+				for z in 0..sz {
+					world.set(i, self.voxels[self.index(x, y, z)].clone());
+					i = step(world, i, 2, 1);
+				}
+				i_y = step(world, i_y, 1, 1);
+			}
+			i_x = step(world, i_x, 0, 1);
+		}
+	}
+
+	// The copy as a prefab schematic. Schematics only name blocks, so voxel colors and states
+	// are left behind.
+	pub fn schematic(&self) -> String {
+		let [sx, sy, sz] = self.size;
+		let mut palette: Vec<(char, block::BlockId)> = vec![('.', block::AIR)];
+		for v in &self.voxels {
+			if palette.iter().any(|&(_, b)| b == v.block) {
+				continue;
+			}
+			let name = block::def(v.block).name;
+			let key = name.chars().next().map(|c| c.to_ascii_uppercase()).into_iter()
+				.chain(SPARE_KEYS.chars())
+				.find(|&c| palette.iter().all(|&(k, _)| k != c));
+			if let Some(key) = key {
+				palette.push((key, v.block));
+			}
+		}
+		let mut text = format!("# Copied out of the world.\nsize {} {} {}\npalette\n", sx, sy, sz);
+		for &(key, b) in &palette {
+			text += &format!("{} {}\n", key, block::def(b).name);
+		}
+		for y in 0..sy {
+			text += "layer\n";
+			for z in 0..sz {
+				let row: String = (0..sx).map(|x| {
+					let b = self.voxels[self.index(x, y, z)].block;
+					// blocks past the last key are left out
+					palette.iter().find(|&&(_, p)| p == b).map_or('_', |&(k, _)| k)
+				}).collect();
+				text += &row;
+				text += "\n";
+			}
+		}
+		text
+	}
+
+	pub fn save(&self, path: &str) -> Result<(), String> {
+		std::fs::write(path, self.schematic()).map_err(|e| format!("{}: {}", path, e))
+	}
+}
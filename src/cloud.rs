@@ -0,0 +1,114 @@
+// Clouds: a layer of them across the world at the height the settings give, drifting slowly with
+// the wind. The layer is flat, patterned with noise that tiles across the world like the terrain
+// does, and is drawn after everything else over the pixels whose ray crosses it before hitting
+// anything, using the distances the raycaster left in the screen. Going around the world along
+// the way up meets the layer again, so looking far enough up or down shows it more than once.
+// Rain and snow thicken and darken it.
+
+use macroquad::prelude::*;
+use crate::gravity::Gravity;
+use crate::noise::{Noise, NoiseParams};
+use crate::rng::Rng;
+
+// voxels across a cloud, roughly
+const CELL: f32 = 24.0;
+const OCTAVES: u32 = 3;
+// voxels a tick the clouds drift
+const WIND: f32 = 0.02;
+// how much of the sky they cover, clear and at the heaviest weather, from 0 to 1
+const COVER: (f32, f32) = (0.45, 0.85);
+// how quickly a cloud thickens from its edge, and how solid it gets
+const EDGE: f32 = 4.0;
+const OPACITY: f32 = 0.85;
+// color in clear weather and in storms
+const WHITE: Vec4 = vec4(0.92, 0.93, 0.95, 1.0);
+const DARK: Vec4 = vec4(0.35, 0.36, 0.4, 1.0);
+
+pub struct Clouds {
+	noise: Noise,
+	// voxels drifted along the wind
+	drift: f32,
+}
+
+// the clouds as they are for a frame, seen from the camera
+#[derive(Clone)]
+pub struct Layer {
+	noise: Noise,
+	// the axis up is along, and where the layer is along it
+	axis: usize,
+	height: f32,
+	// the camera's coordinates in the whole world, and the whole world's size
+	camera: Vec3,
+	size: Vec3,
+	drift: f32,
+	frequency: u32,
+	cover: f32,
+	pub color: Vec4,
+}
+
+impl Clouds {
+	pub fn new(seed: u64) -> Clouds {
+		Clouds { noise: Noise::new(&mut Rng::new(seed ^ 0xc10d)), drift: 0.0 }
+	}
+
+	pub fn tick(&mut self) {
+		self.drift += WIND;
+	}
+
+	// The layer `height` voxels up from the bottom of the world against `gravity`, seen from
+	// `camera` in a world `size` across, in weather `heaviness` from 0 to 1.
+	pub fn layer(&self, gravity: Gravity, height: f32, camera: Vec3, size: [usize; 3], heaviness: f32) -> Layer {
+		let axis = gravity.axis;
+		let size = Vec3::from(size.map(|n| n as f32));
+		let height = if gravity.sign < 0 { height } else { size[axis] - height };
+		// the wind blows along the first axis across the layer
+		let across = (axis + 1) % 3;
+		Layer {
+			noise: self.noise.clone(),
+			axis,
+			height: height.rem_euclid(size[axis]),
+			camera,
+			size,
+			drift: self.drift.rem_euclid(size[across]),
+			frequency: (size[across] / CELL).round().max(1.0) as u32,
+			cover: COVER.0 + (COVER.1 - COVER.0) * heaviness,
+			color: WHITE.lerp(DARK, heaviness),
+		}
+	}
+}
+
+impl Layer {
+	// Where the unit `ray` from the camera crosses the layer before `depth`, nearest first, as the
+	// distance along it and how solid the clouds are there.
+	pub fn crossings(&self, ray: Vec3, depth: f32) -> Vec<(f32, f32)> {
+		let climb = ray[self.axis];
+		if climb.abs() < 1e-4 {
+			return vec![];
+		}
+		let n = self.size[self.axis];
+		let below = self.height - self.camera[self.axis];
+		let first = if climb > 0.0 { below.rem_euclid(n) } else { (-below).rem_euclid(n) };
+		let (first, every) = (first / climb.abs(), n / climb.abs());
+		let mut crossings = vec![];
+		let mut t = first;
+		while t < depth {
+			let density = self.density(self.camera + t * ray);
+			if density > 0.0 {
+				crossings.push((t, density));
+			}
+			t += every;
+		}
+		crossings
+	}
+
+	// how solid the clouds are at `p` in the whole world, from 0 to OPACITY
+	fn density(&self, p: Vec3) -> f32 {
+		let mut q = p;
+		q[(self.axis + 1) % 3] += self.drift;
+		// the same wherever the layer is met along the way up
+		q[self.axis] = 0.0;
+		let params = NoiseParams { amplitude: 1.0, frequency: self.frequency, octaves: OCTAVES };
+		let n = self.noise.fbm(q / self.size, &params);
+		(((n + self.cover - 0.5) * EDGE).clamp(0.0, 1.0)) * OPACITY
+	}
+}
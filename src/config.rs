@@ -0,0 +1,425 @@
+// Settings read from `voxeltorus.toml` at startup, so they can be tuned without recompiling.
+// If the file doesn't exist it's written with the defaults, to have something to edit. Some
+// can be overridden for a single run from the command line. The file is watched while the
+// game runs, and edits to it take effect straight away, except for the world size and shape.
+//
+// Only the simple part of TOML is understood: `key = value` lines, where a value is a number
+// or a list of numbers in square brackets, and `#` comments. Key bindings go in a `[keys]`
+// table, as `action = "Key"` lines, and controller buttons in a `[gamepad]` one. Anything
+// missing or unreadable keeps its default, with a warning.
+
+#[cfg(feature = "graphics")]
+use std::f32::consts::PI;
+use glam::*;
+use crate::{arg_value, parse_size, log, TERRAIN_NOISE, ISLAND_NOISE};
+#[cfg(feature = "graphics")]
+use crate::input::{Binding, Bindings, ACTIONS};
+#[cfg(feature = "graphics")]
+use crate::gamepad::Button;
+use crate::noise::NoiseParams;
+
+pub const FILE: &str = "voxeltorus.toml";
+// seconds between looks at whether the file has changed
+#[cfg(feature = "graphics")]
+const WATCH_INTERVAL: f64 = 1.0;
+
+#[derive(Clone, PartialEq)]
+pub struct Config {
+	// window size, in pixels
+	pub resolution: (f32, f32),
+	// rays cast across and down, each drawn as a square, as big as lets them all fit the window
+	pub screen: (usize, usize),
+	// voxels along each axis of worlds held in memory
+	pub world_size: [usize; 3],
+	// noise shaping the default terrain and the floating islands: how strong it is, lattice
+	// cells across the world for the first octave, and octaves
+	pub terrain_noise: NoiseParams,
+	pub island_noise: NoiseParams,
+	// walking speed in voxels per tick, and the upward speed of a jump
+	pub movement_speed: f32,
+	pub jump_velocity: f32,
+	// degrees turned per pixel the mouse moves, across and up, whether moving it up looks
+	// down, and seconds its turning is spread over to smooth it, 0 for none
+	pub mouse_sensitivity: (f32, f32),
+	pub invert_y: bool,
+	pub mouse_smoothing: f32,
+	// whether the view bobs, dips and rolls as the player moves
+	pub camera_effects: bool,
+	// horizontal field of view in degrees; the vertical one follows from the screen's shape
+	pub fov: f32,
+	// furthest a ray goes, in voxels
+	pub view_distance: usize,
+	// color things fade into with distance
+	pub ambient: Vec4,
+	// a controller's right stick: radians turned per second when it's pushed all the way,
+	// across and up, how far it moves before it counts, from 0 to 1, and the power its tilt is
+	// raised to, above 1 for finer aim
+	pub stick_speed: (f32, f32),
+	pub stick_deadzone: f32,
+	pub stick_curve: f32,
+	// loudness of all the sounds, and of each kind of them, from 0 for silent to 1
+	pub volume: f32,
+	pub footstep_volume: f32,
+	pub block_volume: f32,
+	pub body_volume: f32,
+	// loudness of the music and ambient sound, from 0 to 1, under `volume`
+	pub music_volume: f32,
+	// whether sounds with something solid in the way are muffled
+	pub occlusion: bool,
+	// whether there are clouds, and how many voxels up from the bottom of the world they are
+	pub clouds: bool,
+	pub cloud_height: f32,
+	// whether the world is lit by the sun and glowing blocks, dark under overhangs and in caves
+	pub lighting: bool,
+	// key or mouse button, and controller button, for each action
+	#[cfg(feature = "graphics")]
+	pub keys: Bindings,
+}
+
+impl Default for Config {
+	fn default() -> Config {
+		Config {
+			resolution: (800.0, 600.0),
+			screen: (200, 150),
+			world_size: [64, 64, 64],
+			terrain_noise: TERRAIN_NOISE,
+			island_noise: ISLAND_NOISE,
+			movement_speed: 0.1,
+			jump_velocity: 0.2,
+			mouse_sensitivity: (0.1, 0.1),
+			invert_y: false,
+			mouse_smoothing: 0.0,
+			camera_effects: true,
+			fov: 90.0,
+			view_distance: 128,
+			ambient: vec4(0.0, 0.0, 0.0, 1.0),
+			stick_speed: (3.0, 2.0),
+			stick_deadzone: 0.15,
+			stick_curve: 2.0,
+			volume: 1.0,
+			footstep_volume: 0.5,
+			block_volume: 1.0,
+			body_volume: 0.7,
+			music_volume: 0.5,
+			occlusion: true,
+			clouds: true,
+			cloud_height: 48.0,
+			lighting: true,
+			#[cfg(feature = "graphics")]
+			keys: Bindings::default(),
+		}
+	}
+}
+
+// noise from [amplitude, frequency, octaves], the last two whole numbers of at least 1
+fn noise_params(value: &[f32]) -> Option<NoiseParams> {
+	let &[amplitude, frequency, octaves] = value else { return None };
+	let whole = |n: f32| (n >= 1.0 && n.fract() == 0.0).then_some(n as u32);
+	Some(NoiseParams { amplitude, frequency: whole(frequency)?, octaves: whole(octaves)? })
+}
+
+// the numbers in a value: one on its own, or a list like [1, 2, 3]
+pub fn numbers(value: &str) -> Option<Vec<f32>> {
+	let value = value.trim();
+	let list = match value.strip_prefix('[') {
+		Some(rest) => rest.strip_suffix(']')?,
+		None => value,
+	};
+	list.split(',').map(|n| n.trim()).filter(|n| !n.is_empty()).map(|n| n.parse().ok()).collect()
+}
+
+impl Config {
+	// Read the settings file, or write it with the defaults if there isn't one.
+	pub fn load(path: &str) -> Config {
+		match std::fs::read_to_string(path) {
+			Ok(text) => Config::parse(&text, |e| log::warning!(path, "{}", e)),
+			Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+				let config = Config::default();
+				if let Err(e) = std::fs::write(path, config.to_toml()) {
+					log::warning!(path, "{}", e);
+				}
+				config
+			},
+			Err(e) => {
+				log::warning!(path, "{}", e);
+				Config::default()
+			},
+		}
+	}
+
+	// settings from the text of a settings file, telling `warn` about any line that's wrong
+	pub fn parse(text: &str, mut warn: impl FnMut(String)) -> Config {
+		let mut config = Config::default();
+		// the [table] the lines are in, if any
+		let mut table = "";
+		for (n, line) in text.lines().enumerate() {
+			let line = line.split('#').next().unwrap_or("").trim();
+			if line.is_empty() {
+				continue;
+			}
+			if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+				table = name.trim();
+				if table != "keys" && table != "gamepad" {
+					warn(format!("line {}: there's no table called '{}'", n + 1, table));
+				}
+				continue;
+			}
+			let Some((key, value)) = line.split_once('=') else {
+				warn(format!("line {}: expected 'key = value'", n + 1));
+				continue;
+			};
+			let key = key.trim();
+			#[cfg(feature = "graphics")]
+			if table == "keys" {
+				let name = value.trim().strip_prefix('"').and_then(|v| v.strip_suffix('"'));
+				match name.and_then(Binding::parse) {
+					Some(binding) => if !config.keys.set(key, binding) {
+						warn(format!("line {}: there's no action called '{}'", n + 1, key));
+					},
+					None => warn(format!("line {}: can't bind '{}'; keys are named like \"W\", \"Space\" or \"MouseLeft\"", n + 1, value.trim())),
+				}
+				continue;
+			}
+			#[cfg(feature = "graphics")]
+			if table == "gamepad" {
+				let name = value.trim().strip_prefix('"').and_then(|v| v.strip_suffix('"'));
+				let button = match name {
+					Some("") => Some(None),
+					Some(name) => Button::parse(name).map(Some),
+					None => None,
+				};
+				match button {
+					Some(button) => if !config.keys.set_button(key, button) {
+						warn(format!("line {}: there's no action called '{}'", n + 1, key));
+					},
+					None => warn(format!("line {}: can't bind '{}'; buttons are named like \"A\", \"Start\" or \"RightTrigger\"", n + 1, value.trim())),
+				}
+				continue;
+			}
+			// the dedicated server has no keys to bind, so skips their tables
+			if !table.is_empty() {
+				continue;
+			}
+			let wrong = match (key, numbers(value).as_deref()) {
+				("invert_y", _) if matches!(value.trim(), "true" | "false") => { config.invert_y = value.trim() == "true"; false },
+				("camera_effects", _) if matches!(value.trim(), "true" | "false") => { config.camera_effects = value.trim() == "true"; false },
+				("occlusion", _) if matches!(value.trim(), "true" | "false") => { config.occlusion = value.trim() == "true"; false },
+				("clouds", _) if matches!(value.trim(), "true" | "false") => { config.clouds = value.trim() == "true"; false },
+				("lighting", _) if matches!(value.trim(), "true" | "false") => { config.lighting = value.trim() == "true"; false },
+				("resolution", Some(&[w, h])) => { config.resolution = (w, h); false },
+				("screen", Some(&[w, h])) if w >= 1.0 && h >= 1.0 => { config.screen = (w as usize, h as usize); false },
+				("world_size", Some(&[x, y, z])) if x.min(y).min(z) >= 1.0 => { config.world_size = [x as usize, y as usize, z as usize]; false },
+				("terrain_noise", Some(n)) => noise_params(n).map(|p| config.terrain_noise = p).is_none(),
+				("island_noise", Some(n)) => noise_params(n).map(|p| config.island_noise = p).is_none(),
+				("movement_speed", Some(&[s])) => { config.movement_speed = s; false },
+				("jump_velocity", Some(&[v])) => { config.jump_velocity = v; false },
+				("mouse_sensitivity", Some(&[x, y])) => { config.mouse_sensitivity = (x, y); false },
+				("mouse_smoothing", Some(&[s])) if s >= 0.0 => { config.mouse_smoothing = s; false },
+				("rotation_speed", _) => {
+					warn(format!("line {}: rotation_speed is now mouse_sensitivity, in degrees per pixel", n + 1));
+					false
+				},
+				("fov", Some(&[f])) if f > 0.0 && f < 180.0 => { config.fov = f; false },
+				("view_distance", Some(&[d])) if d >= 1.0 => { config.view_distance = d as usize; false },
+				("ambient", Some(&[r, g, b])) => { config.ambient = vec4(r, g, b, 1.0); false },
+				("stick_speed", Some(&[x, y])) => { config.stick_speed = (x, y); false },
+				("stick_deadzone", Some(&[d])) if (0.0..1.0).contains(&d) => { config.stick_deadzone = d; false },
+				("stick_curve", Some(&[c])) if c > 0.0 => { config.stick_curve = c; false },
+				("volume", Some(&[v])) if (0.0..=1.0).contains(&v) => { config.volume = v; false },
+				("footstep_volume", Some(&[v])) if (0.0..=1.0).contains(&v) => { config.footstep_volume = v; false },
+				("block_volume", Some(&[v])) if (0.0..=1.0).contains(&v) => { config.block_volume = v; false },
+				("body_volume", Some(&[v])) if (0.0..=1.0).contains(&v) => { config.body_volume = v; false },
+				("music_volume", Some(&[v])) if (0.0..=1.0).contains(&v) => { config.music_volume = v; false },
+				("cloud_height", Some(&[h])) if h >= 0.0 => { config.cloud_height = h; false },
+				_ => true,
+			};
+			if wrong {
+				warn(format!("line {}: can't use '{}'", n + 1, line));
+			}
+		}
+		#[cfg(feature = "graphics")]
+		for (k, &(a, first, _, _, _)) in ACTIONS.iter().enumerate() {
+			for &(b, second, _, _, _) in &ACTIONS[k + 1..] {
+				if config.keys.get(a) == config.keys.get(b) {
+					warn(format!("keys: '{}' and '{}' are both on {}", first, second, config.keys.get(a).name()));
+				}
+				if let Some(button) = config.keys.button(a).filter(|&button| config.keys.button(b) == Some(button)) {
+					warn(format!("gamepad: '{}' and '{}' are both on {}", first, second, button.name()));
+				}
+			}
+		}
+		config
+	}
+
+	pub fn to_toml(&self) -> String {
+		let c = self;
+		let text = format!(
+"# voxeltorus settings. Delete a line to go back to its default.
+
+# window size, in pixels
+resolution = [{}, {}]
+# rays cast across and down the window; fewer is faster and blockier
+screen = [{}, {}]
+# voxels along each axis of the world
+world_size = [{}, {}, {}]
+# the noise the default terrain and the floating islands are made from: how strong it is, how
+# many bumps there are across the world, and how many layers of finer ones go on top
+terrain_noise = [{}, {}, {}]
+island_noise = [{}, {}, {}]
+# walking speed and jump speed, in voxels per tick
+movement_speed = {}
+jump_velocity = {}
+# degrees turned per pixel the mouse moves, across and up
+mouse_sensitivity = [{}, {}]
+# true to look down when the mouse moves up
+invert_y = {}
+# seconds to spread the mouse's turning over, smoothing it; 0 turns straight away
+mouse_smoothing = {}
+# true for the view to bob while walking, dip on landing and roll stepping sideways
+camera_effects = {}
+# horizontal field of view, in degrees
+fov = {}
+# furthest you can see, in voxels
+view_distance = {}
+# color of the distance, red green and blue from 0 to 1
+ambient = [{}, {}, {}]
+# a controller's right stick: turning speed across and up in radians per second, how far it's
+# pushed before it moves, from 0 to 1, and the power its tilt is raised to for finer aim
+stick_speed = [{}, {}]
+stick_deadzone = {}
+stick_curve = {}
+# loudness of all the sounds from 0 to 1, and of footsteps, blocks breaking and being placed,
+# and jumping and landing
+volume = {}
+footstep_volume = {}
+block_volume = {}
+body_volume = {}
+# loudness of the music and ambient sound, from 0 to 1
+music_volume = {}
+# true to muffle sounds with something solid between them and you
+occlusion = {}
+# true for clouds drifting over the world, and how many voxels up from its bottom they are
+clouds = {}
+cloud_height = {}
+# true for sunlight and light from glowing blocks, leaving overhangs and caves in the dark
+lighting = {}
+",
+			c.resolution.0, c.resolution.1, c.screen.0, c.screen.1,
+			c.world_size[0], c.world_size[1], c.world_size[2],
+			c.terrain_noise.amplitude, c.terrain_noise.frequency, c.terrain_noise.octaves,
+			c.island_noise.amplitude, c.island_noise.frequency, c.island_noise.octaves,
+			c.movement_speed, c.jump_velocity, c.mouse_sensitivity.0, c.mouse_sensitivity.1, c.invert_y, c.mouse_smoothing, c.camera_effects,
+			c.fov, c.view_distance, c.ambient.x, c.ambient.y, c.ambient.z,
+			c.stick_speed.0, c.stick_speed.1, c.stick_deadzone, c.stick_curve,
+			c.volume, c.footstep_volume, c.block_volume, c.body_volume, c.music_volume, c.occlusion,
+			c.clouds, c.cloud_height, c.lighting,
+		);
+		#[cfg(feature = "graphics")]
+		let text = text + &self.bindings_toml();
+		text
+	}
+
+	// the [keys] and [gamepad] tables
+	#[cfg(feature = "graphics")]
+	fn bindings_toml(&self) -> String {
+		let c = self;
+		let mut keys = String::new();
+		let mut buttons = String::new();
+		for (action, name, _, _, about) in ACTIONS {
+			keys += &format!("{} = \"{}\"  # {}\n", name, c.keys.get(action).name(), about);
+			buttons += &format!("{} = \"{}\"\n", name, c.keys.button(action).map_or("", |b| b.name()));
+		}
+		format!("
+# the key or mouse button for each action: letters and digits like \"W\" and \"Key1\", \"F1\",
+# \"Space\", \"LeftShift\", \"LeftBracket\" and so on, or \"MouseLeft\", \"MouseRight\" or \"MouseMiddle\"
+[keys]
+{}
+# the controller button for each action, or \"\" for none: \"A\", \"B\", \"X\", \"Y\", \"LeftBumper\",
+# \"RightBumper\", \"Back\", \"Start\", \"LeftStick\", \"RightStick\", \"LeftTrigger\" or \"RightTrigger\".
+# The left stick walks and the right stick looks around.
+[gamepad]
+{}", keys, buttons)
+	}
+
+	// Override settings given on the command line: `--world-size 128x64x128`,
+	// `--resolution 1280x720` and `--fov 70`.
+	pub fn apply_args(&mut self, args: &[String]) {
+		if let Some(size) = arg_value(args, "--world-size") {
+			match parse_size(&size) {
+				Some(size) => self.world_size = size,
+				None => log::warning!("world-size", "expected a size like 128x64x128, got '{}'", size),
+			}
+		}
+		if let Some(resolution) = arg_value(args, "--resolution") {
+			let pixels: Option<Vec<f32>> = resolution.split('x').map(|n| n.parse().ok().filter(|&n: &f32| n >= 1.0)).collect();
+			match pixels.as_deref() {
+				Some(&[w, h]) => self.resolution = (w, h),
+				_ => log::warning!("resolution", "expected a size like 1280x720, got '{}'", resolution),
+			}
+		}
+		if let Some(fov) = arg_value(args, "--fov") {
+			match fov.parse() {
+				Ok(f) if f > 0.0 && f < 180.0 => self.fov = f,
+				_ => log::warning!("fov", "expected degrees between 0 and 180, got '{}'", fov),
+			}
+		}
+	}
+
+	// horizontal and vertical field of view, in radians
+	#[cfg(feature = "graphics")]
+	pub fn fov(&self) -> (f32, f32) {
+		let across = self.fov * PI / 180.0;
+		(across, across * self.screen.1 as f32 / self.screen.0 as f32)
+	}
+
+	// radians turned per pixel the mouse moves, across and up, with up turned around if inverted
+	#[cfg(feature = "graphics")]
+	pub fn mouse_rotation(&self) -> (f32, f32) {
+		let (x, y) = self.mouse_sensitivity;
+		(x.to_radians(), if self.invert_y { -y.to_radians() } else { y.to_radians() })
+	}
+}
+
+// Notices when the settings file is changed.
+#[cfg(feature = "graphics")]
+pub struct Watcher {
+	path: String,
+	modified: Option<std::time::SystemTime>,
+	checked: std::time::Instant,
+}
+
+#[cfg(feature = "graphics")]
+fn modified(path: &str) -> Option<std::time::SystemTime> {
+	std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+#[cfg(feature = "graphics")]
+impl Watcher {
+	pub fn new(path: &str) -> Watcher {
+		Watcher { path: path.to_string(), modified: modified(path), checked: std::time::Instant::now() }
+	}
+
+	// the settings, read again, if the file has changed since last time
+	pub fn poll(&mut self) -> Option<Config> {
+		if self.checked.elapsed().as_secs_f64() < WATCH_INTERVAL {
+			return None;
+		}
+		self.checked = std::time::Instant::now();
+		let now = modified(&self.path);
+		if now == self.modified {
+			return None;
+		}
+		self.modified = now;
+		let text = std::fs::read_to_string(&self.path).ok()?;
+		Some(Config::parse(&text, |e| log::warning!(&self.path, "{}", e)))
+	}
+
+	// write settings changed in the game to the file, without reading them back in as an edit
+	pub fn save(&mut self, config: &Config) {
+		match std::fs::write(&self.path, config.to_toml()) {
+			Ok(()) => log::info!("settings", "saved to {}", self.path),
+			Err(e) => log::warning!(&self.path, "{}", e),
+		}
+		self.modified = modified(&self.path);
+	}
+}
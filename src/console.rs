@@ -0,0 +1,268 @@
+// The command console, opened with `/`: a line to type commands into, and the messages they
+// answer with. Commands are parsed here into a `Command` for the game loop to carry out, since
+// that's where everything they act on lives. `Tab` completes command and block names, the up and
+// down arrows go back through the lines typed before, and `Page Up` and `Page Down` scroll
+// through the messages. Opened with `Enter` instead, it's for chatting with the other players,
+// though lines starting with `/` are still commands.
+
+#[cfg(feature = "graphics")]
+use macroquad::prelude::*;
+use crate::block::{self, BlockId};
+use crate::permission::{self, Role};
+use crate::weather;
+
+// messages kept, and how long the latest stay on screen once the console is closed, in seconds
+#[cfg(feature = "graphics")]
+const HISTORY: usize = 200;
+#[cfg(feature = "graphics")]
+const MESSAGE_TIME: f64 = 6.0;
+#[cfg(feature = "graphics")]
+const SHOWN: usize = 8;
+
+// most rays the resolution command casts across or down
+const MAX_RAYS: usize = 2000;
+
+pub const COMMANDS: [(&str, &str); 19] = [
+	("tp", "tp x y z: move to voxel x y z"),
+	("mark", "mark name: mark where you are as a waypoint"),
+	("go", "go name: go back to a waypoint"),
+	("unmark", "unmark name: forget a waypoint"),
+	("marks", "marks: list the waypoints"),
+	("guide", "guide [name]: point the way to walk to a waypoint, or stop"),
+	("setblock", "setblock x y z block: put a block in voxel x y z"),
+	("fill", "fill x y z x y z block: fill the box between two corners"),
+	("count", "count block [radius]: count the voxels of a block in the world, or within radius of you"),
+	("seed", "seed: show the seed the world was made from"),
+	("time", "time: show how long the game has been running"),
+	("resolution", "resolution [across] [down]: show or set the rays cast across and down"),
+	("weather", "weather [clear|rain|snow|storm]: show or change the weather"),
+	("save", "save: keep the world for next time"),
+	("players", "players: list the other players in a game played together"),
+	("role", "role player role: make a player a spectator, builder or admin, when hosting"),
+	("protect", "protect x y z x y z: keep builders out of the box between two corners, when hosting"),
+	("unprotect", "unprotect: stop protecting every box, when hosting"),
+	("help", "help: list the commands"),
+];
+
+// what's in each is read by the game, while the server only acts on a few of them
+#[cfg_attr(not(feature = "graphics"), allow(dead_code))]
+pub enum Command {
+	Teleport([usize; 3]),
+	Mark(String),
+	Go(String),
+	Unmark(String),
+	Marks,
+	// the waypoint to guide to, or none to stop
+	Guide(Option<String>),
+	SetBlock([usize; 3], BlockId),
+	Fill([usize; 3], [usize; 3], BlockId),
+	// the block and the radius to count within, or none for the whole world
+	Count(BlockId, Option<f32>),
+	Seed,
+	Time,
+	// rays across and down, down following from the window's shape if not given; neither
+	// to show them
+	Resolution(Option<(usize, Option<usize>)>),
+	// the weather to change to, or none to show it
+	Weather(Option<weather::Kind>),
+	Save,
+	Players,
+	Role(u32, Role),
+	Protect([usize; 3], [usize; 3]),
+	Unprotect,
+	Help,
+}
+
+// Parse a line typed into the console, with or without its leading `/`, checking voxel
+// coordinates against the world's `size`.
+pub fn parse(line: &str, size: [usize; 3]) -> Result<Command, String> {
+	let words: Vec<&str> = line.trim().trim_start_matches('/').split_whitespace().collect();
+	let coordinates = |w: &[&str]| -> Result<[usize; 3], String> {
+		let mut c = [0; 3];
+		for k in 0..3 {
+			c[k] = w[k].parse().ok().filter(|&n| n < size[k]).ok_or(format!("'{}' isn't a coordinate from 0 to {}", w[k], size[k] - 1))?;
+		}
+		Ok(c)
+	};
+	let rays = |w: &str| w.parse().ok().filter(|n| (1..=MAX_RAYS).contains(n)).ok_or(format!("'{}' isn't a number of rays from 1 to {}", w, MAX_RAYS));
+	let named = |name: &str| block::by_name(name).ok_or(format!("there's no block called '{}'", name));
+	let usage = |name: &str| COMMANDS.iter().find(|(c, _)| *c == name).map_or(String::new(), |(_, u)| format!("usage: {}", u));
+	match words.as_slice() {
+		["tp", w @ ..] if w.len() == 3 => Ok(Command::Teleport(coordinates(w)?)),
+		["setblock", w @ .., b] if w.len() == 3 => Ok(Command::SetBlock(coordinates(w)?, named(b)?)),
+		["fill", w @ .., b] if w.len() == 6 => Ok(Command::Fill(coordinates(&w[..3])?, coordinates(&w[3..])?, named(b)?)),
+		["mark", name] => Ok(Command::Mark(name.to_string())),
+		["go", name] => Ok(Command::Go(name.to_string())),
+		["unmark", name] => Ok(Command::Unmark(name.to_string())),
+		["marks"] => Ok(Command::Marks),
+		["guide"] => Ok(Command::Guide(None)),
+		["guide", name] => Ok(Command::Guide(Some(name.to_string()))),
+		["count", b] => Ok(Command::Count(named(b)?, None)),
+		["count", b, r] => Ok(Command::Count(named(b)?, Some(r.parse().ok().filter(|r: &f32| *r >= 0.0).ok_or(format!("'{}' isn't a radius", r))?))),
+		["seed"] => Ok(Command::Seed),
+		["time"] => Ok(Command::Time),
+		["resolution"] => Ok(Command::Resolution(None)),
+		["resolution", across] => Ok(Command::Resolution(Some((rays(across)?, None)))),
+		["resolution", across, down] => Ok(Command::Resolution(Some((rays(across)?, Some(rays(down)?))))),
+		["weather"] => Ok(Command::Weather(None)),
+		["weather", kind] => Ok(Command::Weather(Some(weather::Kind::parse(kind).ok_or(format!("there's no weather called '{}'; try {}", kind, weather::KINDS.map(|(_, n)| n).join(", ")))?))),
+		["save"] => Ok(Command::Save),
+		["players"] => Ok(Command::Players),
+		["role", player, role] => Ok(Command::Role(
+			player.parse().map_err(|_| format!("'{}' isn't a player's number", player))?,
+			Role::parse(role).ok_or(format!("there's no role called '{}'; try {}", role, permission::ROLES.map(|(_, n)| n).join(", ")))?,
+		)),
+		["protect", w @ ..] if w.len() == 6 => Ok(Command::Protect(coordinates(&w[..3])?, coordinates(&w[3..])?)),
+		["unprotect"] => Ok(Command::Unprotect),
+		["help"] => Ok(Command::Help),
+		[name, ..] if COMMANDS.iter().any(|(c, _)| c == name) => Err(usage(name)),
+		[name, ..] => Err(format!("unknown command '{}', try help", name)),
+		[] => Err(String::new()),
+	}
+}
+
+// a line entered into the console
+#[cfg(feature = "graphics")]
+pub enum Entered {
+	Command(String),
+	Chat(String),
+}
+
+#[cfg(feature = "graphics")]
+pub struct Console {
+	pub open: bool,
+	// whether it was opened for chatting
+	chat: bool,
+	input: String,
+	// messages, with when they were said
+	messages: Vec<(f64, String)>,
+	// lines entered before, the latest last, and which is being gone back to
+	typed: Vec<String>,
+	recalled: Option<usize>,
+	// messages scrolled back past
+	scroll: usize,
+}
+
+#[cfg(feature = "graphics")]
+impl Console {
+	pub fn new() -> Console {
+		Console { open: false, chat: false, input: String::new(), messages: vec![], typed: vec![], recalled: None, scroll: 0 }
+	}
+
+	// open for commands, or for chatting if `chat`
+	pub fn open(&mut self, chat: bool) {
+		self.open = true;
+		self.chat = chat;
+		self.input.clear();
+		self.recalled = None;
+		self.scroll = 0;
+	}
+
+	pub fn say(&mut self, text: impl Into<String>) {
+		self.messages.push((get_time(), text.into()));
+		if self.messages.len() > HISTORY {
+			self.messages.remove(0);
+		}
+	}
+
+	// Take this frame's typing, completing with the names of the waypoints in `marks` where
+	// they're wanted. Returns the line entered, if `Enter` was pressed, which closes the
+	// console, as does `Escape`.
+	pub fn update(&mut self, marks: &[&str]) -> Option<Entered> {
+		while let Some(c) = get_char_pressed() {
+			if !c.is_control() {
+				self.input.push(c);
+			}
+		}
+		if is_key_pressed(KeyCode::Backspace) {
+			self.input.pop();
+		}
+		if is_key_pressed(KeyCode::Tab) {
+			self.complete(marks);
+		}
+		if is_key_pressed(KeyCode::Up) && !self.typed.is_empty() {
+			let k = self.recalled.map_or(self.typed.len() - 1, |k| k.saturating_sub(1));
+			self.recalled = Some(k);
+			self.input = self.typed[k].clone();
+		}
+		if is_key_pressed(KeyCode::Down) {
+			self.recalled = self.recalled.map(|k| k + 1).filter(|&k| k < self.typed.len());
+			self.input = self.recalled.map_or(String::new(), |k| self.typed[k].clone());
+		}
+		if is_key_pressed(KeyCode::PageUp) {
+			self.scroll = (self.scroll + SHOWN).min(self.messages.len().saturating_sub(SHOWN));
+		}
+		if is_key_pressed(KeyCode::PageDown) {
+			self.scroll = self.scroll.saturating_sub(SHOWN);
+		}
+		if is_key_pressed(KeyCode::Escape) {
+			self.open = false;
+		}
+		if is_key_pressed(KeyCode::Enter) {
+			self.open = false;
+			let line = std::mem::take(&mut self.input);
+			if !line.trim().is_empty() && self.typed.last() != Some(&line) {
+				self.typed.push(line.clone());
+			}
+			if self.chat && !line.starts_with('/') {
+				return (!line.trim().is_empty()).then_some(Entered::Chat(line));
+			}
+			self.say(format!("> {}", line));
+			return Some(Entered::Command(line));
+		}
+		None
+	}
+
+	// Complete the word being typed: a command name first, then waypoint or block names. With
+	// more than one way to go on, complete as far as they agree and list them.
+	fn complete(&mut self, marks: &[&str]) {
+		let start = self.input.rfind(' ').map_or(0, |k| k + 1);
+		let word = self.input[start..].trim_start_matches('/').to_string();
+		let command = self.input.trim_start_matches('/').split_whitespace().next().unwrap_or("");
+		let names: Vec<&str> = if start == 0 {
+			COMMANDS.iter().map(|(c, _)| *c).collect()
+		} else if matches!(command, "go" | "unmark" | "mark" | "guide") {
+			marks.to_vec()
+		} else {
+			block::all().iter().map(|b| b.name).collect()
+		};
+		let candidates: Vec<&str> = names.into_iter().filter(|n| n.starts_with(&word)).collect();
+		let Some(first) = candidates.first() else { return };
+		let common = candidates.iter().fold(first.len(), |n, c| {
+			first.bytes().zip(c.bytes()).take(n).take_while(|(a, b)| a == b).count()
+		});
+		self.input.truncate(self.input.len() - word.len());
+		self.input += &first[..common];
+		if candidates.len() == 1 {
+			self.input.push(' ');
+		} else {
+			self.say(candidates.join("  "));
+		}
+	}
+
+	// the line being typed while open, and the latest messages
+	pub fn draw(&self) {
+		let (w, h) = (screen_width(), screen_height());
+		let now = get_time();
+		let shown: Vec<&String> = self.messages.iter().rev()
+			.skip(if self.open { self.scroll } else { 0 })
+			.take_while(|(t, _)| self.open || now - t < MESSAGE_TIME)
+			.take(SHOWN)
+			.map(|(_, m)| m)
+			.collect();
+		let bottom = h - 100.0;
+		if self.open || !shown.is_empty() {
+			let lines = shown.len() + self.open as usize;
+			draw_rectangle(0.0, bottom - lines as f32 * 20.0, w, lines as f32 * 20.0 + 6.0, Color::new(0.0, 0.0, 0.0, 0.5));
+		}
+		for (k, message) in shown.iter().enumerate() {
+			let y = bottom - (k + self.open as usize) as f32 * 20.0;
+			draw_text(message.as_str(), 4.0, y, 20.0, WHITE);
+		}
+		if self.open {
+			let cursor = if ((now * 2.0) as u64).is_multiple_of(2) { "_" } else { "" };
+			let prompt = if self.chat { format!("say: {}{}", self.input, cursor) } else { format!("/{}{}", self.input.trim_start_matches('/'), cursor) };
+			draw_text(prompt, 4.0, bottom, 20.0, YELLOW);
+		}
+	}
+}
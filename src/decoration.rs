@@ -0,0 +1,141 @@
+// Decoration pass: plant trees, cacti and boulders on the surface after terrain generation
+
+use glam::*;
+use crate::{World, Voxel, furl, step, offset};
+use crate::block::{self, BlockId};
+use crate::biome::{Biome, BiomeMap};
+use crate::prefab::{self, Prefab};
+use crate::rng::Rng;
+
+enum Decoration {
+	Tree,
+	Pine,
+	Cactus,
+	Boulder,
+	House,
+	Ruin,
+}
+
+// chance per surface column of each decoration, by biome
+fn odds(biome: Biome) -> &'static [(Decoration, f32)] {
+	match biome {
+		Biome::Snow => &[(Decoration::Pine, 0.012), (Decoration::Boulder, 0.004)],
+		Biome::Grassland => &[(Decoration::Tree, 0.015), (Decoration::Boulder, 0.002), (Decoration::House, 0.0004), (Decoration::Ruin, 0.0003)],
+		Biome::Desert => &[(Decoration::Cactus, 0.008), (Decoration::Boulder, 0.003), (Decoration::Ruin, 0.0006)],
+	}
+}
+
+// fill an empty voxel, leaving anything already solid alone
+fn place(world: &mut World, i: usize, block: BlockId) {
+	if world.transparent(i) {
+		world.set(i, Voxel::of(block));
+	}
+}
+
+// roughly spherical blob of the given radius around voxel `center`
+fn blob(world: &mut World, center: usize, radius: f32, block: BlockId, rng: &mut Rng) {
+	let r = radius.ceil() as i32;
+	for dx in -r..=r {
+		for dy in -r..=r {
+			for dz in -r..=r {
+				let d = vec3(dx as f32, dy as f32, dz as f32).length();
+				if d <= radius + rng.range(-0.3, 0.3) {
+					let i = offset(world, center, [dx, dy, dz]);
+					place(world, i, block);
+				}
+			}
+		}
+	}
+}
+
+// a vertical run of `height` voxels starting at `base`, returning the voxel above its top
+fn column(world: &mut World, base: usize, height: usize, block: BlockId) -> usize {
+	let mut i = base;
+	for _ in 0..height {
+		place(world, i, block);
+		i = step(world, i, 1, 1);
+	}
+	i
+}
+
+pub fn tree(world: &mut World, base: usize, rng: &mut Rng) {
+	let height = 4 + (rng.f32() * 3.0) as usize;
+	let top = column(world, base, height, block::WOOD);
+	let center = step(world, top, 1, -1);
+	blob(world, center, rng.range(1.8, 2.6), block::LEAVES, rng);
+}
+
+// narrow trunk with layers of needles shrinking toward the top
+fn pine(world: &mut World, base: usize, rng: &mut Rng) {
+	let height = 5 + (rng.f32() * 3.0) as usize;
+	let top = column(world, base, height, block::WOOD);
+	let mut layer = offset(world, top, [0, -(height as i32) / 2, 0]);
+	let layers = height / 2 + 1;
+	for k in 0..layers {
+		let r = (layers - k) as i32 / 2 + 1;
+		for dx in -r..=r {
+			for dz in -r..=r {
+				if dx.abs() + dz.abs() <= r {
+					let i = offset(world, layer, [dx, 0, dz]);
+					place(world, i, block::PINE_NEEDLES);
+				}
+			}
+		}
+		layer = step(world, layer, 1, 1);
+	}
+	place(world, layer, block::PINE_NEEDLES);
+}
+
+fn cactus(world: &mut World, base: usize, rng: &mut Rng) {
+	let height = 2 + (rng.f32() * 3.0) as usize;
+	column(world, base, height, block::CACTUS);
+	// sometimes an arm that sticks out sideways and turns upward
+	if height >= 3 && rng.f32() < 0.5 {
+		let axis = if rng.f32() < 0.5 { 0 } else { 2 };
+		let sign = if rng.f32() < 0.5 { 1 } else { -1 };
+		let elbow = step(world, step(world, base, 1, 1), axis, sign);
+		column(world, elbow, 2, block::CACTUS);
+	}
+}
+
+fn boulder(world: &mut World, base: usize, rng: &mut Rng) {
+	blob(world, base, rng.range(0.8, 1.8), block::BOULDER, rng);
+}
+
+// a prefab in a random orientation, standing on the surface
+fn structure(world: &mut World, base: usize, prefab: &Prefab, rng: &mut Rng) {
+	let turns = rng.next_u64() % 4;
+	(0..turns).fold(prefab.clone(), |p, _| p.rotated()).place(world, base);
+}
+
+// `surface[x*nz + z]` is the height of the topmost solid voxel of each column, if any.
+// Decorations are built by walking the neighbor graph, so they wrap across the world's edges.
+pub fn decorate(world: &mut World, nx: usize, ny: usize, nz: usize, surface: &[Option<usize>], biomes: &BiomeMap, rng: &mut Rng) {
+	let house = Prefab::parse(prefab::HOUSE).expect("built-in house prefab");
+	let ruin = Prefab::parse(prefab::RUIN).expect("built-in ruin prefab");
+	for x in 0..nx {
+		for z in 0..nz {
+			let Some(top) = surface[x*nz + z] else { continue };
+			let base = furl(x, (top + 1) % ny, z, ny, nz);
+			// nothing grows under water
+			if world.voxel(base).block != block::AIR {
+				continue;
+			}
+			let mut roll = rng.f32();
+			for (decoration, chance) in odds(biomes.biome(x, z)) {
+				if roll < *chance {
+					match decoration {
+						Decoration::Tree => tree(world, base, rng),
+						Decoration::Pine => pine(world, base, rng),
+						Decoration::Cactus => cactus(world, base, rng),
+						Decoration::Boulder => boulder(world, base, rng),
+						Decoration::House => structure(world, base, &house, rng),
+						Decoration::Ruin => structure(world, base, &ruin, rng),
+					}
+					break;
+				}
+				roll -= chance;
+			}
+		}
+	}
+}
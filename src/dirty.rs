@@ -0,0 +1,63 @@
+// Dirty regions: the chunks with voxels set in them since they were last taken, kept by the
+// world as it changes. Whatever is worked out from the voxels and kept between frames, like
+// the minimap, the statistics and the baked light, takes them once a frame to redo only what's
+// changed rather than going over the world again. Replacing the whole world at once, or
+// turning its gravity, marks everything.
+//
+// Edits, which the network, scripts and the world's own goings-on react to, are kept apart from
+// this, since chunks a streamed world loads change its voxels without being edits.
+
+use std::collections::HashSet;
+
+#[derive(Clone, Default)]
+pub struct Dirty {
+	chunks: HashSet<usize>,
+	everything: bool,
+}
+
+impl Dirty {
+	// a voxel in chunk c was set
+	pub fn mark(&mut self, c: usize) {
+		if !self.everything {
+			self.chunks.insert(c);
+		}
+	}
+
+	// everything changed at once, so there's no use keeping track of which voxels did
+	pub fn mark_everything(&mut self) {
+		self.everything = true;
+		self.chunks.clear();
+	}
+
+	// take in what's been marked in `other` too
+	#[cfg(feature = "graphics")]
+	pub fn merge(&mut self, other: &Dirty) {
+		if other.everything {
+			self.mark_everything();
+		} else if !self.everything {
+			self.chunks.extend(&other.chunks);
+		}
+	}
+
+	#[cfg(feature = "graphics")]
+	pub fn is_empty(&self) -> bool {
+		!self.everything && self.chunks.is_empty()
+	}
+
+	#[cfg(feature = "graphics")]
+	pub fn everything(&self) -> bool {
+		self.everything
+	}
+
+	// whether chunk c is marked, or everything is
+	#[cfg(feature = "graphics")]
+	pub fn contains(&self, c: usize) -> bool {
+		self.everything || self.chunks.contains(&c)
+	}
+
+	// the chunks with voxels set in them, unless everything was
+	#[cfg(feature = "graphics")]
+	pub fn chunks(&self) -> impl Iterator<Item = usize> + '_ {
+		self.chunks.iter().copied()
+	}
+}
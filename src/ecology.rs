@@ -0,0 +1,111 @@
+// Ecology: the world slowly living by itself around the players. The voxels the scheduler
+// picks for random ticks are looked at, so any one near a player is looked at about once a
+// minute. Grass spreads onto dirt around it with air above and dies back to dirt when it's
+// covered over, saplings on the ground grow into trees when there's room, and leaves with no
+// wood near enough to hold them up wither away, now and then dropping a sapling.
+
+use glam::*;
+use crate::{World, Voxel, step, offset};
+use crate::block;
+use crate::decoration;
+use crate::entity::{Entity, Kind};
+use crate::rng::Rng;
+
+// voxels around grass it tries to spread to when it's looked at
+const SPREAD_TRIES: usize = 4;
+// chance a sapling that's looked at grows, if it can, and the room it needs above it
+const GROW_CHANCE: f32 = 0.3;
+const ROOM: usize = 7;
+// steps through leaves that leaves reach for wood, and the chance that withering leaves drop a
+// sapling
+const REACH: usize = 4;
+const SAPLING_CHANCE: f32 = 0.05;
+
+pub struct Ecology {
+	rng: Rng,
+}
+
+impl Ecology {
+	pub fn new(seed: u64) -> Ecology {
+		Ecology { rng: Rng::new(seed ^ 0xec0) }
+	}
+
+	// look at the voxels picked for random ticks
+	pub fn tick(&mut self, world: &mut World, entities: &mut Vec<Entity>, voxels: &[usize]) {
+		for &i in voxels {
+			match world.voxel(i).block {
+				block::GRASS => self.grass(world, i),
+				block::SAPLING => self.sapling(world, i),
+				block::LEAVES | block::PINE_NEEDLES => self.leaves(world, entities, i),
+				_ => {},
+			}
+		}
+	}
+
+	// grass in voxel i dying back if something solid covers it, or spreading to dirt around it
+	fn grass(&mut self, world: &mut World, i: usize) {
+		if !world.transparent(above(world, i)) {
+			world.set(i, Voxel::of(block::DIRT));
+			return;
+		}
+		for _ in 0..SPREAD_TRIES {
+			let d = [(); 3].map(|_| (self.rng.next_u64() % 3) as i32 - 1);
+			let j = offset(world, i, d);
+			let air = above(world, j);
+			if world.voxel(j).block == block::DIRT && world.transparent(air) && !world.liquid(air) {
+				world.set(j, Voxel::of(block::GRASS));
+			}
+		}
+	}
+
+	// a sapling in voxel i growing into a tree, if it's on the ground with room above it, upward
+	// along y like the trees the world's made with
+	fn sapling(&mut self, world: &mut World, i: usize) {
+		if self.rng.f32() >= GROW_CHANCE || !matches!(world.voxel(step(world, i, 1, -1)).block, block::DIRT | block::GRASS) {
+			return;
+		}
+		let mut above = step(world, i, 1, 1);
+		for _ in 0..ROOM {
+			if !world.transparent(above) || world.liquid(above) {
+				return;
+			}
+			above = step(world, above, 1, 1);
+		}
+		world.set(i, Voxel::of(block::AIR));
+		decoration::tree(world, i, &mut self.rng);
+	}
+
+	// leaves in voxel i withering if there's no wood within REACH of them through other leaves
+	fn leaves(&mut self, world: &mut World, entities: &mut Vec<Entity>, i: usize) {
+		let kind = world.voxel(i).block;
+		let mut seen = vec![i];
+		let mut edge = vec![i];
+		for _ in 0..REACH {
+			let mut next = vec![];
+			for &j in &edge {
+				for (axis, sign) in [(0, -1), (0, 1), (1, -1), (1, 1), (2, -1), (2, 1)] {
+					let k = step(world, j, axis, sign);
+					match world.voxel(k).block {
+						block::WOOD => return,
+						b if b == kind && !seen.contains(&k) => {
+							seen.push(k);
+							next.push(k);
+						},
+						_ => {},
+					}
+				}
+			}
+			edge = next;
+		}
+		world.set(i, Voxel::of(block::AIR));
+		if self.rng.f32() < SAPLING_CHANCE {
+			entities.push(Entity::new(Kind::Item(block::SAPLING), i, Vec3::splat(0.5)));
+		}
+	}
+}
+
+// the voxel above voxel i, against gravity
+fn above(world: &World, i: usize) -> usize {
+	let gravity = world.gravity_at(i);
+	step(world, i, gravity.axis, -gravity.sign)
+}
@@ -0,0 +1,61 @@
+// Camera effects: the view bobs while walking, dips on landing and rolls a little when
+// stepping sideways. They only move where the view is drawn from, never the body, so they
+// can't change how the player moves or collides.
+
+use macroquad::prelude::*;
+use crate::approach;
+
+// bobs per voxel walked, and how far the view bobs up and down
+const BOB_RATE: f32 = 1.1;
+const BOB_HEIGHT: f32 = 0.04;
+// how far the view dips on landing per voxel per tick fallen, at most, and how quickly it
+// comes back up, per second
+const DIP_PER_SPEED: f32 = 0.6;
+const MAX_DIP: f32 = 0.2;
+const DIP_RATE: f32 = 8.0;
+// radians rolled stepping sideways, and how quickly the roll follows, per second
+const ROLL: f32 = 0.03;
+const ROLL_RATE: f32 = 8.0;
+
+#[derive(Default)]
+pub struct Effects {
+	// how far through a bob, in radians, and how much of a bob there is, from 0 to 1
+	phase: f32,
+	bob: f32,
+	dip: f32,
+	roll: f32,
+}
+
+impl Effects {
+	// Take a tick of walking `walked` voxels across the ground, or of not being on it. Returns
+	// whether a foot came down, at the bottom of a bob.
+	pub fn tick(&mut self, walked: f32, grounded: bool) -> bool {
+		if !grounded {
+			return false;
+		}
+		let before = self.phase;
+		self.phase = (self.phase + walked * BOB_RATE * std::f32::consts::TAU) % std::f32::consts::TAU;
+		// the bob is lowest at every half turn
+		(before / std::f32::consts::PI).floor() != (self.phase / std::f32::consts::PI).floor()
+	}
+
+	// landing at `speed` voxels per tick
+	pub fn land(&mut self, speed: f32) {
+		self.dip = self.dip.max((speed * DIP_PER_SPEED).min(MAX_DIP));
+	}
+
+	// Ease toward this frame's bobbing and rolling: `walking` while walking on the ground, and
+	// `strafe` from -1 stepping left to 1 stepping right.
+	pub fn update(&mut self, walking: bool, strafe: f32) {
+		self.bob += (if walking { 1.0 } else { 0.0 } - self.bob) * approach(DIP_RATE);
+		self.dip -= self.dip * approach(DIP_RATE);
+		self.roll += (strafe * ROLL - self.roll) * approach(ROLL_RATE);
+	}
+
+	// the view's position, up and right with the effects on, for a body standing up along `rise`
+	pub fn apply(&self, position: Vec3, look: Vec3, up: Vec3, right: Vec3, rise: Vec3) -> (Vec3, Vec3, Vec3) {
+		let height = BOB_HEIGHT * self.bob * self.phase.sin().abs() - self.dip;
+		let roll = Quat::from_axis_angle(look, self.roll);
+		(position + height * rise, roll * up, roll * right)
+	}
+}
@@ -0,0 +1,210 @@
+// Entities: things that move through the world on their own, like the player but without a
+// camera. Each sits somewhere in a voxel of the graph, falls with the gravity there and collides
+// as a small box, and is drawn as a flat disc facing the camera. Projectiles are too fast and too
+// small for boxes, so they fly as points, traced from voxel to voxel the way rays are.
+
+use glam::*;
+use crate::{World, settle, step, lattice_intersect};
+use crate::block::BlockId;
+#[cfg(feature = "graphics")]
+use crate::block;
+use crate::player::{self, Shape};
+
+// fastest an entity moves along any axis, in voxels per tick
+const MAX_SPEED: f32 = 1.0;
+// fraction of sideways velocity kept each tick while resting on the ground
+const GROUND_FRICTION: f32 = 0.8;
+// landing slower than this, in voxels per tick, doesn't bounce
+const MIN_BOUNCE: f32 = 0.05;
+// how far short of a solid face a projectile stops
+const SKIN: f32 = 0.01;
+
+// the server makes items and lit TNT, but none of the kinds only a player or a mob makes
+#[cfg_attr(not(feature = "graphics"), allow(dead_code))]
+#[derive(Clone, Copy, PartialEq)]
+pub enum Kind {
+	// marks where the player respawns
+	SpawnMarker,
+	// a broken block lying around to be picked up
+	Item(BlockId),
+	// a creature wandering about, steered by the mob module
+	Mob,
+	// shot by the player, sticking into the voxel it hits until that voxel is gone
+	Arrow { stuck: Option<usize> },
+	// thrown by the player, breaking the voxel it hits and dropping as an item
+	Thrown(BlockId),
+	// a block of TNT about to go off, in this many ticks
+	Lit { fuse: u32 },
+	// a block come loose, turning back into one where it lands
+	Falling(BlockId),
+	// someone else playing in the same world, moved by their own game
+	Player,
+}
+
+// what an entity did to the world in a tick, for the caller to carry out
+pub enum Impact {
+	// break this voxel
+	Smash(usize),
+	// blow up around this voxel
+	Explode(usize),
+	// set this voxel to this block
+	Land(usize, BlockId),
+}
+
+impl Kind {
+	// half the width of its box and of the disc it's drawn as
+	fn radius(&self) -> f32 {
+		match self {
+			Kind::SpawnMarker => 0.2,
+			Kind::Item(_) => 0.15,
+			Kind::Mob | Kind::Player => 0.3,
+			Kind::Arrow { .. } => 0.08,
+			Kind::Thrown(_) => 0.15,
+			Kind::Lit { .. } => 0.45,
+			// just under a voxel, to fall down shafts one voxel wide
+			Kind::Falling(_) => 0.49,
+		}
+	}
+
+	#[cfg(feature = "graphics")]
+	fn color(&self) -> Vec4 {
+		match self {
+			Kind::SpawnMarker => vec4(1.0, 0.85, 0.2, 1.0),
+			Kind::Item(block) | Kind::Thrown(block) | Kind::Falling(block) => block::def(*block).color,
+			Kind::Mob => vec4(0.9, 0.6, 0.65, 1.0),
+			Kind::Player => vec4(0.4, 0.6, 0.95, 1.0),
+			Kind::Arrow { .. } => vec4(0.85, 0.85, 0.8, 1.0),
+			// flashing faster as the fuse burns down
+			Kind::Lit { fuse } if ((*fuse as f32).sqrt() as u32).is_multiple_of(2) => vec4(1.0, 1.0, 1.0, 1.0),
+			Kind::Lit { .. } => block::def(block::TNT).color,
+		}
+	}
+
+	// fraction of its speed kept bouncing off the ground
+	fn bounciness(&self) -> f32 {
+		match self {
+			Kind::SpawnMarker | Kind::Mob | Kind::Arrow { .. } | Kind::Thrown(_) | Kind::Lit { .. } | Kind::Falling(_) | Kind::Player => 0.0,
+			Kind::Item(_) => 0.4,
+		}
+	}
+}
+
+#[derive(Clone)]
+pub struct Entity {
+	pub kind: Kind,
+	pub i: usize,
+	// position within voxel i, like the camera's
+	pub position: Vec3,
+	// voxels per tick
+	pub velocity: Vec3,
+	// whether it ended the last tick resting on the ground
+	pub grounded: bool,
+	// voxels still to walk through, for those that find their own way
+	#[cfg_attr(not(feature = "graphics"), allow(dead_code))]
+	pub path: Vec<usize>,
+	// used up, to be removed once the tick is over
+	pub spent: bool,
+}
+
+impl Entity {
+	pub fn new(kind: Kind, i: usize, position: Vec3) -> Entity {
+		Entity { kind, i, position, velocity: Vec3::ZERO, grounded: false, path: vec![], spent: false }
+	}
+
+	pub fn radius(&self) -> f32 {
+		self.kind.radius()
+	}
+
+	#[cfg(feature = "graphics")]
+	pub fn color(&self) -> Vec4 {
+		self.kind.color()
+	}
+
+	// one tick of falling, sliding and coming to rest
+	pub fn tick(&mut self, world: &World) -> Option<Impact> {
+		match self.kind {
+			Kind::Arrow { stuck: Some(j) } if !world.transparent(j) => return None,
+			Kind::Arrow { .. } | Kind::Thrown(_) => return self.fly(world),
+			Kind::Lit { fuse: 0 } => {
+				self.spent = true;
+				return Some(Impact::Explode(self.i));
+			},
+			Kind::Lit { fuse } => self.kind = Kind::Lit { fuse: fuse - 1 },
+			_ => {},
+		}
+		let gravity = world.gravity_at(self.i);
+		let up = gravity.up();
+		self.velocity = (self.velocity - gravity.strength * up).clamp(Vec3::splat(-MAX_SPEED), Vec3::splat(MAX_SPEED));
+		let r = self.radius();
+		let shape = Shape { width: 2.0 * r, height: 2.0 * r, eye: r };
+		let (position, blocked) = player::move_shape(world, self.i, self.position, self.velocity, &shape);
+		let landing = -self.velocity.dot(up);
+		self.grounded = blocked[gravity.axis] && landing >= 0.0;
+		for (axis, &b) in blocked.iter().enumerate() {
+			if b {
+				self.velocity[axis] = 0.0;
+			}
+		}
+		if blocked[gravity.axis] && landing > MIN_BOUNCE {
+			self.velocity += landing * self.kind.bounciness() * up;
+		} else if blocked[gravity.axis] {
+			let fall = self.velocity.dot(up) * up;
+			self.velocity = fall + (self.velocity - fall) * GROUND_FRICTION;
+		}
+		(self.i, self.position) = settle(world, self.i, position);
+		match self.kind {
+			Kind::Falling(block) if self.grounded => {
+				self.spent = true;
+				Some(Impact::Land(self.i, block))
+			},
+			_ => None,
+		}
+	}
+
+	// a tick of a projectile's flight, ending against the first solid voxel in its way
+	fn fly(&mut self, world: &World) -> Option<Impact> {
+		let gravity = world.gravity_at(self.i);
+		self.velocity = (self.velocity - gravity.strength * gravity.up()).clamp(Vec3::splat(-MAX_SPEED), Vec3::splat(MAX_SPEED));
+		let (i, position, hit) = sweep(world, self.i, self.position, self.velocity);
+		(self.i, self.position) = (i, position);
+		let hit = hit?;
+		self.velocity = Vec3::ZERO;
+		match self.kind {
+			Kind::Thrown(block) => {
+				self.kind = Kind::Item(block);
+				Some(Impact::Smash(hit))
+			},
+			_ => {
+				self.kind = Kind::Arrow { stuck: Some(hit) };
+				None
+			},
+		}
+	}
+}
+
+// Move a point at `position` in voxel i by `delta`, crossing one voxel face at a time like a ray
+// does. Returns where it ended up and the solid voxel it stopped against, if it did.
+fn sweep(world: &World, i: usize, position: Vec3, delta: Vec3) -> (usize, Vec3, Option<usize>) {
+	let (mut i, mut position) = (i, position);
+	let length = delta.length();
+	if length == 0.0 {
+		return (i, position, None);
+	}
+	let mut left = 1.0;
+	loop {
+		let (next, k, dist) = lattice_intersect(position, delta);
+		let t = dist / length;
+		if t >= left {
+			return (i, position + left * delta, None);
+		}
+		left -= t;
+		let axis = k.iter().position(|&s| s != 0).unwrap();
+		let j = step(world, i, axis, k[axis]);
+		let face = next + vec3(k[0] as f32, k[1] as f32, k[2] as f32);
+		if !world.transparent(j) {
+			// rest just short of the face
+			return (i, face - SKIN * delta / length, Some(j));
+		}
+		(i, position) = (j, next);
+	}
+}
@@ -0,0 +1,158 @@
+// Erosion: wearing down a heightfield before it's turned into voxels, carving valleys where
+// water would run and easing slopes too steep to stand. Heights are indexed [x*nz + z] in
+// voxels, and everything wraps around the edges like the torus does, so a valley running off
+// one side carries on from the other.
+//
+// Water is simulated as droplets, each landing somewhere at random and running downhill,
+// picking up ground while it speeds up and has room for more, and dropping it where it slows
+// or climbs. Then the ground slumps: wherever it's steeper than it can hold, some slides down.
+
+use glam::*;
+use crate::Progress;
+use crate::rng::Rng;
+
+// droplets per column of the world, and the most steps each runs
+const DROPLETS: f32 = 1.5;
+const LIFETIME: usize = 40;
+// how much of its way a droplet keeps each step rather than turning downhill
+const INERTIA: f32 = 0.1;
+// how much ground a droplet can carry for its slope, speed and water, and the least slope it's
+// worked out for, so droplets on the flat still carry some
+const CAPACITY: f32 = 4.0;
+const MIN_SLOPE: f32 = 0.01;
+// fractions of the difference from what it can carry picked up and dropped each step
+const ERODE: f32 = 0.3;
+const DEPOSIT: f32 = 0.3;
+// water lost each step, and how much a drop downhill speeds it up
+const EVAPORATE: f32 = 0.02;
+const GRAVITY: f32 = 4.0;
+// columns away a droplet digs from, so it wears a channel rather than a rut
+const RADIUS: i32 = 2;
+// the steepest difference between neighbors the ground holds, in voxels, how much of what's
+// beyond it slides each round, and rounds of sliding
+const TALUS: f32 = 1.2;
+const SLUMP: f32 = 0.25;
+const SLUMP_ROUNDS: usize = 20;
+
+// wear down `heights`, a field nx by nz
+pub fn erode(heights: &mut [f32], nx: usize, nz: usize, rng: &mut Rng, progress: &Progress) {
+	let droplets = (DROPLETS * (nx*nz) as f32) as usize;
+	// in batches, so progress isn't counted for every droplet
+	let batch = 1000;
+	progress.begin("eroding", droplets.div_ceil(batch) + SLUMP_ROUNDS);
+	for n in 0..droplets {
+		rain(heights, nx, nz, rng);
+		if n % batch == batch - 1 {
+			progress.advance();
+		}
+	}
+	for _ in 0..SLUMP_ROUNDS {
+		slump(heights, nx, nz);
+		progress.advance();
+	}
+}
+
+// the index of column (x, z), wrapped around the edges
+fn column(x: i32, z: i32, nx: usize, nz: usize) -> usize {
+	x.rem_euclid(nx as i32) as usize * nz + z.rem_euclid(nz as i32) as usize
+}
+
+// the four columns around point p and how much of it each gets, adding up to 1
+fn corners(p: Vec2, nx: usize, nz: usize) -> [(usize, f32); 4] {
+	let (cell, f) = (p.floor(), p - p.floor());
+	let (x, z) = (cell.x as i32, cell.y as i32);
+	[
+		(column(x, z, nx, nz), (1.0 - f.x) * (1.0 - f.y)),
+		(column(x + 1, z, nx, nz), f.x * (1.0 - f.y)),
+		(column(x, z + 1, nx, nz), (1.0 - f.x) * f.y),
+		(column(x + 1, z + 1, nx, nz), f.x * f.y),
+	]
+}
+
+// the height at point p, between the columns, and which way is uphill
+fn height_and_slope(heights: &[f32], p: Vec2, nx: usize, nz: usize) -> (f32, Vec2) {
+	let [(a, _), (b, _), (c, _), (d, _)] = corners(p, nx, nz);
+	let f = p - p.floor();
+	let (ha, hb, hc, hd) = (heights[a], heights[b], heights[c], heights[d]);
+	let slope = vec2((hb - ha) * (1.0 - f.y) + (hd - hc) * f.y, (hc - ha) * (1.0 - f.x) + (hd - hb) * f.x);
+	let height = ha * (1.0 - f.x) * (1.0 - f.y) + hb * f.x * (1.0 - f.y) + hc * (1.0 - f.x) * f.y + hd * f.x * f.y;
+	(height, slope)
+}
+
+// the columns within RADIUS of point p and how much of what's dug there each gives, more the
+// nearer they are, adding up to 1
+fn brush(p: Vec2, nx: usize, nz: usize) -> Vec<(usize, f32)> {
+	let (x, z) = (p.x.floor() as i32, p.y.floor() as i32);
+	let mut columns = vec![];
+	for dx in -RADIUS..=RADIUS + 1 {
+		for dz in -RADIUS..=RADIUS + 1 {
+			let distance = (vec2((x + dx) as f32, (z + dz) as f32) - p).length();
+			if distance < RADIUS as f32 {
+				columns.push((column(x + dx, z + dz, nx, nz), RADIUS as f32 - distance));
+			}
+		}
+	}
+	let total: f32 = columns.iter().map(|(_, w)| w).sum();
+	columns.iter().map(|&(k, w)| (k, w / total)).collect()
+}
+
+// one droplet landing at random and running downhill until it stops or dries up
+fn rain(heights: &mut [f32], nx: usize, nz: usize, rng: &mut Rng) {
+	let size = vec2(nx as f32, nz as f32);
+	let mut p = vec2(rng.range(0.0, size.x), rng.range(0.0, size.y));
+	let (mut way, mut speed, mut water, mut sediment) = (Vec2::ZERO, 1.0_f32, 1.0_f32, 0.0_f32);
+	for _ in 0..LIFETIME {
+		let (height, slope) = height_and_slope(heights, p, nx, nz);
+		way = way * INERTIA - slope * (1.0 - INERTIA);
+		if way.length_squared() < 1e-8 {
+			// on the flat, with nowhere to run
+			break;
+		}
+		way = way.normalize();
+		let next = (p + way).rem_euclid(size);
+		let drop = height_and_slope(heights, next, nx, nz).0 - height;
+		let capacity = (-drop).max(MIN_SLOPE) * speed * water * CAPACITY;
+		if drop > 0.0 || sediment > capacity {
+			// climbing, fill in the dip behind it as far as it can, or drop what it can't carry
+			let amount = if drop > 0.0 { drop.min(sediment) } else { (sediment - capacity) * DEPOSIT };
+			sediment -= amount;
+			for (k, share) in corners(p, nx, nz) {
+				heights[k] += amount * share;
+			}
+		} else {
+			// never digging deeper than the drop, so it doesn't carve pits
+			let amount = ((capacity - sediment) * ERODE).min(-drop);
+			sediment += amount;
+			for (k, share) in brush(p, nx, nz) {
+				heights[k] -= amount * share;
+			}
+		}
+		speed = (speed * speed - drop * GRAVITY).max(0.0).sqrt();
+		water *= 1.0 - EVAPORATE;
+		p = next;
+	}
+	// what it still carries is left where it stops
+	for (k, share) in corners(p, nx, nz) {
+		heights[k] += sediment * share;
+	}
+}
+
+// a round of the ground sliding down wherever it's steeper than TALUS
+fn slump(heights: &mut [f32], nx: usize, nz: usize) {
+	let mut moved = vec![0.0; heights.len()];
+	for x in 0..nx as i32 {
+		for z in 0..nz as i32 {
+			let k = column(x, z, nx, nz);
+			for (dx, dz) in [(1, 0), (0, 1)] {
+				let n = column(x + dx, z + dz, nx, nz);
+				let difference = heights[k] - heights[n];
+				let slide = (difference.abs() - TALUS).max(0.0) * SLUMP * difference.signum();
+				moved[k] -= slide;
+				moved[n] += slide;
+			}
+		}
+	}
+	for (height, moved) in heights.iter_mut().zip(moved) {
+		*height += moved;
+	}
+}
@@ -0,0 +1,172 @@
+// Events: what happens in the game, published on a bus by whatever made it happen and taken from
+// it by whatever acts on it, so neither has to know about the other. Parts of the game subscribe
+// to the topics they act on, and each is given its own copy of every event on them, to take when
+// it's ready: the simulation, the network, syncing and scripts each take the edits each tick, for
+// instance. An event nobody's subscribed to is dropped.
+
+use crate::block::BlockId;
+use crate::entity::Entity;
+use crate::input::Action;
+
+#[derive(Clone)]
+pub enum Event {
+	// voxel i was changed
+	Edit(usize),
+	// a console command to run, typed or from a script
+	Command(String),
+	// a message to show in the console
+	Say(String),
+	// a line typed for the other players to read
+	Chat(String),
+	// a sound to play, from the voxel it came from unless it's the player's own, which nothing
+	// takes without the `audio` feature
+	#[cfg_attr(not(feature = "audio"), allow(dead_code))]
+	Sound(Cue, Option<usize>),
+	// a key or button was pressed for this, this frame
+	Action(Action),
+	// a new entity, to join the others
+	Spawn(Entity),
+}
+
+// what a sound is for, played by the sound module when the game's built with the `audio` feature
+// and otherwise only published, so the blocks go unread
+#[cfg_attr(not(feature = "audio"), allow(dead_code))]
+#[derive(Clone, Copy)]
+pub enum Cue {
+	// a foot coming down on a block
+	Step(BlockId),
+	Break(BlockId),
+	Place(BlockId),
+	Explode,
+	Jump,
+	// landing on a block
+	Land(BlockId),
+}
+
+// what an event is about, for subscribing to
+#[derive(Clone, Copy, PartialEq)]
+pub enum Topic {
+	Edit,
+	Command,
+	Say,
+	Chat,
+	Sound,
+	Action,
+	Spawn,
+}
+
+impl Event {
+	pub fn topic(&self) -> Topic {
+		match self {
+			Event::Edit(_) => Topic::Edit,
+			Event::Command(_) => Topic::Command,
+			Event::Say(_) => Topic::Say,
+			Event::Chat(_) => Topic::Chat,
+			Event::Sound(..) => Topic::Sound,
+			Event::Action(_) => Topic::Action,
+			Event::Spawn(_) => Topic::Spawn,
+		}
+	}
+}
+
+// one subscription to the bus, handed back to take what's been published on it since
+pub struct Subscriber(usize);
+
+#[derive(Default)]
+pub struct Bus {
+	// the topics each subscriber takes, and what's been published on them it has yet to
+	subscribers: Vec<(Vec<Topic>, Vec<Event>)>,
+}
+
+impl Bus {
+	// subscribe to `topics`, from now on
+	pub fn subscribe(&mut self, topics: &[Topic]) -> Subscriber {
+		self.subscribers.push((topics.to_vec(), vec![]));
+		Subscriber(self.subscribers.len() - 1)
+	}
+
+	// hand `event` to every subscriber to its topic, or to nobody if none are
+	pub fn publish(&mut self, event: Event) {
+		let topic = event.topic();
+		for (topics, queue) in &mut self.subscribers {
+			if topics.contains(&topic) {
+				queue.push(event.clone());
+			}
+		}
+	}
+
+	pub fn extend(&mut self, events: impl IntoIterator<Item = Event>) {
+		for event in events {
+			self.publish(event);
+		}
+	}
+
+	pub fn say(&mut self, text: impl Into<String>) {
+		self.publish(Event::Say(text.into()));
+	}
+
+	// what's been published for `subscriber` since it last took, oldest first
+	pub fn take(&mut self, subscriber: &Subscriber) -> Vec<Event> {
+		std::mem::take(&mut self.subscribers[subscriber.0].1)
+	}
+
+	// what's been published for `subscriber` that `pick` turns into a T, oldest first
+	fn pick<T>(&mut self, subscriber: &Subscriber, pick: impl FnMut(Event) -> Option<T>) -> Vec<T> {
+		self.take(subscriber).into_iter().filter_map(pick).collect()
+	}
+
+	pub fn edits(&mut self, subscriber: &Subscriber) -> Vec<usize> {
+		self.pick(subscriber, |e| match e { Event::Edit(i) => Some(i), _ => None })
+	}
+
+	pub fn commands(&mut self, subscriber: &Subscriber) -> Vec<String> {
+		self.pick(subscriber, |e| match e { Event::Command(line) => Some(line), _ => None })
+	}
+
+	pub fn said(&mut self, subscriber: &Subscriber) -> Vec<String> {
+		self.pick(subscriber, |e| match e { Event::Say(text) => Some(text), _ => None })
+	}
+
+	pub fn chats(&mut self, subscriber: &Subscriber) -> Vec<String> {
+		self.pick(subscriber, |e| match e { Event::Chat(text) => Some(text), _ => None })
+	}
+
+	#[cfg(feature = "audio")]
+	pub fn sounds(&mut self, subscriber: &Subscriber) -> Vec<(Cue, Option<usize>)> {
+		self.pick(subscriber, |e| match e { Event::Sound(cue, from) => Some((cue, from)), _ => None })
+	}
+
+	pub fn actions(&mut self, subscriber: &Subscriber) -> Vec<Action> {
+		self.pick(subscriber, |e| match e { Event::Action(action) => Some(action), _ => None })
+	}
+
+	pub fn spawns(&mut self, subscriber: &Subscriber) -> Vec<Entity> {
+		self.pick(subscriber, |e| match e { Event::Spawn(entity) => Some(entity), _ => None })
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn every_subscriber_to_a_topic_gets_each_event_once() {
+		let mut bus = Bus::default();
+		let (simulation, network) = (bus.subscribe(&[Topic::Edit]), bus.subscribe(&[Topic::Edit, Topic::Say]));
+		let console = bus.subscribe(&[Topic::Say]);
+		bus.publish(Event::Edit(3));
+		bus.say("hello");
+		bus.publish(Event::Edit(4));
+		// with nobody to take it
+		bus.publish(Event::Chat("hi".to_string()));
+		assert_eq!(bus.edits(&simulation), [3, 4]);
+		assert_eq!(bus.edits(&simulation), []);
+		assert_eq!(bus.take(&network).len(), 3);
+		assert_eq!(bus.said(&console), ["hello"]);
+		// a subscriber only gets what's published after it subscribes
+		let late = bus.subscribe(&[Topic::Edit]);
+		bus.publish(Event::Edit(5));
+		assert_eq!(bus.edits(&late), [5]);
+		assert_eq!(bus.edits(&simulation), [5]);
+	}
+}
@@ -0,0 +1,75 @@
+// Explosions. The blast spreads out over the neighbor graph from the voxel it starts in, keeping
+// track of how far it has come along each axis, so it clears a ball even where that ball
+// crosses a seam or wraps around the world.
+
+use glam::*;
+use crate::{World, Voxel, shade};
+use crate::block;
+use crate::entity::{Entity, Kind};
+use crate::particle::Particles;
+use crate::rng::Rng;
+
+// radius of the ball cleared, in voxels
+pub const RADIUS: f32 = 3.5;
+// things are pushed away by up to this much, in voxels per tick, at the center
+const PUSH: f32 = 0.6;
+// and not at all from this far out
+const REACH: f32 = 2.0 * RADIUS;
+// fraction of the cleared voxels thrown out as items, and separately as specks of debris
+const DEBRIS: f32 = 0.2;
+// bits of smoke in the cloud left behind
+const SMOKE: usize = 60;
+// ticks before a block of TNT caught in a blast goes off itself
+const CHAIN_FUSE: (f32, f32) = (10.0, 30.0);
+// ticks from lighting a block of TNT to its explosion
+const FUSE: u32 = 80;
+
+// light the block of TNT in voxel i, which leaves its place to go off a little later
+pub fn light(world: &mut World, entities: &mut Vec<Entity>, i: usize) {
+	entities.push(Entity::new(Kind::Lit { fuse: FUSE }, i, Vec3::splat(0.5)));
+	world.set(i, Voxel::of(block::AIR));
+}
+
+// Blow up everything within RADIUS of the center of voxel `center`. Blocks of TNT in the way
+// are lit, some of the rest fly out as items or debris, added to `spawned`, and those and the
+// `entities` nearby are pushed away.
+pub fn explode(world: &mut World, entities: &mut [Entity], spawned: &mut Vec<Entity>, particles: &mut Particles, center: usize, rng: &mut Rng) {
+	particles.smoke(center, SMOKE);
+	let blast: Vec<(usize, Vec3)> = world.iter_region(center, RADIUS).collect();
+	for (i, d) in blast {
+		let block = world.voxel(i).block;
+		if block == block::TNT {
+			let mut lit = Entity::new(Kind::Lit { fuse: rng.range(CHAIN_FUSE.0, CHAIN_FUSE.1) as u32 }, i, Vec3::splat(0.5));
+			lit.velocity = push(d);
+			spawned.push(lit);
+		} else if !world.transparent(i) && !world.liquid(i) {
+			if rng.f32() < DEBRIS {
+				let mut item = Entity::new(Kind::Item(block), i, Vec3::splat(0.5));
+				item.velocity = push(d);
+				spawned.push(item);
+			}
+			if rng.f32() < DEBRIS {
+				particles.debris(i, shade(world, i));
+			}
+		}
+		if !world.liquid(i) {
+			world.set(i, Voxel::of(block::AIR));
+		}
+	}
+	for entity in entities.iter_mut().chain(spawned.iter_mut()) {
+		// walls the blast didn't break shelter what's behind them
+		if world.visible(center, Vec3::splat(0.5), entity.i, entity.position) {
+			entity.velocity += push(world.direction(center, Vec3::splat(0.5), entity.i, entity.position));
+		}
+	}
+}
+
+// Velocity given to something at `d` from the center of a blast, away from it and weaker
+// further out.
+pub fn push(d: Vec3) -> Vec3 {
+	let distance = d.length();
+	if distance >= REACH {
+		return Vec3::ZERO;
+	}
+	PUSH * (1.0 - distance / REACH) * d.try_normalize().unwrap_or(Vec3::Y)
+}
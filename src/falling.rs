@@ -0,0 +1,37 @@
+// Falling blocks: blocks like sand can't hang in the air. Whenever a voxel changes, the ones
+// around it are scheduled to be checked, and any falling block left with nothing under it, along
+// the gravity where it is, comes loose as an entity that drops until it lands and turns back
+// into a block. Coming loose changes its voxel in turn, so a whole column drops one block after
+// another.
+
+use glam::*;
+use crate::{World, Voxel, step};
+use crate::block;
+use crate::entity::{Entity, Kind};
+use crate::schedule::{Scheduler, Update};
+
+// whether voxel i holds a block that falls and has nothing to rest on
+fn unsupported(world: &World, i: usize) -> bool {
+	let gravity = world.gravity_at(i);
+	block::def(world.voxel(i).block).falls && world.transparent(step(world, i, gravity.axis, gravity.sign))
+}
+
+// Check voxels in `changed`, and their neighbors, for falling blocks they've left unsupported.
+pub fn notice(world: &World, changed: &[usize], schedule: &mut Scheduler) {
+	for &i in changed {
+		let around = [(0, -1), (0, 1), (1, -1), (1, 1), (2, -1), (2, 1)].map(|(axis, sign)| step(world, i, axis, sign));
+		for j in std::iter::once(i).chain(around) {
+			if block::def(world.voxel(j).block).falls {
+				schedule.schedule(j, Update::Fall, 0);
+			}
+		}
+	}
+}
+
+// Loosen the falling block in voxel i if it's unsupported.
+pub fn loosen(world: &mut World, entities: &mut Vec<Entity>, i: usize) {
+	if unsupported(world, i) {
+		entities.push(Entity::new(Kind::Falling(world.voxel(i).block), i, Vec3::splat(0.5)));
+		world.set(i, Voxel::of(block::AIR));
+	}
+}
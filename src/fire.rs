@@ -0,0 +1,71 @@
+// Fire. A burning voxel catches its flammable neighbors now and then, and burns out to air
+// after a while, or at once when water is next to it. Only the voxels on fire are looked at,
+// scheduled every few ticks from when they're set alight, found from the world's changes.
+
+use crate::{World, Voxel, step};
+use crate::block;
+use crate::entity::Entity;
+use crate::explosion;
+use crate::rng::Rng;
+use crate::schedule::{Scheduler, Update};
+
+// ticks between steps of the fire
+const BURN_INTERVAL: u64 = 10;
+// chance each step of catching each flammable neighbor
+const SPREAD_CHANCE: f32 = 0.15;
+// steps a fire burns for at least, and its chance each step after that of going out
+const MIN_BURN: u8 = 6;
+const BURN_OUT_CHANCE: f32 = 0.1;
+
+pub struct Fire {
+	rng: Rng,
+}
+
+fn neighbors(world: &World, i: usize) -> [usize; 6] {
+	[(0, -1), (0, 1), (1, -1), (1, 1), (2, -1), (2, 1)].map(|(axis, sign)| step(world, i, axis, sign))
+}
+
+// Set voxel i alight if it's flammable. TNT is lit instead.
+pub fn ignite(world: &mut World, entities: &mut Vec<Entity>, i: usize) {
+	let block = world.voxel(i).block;
+	if block == block::TNT {
+		explosion::light(world, entities, i);
+	} else if block::def(block).flammable {
+		world.set(i, Voxel::of(block::FIRE));
+	}
+}
+
+impl Fire {
+	pub fn new(seed: u64) -> Fire {
+		Fire { rng: Rng::new(seed) }
+	}
+
+	// schedule voxels that caught fire to burn
+	pub fn notice(&self, world: &World, changed: &[usize], schedule: &mut Scheduler) {
+		for &i in changed {
+			if world.voxel(i).block == block::FIRE {
+				schedule.schedule(i, Update::Burn, BURN_INTERVAL);
+			}
+		}
+	}
+
+	// a step of the fire in voxel i, if it's still burning
+	pub fn burn(&mut self, world: &mut World, entities: &mut Vec<Entity>, i: usize, schedule: &mut Scheduler) {
+		if world.voxel(i).block != block::FIRE {
+			return;
+		}
+		let around = neighbors(world, i);
+		let age = world.voxel(i).state;
+		if around.iter().any(|&j| world.liquid(j)) || (age >= MIN_BURN && self.rng.f32() < BURN_OUT_CHANCE) {
+			world.set(i, Voxel::of(block::AIR));
+			return;
+		}
+		for j in around {
+			if self.rng.f32() < SPREAD_CHANCE {
+				ignite(world, entities, j);
+			}
+		}
+		world.set(i, Voxel::of(block::FIRE).with_state(age.saturating_add(1)));
+		schedule.schedule(i, Update::Burn, BURN_INTERVAL);
+	}
+}
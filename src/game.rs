@@ -0,0 +1,1407 @@
+// The game in a window: everything kept from frame to frame, made from the command line and the
+// settings when it starts, and a frame of it at a time. A frame takes up changed settings, reads
+// the controls, runs commands, moves the player and everything else in fixed ticks, builds and
+// breaks, and draws the view and everything over it, in that order.
+
+use std::sync::Arc;
+use macroquad::prelude::*;
+use crate::*;
+// ours, not macroquad's
+use crate::Camera;
+use crate::entity::Impact;
+use crate::event::{Bus, Cue, Event, Subscriber, Topic};
+use crate::input::Action;
+use crate::inventory::Inventory;
+use crate::player::Life;
+
+// the game's subscriptions to the bus: the console showing messages, commands being run, chat
+// going out, sounds, everything acting on edits (the simulation, which a joining game leaves to
+// the host, the network, syncing and scripts), what acts on keys pressed, and the entities
+// joined by new ones
+struct Subscribed {
+	shown: Subscriber,
+	run: Subscriber,
+	chatted: Subscriber,
+	#[cfg(feature = "audio")]
+	heard: Subscriber,
+	simulated: Option<Subscriber>,
+	networked: Option<Subscriber>,
+	synced: Option<Subscriber>,
+	scripted: Subscriber,
+	controls: Subscriber,
+	moves: Subscriber,
+	builds: Subscriber,
+	joins: Subscriber,
+}
+
+// what the controls are doing this frame, worked out before the player moves
+struct Frame {
+	// actions held down
+	down: Vec<Action>,
+	// keys go to the sign, console, map or menu while they're open instead of moving the player
+	typing: bool,
+	// while spectating, the body is left standing
+	spectating: bool,
+	// the way the camera looks, is up and is to its right, stood up against gravity
+	look: Vec3,
+	up: Vec3,
+	right: Vec3,
+	sneaking: bool,
+	sprinting: bool,
+	// how far the left stick and touches walk
+	walk: Vec2,
+}
+
+impl Frame {
+	fn pressing(&self, action: Action) -> bool {
+		!self.typing && self.down.contains(&action)
+	}
+
+	fn held(&self, action: Action) -> bool {
+		!self.spectating && self.pressing(action)
+	}
+}
+
+pub struct Game {
+	args: Vec<String>,
+	config: config::Config,
+	fullscreen: bool,
+	stamp_image: Option<image::RgbaImage>,
+	user_prefab: Option<prefab::Prefab>,
+	clipboard_file: String,
+	mesh_model: Option<mesh::VoxelModel>,
+	seed: u64,
+	rng: Rng,
+	world: World,
+	streamer: Option<Streamer>,
+	// streamed worlds can only be seen as far as the loaded window goes
+	window_view: Option<usize>,
+	view_distance: usize,
+	camera: Camera,
+	screen: Screen,
+	watcher: config::Watcher,
+	renderer: Renderer,
+	// chunks changed since the last view the renderer was handed
+	unseen: Dirty,
+	grabbed: bool,
+	spawn: (usize, Vec3),
+	entities: Vec<Entity>,
+	inventory: Inventory,
+	particles: Particles,
+	weather: weather::Weather,
+	clouds: cloud::Clouds,
+	lighting: Option<light::Lighting>,
+	automaton: Option<automaton::Automaton>,
+	hotbar: hotbar::Hotbar,
+	// creative mode builds without using up the inventory, and breaking drops nothing
+	creative: bool,
+	inventory_open: bool,
+	// corners marked for editing a box of voxels at once
+	selection: selection::Selection,
+	clipboard: Option<clipboard::Clipboard>,
+	// shape placing and breaking work on
+	brush: brush::Brush,
+	// whether left click paints blocks instead of breaking them
+	painting: bool,
+	symmetry: symmetry::Symmetry,
+	// the sign being written on, if any
+	writing: Option<sign::Editor>,
+	scripts: script::Scripts,
+	#[cfg(feature = "audio")]
+	sounds: sound::Sounds,
+	#[cfg(feature = "audio")]
+	music: music::Music,
+	session: Option<net::Session>,
+	// syncing as equals, rather than hosting or joining
+	sync: Option<sync::Sync>,
+	console: console::Console,
+	// what's happened that other parts of the game have yet to act on
+	bus: Bus,
+	subscribed: Subscribed,
+	waypoints: waypoint::Waypoints,
+	guide: Option<guide::Guide>,
+	locator: locator::Locator,
+	minimap: minimap::Minimap,
+	show_minimap: bool,
+	stats: stats::Stats,
+	map: map::Map,
+	// solid voxels the view sees through, to look inside builds and caves
+	xray: usize,
+	menu: menu::Menu,
+	gamepad: gamepad::Gamepad,
+	touch: touch::Touch,
+	// the free camera, while spectating
+	spectator: Option<spectator::Spectator>,
+	// settings changed in the menu, taken up at the start of the next frame
+	edited: Option<config::Config>,
+	// gravity's strength, kept to turn it back on with after the menu turns it off
+	weight: f32,
+	gravity_on: bool,
+	auto_step: bool,
+	simulation: Simulation,
+	//upward velocity (for gravity)
+	upward_velocity: f32,
+	on_ground: bool,
+	previous_position: Vec3,
+	accumulator: f32,
+	flying: bool,
+	last_jump_press: f64,
+	fov_scale: f32,
+	effects: effects::Effects,
+	// mouse turning still to be done, when it's smoothed
+	turning: Vec2,
+	orientation: Quat,
+	health: f32,
+	life: Life,
+	// sideways push from explosions, dying away
+	knockback: Vec3,
+	// voxel being broken and seconds spent on it so far
+	breaking: (usize, f32),
+	ticks: u64,
+}
+
+// the spawn point, somewhere to stand with the camera at eye height
+fn spawn_point(world: &World) -> (usize, Vec3) {
+	let i = find_spawn(world);
+	settle(world, i, Vec3::splat(0.5) + (player::EYE_HEIGHT - 0.5) * world.gravity_at(i).up())
+}
+
+// as far as `distance`, or as far as the loaded window of a streamed world goes if that's less
+fn limit_view(window_view: Option<usize>, distance: usize) -> usize {
+	window_view.map_or(distance, |w| w.min(distance))
+}
+
+impl Game {
+	// Make the game from the command line `args`, showing how far along making the world is, or
+	// none if there's no world to play in, or it was only made for `--benchmark`.
+	pub async fn start(args: Vec<String>) -> Option<Game> {
+		let mut config = config::Config::load(config::FILE);
+		config.apply_args(&args);
+		request_new_screen_size(config.resolution.0, config.resolution.1);
+		let fullscreen = args.iter().any(|a| a == "--fullscreen");
+		if fullscreen {
+			set_fullscreen(true);
+		}
+		next_frame().await;
+		// what goes wrong loading things asked for on the command line is said in the console
+		let progress = Arc::new(Progress::default());
+		if let Some(topology) = arg_value(&args, "--topology").filter(|t| t != "torus") {
+			progress.warn("topology", format!("only 'torus' is supported, not '{}'", topology));
+		}
+		if arg_value(&args, "--load").is_some() {
+			progress.warn("load", "worlds can't be saved or loaded yet");
+		}
+		block::load_packs(block::PACK_FOLDER, |e| progress.warn("blocks", e));
+
+		let stamp_image = arg_value(&args, "--stamp").and_then(|path| {
+			heightmap::load_image(&path).map_err(|e| progress.warn("stamp", e)).ok()
+		});
+		let user_prefab = arg_value(&args, "--prefab").and_then(|path| {
+			prefab::Prefab::load(&path).map_err(|e| progress.warn("prefab", e)).ok()
+		});
+		let clipboard_file = arg_value(&args, "--clipboard-file").unwrap_or(CLIPBOARD_FILE.to_string());
+		let mesh_model = arg_value(&args, "--mesh").and_then(|path| {
+			mesh::load_mesh(&path).map_err(|e| progress.warn("mesh", e)).ok()
+		}).map(|m| {
+			let size = arg_value(&args, "--mesh-size").and_then(|s| s.parse().ok()).unwrap_or(16);
+			mesh::voxelize(&m, size, !args.iter().any(|a| a == "--mesh-hollow"))
+		});
+
+		// a game joining another makes the world it's told to
+		let joining = match arg_value(&args, "--join") {
+			Some(address) => match net::Session::join(&address, &recipe(&args, &config.terrain_noise, &config.island_noise)) {
+				Ok(joined) => Some(joined),
+				Err(e) => {
+					fail(&e).await;
+					return None;
+				},
+			},
+			None => None,
+		};
+
+		// Build world
+		let seed = match &joining {
+			Some((_, seed, _)) => *seed,
+			None => arg_value(&args, "--seed").and_then(|s| s.parse().ok()).unwrap_or_else(rng::random_seed),
+		};
+		log::info!("world", "seed {}", seed);
+		let mut rng = Rng::new(seed);
+		let started = std::time::Instant::now();
+		let generation = {
+			let (args, progress) = (args.clone(), progress.clone());
+			let size = joining.as_ref().map_or(config.world_size, |&(_, _, size)| size);
+			let (terrain, islands) = (config.terrain_noise, config.island_noise);
+			std::thread::spawn(move || (generate_world(&args, size, &terrain, &islands, &mut rng, &progress), rng))
+		};
+		while !generation.is_finished() {
+			clear_background(BLACK);
+			let (w, h) = (screen_width(), screen_height());
+			draw_text(format!("{}...", progress.stage()), w/4.0, h/2.0 - 12.0, 24.0, WHITE);
+			draw_rectangle_lines(w/4.0, h/2.0, w/2.0, 16.0, 2.0, WHITE);
+			draw_rectangle(w/4.0, h/2.0, w/2.0 * progress.fraction(), 16.0, WHITE);
+			next_frame().await;
+		}
+		let (generated, rng) = match generation.join() {
+			Ok((generated, rng)) => (generated, rng),
+			Err(_) => (Err("world generation crashed".to_string()), Rng::new(seed)),
+		};
+		let (mut world, streamer) = match generated {
+			Ok(generated) => generated,
+			Err(e) => {
+				fail(&e).await;
+				return None;
+			},
+		};
+		// the world is left the way it was generated, overhangs and all, until it's edited
+		world.take_changes();
+		world.set_gravity(parse_gravity(&args));
+		if let Some(field) = parse_gravity_field(&args, &world) {
+			if streamer.is_some() {
+				log::warning!("gravity", "fields don't work with streamed worlds");
+			} else {
+				world.set_gravity_field(field);
+			}
+		}
+		log::info!("world", "made {}x{}x{} in {:.2} s, taking {:.1} MiB", world.size()[0], world.size()[1], world.size()[2], started.elapsed().as_secs_f64(), world.memory() as f64 / (1024.0 * 1024.0));
+		if args.iter().any(|a| a == "--validate") {
+			let validation = world.validate();
+			for line in validation.lines() {
+				if validation.ok() {
+					log::info!("validate", "{}", line);
+				} else {
+					log::warning!("validate", "{}", line);
+				}
+			}
+		}
+		let window_view = streamer.as_ref().map(|s| s.view_distance());
+		let view_distance = limit_view(window_view, config.view_distance);
+
+		// Place camera
+		let mut camera = Camera {
+			i: 0,
+			position: vec3(0.5, 0.5, 0.5),
+			rotation: Quat::IDENTITY,
+			movement_speed: config.movement_speed,
+			rotation_speed: config.mouse_rotation(),
+			fov: config.fov(),
+			screen: config.screen,
+		};
+		let screen: Screen = vec![vec![(config.ambient, 0.0); camera.screen.1]; camera.screen.0];
+
+		// Unstuck camera
+		let spawn = spawn_point(&world);
+		(camera.i, camera.position) = spawn;
+		if args.iter().any(|a| a == "--benchmark") {
+			benchmark(&world, &camera, view_distance, config.ambient);
+			return None;
+		}
+		let life_world = arg_value(&args, "--generator").as_deref() == Some("life");
+		let automaton = life(&args, &world);
+		let mut hotbar = hotbar::Hotbar::new();
+		if automaton.is_some() {
+			*hotbar.selected_mut() = Voxel::of(block::CELL);
+		}
+		let symmetry = symmetry::Symmetry::new(
+			&arg_value(&args, "--symmetry").unwrap_or("x".to_string()),
+			arg_value(&args, "--symmetry-repeat").and_then(|n| n.parse().ok()).unwrap_or(1),
+		);
+		let mut scripts = script::Scripts::load(script::FOLDER, seed, |e| progress.warn("scripts", e));
+		#[cfg(feature = "audio")]
+		let sounds = sound::Sounds::load(sound::FOLDER, |e| progress.warn("sounds", e)).await;
+		#[cfg(feature = "audio")]
+		let music = music::Music::load(music::FOLDER, |e| progress.warn("music", e)).await;
+		let session = match joining {
+			Some((session, _, _)) => Some(session),
+			None if args.iter().any(|a| a == "--host") => {
+				let port = arg_value(&args, "--port").and_then(|p| p.parse().ok()).unwrap_or(net::PORT);
+				match net::Session::host(port, seed, &recipe(&args, &config.terrain_noise, &config.island_noise), default_role(&args, |e| progress.warn("net", e))) {
+					Ok(session) => {
+						log::info!("net", "hosting on port {}", port);
+						Some(session)
+					},
+					Err(e) => {
+						progress.warn("net", format!("can't host on port {}: {}", port, e));
+						None
+					},
+				}
+			},
+			None => None,
+		};
+		let made_from = recipe(&args, &config.terrain_noise, &config.island_noise);
+		let mut sync = match arg_value(&args, "--sync") {
+			Some(address) => Some(sync::Sync::connect(&address, seed, &made_from)),
+			None if args.iter().any(|a| a == "--sync-listen") => {
+				let port = arg_value(&args, "--port").and_then(|p| p.parse().ok()).unwrap_or(sync::PORT);
+				match sync::Sync::listen(port, seed, &made_from) {
+					Ok(sync) => {
+						log::info!("sync", "listening on port {}", port);
+						Some(sync)
+					},
+					Err(e) => {
+						progress.warn("sync", format!("can't listen on port {}: {}", port, e));
+						None
+					},
+				}
+			},
+			None => None,
+		};
+		if session.is_some() && sync.take().is_some() {
+			progress.warn("sync", "a game can't sync while hosting or joining");
+		}
+		if streamer.is_some() && sync.take().is_some() {
+			progress.warn("sync", "streamed worlds can't be synced");
+		}
+		let mut bus = Bus::default();
+		let subscribed = Subscribed {
+			shown: bus.subscribe(&[Topic::Say]),
+			run: bus.subscribe(&[Topic::Command]),
+			chatted: bus.subscribe(&[Topic::Chat]),
+			#[cfg(feature = "audio")]
+			heard: bus.subscribe(&[Topic::Sound]),
+			simulated: (!session.as_ref().is_some_and(|s| s.joined())).then(|| bus.subscribe(&[Topic::Edit])),
+			networked: session.as_ref().map(|_| bus.subscribe(&[Topic::Edit])),
+			synced: sync.as_ref().map(|_| bus.subscribe(&[Topic::Edit])),
+			scripted: bus.subscribe(&[Topic::Edit]),
+			controls: bus.subscribe(&[Topic::Action]),
+			moves: bus.subscribe(&[Topic::Action]),
+			builds: bus.subscribe(&[Topic::Action]),
+			joins: bus.subscribe(&[Topic::Spawn]),
+		};
+		for warning in progress.take_warnings() {
+			bus.say(warning);
+		}
+		// a marker to find the way back to the spawn point by; streamed worlds move their voxels
+		// around under it, so they go without
+		if streamer.is_none() {
+			bus.publish(Event::Spawn(Entity::new(Kind::SpawnMarker, spawn.0, spawn.1)));
+		}
+		let look = world.gravity_at(camera.i).frame() * camera.basis().0;
+		bus.extend(scripts.start(&mut world, &script::Player { i: camera.i, position: camera.position, look }));
+		Some(Game {
+			weight: if world.gravity().strength != 0.0 { world.gravity().strength } else { Gravity::DEFAULT.strength },
+			gravity_on: world.gravity().strength != 0.0,
+			auto_step: args.iter().any(|a| a == "--auto-step"),
+			simulation: Simulation::new(seed, arg_value(&args, "--water").as_deref() != Some("finite")),
+			creative: !args.iter().any(|a| a == "--survival"),
+			previous_position: camera.position,
+			orientation: Quat::from_mat3(&world.gravity_at(camera.i).frame()),
+			// the life generator's world is all air around the cells
+			flying: life_world,
+			args,
+			config,
+			fullscreen,
+			stamp_image,
+			user_prefab,
+			clipboard_file,
+			mesh_model,
+			seed,
+			rng,
+			world,
+			streamer,
+			window_view,
+			view_distance,
+			camera,
+			screen,
+			watcher: config::Watcher::new(config::FILE),
+			renderer: Renderer::spawn(),
+			unseen: Dirty::default(),
+			grabbed: true,
+			spawn,
+			entities: vec![],
+			inventory: Inventory::new(),
+			particles: Particles::new(seed),
+			weather: weather::Weather::new(seed),
+			clouds: cloud::Clouds::new(seed),
+			lighting: None,
+			automaton,
+			hotbar,
+			inventory_open: false,
+			selection: selection::Selection::default(),
+			clipboard: None,
+			brush: brush::Brush::new(),
+			painting: false,
+			symmetry,
+			writing: None,
+			scripts,
+			#[cfg(feature = "audio")]
+			sounds,
+			#[cfg(feature = "audio")]
+			music,
+			session,
+			sync,
+			console: console::Console::new(),
+			bus,
+			subscribed,
+			waypoints: waypoint::Waypoints::default(),
+			guide: None,
+			locator: locator::Locator::default(),
+			minimap: minimap::Minimap::new(),
+			show_minimap: true,
+			stats: stats::Stats::new(),
+			map: map::Map::new(),
+			xray: 0,
+			menu: menu::Menu::new(),
+			gamepad: gamepad::Gamepad::open(),
+			touch: touch::Touch::new(),
+			spectator: None,
+			edited: None,
+			upward_velocity: 0.0,
+			on_ground: false,
+			accumulator: 0.0,
+			last_jump_press: f64::MIN,
+			fov_scale: 1.0,
+			effects: effects::Effects::default(),
+			turning: Vec2::ZERO,
+			health: player::MAX_HEALTH,
+			life: Life::Alive,
+			knockback: Vec3::ZERO,
+			breaking: (usize::MAX, 0.0),
+			ticks: 0,
+		})
+	}
+
+	// A frame of the game, until it's drawn; false once it's been quit from the menu.
+	pub fn frame(&mut self) -> bool {
+		self.settings();
+
+		// Take player input
+		self.gamepad.update();
+		self.touch.update();
+		let keys = input::Controls { bindings: &self.config.keys, gamepad: &self.gamepad, touch: &self.touch };
+		let mut down = vec![];
+		for (action, ..) in input::ACTIONS {
+			if keys.pressed(action) {
+				self.bus.publish(Event::Action(action));
+			}
+			if keys.down(action) {
+				down.push(action);
+			}
+		}
+		// Escape closes whatever is open, or else pauses
+		let overlay = self.writing.is_some() || self.console.open || self.map.open || self.inventory_open;
+		self.type_in();
+		self.run_commands();
+		self.chat();
+		let typing = self.toggle(overlay, &down);
+		let frame = self.steer(typing, down);
+		self.ticks(&frame);
+		self.respawn();
+		let (view_i, view_position, view_up, view_right) = self.view_point(&frame);
+		let target_i = self.build(&frame);
+		self.render(&frame, target_i, (view_i, view_position, view_up, view_right));
+		self.draw(&frame, target_i)
+	}
+
+	// Take up settings changed in the menu or edits to the settings file
+	fn settings(&mut self) {
+		let reloaded = self.watcher.poll().map(|mut changed| {
+			changed.apply_args(&self.args);
+			log::info!("settings", "reloaded {}", config::FILE);
+			changed
+		});
+		if let Some(changed) = self.edited.take().or(reloaded) {
+			if changed.world_size != self.config.world_size {
+				log::info!("settings", "the new world size takes effect the next time the game starts");
+			}
+			if changed.resolution != self.config.resolution {
+				request_new_screen_size(changed.resolution.0, changed.resolution.1);
+			}
+			if changed.screen != self.config.screen {
+				self.camera.screen = changed.screen;
+				self.screen = vec![vec![(changed.ambient, 0.0); self.camera.screen.1]; self.camera.screen.0];
+			}
+			self.camera.fov = changed.fov();
+			self.camera.rotation_speed = changed.mouse_rotation();
+			self.camera.movement_speed = changed.movement_speed;
+			self.view_distance = limit_view(self.window_view, changed.view_distance);
+			self.config = changed;
+		}
+	}
+
+	// type on the sign being written on, and into the console
+	fn type_in(&mut self) {
+		if let Some(editor) = &mut self.writing {
+			if editor.update(&mut self.world) {
+				self.writing = None;
+			}
+		}
+		if self.console.open {
+			let marks: Vec<&str> = self.waypoints.names().collect();
+			match self.console.update(&marks) {
+				Some(console::Entered::Command(line)) => self.bus.publish(Event::Command(script::relative(&line, &self.world, self.camera.i))),
+				Some(console::Entered::Chat(text)) => self.bus.publish(Event::Chat(text)),
+				None => {},
+			}
+		}
+	}
+
+	// commands typed, and those scripts queued since the last frame
+	fn run_commands(&mut self) {
+		for line in self.bus.commands(&self.subscribed.run) {
+			let [_, ny, nz] = self.world.size();
+			match console::parse(&line, self.world.size()) {
+				Ok(console::Command::Teleport([x, y, z])) => {
+					let i = furl(x, y, z, ny, nz);
+					(self.camera.i, self.camera.position) = settle(&self.world, i, Vec3::splat(0.5) + (player::EYE_HEIGHT - 0.5) * self.world.gravity_at(i).up());
+					self.previous_position = self.camera.position;
+					self.upward_velocity = 0.0;
+				},
+				Ok(console::Command::Mark(name)) => {
+					self.waypoints.mark(&name, waypoint::Waypoint { i: self.camera.i, position: self.camera.position, rotation: self.camera.rotation });
+					self.bus.say(format!("marked {}", name));
+				},
+				Ok(console::Command::Go(name)) => match self.waypoints.get(&name) {
+					Some(w) => {
+						(self.camera.i, self.camera.position, self.camera.rotation) = (w.i, w.position, w.rotation);
+						self.previous_position = self.camera.position;
+						self.upward_velocity = 0.0;
+					},
+					None => self.bus.say(format!("no waypoint called {}", name)),
+				},
+				Ok(console::Command::Unmark(name)) => {
+					if !self.waypoints.unmark(&name) {
+						self.bus.say(format!("no waypoint called {}", name));
+					}
+				},
+				Ok(console::Command::Marks) => {
+					let names: Vec<&str> = self.waypoints.names().collect();
+					self.bus.say(if names.is_empty() { "no waypoints yet".to_string() } else { names.join("  ") });
+				},
+				Ok(console::Command::Guide(Some(name))) => match self.waypoints.get(&name) {
+					Some(w) => {
+						let target = settle(&self.world, w.i, w.position - (player::EYE_HEIGHT - 0.1) * self.world.gravity_at(w.i).up()).0;
+						self.bus.say(format!("guiding to {}", name));
+						self.guide = Some(guide::Guide::new(name, target));
+					},
+					None => self.bus.say(format!("no waypoint called {}", name)),
+				},
+				Ok(console::Command::Guide(None)) => {
+					if self.guide.take().is_some() {
+						self.bus.say("stopped guiding");
+					}
+				},
+				Ok(console::Command::SetBlock([x, y, z], b)) => self.world.set(furl(x, y, z, ny, nz), Voxel::of(b)),
+				Ok(console::Command::Fill([x0, y0, z0], [x1, y1, z1], b)) => {
+					let region = selection::Region::between(&self.world, furl(x0, y0, z0, ny, nz), furl(x1, y1, z1, ny, nz));
+					selection::fill(&mut self.world, &region, &Voxel::of(b));
+					let [sx, sy, sz] = region.size;
+					self.bus.say(format!("filled {} voxels", sx*sy*sz));
+				},
+				Ok(console::Command::Count(b, radius)) => {
+					let counted = match radius {
+						Some(r) => self.world.iter_region(self.camera.i, r).filter(|&(i, _)| self.world.voxel(i).block == b).count(),
+						None => self.world.count_matching(|v| v.block == b),
+					};
+					self.bus.say(format!("{} voxels of {}", counted, block::def(b).name));
+				},
+				Ok(console::Command::Seed) => self.bus.say(format!("seed {}", self.seed)),
+				Ok(console::Command::Time) => {
+					let t = get_time() as u64;
+					self.bus.say(format!("running for {}:{:02}:{:02}", t / 3600, t / 60 % 60, t % 60));
+				},
+				Ok(console::Command::Resolution(None)) => self.bus.say(format!("{} rays across and {} down", self.camera.screen.0, self.camera.screen.1)),
+				Ok(console::Command::Resolution(Some((across, down)))) => {
+					let down = down.unwrap_or(((across as f32 * screen_height() / screen_width()) as usize).max(1));
+					let changed = config::Config { screen: (across, down), ..self.config.clone() };
+					self.watcher.save(&changed);
+					self.edited = Some(changed);
+					self.bus.say(format!("{} rays across and {} down", across, down));
+				},
+				Ok(console::Command::Weather(None)) => self.bus.say(format!("the weather is {}", self.weather.kind.name())),
+				Ok(console::Command::Weather(Some(kind))) => {
+					self.weather.set(kind);
+					self.bus.say(format!("the weather is now {}", kind.name()));
+				},
+				Ok(console::Command::Save) => self.bus.say("worlds can't be saved yet; they last until the game is closed"),
+				Ok(command @ (console::Command::Players | console::Command::Role(..) | console::Command::Protect(..) | console::Command::Unprotect)) => match &mut self.session {
+					Some(session) => self.bus.say(session.administer(&self.world, self.streamer.as_ref(), command)),
+					None => self.bus.say("nobody else is playing; that's for games hosted with --host"),
+				},
+				Ok(console::Command::Help) => {
+					for (_, usage) in console::COMMANDS {
+						self.bus.say(usage);
+					}
+				},
+				Err(e) if e.is_empty() => {},
+				Err(e) => self.bus.say(e),
+			}
+		}
+	}
+
+	// send what's been typed for the other players to read
+	fn chat(&mut self) {
+		for text in self.bus.chats(&self.subscribed.chatted) {
+			match &mut self.session {
+				Some(session) => {
+					session.chat(&text);
+					self.bus.say(format!("you: {}", text));
+				},
+				None => self.bus.say("nobody else is playing; chat works with --host or --join"),
+			}
+		}
+	}
+
+	// Open and close what the keys pressed toggle, given whether a sign, the console, the map or
+	// the inventory was open at the start of the frame, and say whether keys are being typed.
+	fn toggle(&mut self, overlay: bool, down: &[Action]) -> bool {
+		let pressed = self.bus.actions(&self.subscribed.controls);
+		if self.map.open {
+			self.map.update(self.world.size(), locate(&self.world, None, self.camera.i).0);
+		}
+		if pressed.contains(&Action::Map) && self.writing.is_none() && !self.console.open {
+			if self.map.open {
+				self.map.open = false;
+			} else {
+				self.map.show(locate(&self.world, None, self.camera.i).0);
+			}
+		}
+		if pressed.contains(&Action::Pause) {
+			if !overlay {
+				self.menu.open = !self.menu.open;
+				if !self.menu.open {
+					self.watcher.save(&self.config);
+				}
+			}
+			self.map.open = false;
+			self.inventory_open = false;
+		}
+		// keys go to the sign, console, map or menu while they're open instead of moving the player
+		let typing = self.writing.is_some() || self.console.open || self.map.open || self.menu.open;
+		if pressed.contains(&Action::Inventory) && !typing {
+			self.inventory_open = !self.inventory_open;
+			self.grabbed = !self.inventory_open;
+		}
+		if !typing {
+			self.hotbar.update();
+			// the bumpers move along the hotbar
+			for (button, next) in [(gamepad::Button::LeftBumper, false), (gamepad::Button::RightBumper, true)] {
+				if self.gamepad.pressed(button) {
+					self.hotbar.step(next);
+				}
+			}
+		}
+		if pressed.contains(&Action::Console) && !typing && !self.inventory_open {
+			self.console.open(false);
+		}
+		// not with the `Enter` that just closed the console or a sign
+		if pressed.contains(&Action::Chat) && !typing && !overlay {
+			self.console.open(true);
+		}
+		if pressed.contains(&Action::Minimap) {
+			self.show_minimap = !self.show_minimap;
+		}
+		if pressed.contains(&Action::Stats) {
+			self.stats.toggle();
+		}
+		#[cfg(feature = "audio")]
+		if pressed.contains(&Action::NextTrack) {
+			if let Some(name) = self.music.skip() {
+				self.bus.say(format!("playing {}", name));
+			}
+		}
+		if pressed.contains(&Action::Xray) {
+			self.xray = match self.xray { 0 => 1, n if n < MAX_XRAY => 2 * n, _ => 0 };
+		}
+		if pressed.contains(&Action::Fullscreen) {
+			self.fullscreen = !self.fullscreen;
+			set_fullscreen(self.fullscreen);
+		}
+		if pressed.contains(&Action::Spectate) && !typing {
+			self.spectator = match self.spectator {
+				None => Some(spectator::Spectator::new(self.camera.i, self.camera.position)),
+				Some(_) => None,
+			};
+		}
+		if pressed.contains(&Action::Creative) {
+			self.creative = !self.creative;
+		}
+		if is_mouse_button_released(MouseButton::Left) && !self.inventory_open && !self.menu.open {
+			self.grabbed = true;
+		}
+		if self.menu.open || down.contains(&Action::Pause) {
+			self.grabbed = false;
+		}
+		set_cursor_grab(self.grabbed);
+		show_mouse(!self.grabbed);
+		// touches stand in for the mouse only on screens that are clicked
+		if self.touch.active {
+			simulate_mouse_with_touch(self.menu.open || self.inventory_open);
+		}
+		typing
+	}
+
+	// Turn the camera and work out which way the player's going from the controls held `down`.
+	fn steer(&mut self, typing: bool, down: Vec<Action>) -> Frame {
+		// Rotate player camera
+		let mut mouse_delta = vec2(0.0, 0.0);
+		if self.grabbed {
+			// in pixels, so turning doesn't depend on the size of the window
+			mouse_delta = mouse_delta_position() * vec2(screen_width(), screen_height()) / 2.0;
+		}
+		// zoomed in, the mouse turns as much less as the view is narrower
+		self.turning += self.fov_scale.min(1.0) * vec2(self.camera.rotation_speed.0 * mouse_delta.x, -self.camera.rotation_speed.1 * mouse_delta.y);
+		// smoothing lets the turn out over time, the same share of it each second whatever the
+		// frame rate
+		let share = if self.config.mouse_smoothing > 0.0 { approach(1.0 / self.config.mouse_smoothing) } else { 1.0 };
+		self.camera.turn(-share * self.turning.x, -share * self.turning.y);
+		self.turning -= share * self.turning;
+		// and with a controller's right stick, turning faster the further it's pushed
+		let steering = !typing && !self.inventory_open;
+		let mut frame = Frame {
+			down,
+			typing,
+			spectating: self.spectator.is_some(),
+			look: Vec3::ZERO,
+			up: Vec3::ZERO,
+			right: Vec3::ZERO,
+			sneaking: false,
+			sprinting: false,
+			walk: Vec2::ZERO,
+		};
+		if steering {
+			let aim = self.gamepad.stick(true, self.config.stick_deadzone, self.config.stick_curve);
+			let stick = vec2(self.config.stick_speed.0 * aim.x, self.config.stick_speed.1 * aim.y) * get_frame_time();
+			self.camera.turn(stick.x, stick.y);
+			let dragged = self.touch.look();
+			self.camera.turn(dragged.x, dragged.y);
+			// and roll, to stand the view at any angle
+			let roll = (frame.pressing(Action::RollRight) as i32 - frame.pressing(Action::RollLeft) as i32) as f32;
+			self.camera.roll(roll * ROLL_SPEED * get_frame_time());
+		}
+
+		// Move player, in fixed ticks however long the frame took
+		let (look, up, right) = self.camera.basis();
+		// stand the camera up against gravity, turning smoothly when gravity changes direction
+		let target = Quat::from_mat3(&self.world.gravity_at(self.camera.i).frame());
+		self.orientation = self.orientation.slerp(target, approach(ORIENTATION_RATE));
+		let rotation = Mat3::from_quat(self.orientation);
+		(frame.look, frame.up, frame.right) = (rotation * look, rotation * up, rotation * right);
+
+		if self.bus.actions(&self.subscribed.moves).contains(&Action::Jump) && !typing && !frame.spectating {
+			let now = get_time();
+			if now - self.last_jump_press < DOUBLE_TAP_TIME {
+				self.flying = !self.flying;
+			}
+			self.last_jump_press = now;
+		}
+		frame.sneaking = !self.flying && frame.held(Action::Sneak);
+		// the left stick walks, slower when it's only pushed part way
+		frame.walk = if steering { (self.gamepad.stick(false, self.config.stick_deadzone, 1.0) + self.touch.walk()).clamp_length_max(1.0) } else { Vec2::ZERO };
+		frame.sprinting = !frame.sneaking && !self.flying && frame.held(Action::Sprint) && (frame.held(Action::Forward) || frame.walk.y > 0.5);
+		// narrow the view while zooming and widen it while sprinting, easing in and out
+		let fov_target = if frame.pressing(Action::Zoom) { ZOOM_FOV } else if frame.sprinting { SPRINT_FOV } else { 1.0 };
+		self.fov_scale += (fov_target - self.fov_scale) * approach(FOV_RATE);
+		let stepping = [Action::Forward, Action::Back, Action::Left, Action::Right].into_iter().any(|a| frame.held(a)) || frame.walk != Vec2::ZERO;
+		let strafe = (frame.held(Action::Right) as i32 - frame.held(Action::Left) as i32) as f32 + frame.walk.x;
+		self.effects.update(stepping && self.on_ground && !frame.spectating, strafe.clamp(-1.0, 1.0));
+		frame
+	}
+
+	// run as many ticks as the frame took
+	fn ticks(&mut self, frame: &Frame) {
+		// the game stands still while paused
+		if !self.menu.open {
+			self.accumulator += get_frame_time().min(MAX_FRAME_TIME);
+		}
+		while self.accumulator >= TICK {
+			self.accumulator -= TICK;
+			self.previous_position = self.camera.position;
+			self.tick_world(frame);
+			if self.life == Life::Alive {
+				self.tick_player(frame);
+			}
+		}
+	}
+
+	// a tick of everything but the player
+	fn tick_world(&mut self, frame: &Frame) {
+		self.entities.extend(self.bus.spawns(&self.subscribed.joins));
+		let mut impacts = vec![];
+		for entity in &mut self.entities {
+			if entity.kind == Kind::Mob {
+				mob::think(entity, &self.world, &mut self.rng);
+			}
+			impacts.extend(entity.tick(&self.world));
+		}
+		self.entities.retain(|entity| !entity.spent);
+		self.particles.tick(&self.world);
+		self.clouds.tick();
+		let joined = self.session.as_ref().is_some_and(|s| s.joined());
+		// a joining game's snow comes from the host
+		self.weather.tick(&mut self.world, &mut self.particles, self.camera.i, self.camera.position, !joined);
+		self.bus.extend(self.world.take_changes().into_iter().map(Event::Edit));
+		if let (Some(session), Some(networked)) = (&mut self.session, &self.subscribed.networked) {
+			let changed = self.bus.edits(networked);
+			for text in session.tick(&mut self.world, self.streamer.as_mut(), &changed, Some((self.camera.i, self.camera.position))) {
+				self.bus.say(text);
+			}
+			// where the host put the player back, with the moves since made again from there
+			if let Some((i, position)) = session.corrected() {
+				(self.camera.i, self.camera.position) = (i, position);
+				self.previous_position = position;
+			}
+		}
+		if let (Some(sync), Some(synced)) = (&mut self.sync, &self.subscribed.synced) {
+			let changed = self.bus.edits(synced);
+			for text in sync.tick(&mut self.world, &changed) {
+				self.bus.say(text);
+			}
+		}
+		self.ticks += 1;
+		let changed = self.bus.edits(&self.subscribed.scripted);
+		let player = script::Player { i: self.camera.i, position: self.camera.position, look: frame.look };
+		self.bus.extend(self.scripts.tick(&mut self.world, self.ticks, &changed, &player));
+		// A joining game's water, fire, falling blocks, circuits and plants are only the host's
+		// to run, which random ticks around everyone and sends what they change. Run here too,
+		// what they changed would go to the host as if the player had, so it doesn't subscribe.
+		if let Some(simulated) = &self.subscribed.simulated {
+			let changed = self.bus.edits(simulated);
+			let near: Vec<usize> = std::iter::once(self.camera.i).chain(self.session.iter().flat_map(|s| s.players(&self.world, self.streamer.as_ref())).map(|e| e.i)).collect();
+			publish_spawns(&mut self.bus, |spawned| simulate(&mut self.world, spawned, &changed, &near, &mut self.simulation, self.automaton.as_mut()));
+		}
+		for impact in impacts {
+			match impact {
+				Impact::Smash(i) => {
+					self.bus.publish(Event::Sound(Cue::Break(self.world.voxel(i).block), Some(i)));
+					publish_spawns(&mut self.bus, |spawned| break_block(&mut self.world, spawned, &mut self.particles, i, true));
+				},
+				Impact::Explode(i) => {
+					self.bus.publish(Event::Sound(Cue::Explode, Some(i)));
+					publish_spawns(&mut self.bus, |spawned| explosion::explode(&mut self.world, &mut self.entities, spawned, &mut self.particles, i, &mut self.rng));
+					let sheltered = !self.world.visible(i, Vec3::splat(0.5), self.camera.i, self.camera.position);
+					let push = if sheltered { Vec3::ZERO } else { explosion::push(self.world.direction(i, Vec3::splat(0.5), self.camera.i, self.camera.position)) };
+					let rise = self.world.gravity_at(self.camera.i).up();
+					self.upward_velocity += push.dot(rise);
+					self.knockback += push - push.dot(rise) * rise;
+				},
+				Impact::Land(i, block) => {
+					if self.world.transparent(i) && !player::occupies(&self.world, self.camera.i, self.camera.position, i) {
+						self.world.set(i, Voxel::of(block));
+						self.bus.publish(Event::Sound(Cue::Place(block), Some(i)));
+					} else {
+						self.bus.publish(Event::Spawn(Entity::new(Kind::Item(block), i, Vec3::splat(0.5))));
+					}
+				},
+			}
+		}
+	}
+
+	// a tick of the player, while they're alive
+	fn tick_player(&mut self, frame: &Frame) {
+		let (look, right, walk) = (frame.look, frame.right, frame.walk);
+		if let Some(spectator) = &mut self.spectator {
+			let rise = self.world.gravity_at(self.camera.i).up();
+			let mut fly = walk.y * look + walk.x * right;
+			for (action, direction) in [(Action::Forward, look), (Action::Back, -look), (Action::Left, -right), (Action::Right, right), (Action::Jump, rise), (Action::Sprint, -rise)] {
+				if frame.pressing(action) {
+					fly += direction;
+				}
+			}
+			spectator.tick(&self.world, self.camera.movement_speed * SPECTATOR_SPEED * fly.clamp_length_max(1.0));
+		}
+
+		let mut dx = vec3(0.0, 0.0, 0.0);
+
+		if frame.held(Action::Forward) {
+			dx += look;
+		}
+		if frame.held(Action::Back) {
+			dx -= look;
+		}
+		if frame.held(Action::Left) {
+			dx -= right;
+		}
+		if frame.held(Action::Right) {
+			dx += right;
+		}
+		dx += walk.y * look + walk.x * right;
+		let throttle = if walk == Vec2::ZERO { 1.0 } else { walk.length().min(1.0) };
+		let gravity = self.world.gravity_at(self.camera.i);
+		let rise = gravity.up();
+		if self.flying {
+			// fly along the ground's plane, up with space and down with shift
+			dx = (dx - dx.dot(rise) * rise).normalize_or_zero();
+			if frame.held(Action::Jump) {
+				dx += rise;
+			}
+			if frame.held(Action::Sprint) {
+				dx -= rise;
+			}
+		}
+		let swimming = !self.flying && self.world.liquid(self.camera.i);
+		let speed = self.camera.movement_speed * if frame.sneaking { SNEAK_SPEED } else if swimming { SWIM_SPEED } else if frame.sprinting { SPRINT_SPEED } else { 1.0 };
+		let mut delta = dx.try_normalize().map_or(Vec3::ZERO, |dx| speed * throttle * dx) + self.knockback;
+		self.knockback *= KNOCKBACK_DRAG;
+
+		if self.flying {
+			self.upward_velocity = 0.0;
+		} else if swimming {
+			// water holds the player up: gravity is weak, drag slows any motion, and holding
+			// space swims upward
+			self.upward_velocity = self.upward_velocity * WATER_DRAG - gravity.strength * WATER_GRAVITY;
+			if frame.held(Action::Jump) {
+				self.upward_velocity += SWIM_ACCELERATION;
+			}
+			self.upward_velocity = self.upward_velocity.clamp(-SINK_VELOCITY, SWIM_VELOCITY);
+			delta += self.upward_velocity * rise;
+		} else {
+			if frame.held(Action::Jump) & self.on_ground {
+				self.upward_velocity = self.config.jump_velocity;
+				self.bus.publish(Event::Sound(Cue::Jump, None));
+			}
+			self.upward_velocity = (self.upward_velocity - gravity.strength).max(-TERMINAL_VELOCITY);
+			delta += self.upward_velocity * rise;
+		}
+
+		let (position, blocked) = if self.flying {
+			player::move_body(&self.world, self.camera.i, self.camera.position, delta)
+		} else if frame.sneaking && self.on_ground {
+			player::move_body_sneaking(&self.world, self.camera.i, self.camera.position, delta)
+		} else if self.auto_step && self.on_ground {
+			player::move_body_stepping(&self.world, self.camera.i, self.camera.position, delta)
+		} else {
+			player::move_body(&self.world, self.camera.i, self.camera.position, delta)
+		};
+		let was_on_ground = self.on_ground;
+		self.on_ground = blocked[gravity.axis] && delta.dot(rise) < 0.0;
+		let ground = self.world.voxel(player::ground(&self.world, self.camera.i, position)).block;
+		if self.on_ground && !was_on_ground {
+			self.effects.land(-self.upward_velocity);
+			self.bus.publish(Event::Sound(Cue::Land(ground), None));
+		}
+		let moved = position - self.camera.position;
+		if self.effects.tick((moved - moved.dot(rise) * rise).length(), self.on_ground) && !swimming && ground != block::AIR {
+			self.bus.publish(Event::Sound(Cue::Step(ground), None));
+		}
+		// landing ends flight
+		self.flying &= !self.on_ground;
+		if self.on_ground && !swimming {
+			self.health -= player::fall_damage(-self.upward_velocity);
+			if self.health <= 0.0 {
+				self.life = Life::Dead { since: get_time() };
+			}
+		}
+		if blocked[gravity.axis] {
+			self.upward_velocity = 0.0;
+		}
+
+		let (i, position) = settle(&self.world, self.camera.i, position);
+		// keep the previous position relative to the camera's voxel for interpolation
+		self.previous_position += position - self.camera.position;
+		let (before, size) = locate(&self.world, self.streamer.as_ref(), self.camera.i);
+		(self.camera.i, self.camera.position) = (i, position);
+		self.locator.track(size, before, locate(&self.world, self.streamer.as_ref(), self.camera.i).0);
+		if let Some(streamer) = &mut self.streamer {
+			streamer.update(&mut self.world, self.camera.i);
+		}
+
+		// pick up items the player's box has reached
+		self.entities.retain(|entity| match entity.kind {
+			Kind::Item(block) if player::occupies(&self.world, self.camera.i, self.camera.position, entity.i) => {
+				self.inventory.add(block);
+				false
+			},
+			_ => true,
+		});
+		// mobs would be carried off with the voxels of a streamed world too
+		if self.streamer.is_none() {
+			if let Some(mob) = mob::populate(&mut self.entities, &self.world, self.camera.i, &mut self.rng) {
+				self.bus.publish(Event::Spawn(mob));
+			}
+		}
+	}
+
+	// bring the player back to life at the spawn point a while after dying
+	fn respawn(&mut self) {
+		if let Life::Dead { since } = self.life {
+			if get_time() - since > RESPAWN_DELAY {
+				// the spawn voxel of a streamed world may hold other ground by now, so look again
+				(self.camera.i, self.camera.position) = if self.streamer.is_some() { spawn_point(&self.world) } else { self.spawn };
+				self.previous_position = self.camera.position;
+				self.upward_velocity = 0.0;
+				self.knockback = Vec3::ZERO;
+				self.flying = false;
+				self.health = player::MAX_HEALTH;
+				self.life = Life::Alive;
+				if let Some(streamer) = &mut self.streamer {
+					streamer.update(&mut self.world, self.camera.i);
+				}
+			}
+		}
+	}
+
+	// where the view's drawn from: its voxel, position in it, up and right
+	fn view_point(&self, frame: &Frame) -> (usize, Vec3, Vec3, Vec3) {
+		// draw the camera partway from its previous tick to its latest one
+		let mut view_position = self.previous_position.lerp(self.camera.position, self.accumulator / TICK);
+		if frame.sneaking {
+			view_position -= SNEAK_CAMERA_DROP * self.world.gravity_at(self.camera.i).up();
+		}
+		let (view_position, view_up, view_right) = if self.config.camera_effects && !frame.spectating {
+			self.effects.apply(view_position, frame.look, frame.up, frame.right, self.world.gravity_at(self.camera.i).up())
+		} else {
+			(view_position, frame.up, frame.right)
+		};
+		let (view_i, view_position) = match &self.spectator {
+			Some(spectator) => spectator.view(&self.world, self.accumulator / TICK),
+			None => settle(&self.world, self.camera.i, view_position),
+		};
+		(view_i, view_position, view_up, view_right)
+	}
+
+	// Build, break and edit with the keys pressed, and say which voxel the player's looking at.
+	fn build(&mut self, frame: &Frame) -> usize {
+		let (look, right) = (frame.look, frame.right);
+		let (target_i, target_x, _) = raycast(&self.world, self.camera.i, self.camera.position, look, TOUCH_DISTANCE);
+		let pressed = self.bus.actions(&self.subscribed.builds);
+		// the dead can't build, and clicks on the inventory screen are for it
+		if self.life != Life::Alive || self.inventory_open || frame.typing || frame.spectating {
+			return target_i;
+		}
+		if pressed.contains(&Action::Paint) {
+			self.painting = !self.painting;
+		}
+		// the paint tool recolors whatever the button is held over, leaving the blocks be
+		if self.painting && frame.down.contains(&Action::Break) && ! self.world.transparent(target_i) {
+			let color = self.hotbar.selected().color;
+			let covered: Vec<usize> = self.brush.voxels(&self.world, target_i).into_iter().flat_map(|i| self.symmetry.images(&self.world, i)).collect();
+			for i in covered {
+				if ! self.world.transparent(i) {
+					let painted = Voxel { color, ..self.world.voxel(i).clone() };
+					self.world.set(i, painted);
+				}
+			}
+		// blocks break after the button has been held on them for as long as their hardness
+		} else if !self.painting && frame.down.contains(&Action::Break) && ! self.world.transparent(target_i) {
+			if self.breaking.0 != target_i {
+				// keep any wait left from the last block broken
+				self.breaking = (target_i, self.breaking.1.min(0.0));
+			}
+			// a tap on a touch screen breaks it outright
+			self.breaking.1 += if self.touch.tapped() { f32::INFINITY } else { get_frame_time() };
+			if self.breaking.1 >= block::def(self.world.voxel(target_i).block).hardness {
+				self.bus.publish(Event::Sound(Cue::Break(self.world.voxel(target_i).block), Some(target_i)));
+				let covered: Vec<usize> = self.brush.voxels(&self.world, target_i).into_iter().flat_map(|i| self.symmetry.images(&self.world, i)).collect();
+				for i in covered {
+					publish_spawns(&mut self.bus, |spawned| break_block(&mut self.world, spawned, &mut self.particles, i, !self.creative));
+				}
+				self.breaking = (usize::MAX, -BREAK_DELAY);
+			}
+		} else {
+			self.breaking = (usize::MAX, 0.0);
+		}
+		// pick up the targeted block to build more like it, color and all
+		if pressed.contains(&Action::Pick) && ! self.world.transparent(target_i) {
+			let picked = self.world.voxel(target_i);
+			*self.hotbar.selected_mut() = Voxel::new(picked.block, picked.color);
+		}
+		if pressed.contains(&Action::Use) {
+			let used = interact::target(&self.world, self.camera.i, self.camera.position, look, TOUCH_DISTANCE);
+			if self.world.voxel(used).block == block::SIGN {
+				self.writing = Some(sign::Editor::new(&self.world, used));
+			// a door shutting on the player would trap them
+			} else if !player::occupies(&self.world, self.camera.i, self.camera.position, used) {
+				interact::interact(&mut self.world, used);
+			}
+		}
+		let placing = [
+			(pressed.contains(&Action::Place), self.hotbar.selected().clone()),
+			(pressed.contains(&Action::Tnt), Voxel::of(block::TNT)),
+			(pressed.contains(&Action::Wire), Voxel::of(block::WIRE)),
+			(pressed.contains(&Action::Switch), Voxel::of(block::SWITCH)),
+			(pressed.contains(&Action::Lamp), Voxel::of(block::LAMP)),
+		].into_iter().find_map(|(pressed, voxel)| pressed.then_some(voxel));
+		if let Some(voxel) = placing.filter(|_| ! self.world.transparent(target_i)) {
+			let (i, x, _) = raycast(&self.world, target_i, target_x, -look, 1);
+			let mut placed = false;
+			let covered: Vec<usize> = self.brush.voxels(&self.world, i).into_iter().flat_map(|j| self.symmetry.images(&self.world, j)).collect();
+			for j in covered {
+				if self.world.transparent(j) && !player::occupies(&self.world, self.camera.i, self.camera.position, j) && (self.creative || self.inventory.take(voxel.block)) {
+					self.world.set(j, voxel.clone());
+					placed = true;
+				}
+			}
+			if placed {
+				self.bus.publish(Event::Sound(Cue::Place(voxel.block), Some(i)));
+				// a few specks in front of the new block, where they can be seen
+				let (front, _, _) = raycast(&self.world, i, x, -look, 1);
+				self.particles.debris(front, voxel.color);
+			}
+			if voxel.block == block::SIGN && self.world.voxel(i).block == block::SIGN {
+				self.writing = Some(sign::Editor::new(&self.world, i));
+			}
+		}
+		// turn symmetry on around the targeted block, or off
+		if pressed.contains(&Action::Symmetry) {
+			self.symmetry.center = match self.symmetry.center {
+				None if ! self.world.transparent(target_i) => Some(target_i),
+				_ => None,
+			};
+		}
+		if pressed.contains(&Action::Brush) {
+			self.brush.next_shape();
+		}
+		if pressed.contains(&Action::BrushBigger) {
+			self.brush.resize(1);
+		}
+		if pressed.contains(&Action::BrushSmaller) {
+			self.brush.resize(-1);
+		}
+		// mark the corners of a box, then fill it, swap the targeted block's kind for the
+		// one being built with, or hollow it out
+		if pressed.contains(&Action::Corner1) && ! self.world.transparent(target_i) {
+			self.selection.corners[0] = Some(target_i);
+		}
+		if pressed.contains(&Action::Corner2) && ! self.world.transparent(target_i) {
+			self.selection.corners[1] = Some(target_i);
+		}
+		if pressed.contains(&Action::Deselect) {
+			self.selection = selection::Selection::default();
+		}
+		if let Some(region) = self.selection.region(&self.world) {
+			if pressed.contains(&Action::Fill) {
+				selection::fill(&mut self.world, &region, self.hotbar.selected());
+			}
+			if pressed.contains(&Action::Replace) && ! self.world.transparent(target_i) {
+				let from = self.world.voxel(target_i).block;
+				selection::replace(&mut self.world, &region, from, self.hotbar.selected());
+			}
+			if pressed.contains(&Action::Hollow) {
+				selection::hollow(&mut self.world, &region);
+			}
+			if pressed.contains(&Action::Copy) {
+				self.clipboard = Some(clipboard::Clipboard::copy(&self.world, &region));
+			}
+		}
+		if let Some(copy) = &mut self.clipboard {
+			if pressed.contains(&Action::Rotate) {
+				*copy = copy.rotated();
+			}
+			if pressed.contains(&Action::Paste) && ! self.world.transparent(target_i) {
+				let (i, _, _) = raycast(&self.world, target_i, target_x, -look, 1);
+				copy.paste(&mut self.world, i);
+			}
+			if pressed.contains(&Action::SaveClipboard) {
+				match copy.save(&self.clipboard_file) {
+					Ok(()) => log::info!("clipboard", "written to {}", self.clipboard_file),
+					Err(e) => log::warning!("clipboard", "{}", e),
+				}
+			}
+		}
+		if let Some(automaton) = &mut self.automaton {
+			if pressed.contains(&Action::RunLife) {
+				automaton.running = !automaton.running;
+			}
+			if pressed.contains(&Action::StepLife) && !automaton.running {
+				automaton.step(&mut self.world);
+			}
+		}
+		if pressed.contains(&Action::Ignite) && ! self.world.transparent(target_i) {
+			publish_spawns(&mut self.bus, |spawned| fire::ignite(&mut self.world, spawned, target_i));
+		}
+		if pressed.contains(&Action::Shoot) {
+			let mut arrow = Entity::new(Kind::Arrow { stuck: None }, self.camera.i, self.camera.position);
+			arrow.velocity = ARROW_SPEED * look;
+			self.bus.publish(Event::Spawn(arrow));
+		}
+		if pressed.contains(&Action::Throw) {
+			if let Some(block) = self.inventory.take_first() {
+				let mut thrown = Entity::new(Kind::Thrown(block), self.camera.i, self.camera.position);
+				thrown.velocity = THROW_SPEED * look;
+				self.bus.publish(Event::Spawn(thrown));
+			}
+		}
+		if let Some(image) = &self.stamp_image {
+			if pressed.contains(&Action::Stamp) && ! self.world.transparent(target_i) {
+				let (i, _, _) = raycast(&self.world, target_i, target_x, -look, 1);
+				stamp::stamp_image(&mut self.world, i, right, image);
+			}
+		}
+		if let Some(structure) = &self.user_prefab {
+			if pressed.contains(&Action::Prefab) && ! self.world.transparent(target_i) {
+				let (i, _, _) = raycast(&self.world, target_i, target_x, -look, 1);
+				structure.facing(look).place(&mut self.world, i);
+			}
+		}
+		if let Some(model) = &self.mesh_model {
+			if pressed.contains(&Action::Model) && ! self.world.transparent(target_i) {
+				let (i, _, _) = raycast(&self.world, target_i, target_x, -look, 1);
+				mesh::place_model(&mut self.world, i, model);
+			}
+		}
+		target_i
+	}
+
+	// Catch up with the voxels changed and hand the renderer a view from `view_point`, drawing
+	// the last one it finished.
+	fn render(&mut self, frame: &Frame, target_i: usize, (view_i, view_position, view_up, view_right): (usize, Vec3, Vec3, Vec3)) {
+		// what's kept worked out from the voxels catches up with them
+		let dirty = self.world.take_dirty();
+		self.minimap.notice(&self.world, &dirty);
+		self.stats.notice(&dirty);
+		// the light is baked for worlds that stay put, not streamed ones
+		if !self.config.lighting || self.streamer.is_some() {
+			self.lighting = None;
+		} else if let Some(lighting) = &mut self.lighting {
+			lighting.update(&self.world, &dirty);
+		} else {
+			self.lighting = Some(light::Lighting::start(&self.world));
+		}
+
+		// Draw pixels
+
+		self.unseen.merge(&dirty);
+		self.entities.extend(self.bus.spawns(&self.subscribed.joins));
+		self.renderer.submit(|| View {
+			world: self.world.clone(),
+			dirty: std::mem::take(&mut self.unseen),
+			i: view_i,
+			position: view_position,
+			look: frame.look,
+			right: view_right,
+			up: view_up,
+			fov: (self.camera.fov.0 * self.fov_scale, self.camera.fov.1 * self.fov_scale),
+			screen: self.camera.screen,
+			view_distance: self.weather.view_distance(self.view_distance),
+			target: target_i,
+			breaking: if self.breaking.0 == target_i { (self.breaking.1 / block::def(self.world.voxel(target_i).block).hardness.max(f32::EPSILON)).clamp(0.0, 1.0) } else { 0.0 },
+			selection: match self.selection.corners {
+				[Some(a), None] | [None, Some(a)] => Some(selection::Region::between(&self.world, a, a)),
+				_ => self.selection.region(&self.world),
+			},
+			xray: self.xray,
+			ambient: self.weather.sky(self.config.ambient),
+			entities: self.entities.iter().cloned().chain(self.session.iter().flat_map(|s| s.players(&self.world, self.streamer.as_ref()))).collect(),
+			particles: self.particles.list.clone(),
+			clouds: self.config.clouds.then(|| {
+				let (at, size) = locate(&self.world, self.streamer.as_ref(), view_i);
+				let camera = Vec3::from(at.map(|n| n as f32)) + view_position;
+				self.clouds.layer(self.world.gravity(), self.config.cloud_height, camera, size, self.weather.heaviness())
+			}),
+			light: self.lighting.as_ref().and_then(|l| l.light()).cloned(),
+			time: self.ticks as f32 * TICK,
+		});
+		if let Some(finished) = self.renderer.finished() {
+			self.stats.frame(finished.rays, finished.steps);
+			// unless it was started before the number of rays changed
+			if finished.screen.len() == self.camera.screen.0 && finished.screen.iter().all(|column| column.len() == self.camera.screen.1) {
+				self.screen = finished.screen;
+			}
+		}
+
+		// the rays fill as much of the window as keeps their shape, with bars along the sides
+		let (corner, rect) = letterbox(self.camera.screen);
+		clear_background(BLACK);
+		self.screen.iter().enumerate().for_each(|(i, screen_i)| {
+			screen_i.iter().enumerate().for_each(|(j, _)| {
+				draw_rectangle(
+					corner.x + rect*(i as f32),
+					corner.y + rect*(j as f32),
+					rect,
+					rect,
+					Color::from_vec(self.screen[i][j].0)
+				);
+			})
+		});
+	}
+
+	// Draw everything over the view, play the frame's sounds and show the menu, saying whether
+	// the game goes on.
+	fn draw(&mut self, frame: &Frame, target_i: usize) -> bool {
+		let (look, right) = (frame.look, frame.right);
+		// Screen text
+
+		draw_text(format!("{}", (1.0 / get_frame_time()) as usize), 2.0, 16.0, 24.0, WHITE);
+		let located = self.locator.describe(locate(&self.world, self.streamer.as_ref(), self.camera.i).0, look);
+		draw_text(&located, screen_width() - measure_text(&located, None, 20, 1.0).width - 4.0, 16.0, 20.0, WHITE);
+		if let Some(leading) = &mut self.guide {
+			let rise = self.world.gravity_at(self.camera.i).up();
+			let feet = settle(&self.world, self.camera.i, self.camera.position - (player::EYE_HEIGHT - 0.1) * rise).0;
+			match leading.update(&self.world, feet) {
+				guide::Leading::Ahead => leading.draw(&self.world, feet, look, right, rise),
+				guide::Leading::Arrived => {
+					self.bus.say(format!("arrived at {}", leading.name));
+					self.guide = None;
+				},
+				guide::Leading::Lost => {
+					self.bus.say(format!("can't find a way to walk to {}", leading.name));
+					self.guide = None;
+				},
+			}
+		}
+		if self.show_minimap {
+			self.minimap.update(&self.world, self.camera.i);
+			self.minimap.draw(screen_width() - self.minimap.size() - 4.0, 24.0, look);
+		}
+		if ! self.world.transparent(target_i) {
+			let name = block::def(self.world.voxel(target_i).block).name;
+			match self.world.text(target_i) {
+				Some(text) => draw_text(format!("{}: {}", name, text), 2.0, 36.0, 24.0, WHITE),
+				None => draw_text(name, 2.0, 36.0, 24.0, WHITE),
+			};
+		}
+		let (w, h) = (screen_width(), screen_height());
+		draw_rectangle(w/2.0 - 100.0, h - 24.0, 200.0 * (self.health / player::MAX_HEALTH).max(0.0), 12.0, RED);
+		draw_rectangle_lines(w/2.0 - 100.0, h - 24.0, 200.0, 12.0, 2.0, WHITE);
+		let mode = if self.creative { "creative" } else { "survival" };
+		let tool = if self.painting {
+			"painting in the hotbar's color".to_string()
+		} else {
+			format!("building with {}", block::def(self.hotbar.selected().block).name)
+		};
+		draw_text(format!("{}  {}  brush {}", mode, tool, self.brush.name()), 2.0, h - 30.0, 20.0, WHITE);
+		self.hotbar.draw(h - 32.0, &self.inventory, self.creative);
+		let dimensions = |[x, y, z]: [usize; 3]| format!("{}x{}x{}", x, y, z);
+		let selected = self.selection.region(&self.world).map(|r| format!("selected {}  ", dimensions(r.size)));
+		let copied = self.clipboard.as_ref().map(|c| format!("copied {}  ", dimensions(c.size)));
+		let mirrored = self.symmetry.center.map(|_| format!("{}  ", self.symmetry.describe()));
+		let seeing = (self.xray > 0).then(|| format!("x-ray {}  ", self.xray));
+		let spectating = self.spectator.is_some().then_some("spectating");
+		draw_text(format!("{}{}{}{}{}", selected.unwrap_or_default(), copied.unwrap_or_default(), mirrored.unwrap_or_default(), seeing.unwrap_or_default(), spectating.unwrap_or_default()), 2.0, h - 50.0, 20.0, WHITE);
+		self.touch.draw();
+		if self.inventory_open {
+			self.inventory.draw_screen(self.creative, self.hotbar.selected_mut());
+		}
+		if let Some(automaton) = &self.automaton {
+			let state = if automaton.running { "running" } else { "paused" };
+			draw_text(format!("life {}  generation {}  {}", automaton.rule(), automaton.generation, state), 2.0, 56.0, 20.0, WHITE);
+		}
+		self.stats.update(&self.world);
+		self.stats.draw();
+		if let Some(editor) = &self.writing {
+			editor.draw();
+		}
+		#[cfg(feature = "audio")]
+		{
+			let ear = sound::Ear { i: self.camera.i, position: self.camera.position, right };
+			for (cue, from) in self.bus.sounds(&self.subscribed.heard) {
+				self.sounds.play(cue, from, &self.world, &ear, &self.config);
+			}
+			self.music.update(&self.world, self.camera.i, self.camera.position, &self.config);
+		}
+		for text in self.bus.said(&self.subscribed.shown) {
+			self.console.say(text);
+		}
+		self.console.draw();
+		if self.map.open {
+			self.map.draw(&self.world, locate(&self.world, None, self.camera.i).0);
+		}
+		if self.menu.open {
+			let was_on = self.gravity_on;
+			self.edited = self.menu.draw(&self.config, &mut self.gravity_on, &mut self.flying);
+			if self.gravity_on != was_on {
+				self.world.set_gravity(Gravity { strength: if self.gravity_on { self.weight } else { 0.0 }, ..self.world.gravity() });
+			}
+			if !self.menu.open || self.menu.quit {
+				self.watcher.save(self.edited.as_ref().unwrap_or(&self.config));
+			}
+			if self.menu.quit {
+				return false;
+			}
+		}
+		if self.life != Life::Alive {
+			draw_text("You died", w/2.0 - 60.0, h/2.0, 40.0, RED);
+		}
+		true
+	}
+}
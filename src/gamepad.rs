@@ -0,0 +1,139 @@
+// A game controller, read on Linux from the joystick device the kernel makes for it. Buttons
+// and axes are numbered the way the xpad driver numbers an Xbox-style controller; others may
+// have theirs in other places. A controller can be plugged in or out while the game runs.
+// There's no controller support on other systems yet.
+
+use std::sync::{Arc, Mutex};
+use macroquad::prelude::*;
+
+#[cfg(target_os = "linux")]
+const DEVICE: &str = "/dev/input/js0";
+// seconds between looks for a controller while there isn't one
+#[cfg(target_os = "linux")]
+const RETRY: f32 = 2.0;
+// how far a trigger goes in before it counts as pressed, from 0 to 1
+const TRIGGER_PRESS: f32 = 0.5;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Button {
+	A, B, X, Y, LeftBumper, RightBumper, Back, Start, LeftStick, RightStick,
+	LeftTrigger, RightTrigger,
+}
+
+// what the buttons are called in the settings file, with the driver's number for each, or
+// for the triggers the number of their axis
+pub const BUTTONS: [(&str, Button, usize); 12] = [
+	("A", Button::A, 0), ("B", Button::B, 1), ("X", Button::X, 2), ("Y", Button::Y, 3),
+	("LeftBumper", Button::LeftBumper, 4), ("RightBumper", Button::RightBumper, 5),
+	("Back", Button::Back, 6), ("Start", Button::Start, 7),
+	("LeftStick", Button::LeftStick, 9), ("RightStick", Button::RightStick, 10),
+	("LeftTrigger", Button::LeftTrigger, 2), ("RightTrigger", Button::RightTrigger, 5),
+];
+
+impl Button {
+	pub fn parse(name: &str) -> Option<Button> {
+		BUTTONS.iter().find(|(n, _, _)| *n == name).map(|&(_, b, _)| b)
+	}
+
+	pub fn name(self) -> &'static str {
+		BUTTONS.iter().find(|&&(_, b, _)| b == self).map_or("?", |(n, _, _)| n)
+	}
+}
+
+// which way the sticks point, from -1 to 1, with x to the right and y down
+const LEFT_STICK: (usize, usize) = (0, 1);
+const RIGHT_STICK: (usize, usize) = (3, 4);
+
+#[derive(Clone, Default)]
+struct State {
+	axes: [f32; 8],
+	buttons: [bool; 16],
+}
+
+impl State {
+	fn down(&self, button: Button) -> bool {
+		let (_, _, n) = BUTTONS.iter().find(|&&(_, b, _)| b == button).unwrap();
+		match button {
+			// triggers rest at -1 and go to 1 when pulled all the way
+			Button::LeftTrigger | Button::RightTrigger => (self.axes[*n] + 1.0) / 2.0 > TRIGGER_PRESS,
+			_ => self.buttons[*n],
+		}
+	}
+}
+
+pub struct Gamepad {
+	// written by the thread reading the device
+	shared: Arc<Mutex<State>>,
+	// as of this frame and the one before
+	now: State,
+	before: State,
+}
+
+impl Gamepad {
+	// start listening for a controller
+	pub fn open() -> Gamepad {
+		let shared = Arc::new(Mutex::new(State::default()));
+		#[cfg(target_os = "linux")]
+		{
+			let shared = shared.clone();
+			std::thread::spawn(move || listen(&shared));
+		}
+		Gamepad { shared, now: State::default(), before: State::default() }
+	}
+
+	// take the controller's state for this frame
+	pub fn update(&mut self) {
+		self.before = std::mem::replace(&mut self.now, self.shared.lock().unwrap().clone());
+	}
+
+	pub fn down(&self, button: Button) -> bool {
+		self.now.down(button)
+	}
+
+	pub fn pressed(&self, button: Button) -> bool {
+		self.now.down(button) && !self.before.down(button)
+	}
+
+	// Where the left or right stick points, with x to the right and y up. Anything inside
+	// `deadzone` is taken for the stick at rest, and the rest of the way is bent by `curve`:
+	// above 1 for finer control near the middle.
+	pub fn stick(&self, right: bool, deadzone: f32, curve: f32) -> Vec2 {
+		let (x, y) = if right { RIGHT_STICK } else { LEFT_STICK };
+		let tilt = vec2(self.now.axes[x], -self.now.axes[y]);
+		let length = tilt.length();
+		if length <= deadzone {
+			return Vec2::ZERO;
+		}
+		let amount = ((length - deadzone) / (1.0 - deadzone)).min(1.0).powf(curve);
+		tilt / length * amount
+	}
+}
+
+// Read events from the device as they come, for as long as the game runs. Each is 8 bytes: a
+// timestamp, a 16 bit value, the kind of event and the button or axis it's for.
+#[cfg(target_os = "linux")]
+fn listen(shared: &Mutex<State>) {
+	use std::io::Read;
+	// set on the events describing the controller as it was when it was opened
+	const INITIAL: u8 = 0x80;
+	const BUTTON: u8 = 1;
+	const AXIS: u8 = 2;
+	loop {
+		if let Ok(mut device) = std::fs::File::open(DEVICE) {
+			let mut event = [0; 8];
+			while device.read_exact(&mut event).is_ok() {
+				let value = i16::from_ne_bytes([event[4], event[5]]);
+				let n = event[7] as usize;
+				let mut state = shared.lock().unwrap();
+				match event[6] & !INITIAL {
+					BUTTON => if let Some(b) = state.buttons.get_mut(n) { *b = value != 0 },
+					AXIS => if let Some(a) = state.axes.get_mut(n) { *a = value as f32 / i16::MAX as f32 },
+					_ => {},
+				}
+			}
+			// unplugged
+			*shared.lock().unwrap() = State::default();
+		}
+		std::thread::sleep(std::time::Duration::from_secs_f32(RETRY));
+	}
+}
@@ -0,0 +1,116 @@
+// Gravity: which way things fall. A world has one gravity, which a field can turn to point
+// another way voxel by voxel, so that walls and ceilings can be walked on.
+
+use std::collections::VecDeque;
+use glam::*;
+use rayon::prelude::*;
+use crate::{World, step};
+
+// Which way is down and how strongly things fall, in voxels per tick per tick. Down is always
+// along one of the axes, so the player's box stays lined up with the voxels.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Gravity {
+	pub axis: usize,
+	// +1 if things fall toward increasing coordinates along the axis, otherwise -1
+	pub sign: i32,
+	pub strength: f32,
+}
+
+impl Gravity {
+	pub const DEFAULT: Gravity = Gravity { axis: 1, sign: -1, strength: 0.01 };
+
+	// unit vector pointing up, against the pull
+	pub fn up(&self) -> Vec3 {
+		let mut up = Vec3::ZERO;
+		up[self.axis] = -self.sign as f32;
+		up
+	}
+
+	// rotation from the player's own frame, where up is +y, to the world's
+	#[cfg(feature = "graphics")]
+	pub fn frame(&self) -> Mat3 {
+		let (x, y, z) = (Vec3::X, Vec3::Y, Vec3::Z);
+		match (self.axis, self.sign < 0) {
+			(0, true) => Mat3::from_cols(-y, x, z),
+			(0, false) => Mat3::from_cols(y, -x, z),
+			(1, true) => Mat3::from_cols(x, y, z),
+			(1, false) => Mat3::from_cols(x, -y, -z),
+			(_, true) => Mat3::from_cols(x, z, -y),
+			(_, false) => Mat3::from_cols(x, -z, y),
+		}
+	}
+}
+
+
+// marks a voxel that keeps the world's own direction
+const UNCHANGED: u8 = u8::MAX;
+
+// Which way is down at each voxel; the strength is always the world's.
+pub struct GravityField {
+	// axis*2, plus 1 when falling toward increasing coordinates
+	down: Vec<u8>,
+}
+
+fn encode(axis: usize, sign: i32) -> u8 {
+	(axis * 2) as u8 + (sign > 0) as u8
+}
+
+impl GravityField {
+	pub fn at(&self, i: usize, world: Gravity) -> Gravity {
+		match self.down[i] {
+			UNCHANGED => world,
+			d => Gravity { axis: d as usize / 2, sign: if d % 2 == 1 { 1 } else { -1 }, ..world },
+		}
+	}
+
+	// Down toward the nearest voxel where `core` holds, counted in steps along the neighbor
+	// graph so the pull follows the world's topology. Where several ways are equally short the
+	// world's own down wins, then x, y and z in turn. Core voxels keep the world's gravity.
+	pub fn toward(world: &World, core: impl Fn(usize) -> bool + Sync) -> GravityField {
+		let [nx, ny, nz] = world.size();
+		let mut distance = vec![u32::MAX; nx*ny*nz];
+		let mut queue = VecDeque::new();
+		for (i, d) in distance.iter_mut().enumerate() {
+			if core(i) {
+				*d = 0;
+				queue.push_back(i);
+			}
+		}
+		while let Some(i) = queue.pop_front() {
+			for (axis, sign) in DIRECTIONS {
+				let j = step(world, i, axis, sign);
+				if distance[j] == u32::MAX {
+					distance[j] = distance[i] + 1;
+					queue.push_back(j);
+				}
+			}
+		}
+
+		let base = world.gravity();
+		let preferred = std::iter::once((base.axis, base.sign)).chain(DIRECTIONS);
+		let down = (0..distance.len()).into_par_iter().map(|i| {
+			let d = distance[i];
+			if d == 0 || d == u32::MAX {
+				return UNCHANGED;
+			}
+			preferred.clone()
+				.find(|&(axis, sign)| distance[step(world, i, axis, sign)] == d - 1)
+				.map_or(UNCHANGED, |(axis, sign)| encode(axis, sign))
+		}).collect();
+		GravityField { down }
+	}
+
+	// The world's gravity below `plane` along its axis and reversed from there on, so the
+	// ground on one side and the ceiling on the other can both be walked on.
+	pub fn flipped(world: &World, plane: usize) -> GravityField {
+		let [_, ny, nz] = world.size();
+		let base = world.gravity();
+		let down = (0..world.size().iter().product()).into_par_iter().map(|i: usize| {
+			let coordinate = [i / (ny*nz), i / nz % ny, i % nz][base.axis];
+			if coordinate < plane { UNCHANGED } else { encode(base.axis, -base.sign) }
+		}).collect();
+		GravityField { down }
+	}
+}
+
+const DIRECTIONS: [(usize, i32); 6] = [(0, -1), (0, 1), (1, -1), (1, 1), (2, -1), (2, 1)];
@@ -0,0 +1,65 @@
+// The guide, started from the console: an arrow at the top of the screen pointing the way to
+// walk to a waypoint. It follows a path found over the neighbor graph, so it leads around
+// whatever's in the way and the short way around the world.
+
+use macroquad::prelude::*;
+use crate::World;
+use crate::path::{self, PathOptions};
+
+// steps along the path the arrow points at, to smooth over its corners
+const LOOKAHEAD: usize = 3;
+const ARROW: f32 = 20.0;
+
+pub enum Leading {
+	Ahead,
+	Arrived,
+	// there's no way to walk there from here
+	Lost,
+}
+
+pub struct Guide {
+	pub name: String,
+	target: usize,
+	// voxels still to walk through, ending with the target
+	path: Vec<usize>,
+}
+
+impl Guide {
+	// guide to standing in voxel `target`
+	pub fn new(name: String, target: usize) -> Guide {
+		Guide { name, target, path: vec![] }
+	}
+
+	// Keep up with the player standing in `feet`: move along the path as they walk it, or find
+	// a new one once they've left it. Off the ground, the old path is kept until they land.
+	pub fn update(&mut self, world: &World, feet: usize) -> Leading {
+		if feet == self.target {
+			return Leading::Arrived;
+		}
+		if let Some(k) = self.path.iter().position(|&i| i == feet) {
+			self.path.drain(..=k);
+		} else if path::standable(world, feet, &PathOptions::PLAYER) {
+			match world.find_path(feet, self.target, &PathOptions::PLAYER) {
+				Some(found) => self.path = found,
+				None => return Leading::Lost,
+			}
+		}
+		Leading::Ahead
+	}
+
+	// the arrow, turned from the way the player looks, with the steps left under it
+	pub fn draw(&self, world: &World, feet: usize, look: Vec3, right: Vec3, rise: Vec3) {
+		let Some(&ahead) = self.path.get(LOOKAHEAD.min(self.path.len().saturating_sub(1))) else { return };
+		let [dx, dy, dz] = world.displacement(feet, ahead);
+		let d = vec3(dx as f32, dy as f32, dz as f32);
+		let level = d - d.dot(rise) * rise;
+		let center = vec2(screen_width() / 2.0, 2.0 * ARROW);
+		if level != Vec3::ZERO {
+			let angle = level.dot(right).atan2(level.dot(look));
+			let point = |a: f32, r: f32| center + r * vec2(a.sin(), -a.cos());
+			draw_triangle(point(angle, ARROW), point(angle + 2.5, 0.7 * ARROW), point(angle - 2.5, 0.7 * ARROW), YELLOW);
+		}
+		let text = format!("{}: {} steps", self.name, self.path.len());
+		draw_text(&text, center.x - measure_text(&text, None, 20, 1.0).width / 2.0, center.y + 1.5 * ARROW + 4.0, 20.0, WHITE);
+	}
+}
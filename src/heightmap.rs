@@ -0,0 +1,72 @@
+// Heightmap import: build terrain from a grayscale image, optionally colorized by a second image
+
+use glam::*;
+use image::RgbaImage;
+use crate::{World, Voxel, Progress, link_world, fill_columns};
+use crate::block;
+use crate::rng::Rng;
+
+pub fn load_image(path: &str) -> Result<RgbaImage, String> {
+	let bytes = std::fs::read(path).map_err(|e| format!("{}: {}", path, e))?;
+	image::load_from_memory(&bytes).map(|i| i.to_rgba8()).map_err(|e| format!("{}: {}", path, e))
+}
+
+// the color of pixel (x, y), each channel from 0 to 1
+pub fn pixel(image: &RgbaImage, x: u32, y: u32) -> Vec4 {
+	Vec4::from(image.get_pixel(x, y).0.map(|c| c as f32 / 255.0))
+}
+
+// bilinear sample at texel coordinates (u, v), wrapping at the image edges so the
+// terrain joins up seamlessly across the torus
+fn sample(image: &RgbaImage, u: f32, v: f32) -> Vec4 {
+	let (w, h) = (image.width() as i32, image.height() as i32);
+	let (u0, v0) = (u.floor(), v.floor());
+	let (fu, fv) = (u - u0, v - v0);
+	let texel = |du: i32, dv: i32| -> Vec4 {
+		let x = (u0 as i32 + du).rem_euclid(w) as u32;
+		let y = (v0 as i32 + dv).rem_euclid(h) as u32;
+		pixel(image, x, y)
+	};
+	let top = texel(0, 0).lerp(texel(1, 0), fu);
+	let bottom = texel(0, 1).lerp(texel(1, 1), fu);
+	top.lerp(bottom, fv)
+}
+
+fn luminance(c: Vec4) -> f32 {
+	0.2126*c.x + 0.7152*c.y + 0.0722*c.z
+}
+
+// The height of the ground in each column of a world nx by ny by nz, indexed [x*nz + z], from
+// a grayscale image stretched over it. Black maps to a single layer of ground and white to one
+// below the ceiling, so there is always something to stand on and room to stand.
+pub fn heights(image: &RgbaImage, nx: usize, ny: usize, nz: usize) -> Vec<f32> {
+	(0..nx*nz).map(|k| {
+		let (u, v) = scale(image, nx, nz, k / nz, k % nz);
+		1.0 + luminance(sample(image, u, v)) * (ny as f32 - 2.0)
+	}).collect()
+}
+
+// texel coordinates of column (x, z) of a world nx by nz in `image`
+fn scale(image: &RgbaImage, nx: usize, nz: usize, x: usize, z: usize) -> (f32, f32) {
+	(x as f32 * image.width() as f32 / nx as f32, z as f32 * image.height() as f32 / nz as f32)
+}
+
+// terrain from `heights`, colored from `colors` if there's an image of them
+pub fn build_world_from_heightmap(nx: usize, ny: usize, nz: usize, heights: &[f32], colors: Option<&RgbaImage>, rng: &mut Rng, progress: &Progress) -> World {
+	let mut world = link_world(nx, ny, nz, progress);
+	fill_columns(&mut world, nx, ny, nz, |x, z| {
+		let height = heights[x*nz + z].round().clamp(1.0, ny as f32 - 1.0) as usize;
+		let color = match colors {
+			Some(image) => {
+				let (u, v) = scale(image, nx, nz, x, z);
+				sample(image, u, v).truncate().extend(1.0)
+			},
+			None => {
+				let g = rng.range(-0.025, 0.025);
+				block::def(block::STONE).color + vec4(g, g, g, 0.0)
+			},
+		};
+		(height, Voxel::new(block::STONE, color))
+	});
+	world
+}
@@ -0,0 +1,76 @@
+// The hotbar: a row of blocks to build with, shown along the bottom of the screen, so building
+// can switch between them without opening the inventory. Keys `1` to `9` pick a slot and the
+// mouse wheel moves through them; choosing a block on the inventory screen or picking one
+// from the world puts it in the slot in use.
+
+use macroquad::prelude::*;
+use crate::Voxel;
+use crate::block;
+use crate::inventory::Inventory;
+
+pub const SLOTS: usize = 9;
+// size of a slot on screen, in pixels
+const SLOT: f32 = 36.0;
+
+const KEYS: [KeyCode; SLOTS] = [
+	KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5,
+	KeyCode::Key6, KeyCode::Key7, KeyCode::Key8, KeyCode::Key9,
+];
+
+pub struct Hotbar {
+	slots: [Voxel; SLOTS],
+	active: usize,
+}
+
+impl Hotbar {
+	pub fn new() -> Hotbar {
+		let slots = [
+			block::PLANKS, block::STONE, block::DIRT, block::GRASS, block::SAND,
+			block::WOOD, block::LEAVES, block::DOOR, block::SIGN,
+		].map(Voxel::of);
+		Hotbar { slots, active: 0 }
+	}
+
+	// the block in the slot in use
+	pub fn selected(&self) -> &Voxel {
+		&self.slots[self.active]
+	}
+
+	pub fn selected_mut(&mut self) -> &mut Voxel {
+		&mut self.slots[self.active]
+	}
+
+	// switch slots with the number keys and the mouse wheel
+	pub fn update(&mut self) {
+		if let Some(k) = KEYS.iter().position(|&key| is_key_pressed(key)) {
+			self.active = k;
+		}
+		let (_, wheel) = mouse_wheel();
+		if wheel != 0.0 {
+			self.step(wheel < 0.0);
+		}
+	}
+
+	// move to the next slot, or the one before
+	pub fn step(&mut self, next: bool) {
+		self.active = (self.active + if next { 1 } else { SLOTS - 1 }) % SLOTS;
+	}
+
+	// Draw the slots centered above `bottom`, with how many of each block there are to build
+	// with in survival mode.
+	pub fn draw(&self, bottom: f32, inventory: &Inventory, creative: bool) {
+		let left = screen_width()/2.0 - SLOTS as f32 * SLOT / 2.0;
+		let top = bottom - SLOT;
+		draw_rectangle(left - 4.0, top - 4.0, SLOTS as f32 * SLOT + 8.0, SLOT + 8.0, Color::new(0.0, 0.0, 0.0, 0.5));
+		for (k, voxel) in self.slots.iter().enumerate() {
+			let x = left + k as f32 * SLOT;
+			draw_rectangle(x + 4.0, top + 4.0, SLOT - 8.0, SLOT - 8.0, Color::from_vec(voxel.color));
+			if !creative {
+				draw_text(format!("{}", inventory.count(voxel.block)), x + 4.0, top + SLOT - 4.0, 16.0, WHITE);
+			}
+			if k == self.active {
+				draw_rectangle_lines(x, top, SLOT, SLOT, 3.0, YELLOW);
+			}
+		}
+	}
+}
@@ -0,0 +1,203 @@
+// The keys, mouse buttons and controller buttons the game is played with, looked up by what
+// they do rather than by name, so they can be changed in the settings file for other keyboard
+// layouts or hands. Typing into signs and the console, the hotbar's number keys and the menus'
+// own mouse clicks stay where they are.
+
+use macroquad::prelude::*;
+use crate::gamepad::{Button, Gamepad};
+use crate::touch::Touch;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Binding {
+	Key(KeyCode),
+	Mouse(MouseButton),
+}
+
+// what the keys and buttons are called in the settings file
+const KEYS: [(&str, KeyCode); 91] = [
+	("A", KeyCode::A), ("B", KeyCode::B), ("C", KeyCode::C), ("D", KeyCode::D), ("E", KeyCode::E),
+	("F", KeyCode::F), ("G", KeyCode::G), ("H", KeyCode::H), ("I", KeyCode::I), ("J", KeyCode::J),
+	("K", KeyCode::K), ("L", KeyCode::L), ("M", KeyCode::M), ("N", KeyCode::N), ("O", KeyCode::O),
+	("P", KeyCode::P), ("Q", KeyCode::Q), ("R", KeyCode::R), ("S", KeyCode::S), ("T", KeyCode::T),
+	("U", KeyCode::U), ("V", KeyCode::V), ("W", KeyCode::W), ("X", KeyCode::X), ("Y", KeyCode::Y),
+	("Z", KeyCode::Z),
+	("Key0", KeyCode::Key0), ("Key1", KeyCode::Key1), ("Key2", KeyCode::Key2), ("Key3", KeyCode::Key3),
+	("Key4", KeyCode::Key4), ("Key5", KeyCode::Key5), ("Key6", KeyCode::Key6), ("Key7", KeyCode::Key7),
+	("Key8", KeyCode::Key8), ("Key9", KeyCode::Key9),
+	("F1", KeyCode::F1), ("F2", KeyCode::F2), ("F3", KeyCode::F3), ("F4", KeyCode::F4),
+	("F5", KeyCode::F5), ("F6", KeyCode::F6), ("F7", KeyCode::F7), ("F8", KeyCode::F8),
+	("F9", KeyCode::F9), ("F10", KeyCode::F10), ("F11", KeyCode::F11), ("F12", KeyCode::F12),
+	("Space", KeyCode::Space), ("Tab", KeyCode::Tab), ("Escape", KeyCode::Escape), ("Enter", KeyCode::Enter),
+	("Backspace", KeyCode::Backspace), ("Delete", KeyCode::Delete), ("Insert", KeyCode::Insert),
+	("Home", KeyCode::Home), ("End", KeyCode::End), ("PageUp", KeyCode::PageUp), ("PageDown", KeyCode::PageDown),
+	("Up", KeyCode::Up), ("Down", KeyCode::Down), ("Left", KeyCode::Left), ("Right", KeyCode::Right),
+	("LeftShift", KeyCode::LeftShift), ("RightShift", KeyCode::RightShift),
+	("LeftControl", KeyCode::LeftControl), ("RightControl", KeyCode::RightControl),
+	("LeftAlt", KeyCode::LeftAlt), ("RightAlt", KeyCode::RightAlt), ("CapsLock", KeyCode::CapsLock),
+	("Minus", KeyCode::Minus), ("Equal", KeyCode::Equal), ("LeftBracket", KeyCode::LeftBracket),
+	("RightBracket", KeyCode::RightBracket), ("Backslash", KeyCode::Backslash), ("Semicolon", KeyCode::Semicolon),
+	("Apostrophe", KeyCode::Apostrophe), ("Comma", KeyCode::Comma), ("Period", KeyCode::Period),
+	("Slash", KeyCode::Slash), ("GraveAccent", KeyCode::GraveAccent),
+	("Kp0", KeyCode::Kp0), ("Kp1", KeyCode::Kp1), ("Kp2", KeyCode::Kp2), ("Kp3", KeyCode::Kp3),
+	("Kp4", KeyCode::Kp4), ("Kp5", KeyCode::Kp5), ("Kp6", KeyCode::Kp6), ("Kp7", KeyCode::Kp7),
+	("Kp8", KeyCode::Kp8), ("Kp9", KeyCode::Kp9),
+];
+const BUTTONS: [(&str, MouseButton); 3] = [
+	("MouseLeft", MouseButton::Left), ("MouseRight", MouseButton::Right), ("MouseMiddle", MouseButton::Middle),
+];
+
+impl Binding {
+	pub fn parse(name: &str) -> Option<Binding> {
+		KEYS.iter().find(|(n, _)| *n == name).map(|&(_, k)| Binding::Key(k))
+			.or_else(|| BUTTONS.iter().find(|(n, _)| *n == name).map(|&(_, b)| Binding::Mouse(b)))
+	}
+
+	pub fn name(self) -> &'static str {
+		match self {
+			Binding::Key(key) => KEYS.iter().find(|&&(_, k)| k == key).map_or("?", |(n, _)| n),
+			Binding::Mouse(button) => BUTTONS.iter().find(|&&(_, b)| b == button).map_or("?", |(n, _)| n),
+		}
+	}
+
+	fn down(self) -> bool {
+		match self {
+			Binding::Key(k) => is_key_down(k),
+			Binding::Mouse(b) => is_mouse_button_down(b),
+		}
+	}
+
+	fn pressed(self) -> bool {
+		match self {
+			Binding::Key(k) => is_key_pressed(k),
+			Binding::Mouse(b) => is_mouse_button_pressed(b),
+		}
+	}
+}
+
+// Everything a key or button can be bound to, in the order of ACTIONS.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+	Forward, Back, Left, Right, Jump, Sneak, Sprint, Zoom, RollLeft, RollRight,
+	Break, Place, Pick, Use, Paint,
+	Inventory, Console, Chat, Pause, Map, Minimap, Stats, Xray, Creative, Fullscreen, Spectate, NextTrack,
+	Tnt, Wire, Switch, Lamp, Ignite, Shoot, Throw,
+	Symmetry, Brush, BrushBigger, BrushSmaller,
+	Corner1, Corner2, Deselect, Fill, Replace, Hollow,
+	Copy, Rotate, Paste, SaveClipboard,
+	RunLife, StepLife, Stamp, Prefab, Model,
+}
+
+// each action's name in the settings file, its usual key and controller button, and what it
+// does
+pub const ACTIONS: [(Action, &str, Binding, Option<Button>, &str); 53] = [
+	(Action::Forward, "forward", Binding::Key(KeyCode::W), None, "walk forward"),
+	(Action::Back, "back", Binding::Key(KeyCode::S), None, "walk backward"),
+	(Action::Left, "left", Binding::Key(KeyCode::A), None, "step left"),
+	(Action::Right, "right", Binding::Key(KeyCode::D), None, "step right"),
+	(Action::Jump, "jump", Binding::Key(KeyCode::Space), Some(Button::A), "jump, swim or fly up; twice quickly to fly"),
+	(Action::Sneak, "sneak", Binding::Key(KeyCode::LeftControl), Some(Button::B), "sneak"),
+	(Action::Sprint, "sprint", Binding::Key(KeyCode::LeftShift), Some(Button::LeftStick), "sprint, or fly down"),
+	(Action::Zoom, "zoom", Binding::Key(KeyCode::C), None, "hold to zoom in"),
+	(Action::RollLeft, "roll_left", Binding::Key(KeyCode::Semicolon), None, "roll the view to the left"),
+	(Action::RollRight, "roll_right", Binding::Key(KeyCode::Apostrophe), None, "roll the view to the right"),
+	(Action::Break, "break", Binding::Mouse(MouseButton::Left), Some(Button::RightTrigger), "break blocks, or paint them"),
+	(Action::Place, "place", Binding::Mouse(MouseButton::Right), Some(Button::LeftTrigger), "place the hotbar's block"),
+	(Action::Pick, "pick", Binding::Mouse(MouseButton::Middle), Some(Button::RightStick), "pick the block looked at"),
+	(Action::Use, "use", Binding::Key(KeyCode::E), Some(Button::X), "open doors, flip switches, write on signs"),
+	(Action::Paint, "paint", Binding::Key(KeyCode::Q), None, "switch between breaking and painting"),
+	(Action::Inventory, "inventory", Binding::Key(KeyCode::Tab), Some(Button::Y), "open the inventory"),
+	(Action::Console, "console", Binding::Key(KeyCode::Slash), None, "open the console"),
+	(Action::Chat, "chat", Binding::Key(KeyCode::Enter), None, "chat with the other players"),
+	(Action::Pause, "pause", Binding::Key(KeyCode::Escape), Some(Button::Start), "pause, or close what's open"),
+	(Action::Map, "map", Binding::Key(KeyCode::F3), Some(Button::Back), "open the map"),
+	(Action::Minimap, "minimap", Binding::Key(KeyCode::F2), None, "show or hide the minimap"),
+	(Action::Stats, "stats", Binding::Key(KeyCode::F9), None, "show or hide world and renderer statistics"),
+	(Action::Xray, "xray", Binding::Key(KeyCode::F6), None, "see through more solid voxels"),
+	(Action::Creative, "creative", Binding::Key(KeyCode::F4), None, "switch between creative and survival"),
+	(Action::Fullscreen, "fullscreen", Binding::Key(KeyCode::F11), None, "switch fullscreen on or off"),
+	(Action::Spectate, "spectate", Binding::Key(KeyCode::F7), None, "fly the camera freely, or snap back to the body"),
+	(Action::NextTrack, "next_track", Binding::Key(KeyCode::F8), None, "skip to the next music track"),
+	(Action::Tnt, "tnt", Binding::Key(KeyCode::T), None, "place TNT"),
+	(Action::Wire, "wire", Binding::Key(KeyCode::Z), None, "place wire"),
+	(Action::Switch, "switch", Binding::Key(KeyCode::X), None, "place a switch"),
+	(Action::Lamp, "lamp", Binding::Key(KeyCode::Period), None, "place a lamp"),
+	(Action::Ignite, "ignite", Binding::Key(KeyCode::L), None, "set the block looked at on fire"),
+	(Action::Shoot, "shoot", Binding::Key(KeyCode::F), None, "shoot an arrow"),
+	(Action::Throw, "throw", Binding::Key(KeyCode::G), None, "throw a block from the inventory"),
+	(Action::Symmetry, "symmetry", Binding::Key(KeyCode::Y), None, "mirror building around the block looked at"),
+	(Action::Brush, "brush", Binding::Key(KeyCode::V), None, "change the brush's shape"),
+	(Action::BrushBigger, "brush_bigger", Binding::Key(KeyCode::Equal), None, "make the brush bigger"),
+	(Action::BrushSmaller, "brush_smaller", Binding::Key(KeyCode::Minus), None, "make the brush smaller"),
+	(Action::Corner1, "corner1", Binding::Key(KeyCode::LeftBracket), None, "mark a selection's first corner"),
+	(Action::Corner2, "corner2", Binding::Key(KeyCode::RightBracket), None, "mark a selection's second corner"),
+	(Action::Deselect, "deselect", Binding::Key(KeyCode::Backspace), None, "clear the selection"),
+	(Action::Fill, "fill", Binding::Key(KeyCode::J), None, "fill the selection"),
+	(Action::Replace, "replace", Binding::Key(KeyCode::K), None, "replace the block looked at in the selection"),
+	(Action::Hollow, "hollow", Binding::Key(KeyCode::H), None, "hollow out the selection"),
+	(Action::Copy, "copy", Binding::Key(KeyCode::O), None, "copy the selection"),
+	(Action::Rotate, "rotate", Binding::Key(KeyCode::U), None, "turn what's copied"),
+	(Action::Paste, "paste", Binding::Key(KeyCode::I), None, "paste what's copied"),
+	(Action::SaveClipboard, "save_clipboard", Binding::Key(KeyCode::F5), None, "write what's copied to a file"),
+	(Action::RunLife, "run_life", Binding::Key(KeyCode::R), None, "start or stop the cellular automaton"),
+	(Action::StepLife, "step_life", Binding::Key(KeyCode::N), None, "step the stopped automaton once"),
+	(Action::Stamp, "stamp", Binding::Key(KeyCode::P), None, "stamp the image"),
+	(Action::Prefab, "prefab", Binding::Key(KeyCode::B), None, "place the prefab"),
+	(Action::Model, "model", Binding::Key(KeyCode::M), None, "place the model"),
+];
+
+// the key or mouse button, and the controller button if any, bound to each action
+#[derive(Clone, PartialEq, Debug)]
+pub struct Bindings {
+	keys: [Binding; ACTIONS.len()],
+	buttons: [Option<Button>; ACTIONS.len()],
+}
+
+impl Default for Bindings {
+	fn default() -> Bindings {
+		Bindings {
+			keys: ACTIONS.map(|(_, _, key, _, _)| key),
+			buttons: ACTIONS.map(|(_, _, _, button, _)| button),
+		}
+	}
+}
+
+fn action(name: &str) -> Option<usize> {
+	ACTIONS.iter().position(|(_, n, _, _, _)| *n == name)
+}
+
+impl Bindings {
+	pub fn get(&self, action: Action) -> Binding {
+		self.keys[action as usize]
+	}
+
+	pub fn button(&self, action: Action) -> Option<Button> {
+		self.buttons[action as usize]
+	}
+
+	// bind the action called `name` to a key, returning whether there is one
+	pub fn set(&mut self, name: &str, binding: Binding) -> bool {
+		action(name).map(|a| self.keys[a] = binding).is_some()
+	}
+
+	// bind the action called `name` to a controller button, or to none
+	pub fn set_button(&mut self, name: &str, button: Option<Button>) -> bool {
+		action(name).map(|a| self.buttons[a] = button).is_some()
+	}
+}
+
+// The keyboard, mouse, controller and touch screen together, as this frame finds them.
+pub struct Controls<'a> {
+	pub bindings: &'a Bindings,
+	pub gamepad: &'a Gamepad,
+	pub touch: &'a Touch,
+}
+
+impl Controls<'_> {
+	pub fn down(&self, action: Action) -> bool {
+		self.bindings.get(action).down() || self.bindings.button(action).is_some_and(|b| self.gamepad.down(b)) || self.touch.down(action)
+	}
+
+	pub fn pressed(&self, action: Action) -> bool {
+		self.bindings.get(action).pressed() || self.bindings.button(action).is_some_and(|b| self.gamepad.pressed(b)) || self.touch.pressed(action)
+	}
+}
@@ -0,0 +1,47 @@
+// Blocks that do something when the player uses them with `E`: doors open and close, and
+// switches flip. What using a block does is looked up from its type.
+
+use glam::*;
+#[cfg(feature = "graphics")]
+use crate::{World, Voxel, raycast};
+use crate::block::{self, BlockId};
+#[cfg(feature = "graphics")]
+use crate::circuit;
+
+#[cfg(feature = "graphics")]
+fn interactive(block: BlockId) -> bool {
+	matches!(block, block::DOOR | block::SWITCH)
+}
+
+// whether a voxel of `block` in `state` can be seen through, as open doors can
+pub fn transparent(block: BlockId, state: u8) -> bool {
+	block::def(block).transparent || (block == block::DOOR && state != 0)
+}
+
+// The first voxel along `ray` from `position` in voxel i, up to `reach` voxels away, that can be
+// used or is solid. Unlike a plain raycast this stops at open doors too, which are see-through.
+#[cfg(feature = "graphics")]
+pub fn target(world: &World, i: usize, position: Vec3, ray: Vec3, reach: usize) -> usize {
+	let (mut i, mut x) = (i, position);
+	for _ in 0..reach {
+		(i, x, _) = raycast(world, i, x, ray, 1);
+		if !world.transparent(i) || interactive(world.voxel(i).block) {
+			break;
+		}
+	}
+	i
+}
+
+// use the block in voxel i, if it does anything
+#[cfg(feature = "graphics")]
+pub fn interact(world: &mut World, i: usize) {
+	match world.voxel(i).block {
+		block::DOOR => {
+			let door = world.voxel(i).clone();
+			let open = door.state == 0;
+			world.set(i, Voxel { transparent: transparent(block::DOOR, open as u8), ..door.with_state(open as u8) });
+		},
+		block::SWITCH => circuit::toggle(world, i),
+		_ => {},
+	}
+}
@@ -0,0 +1,1304 @@
+// An interpreter for the part of Lua scripts are written in (see the script module): nil,
+// booleans, numbers, strings, tables, and functions that keep the variables around them; local
+// and global variables; if, while, repeat, numeric and generic for, break and return; calls
+// giving back any number of values, and methods called with `:`. Left out are varargs, goto,
+// metatables, coroutines and most of the standard library, of which there's type, tostring,
+// tonumber, pairs, ipairs, next, error and the math table. Whatever runs the scripts adds
+// functions of its own, and is called back by their names when a script calls them.
+//
+// A script is parsed into a tree of statements and expressions, which is walked to run it. A
+// call into a script fails after MAX_STEPS statements and loop turns, or MAX_DEPTH calls deep,
+// so one that's stuck going around a loop or calling itself can't hang the game.
+
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::ops::Bound;
+use std::rc::Rc;
+use crate::rng::Rng;
+
+// statements and loop turns a call runs before it's stopped
+const MAX_STEPS: usize = 1_000_000;
+// how deep calls, and brackets and blocks in the text, can go; each call takes a good deal of
+// stack to walk, and twice this overflows a test's two megabytes unoptimized
+const MAX_DEPTH: usize = 100;
+
+const KEYWORDS: [&str; 21] = ["and", "break", "do", "else", "elseif", "end", "false", "for", "function", "goto", "if", "in", "local", "nil", "not", "or", "repeat", "return", "then", "true", "until"];
+// longest first, so `..` isn't taken for two `.`
+const SYMBOLS: [&str; 27] = ["...", "..", "==", "~=", "<=", ">=", "//", "+", "-", "*", "/", "%", "^", "#", "<", ">", "=", "(", ")", "{", "}", "[", "]", ";", ":", ",", "."];
+
+// what went wrong, and on which line
+#[derive(Debug)]
+pub struct Error {
+	pub line: usize,
+	pub message: String,
+}
+
+impl fmt::Display for Error {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		write!(f, "line {}: {}", self.line, self.message)
+	}
+}
+
+// what runs the scripts, for the functions it adds
+pub trait Host {
+	// call its function `name`, registered with `Interpreter::register`
+	fn call(&mut self, name: &str, args: Vec<Value>) -> Result<Vec<Value>, String>;
+}
+
+#[derive(Clone)]
+pub enum Value {
+	Nil,
+	Bool(bool),
+	Number(f64),
+	Str(Rc<str>),
+	Table(Rc<RefCell<Table>>),
+	Function(Rc<Function>),
+}
+
+impl Value {
+	pub fn number(&self) -> Option<f64> {
+		match self {
+			Value::Number(n) => Some(*n),
+			_ => None,
+		}
+	}
+
+	pub fn type_name(&self) -> &'static str {
+		match self {
+			Value::Nil => "nil",
+			Value::Bool(_) => "boolean",
+			Value::Number(_) => "number",
+			Value::Str(_) => "string",
+			Value::Table(_) => "table",
+			Value::Function(_) => "function",
+		}
+	}
+
+	// everything but nil and false counts as true
+	fn truthy(&self) -> bool {
+		!matches!(self, Value::Nil | Value::Bool(false))
+	}
+
+	fn equals(&self, other: &Value) -> bool {
+		match (self, other) {
+			(Value::Nil, Value::Nil) => true,
+			(Value::Bool(a), Value::Bool(b)) => a == b,
+			(Value::Number(a), Value::Number(b)) => a == b,
+			(Value::Str(a), Value::Str(b)) => a == b,
+			(Value::Table(a), Value::Table(b)) => Rc::ptr_eq(a, b),
+			(Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+			_ => false,
+		}
+	}
+}
+
+impl From<&str> for Value {
+	fn from(text: &str) -> Value {
+		Value::Str(text.into())
+	}
+}
+
+impl From<f64> for Value {
+	fn from(n: f64) -> Value {
+		Value::Number(n)
+	}
+}
+
+impl fmt::Display for Value {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			Value::Nil => write!(f, "nil"),
+			Value::Bool(b) => write!(f, "{}", b),
+			// whole numbers without a point, the way Lua shows its integers
+			Value::Number(n) if n.fract() == 0.0 && n.abs() < 1e15 => write!(f, "{}", *n as i64),
+			Value::Number(n) => write!(f, "{}", n),
+			Value::Str(s) => write!(f, "{}", s),
+			Value::Table(t) => write!(f, "table: {:p}", Rc::as_ptr(t)),
+			Value::Function(g) => write!(f, "function: {:p}", Rc::as_ptr(g)),
+		}
+	}
+}
+
+// What a table can be indexed by. Tables and functions can't, unlike in Lua.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+enum Key {
+	Bool(bool),
+	Number(Number),
+	Str(Rc<str>),
+}
+
+// a number as a key, ordered so tables go through their keys in order
+#[derive(Clone, Copy)]
+struct Number(f64);
+
+impl PartialEq for Number {
+	fn eq(&self, other: &Number) -> bool {
+		self.cmp(other) == Ordering::Equal
+	}
+}
+
+impl Eq for Number {}
+
+impl PartialOrd for Number {
+	fn partial_cmp(&self, other: &Number) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+
+impl Ord for Number {
+	fn cmp(&self, other: &Number) -> Ordering {
+		self.0.total_cmp(&other.0)
+	}
+}
+
+impl Key {
+	fn of(value: &Value) -> Result<Key, String> {
+		match value {
+			Value::Bool(b) => Ok(Key::Bool(*b)),
+			Value::Number(n) if n.is_nan() => Err("a table can't be indexed by NaN".to_string()),
+			// -0 and 0 are the same key
+			Value::Number(n) => Ok(Key::Number(Number(if *n == 0.0 { 0.0 } else { *n }))),
+			Value::Str(s) => Ok(Key::Str(s.clone())),
+			_ => Err(format!("a table can't be indexed by a {} value", value.type_name())),
+		}
+	}
+
+	fn value(&self) -> Value {
+		match self {
+			Key::Bool(b) => Value::Bool(*b),
+			Key::Number(n) => Value::Number(n.0),
+			Key::Str(s) => Value::Str(s.clone()),
+		}
+	}
+}
+
+#[derive(Default)]
+pub struct Table {
+	// none are nil: setting one to nil takes it out
+	entries: BTreeMap<Key, Value>,
+}
+
+impl Table {
+	fn get(&self, key: &Value) -> Value {
+		Key::of(key).ok().and_then(|k| self.entries.get(&k).cloned()).unwrap_or(Value::Nil)
+	}
+
+	fn set(&mut self, key: &Value, value: Value) -> Result<(), String> {
+		let key = Key::of(key)?;
+		if matches!(value, Value::Nil) {
+			self.entries.remove(&key);
+		} else {
+			self.entries.insert(key, value);
+		}
+		Ok(())
+	}
+
+	// the last of the keys 1, 2, 3... that the table has, like Lua's `#`
+	fn length(&self) -> usize {
+		(1..).take_while(|&n| self.entries.contains_key(&Key::Number(Number(n as f64)))).count()
+	}
+
+	// the key and value after `key` in order, or the first with none, like Lua's `next`
+	fn next(&self, key: &Value) -> Result<Option<(Value, Value)>, String> {
+		let mut after = match key {
+			Value::Nil => self.entries.range(..),
+			key => self.entries.range((Bound::Excluded(Key::of(key)?), Bound::Unbounded)),
+		};
+		Ok(after.next().map(|(k, v)| (k.value(), v.clone())))
+	}
+}
+
+pub enum Function {
+	// from a script, with the variables it was made among
+	Script(Rc<Body>, Rc<Scope>),
+	Builtin(Builtin),
+	// one the host added, by name
+	Host(Rc<str>),
+}
+
+#[derive(Clone, Copy)]
+pub enum Builtin {
+	Type,
+	ToString,
+	ToNumber,
+	Pairs,
+	IPairs,
+	// what `ipairs` goes through a table with
+	IPairsStep,
+	Next,
+	Error,
+	Floor,
+	Ceil,
+	Abs,
+	Sqrt,
+	Sin,
+	Cos,
+	Min,
+	Max,
+	Random,
+}
+
+// a variable, shared with the functions made where it can be seen
+type Variable = Rc<RefCell<Value>>;
+
+// the local variables of a block, and of the blocks around it
+pub struct Scope {
+	variables: RefCell<Vec<(Rc<str>, Variable)>>,
+	parent: Option<Rc<Scope>>,
+}
+
+impl Scope {
+	fn within(parent: &Rc<Scope>) -> Rc<Scope> {
+		Rc::new(Scope { variables: RefCell::new(vec![]), parent: Some(parent.clone()) })
+	}
+
+	fn declare(&self, name: &Rc<str>, value: Value) {
+		self.variables.borrow_mut().push((name.clone(), Rc::new(RefCell::new(value))));
+	}
+
+	// the variable `name` declared latest, in this block or around it
+	fn find(&self, name: &str) -> Option<Variable> {
+		let variables = self.variables.borrow();
+		match variables.iter().rev().find(|(n, _)| &**n == name) {
+			Some((_, cell)) => Some(cell.clone()),
+			None => self.parent.as_ref().and_then(|p| p.find(name)),
+		}
+	}
+}
+
+// a function's parameters and statements, or a whole script's
+pub struct Body {
+	params: Vec<Rc<str>>,
+	block: Vec<Statement>,
+}
+
+struct Statement {
+	line: usize,
+	kind: Kind,
+}
+
+enum Kind {
+	Local(Vec<Rc<str>>, Vec<Expr>),
+	LocalFunction(Rc<str>, Rc<Body>),
+	// to variables and table entries
+	Assign(Vec<Expr>, Vec<Expr>),
+	Call(Expr),
+	Do(Vec<Statement>),
+	// each condition with what it runs, and what runs if none hold
+	If(Vec<(Expr, Vec<Statement>)>, Option<Vec<Statement>>),
+	While(Expr, Vec<Statement>),
+	Repeat(Vec<Statement>, Expr),
+	// the variable, from, to, step
+	For(Rc<str>, Expr, Expr, Option<Expr>, Vec<Statement>),
+	// the variables and what gives the values for them
+	ForIn(Vec<Rc<str>>, Vec<Expr>, Vec<Statement>),
+	Return(Vec<Expr>),
+	Break,
+}
+
+enum Expr {
+	Nil,
+	Bool(bool),
+	Number(f64),
+	Str(Rc<str>),
+	Name(Rc<str>),
+	Index(Box<Expr>, Box<Expr>),
+	Call(Box<Expr>, Vec<Expr>),
+	// the table, the method's name and the arguments after the table itself
+	Method(Box<Expr>, Rc<str>, Vec<Expr>),
+	Function(Rc<Body>),
+	// the key of each entry, or none to number it on from the last
+	Table(Vec<(Option<Expr>, Expr)>),
+	Binary(Box<Expr>, &'static str, Box<Expr>),
+	Unary(&'static str, Box<Expr>),
+	// in brackets, which keeps only the first of a call's values
+	Paren(Box<Expr>),
+}
+
+enum Token {
+	Name(Rc<str>),
+	Number(f64),
+	Str(Rc<str>),
+	Symbol(&'static str),
+	End,
+}
+
+// the tokens in `text`, each with its line
+fn lex(text: &str) -> Result<Vec<(Token, usize)>, Error> {
+	let chars: Vec<char> = text.chars().collect();
+	let mut tokens = vec![];
+	let (mut k, mut line) = (0, 1);
+	let fail = |line, message: &str| Error { line, message: message.to_string() };
+	while k < chars.len() {
+		let c = chars[k];
+		if c == '\n' {
+			line += 1;
+			k += 1;
+		} else if c.is_whitespace() {
+			k += 1;
+		} else if chars[k..].starts_with(&['-', '-']) {
+			// a comment, to the end of the line, or to `]]` if it starts with `[[`
+			let long = chars[k + 2..].starts_with(&['[', '[']);
+			k += 2;
+			while k < chars.len() && !(if long { chars[k..].starts_with(&[']', ']']) } else { chars[k] == '\n' }) {
+				line += (chars[k] == '\n') as usize;
+				k += 1;
+			}
+			if long {
+				k += 2;
+			}
+		} else if c.is_ascii_digit() || (c == '.' && chars.get(k + 1).is_some_and(char::is_ascii_digit)) {
+			let start = k;
+			while k < chars.len() && (chars[k].is_ascii_alphanumeric() || chars[k] == '.' || (matches!(chars[k], '+' | '-') && matches!(chars[k - 1], 'e' | 'E'))) {
+				k += 1;
+			}
+			let word: String = chars[start..k].iter().collect();
+			let n = word.parse().map_err(|_| fail(line, &format!("'{}' isn't a number", word)))?;
+			tokens.push((Token::Number(n), line));
+		} else if c.is_alphabetic() || c == '_' {
+			let start = k;
+			while k < chars.len() && (chars[k].is_alphanumeric() || chars[k] == '_') {
+				k += 1;
+			}
+			tokens.push((Token::Name(chars[start..k].iter().collect::<String>().into()), line));
+		} else if c == '"' || c == '\'' {
+			let mut text = String::new();
+			k += 1;
+			loop {
+				match chars.get(k) {
+					None | Some('\n') => return Err(fail(line, "a string isn't closed")),
+					Some(&end) if end == c => break,
+					Some('\\') => {
+						text.push(match chars.get(k + 1) {
+							Some('n') => '\n',
+							Some('t') => '\t',
+							Some(&e @ ('\\' | '"' | '\'')) => e,
+							_ => return Err(fail(line, "unknown escape in a string")),
+						});
+						k += 1;
+					},
+					Some(&other) => text.push(other),
+				}
+				k += 1;
+			}
+			k += 1;
+			tokens.push((Token::Str(text.into()), line));
+		} else if let Some(&symbol) = SYMBOLS.iter().find(|s| chars[k..].starts_with(&s.chars().collect::<Vec<_>>())) {
+			tokens.push((Token::Symbol(symbol), line));
+			k += symbol.len();
+		} else {
+			return Err(fail(line, &format!("unexpected '{}'", c)));
+		}
+	}
+	tokens.push((Token::End, line));
+	Ok(tokens)
+}
+
+struct Parser {
+	tokens: Vec<(Token, usize)>,
+	at: usize,
+	// how deep in brackets and blocks, and in loops, for `break`
+	depth: usize,
+	loops: usize,
+}
+
+// how tightly each binary operator holds on its left and on its right, as in Lua
+fn priority(op: &str) -> Option<(u8, u8)> {
+	Some(match op {
+		"or" => (1, 1),
+		"and" => (2, 2),
+		"<" | ">" | "<=" | ">=" | "~=" | "==" => (3, 3),
+		".." => (9, 8),
+		"+" | "-" => (10, 10),
+		"*" | "/" | "//" | "%" => (11, 11),
+		"^" => (14, 13),
+		_ => return None,
+	})
+}
+
+// how tightly `not`, `#` and `-` hold on what's after them
+const UNARY: u8 = 12;
+
+impl Parser {
+	fn line(&self) -> usize {
+		self.tokens[self.at].1
+	}
+
+	fn fail<T>(&self, message: impl Into<String>) -> Result<T, Error> {
+		Err(Error { line: self.line(), message: message.into() })
+	}
+
+	// whether the next token is this symbol or keyword
+	fn is(&self, word: &str) -> bool {
+		match &self.tokens[self.at].0 {
+			Token::Symbol(s) => *s == word,
+			Token::Name(n) => &**n == word,
+			_ => false,
+		}
+	}
+
+	// take the next token if it's this symbol or keyword
+	fn take(&mut self, word: &str) -> bool {
+		let is = self.is(word);
+		self.at += is as usize;
+		is
+	}
+
+	fn expect(&mut self, word: &str) -> Result<(), Error> {
+		if self.take(word) { Ok(()) } else { self.fail(format!("expected '{}'", word)) }
+	}
+
+	fn name(&mut self) -> Result<Rc<str>, Error> {
+		match &self.tokens[self.at].0 {
+			Token::Name(n) if !KEYWORDS.contains(&&**n) => {
+				let n = n.clone();
+				self.at += 1;
+				Ok(n)
+			},
+			_ => self.fail("expected a name"),
+		}
+	}
+
+	fn deeper(&mut self) -> Result<(), Error> {
+		self.depth += 1;
+		if self.depth > MAX_DEPTH { self.fail("nested too deep") } else { Ok(()) }
+	}
+
+	// statements up to one of the words that end a block, which is left for the caller
+	fn block(&mut self) -> Result<Vec<Statement>, Error> {
+		self.deeper()?;
+		let mut block = vec![];
+		while !["end", "else", "elseif", "until"].iter().any(|w| self.is(w)) && !matches!(self.tokens[self.at].0, Token::End) {
+			if self.take(";") {
+				continue;
+			}
+			let line = self.line();
+			let kind = self.statement()?;
+			let last = matches!(kind, Kind::Return(_));
+			block.push(Statement { line, kind });
+			// nothing can come after a return, which would never run
+			if last {
+				self.take(";");
+				break;
+			}
+		}
+		self.depth -= 1;
+		Ok(block)
+	}
+
+	fn statement(&mut self) -> Result<Kind, Error> {
+		if self.take("if") {
+			let mut arms = vec![];
+			let mut otherwise = None;
+			loop {
+				let condition = self.expr(0)?;
+				self.expect("then")?;
+				arms.push((condition, self.block()?));
+				if self.take("elseif") {
+					continue;
+				}
+				if self.take("else") {
+					otherwise = Some(self.block()?);
+				}
+				self.expect("end")?;
+				return Ok(Kind::If(arms, otherwise));
+			}
+		}
+		if self.take("while") {
+			let condition = self.expr(0)?;
+			self.expect("do")?;
+			let body = self.looped()?;
+			self.expect("end")?;
+			return Ok(Kind::While(condition, body));
+		}
+		if self.take("repeat") {
+			let body = self.looped()?;
+			self.expect("until")?;
+			return Ok(Kind::Repeat(body, self.expr(0)?));
+		}
+		if self.take("for") {
+			let first = self.name()?;
+			let kind = if self.take("=") {
+				let from = self.expr(0)?;
+				self.expect(",")?;
+				let to = self.expr(0)?;
+				let step = if self.take(",") { Some(self.expr(0)?) } else { None };
+				self.expect("do")?;
+				Kind::For(first, from, to, step, self.looped()?)
+			} else {
+				let mut names = vec![first];
+				while self.take(",") {
+					names.push(self.name()?);
+				}
+				self.expect("in")?;
+				let values = self.exprs()?;
+				self.expect("do")?;
+				Kind::ForIn(names, values, self.looped()?)
+			};
+			self.expect("end")?;
+			return Ok(kind);
+		}
+		if self.take("do") {
+			let block = self.block()?;
+			self.expect("end")?;
+			return Ok(Kind::Do(block));
+		}
+		if self.take("function") {
+			// a name, or a field of a table, and `:` to take the table as `self`
+			let mut target = Expr::Name(self.name()?);
+			while self.take(".") {
+				target = Expr::Index(Box::new(target), Box::new(Expr::Str(self.name()?)));
+			}
+			let method = self.take(":");
+			if method {
+				target = Expr::Index(Box::new(target), Box::new(Expr::Str(self.name()?)));
+			}
+			return Ok(Kind::Assign(vec![target], vec![Expr::Function(self.body(method)?)]));
+		}
+		if self.take("local") {
+			if self.take("function") {
+				let name = self.name()?;
+				return Ok(Kind::LocalFunction(name, self.body(false)?));
+			}
+			let mut names = vec![self.name()?];
+			while self.take(",") {
+				names.push(self.name()?);
+			}
+			let values = if self.take("=") { self.exprs()? } else { vec![] };
+			return Ok(Kind::Local(names, values));
+		}
+		if self.take("return") {
+			let ends = ["end", "else", "elseif", "until", ";"].iter().any(|w| self.is(w)) || matches!(self.tokens[self.at].0, Token::End);
+			return Ok(Kind::Return(if ends { vec![] } else { self.exprs()? }));
+		}
+		if self.take("break") {
+			return if self.loops > 0 { Ok(Kind::Break) } else { self.fail("break outside a loop") };
+		}
+		let first = self.suffixed()?;
+		if self.is("=") || self.is(",") {
+			let mut targets = vec![first];
+			while self.take(",") {
+				targets.push(self.suffixed()?);
+			}
+			if !targets.iter().all(|t| matches!(t, Expr::Name(_) | Expr::Index(..))) {
+				return self.fail("can only assign to a variable or a table's entry");
+			}
+			self.expect("=")?;
+			return Ok(Kind::Assign(targets, self.exprs()?));
+		}
+		match first {
+			Expr::Call(..) | Expr::Method(..) => Ok(Kind::Call(first)),
+			_ => self.fail("expected a statement"),
+		}
+	}
+
+	// the body of a loop, where `break` can be
+	fn looped(&mut self) -> Result<Vec<Statement>, Error> {
+		self.loops += 1;
+		let block = self.block();
+		self.loops -= 1;
+		block
+	}
+
+	// a function's parameters and body, after its name, with `self` first for a method
+	fn body(&mut self, method: bool) -> Result<Rc<Body>, Error> {
+		let mut params: Vec<Rc<str>> = if method { vec!["self".into()] } else { vec![] };
+		self.expect("(")?;
+		if !self.take(")") {
+			loop {
+				if self.is("...") {
+					return self.fail("functions taking any number of arguments can't be written here");
+				}
+				params.push(self.name()?);
+				if self.take(")") {
+					break;
+				}
+				self.expect(",")?;
+			}
+		}
+		// a loop around the function doesn't go into it
+		let loops = std::mem::take(&mut self.loops);
+		let block = self.block()?;
+		self.loops = loops;
+		self.expect("end")?;
+		Ok(Rc::new(Body { params, block }))
+	}
+
+	fn exprs(&mut self) -> Result<Vec<Expr>, Error> {
+		let mut exprs = vec![self.expr(0)?];
+		while self.take(",") {
+			exprs.push(self.expr(0)?);
+		}
+		Ok(exprs)
+	}
+
+	// an expression, as far as operators holding tighter on their left than `limit` go
+	fn expr(&mut self, limit: u8) -> Result<Expr, Error> {
+		self.deeper()?;
+		let unary = ["not", "#", "-"].into_iter().find(|op| self.is(op));
+		let mut left = match unary {
+			Some(op) => {
+				self.at += 1;
+				Expr::Unary(op, Box::new(self.expr(UNARY)?))
+			},
+			None => self.simple()?,
+		};
+		loop {
+			let op = match &self.tokens[self.at].0 {
+				Token::Symbol(s) => *s,
+				Token::Name(n) if &**n == "and" => "and",
+				Token::Name(n) if &**n == "or" => "or",
+				_ => break,
+			};
+			let Some((_, right)) = priority(op).filter(|&(left, _)| left > limit) else { break };
+			self.at += 1;
+			left = Expr::Binary(Box::new(left), op, Box::new(self.expr(right)?));
+		}
+		self.depth -= 1;
+		Ok(left)
+	}
+
+	fn simple(&mut self) -> Result<Expr, Error> {
+		let expr = match &self.tokens[self.at].0 {
+			Token::Number(n) => Expr::Number(*n),
+			Token::Str(s) => Expr::Str(s.clone()),
+			Token::Name(n) if &**n == "nil" => Expr::Nil,
+			Token::Name(n) if &**n == "true" => Expr::Bool(true),
+			Token::Name(n) if &**n == "false" => Expr::Bool(false),
+			Token::Name(n) if &**n == "function" => {
+				self.at += 1;
+				return Ok(Expr::Function(self.body(false)?));
+			},
+			Token::Symbol("{") => return self.table(),
+			_ => return self.suffixed(),
+		};
+		self.at += 1;
+		Ok(expr)
+	}
+
+	// a name or an expression in brackets, with any indexing and calls after it
+	fn suffixed(&mut self) -> Result<Expr, Error> {
+		let mut expr = if self.take("(") {
+			let inner = self.expr(0)?;
+			self.expect(")")?;
+			Expr::Paren(Box::new(inner))
+		} else {
+			Expr::Name(self.name()?)
+		};
+		loop {
+			if self.take(".") {
+				expr = Expr::Index(Box::new(expr), Box::new(Expr::Str(self.name()?)));
+			} else if self.take("[") {
+				let key = self.expr(0)?;
+				self.expect("]")?;
+				expr = Expr::Index(Box::new(expr), Box::new(key));
+			} else if self.take(":") {
+				let name = self.name()?;
+				expr = Expr::Method(Box::new(expr), name, self.args()?);
+			} else if self.is("(") || self.is("{") || matches!(self.tokens[self.at].0, Token::Str(_)) {
+				expr = Expr::Call(Box::new(expr), self.args()?);
+			} else {
+				return Ok(expr);
+			}
+		}
+	}
+
+	// a call's arguments: a list in brackets, or just a table or a string
+	fn args(&mut self) -> Result<Vec<Expr>, Error> {
+		if let Token::Str(s) = &self.tokens[self.at].0 {
+			let s = s.clone();
+			self.at += 1;
+			return Ok(vec![Expr::Str(s)]);
+		}
+		if self.is("{") {
+			return Ok(vec![self.table()?]);
+		}
+		self.expect("(")?;
+		if self.take(")") {
+			return Ok(vec![]);
+		}
+		let args = self.exprs()?;
+		self.expect(")")?;
+		Ok(args)
+	}
+
+	fn table(&mut self) -> Result<Expr, Error> {
+		self.expect("{")?;
+		let mut entries = vec![];
+		while !self.take("}") {
+			let key = if self.take("[") {
+				let key = self.expr(0)?;
+				self.expect("]")?;
+				self.expect("=")?;
+				Some(key)
+			} else if matches!(self.tokens[self.at + 1].0, Token::Symbol("=")) {
+				let name = self.name()?;
+				self.at += 1;
+				Some(Expr::Str(name))
+			} else {
+				None
+			};
+			entries.push((key, self.expr(0)?));
+			if !self.take(",") && !self.take(";") {
+				self.expect("}")?;
+				break;
+			}
+		}
+		Ok(Expr::Table(entries))
+	}
+}
+
+// what running a block comes to
+enum Flow {
+	Normal,
+	Break,
+	Return(Vec<Value>),
+}
+
+pub struct Interpreter {
+	globals: Rc<RefCell<Table>>,
+	// statements run since the host last called in, and how deep in calls
+	steps: usize,
+	depth: usize,
+	// the line running, for errors
+	line: usize,
+	rng: Rng,
+}
+
+impl Interpreter {
+	// one with the standard functions, whose `math.random` starts from `seed`
+	pub fn new(seed: u64) -> Interpreter {
+		let mut interpreter = Interpreter { globals: Rc::default(), steps: 0, depth: 0, line: 0, rng: Rng::new(seed) };
+		let builtins = [
+			("type", Builtin::Type), ("tostring", Builtin::ToString), ("tonumber", Builtin::ToNumber),
+			("pairs", Builtin::Pairs), ("ipairs", Builtin::IPairs), ("next", Builtin::Next), ("error", Builtin::Error),
+			("math.floor", Builtin::Floor), ("math.ceil", Builtin::Ceil), ("math.abs", Builtin::Abs), ("math.sqrt", Builtin::Sqrt),
+			("math.sin", Builtin::Sin), ("math.cos", Builtin::Cos), ("math.min", Builtin::Min), ("math.max", Builtin::Max),
+			("math.random", Builtin::Random),
+		];
+		for (name, builtin) in builtins {
+			interpreter.define(name, Value::Function(Rc::new(Function::Builtin(builtin))));
+		}
+		interpreter.define("math.pi", Value::Number(std::f64::consts::PI));
+		interpreter.define("math.huge", Value::Number(f64::INFINITY));
+		interpreter
+	}
+
+	// Add the host's function `name`, which is either global or, like `world.get`, in a global
+	// table, made if it isn't there yet.
+	pub fn register(&mut self, name: &str) {
+		self.define(name, Value::Function(Rc::new(Function::Host(name.into()))));
+	}
+
+	fn define(&mut self, name: &str, value: Value) {
+		let (table, field) = match name.split_once('.') {
+			Some((table, field)) => {
+				let key = Value::from(table);
+				let existing = self.globals.borrow().get(&key);
+				let table = match existing {
+					Value::Table(t) => t,
+					_ => {
+						let t: Rc<RefCell<Table>> = Rc::default();
+						let _ = self.globals.borrow_mut().set(&key, Value::Table(t.clone()));
+						t
+					},
+				};
+				(table, field)
+			},
+			None => (self.globals.clone(), name),
+		};
+		let _ = table.borrow_mut().set(&Value::from(field), value);
+	}
+
+	// A script, as a function that runs it when it's called.
+	pub fn load(&mut self, text: &str) -> Result<Value, Error> {
+		let mut parser = Parser { tokens: lex(text)?, at: 0, depth: 0, loops: 0 };
+		let block = parser.block()?;
+		if !matches!(parser.tokens[parser.at].0, Token::End) {
+			return parser.fail("expected the end of the script");
+		}
+		let scope = Rc::new(Scope { variables: RefCell::new(vec![]), parent: None });
+		Ok(Value::Function(Rc::new(Function::Script(Rc::new(Body { params: vec![], block }), scope))))
+	}
+
+	// call `f` from the host, which is called back for its own functions
+	pub fn call(&mut self, f: &Value, args: Vec<Value>, host: &mut dyn Host) -> Result<Vec<Value>, Error> {
+		(self.steps, self.depth) = (0, 0);
+		self.call_value(f, args, host)
+	}
+
+	fn fail(&self, message: impl Into<String>) -> Error {
+		Error { line: self.line, message: message.into() }
+	}
+
+	fn step(&mut self) -> Result<(), Error> {
+		self.steps += 1;
+		if self.steps > MAX_STEPS { Err(self.fail(format!("ran for more than {} steps", MAX_STEPS))) } else { Ok(()) }
+	}
+
+	fn call_value(&mut self, f: &Value, args: Vec<Value>, host: &mut dyn Host) -> Result<Vec<Value>, Error> {
+		let Value::Function(f) = f else {
+			return Err(self.fail(format!("attempt to call a {} value", f.type_name())));
+		};
+		match &**f {
+			Function::Script(body, scope) => {
+				if self.depth >= MAX_DEPTH {
+					return Err(self.fail(format!("calls went more than {} deep", MAX_DEPTH)));
+				}
+				self.depth += 1;
+				let line = self.line;
+				let scope = Scope::within(scope);
+				let mut args = args.into_iter();
+				for param in &body.params {
+					scope.declare(param, args.next().unwrap_or(Value::Nil));
+				}
+				let flow = self.block(&body.block, &scope, host);
+				self.depth -= 1;
+				self.line = line;
+				match flow? {
+					Flow::Return(values) => Ok(values),
+					_ => Ok(vec![]),
+				}
+			},
+			Function::Builtin(builtin) => self.builtin(*builtin, args).map_err(|e| self.fail(e)),
+			Function::Host(name) => host.call(name, args).map_err(|e| self.fail(e)),
+		}
+	}
+
+	fn builtin(&mut self, builtin: Builtin, args: Vec<Value>) -> Result<Vec<Value>, String> {
+		let arg = |k: usize| args.get(k).cloned().unwrap_or(Value::Nil);
+		let number = |k: usize| arg(k).number().ok_or(format!("expected a number as argument {}", k + 1));
+		let table = |k: usize| match arg(k) {
+			Value::Table(t) => Ok(t),
+			other => Err(format!("expected a table as argument {}, got a {} value", k + 1, other.type_name())),
+		};
+		let function = |builtin| Value::Function(Rc::new(Function::Builtin(builtin)));
+		Ok(match builtin {
+			Builtin::Type => vec![Value::from(arg(0).type_name())],
+			Builtin::ToString => vec![Value::from(&*arg(0).to_string())],
+			Builtin::ToNumber => vec![match arg(0) {
+				Value::Number(n) => Value::Number(n),
+				Value::Str(s) => s.trim().parse().map_or(Value::Nil, Value::Number),
+				_ => Value::Nil,
+			}],
+			Builtin::Pairs => vec![function(Builtin::Next), Value::Table(table(0)?), Value::Nil],
+			Builtin::IPairs => vec![function(Builtin::IPairsStep), Value::Table(table(0)?), Value::Number(0.0)],
+			Builtin::IPairsStep => {
+				let n = number(1)? + 1.0;
+				match table(0)?.borrow().get(&Value::Number(n)) {
+					Value::Nil => vec![Value::Nil],
+					value => vec![Value::Number(n), value],
+				}
+			},
+			Builtin::Next => match table(0)?.borrow().next(&arg(1))? {
+				Some((key, value)) => vec![key, value],
+				None => vec![Value::Nil],
+			},
+			Builtin::Error => return Err(arg(0).to_string()),
+			Builtin::Floor => vec![Value::Number(number(0)?.floor())],
+			Builtin::Ceil => vec![Value::Number(number(0)?.ceil())],
+			Builtin::Abs => vec![Value::Number(number(0)?.abs())],
+			Builtin::Sqrt => vec![Value::Number(number(0)?.sqrt())],
+			Builtin::Sin => vec![Value::Number(number(0)?.sin())],
+			Builtin::Cos => vec![Value::Number(number(0)?.cos())],
+			Builtin::Min | Builtin::Max => {
+				let mut best = number(0)?;
+				for k in 1..args.len() {
+					let n = number(k)?;
+					best = if matches!(builtin, Builtin::Min) { best.min(n) } else { best.max(n) };
+				}
+				vec![Value::Number(best)]
+			},
+			// a fraction in [0, 1), or a whole number from 1 to m, or from m to n
+			Builtin::Random => {
+				let f = self.rng.next_u64() as f64 / 2f64.powi(64);
+				let (low, high) = match args.len() {
+					0 => return Ok(vec![Value::Number(f)]),
+					1 => (1.0, number(0)?.floor()),
+					_ => (number(0)?.floor(), number(1)?.floor()),
+				};
+				if low > high {
+					return Err("the interval is empty".to_string());
+				}
+				vec![Value::Number((low + (f * (high - low + 1.0)).floor()).min(high))]
+			},
+		})
+	}
+
+	fn block(&mut self, block: &[Statement], scope: &Rc<Scope>, host: &mut dyn Host) -> Result<Flow, Error> {
+		for statement in block {
+			self.line = statement.line;
+			self.step()?;
+			match self.statement(&statement.kind, scope, host)? {
+				Flow::Normal => {},
+				flow => return Ok(flow),
+			}
+		}
+		Ok(Flow::Normal)
+	}
+
+	// a block in its own scope
+	fn inner(&mut self, block: &[Statement], scope: &Rc<Scope>, host: &mut dyn Host) -> Result<Flow, Error> {
+		self.block(block, &Scope::within(scope), host)
+	}
+
+	fn statement(&mut self, kind: &Kind, scope: &Rc<Scope>, host: &mut dyn Host) -> Result<Flow, Error> {
+		match kind {
+			Kind::Local(names, exprs) => {
+				let mut values = self.exprs(exprs, scope, host)?.into_iter();
+				for name in names {
+					scope.declare(name, values.next().unwrap_or(Value::Nil));
+				}
+			},
+			Kind::LocalFunction(name, body) => {
+				// declared first, so it can call itself
+				scope.declare(name, Value::Nil);
+				let f = Value::Function(Rc::new(Function::Script(body.clone(), scope.clone())));
+				*scope.find(name).expect("just declared").borrow_mut() = f;
+			},
+			Kind::Assign(targets, exprs) => {
+				// where each goes is worked out before the values, as in Lua
+				let mut places = vec![];
+				for target in targets {
+					places.push(match target {
+						Expr::Index(table, key) => Some((self.expr(table, scope, host)?, self.expr(key, scope, host)?)),
+						_ => None,
+					});
+				}
+				let mut values = self.exprs(exprs, scope, host)?.into_iter();
+				for (target, place) in targets.iter().zip(places) {
+					let value = values.next().unwrap_or(Value::Nil);
+					match (target, place) {
+						(_, Some((table, key))) => self.set_index(&table, &key, value)?,
+						(Expr::Name(name), None) => match scope.find(name) {
+							Some(cell) => *cell.borrow_mut() = value,
+							None => self.globals.borrow_mut().set(&Value::Str(name.clone()), value).map_err(|e| self.fail(e))?,
+						},
+						_ => unreachable!("only variables and entries are parsed as targets"),
+					}
+				}
+			},
+			Kind::Call(call) => {
+				self.values(call, scope, host)?;
+			},
+			Kind::Do(block) => return self.inner(block, scope, host),
+			Kind::If(arms, otherwise) => {
+				for (condition, block) in arms {
+					if self.expr(condition, scope, host)?.truthy() {
+						return self.inner(block, scope, host);
+					}
+				}
+				if let Some(block) = otherwise {
+					return self.inner(block, scope, host);
+				}
+			},
+			Kind::While(condition, block) => {
+				while self.expr(condition, scope, host)?.truthy() {
+					self.step()?;
+					match self.inner(block, scope, host)? {
+						Flow::Normal => {},
+						Flow::Break => break,
+						flow => return Ok(flow),
+					}
+				}
+			},
+			Kind::Repeat(block, condition) => loop {
+				self.step()?;
+				// the condition can see the block's own locals
+				let inside = Scope::within(scope);
+				match self.block(block, &inside, host)? {
+					Flow::Normal => {},
+					Flow::Break => break,
+					flow => return Ok(flow),
+				}
+				if self.expr(condition, &inside, host)?.truthy() {
+					break;
+				}
+			},
+			Kind::For(name, from, to, step, block) => {
+				let mut number = |expr: &Expr, what: &str| match self.expr(expr, scope, host)? {
+					Value::Number(n) => Ok(n),
+					_ => Err(self.fail(format!("a for loop's {} must be a number", what))),
+				};
+				let (from, to) = (number(from, "start")?, number(to, "end")?);
+				let step = match step {
+					Some(step) => number(step, "step")?,
+					None => 1.0,
+				};
+				if step == 0.0 {
+					return Err(self.fail("a for loop's step can't be 0"));
+				}
+				let mut n = from;
+				while if step > 0.0 { n <= to } else { n >= to } {
+					self.step()?;
+					let inside = Scope::within(scope);
+					inside.declare(name, Value::Number(n));
+					match self.block(block, &inside, host)? {
+						Flow::Normal => {},
+						Flow::Break => break,
+						flow => return Ok(flow),
+					}
+					n += step;
+				}
+			},
+			Kind::ForIn(names, exprs, block) => {
+				let mut values = self.exprs(exprs, scope, host)?.into_iter();
+				let f = values.next().unwrap_or(Value::Nil);
+				let state = values.next().unwrap_or(Value::Nil);
+				let mut control = values.next().unwrap_or(Value::Nil);
+				loop {
+					self.step()?;
+					let mut got = self.call_value(&f, vec![state.clone(), control.clone()], host)?.into_iter();
+					control = got.next().unwrap_or(Value::Nil);
+					if matches!(control, Value::Nil) {
+						break;
+					}
+					let inside = Scope::within(scope);
+					inside.declare(&names[0], control.clone());
+					for name in &names[1..] {
+						inside.declare(name, got.next().unwrap_or(Value::Nil));
+					}
+					match self.block(block, &inside, host)? {
+						Flow::Normal => {},
+						Flow::Break => break,
+						flow => return Ok(flow),
+					}
+				}
+			},
+			Kind::Return(exprs) => return Ok(Flow::Return(self.exprs(exprs, scope, host)?)),
+			Kind::Break => return Ok(Flow::Break),
+		}
+		Ok(Flow::Normal)
+	}
+
+	// the values of `exprs`, with all of the last one's if it's a call
+	fn exprs(&mut self, exprs: &[Expr], scope: &Rc<Scope>, host: &mut dyn Host) -> Result<Vec<Value>, Error> {
+		let mut values = vec![];
+		for (k, expr) in exprs.iter().enumerate() {
+			if k + 1 == exprs.len() {
+				values.extend(self.values(expr, scope, host)?);
+			} else {
+				values.push(self.expr(expr, scope, host)?);
+			}
+		}
+		Ok(values)
+	}
+
+	// every value an expression has, which is more than one only for calls
+	fn values(&mut self, expr: &Expr, scope: &Rc<Scope>, host: &mut dyn Host) -> Result<Vec<Value>, Error> {
+		match expr {
+			Expr::Call(f, args) => {
+				let f = self.expr(f, scope, host)?;
+				let args = self.exprs(args, scope, host)?;
+				self.call_value(&f, args, host)
+			},
+			Expr::Method(table, name, args) => {
+				let table = self.expr(table, scope, host)?;
+				let f = self.index(&table, &Value::Str(name.clone()))?;
+				let mut all = vec![table];
+				all.extend(self.exprs(args, scope, host)?);
+				self.call_value(&f, all, host)
+			},
+			_ => Ok(vec![self.expr(expr, scope, host)?]),
+		}
+	}
+
+	fn expr(&mut self, expr: &Expr, scope: &Rc<Scope>, host: &mut dyn Host) -> Result<Value, Error> {
+		Ok(match expr {
+			Expr::Nil => Value::Nil,
+			Expr::Bool(b) => Value::Bool(*b),
+			Expr::Number(n) => Value::Number(*n),
+			Expr::Str(s) => Value::Str(s.clone()),
+			Expr::Name(name) => match scope.find(name) {
+				Some(cell) => cell.borrow().clone(),
+				None => self.globals.borrow().get(&Value::Str(name.clone())),
+			},
+			Expr::Index(table, key) => {
+				let table = self.expr(table, scope, host)?;
+				let key = self.expr(key, scope, host)?;
+				self.index(&table, &key)?
+			},
+			Expr::Call(..) | Expr::Method(..) => self.values(expr, scope, host)?.into_iter().next().unwrap_or(Value::Nil),
+			Expr::Function(body) => Value::Function(Rc::new(Function::Script(body.clone(), scope.clone()))),
+			Expr::Table(entries) => {
+				let mut table = Table::default();
+				let mut n = 0.0;
+				for (k, (key, value)) in entries.iter().enumerate() {
+					match key {
+						Some(key) => {
+							let key = self.expr(key, scope, host)?;
+							let value = self.expr(value, scope, host)?;
+							table.set(&key, value).map_err(|e| self.fail(e))?;
+						},
+						// all of a call's values if it comes last
+						None if k + 1 == entries.len() => {
+							for value in self.values(value, scope, host)? {
+								n += 1.0;
+								table.set(&Value::Number(n), value).map_err(|e| self.fail(e))?;
+							}
+						},
+						None => {
+							n += 1.0;
+							let value = self.expr(value, scope, host)?;
+							table.set(&Value::Number(n), value).map_err(|e| self.fail(e))?;
+						},
+					}
+				}
+				Value::Table(Rc::new(RefCell::new(table)))
+			},
+			Expr::Binary(left, "and", right) => {
+				let left = self.expr(left, scope, host)?;
+				if left.truthy() { self.expr(right, scope, host)? } else { left }
+			},
+			Expr::Binary(left, "or", right) => {
+				let left = self.expr(left, scope, host)?;
+				if left.truthy() { left } else { self.expr(right, scope, host)? }
+			},
+			Expr::Binary(left, op, right) => {
+				let left = self.expr(left, scope, host)?;
+				let right = self.expr(right, scope, host)?;
+				binary(op, &left, &right).map_err(|e| self.fail(e))?
+			},
+			Expr::Unary(op, operand) => {
+				let operand = self.expr(operand, scope, host)?;
+				match (*op, operand) {
+					("not", value) => Value::Bool(!value.truthy()),
+					("-", Value::Number(n)) => Value::Number(-n),
+					("#", Value::Str(s)) => Value::Number(s.len() as f64),
+					("#", Value::Table(t)) => Value::Number(t.borrow().length() as f64),
+					(op, value) => return Err(self.fail(format!("can't use '{}' on a {} value", op, value.type_name()))),
+				}
+			},
+			Expr::Paren(inner) => self.expr(inner, scope, host)?,
+		})
+	}
+
+	fn index(&self, table: &Value, key: &Value) -> Result<Value, Error> {
+		match table {
+			Value::Table(t) => Ok(t.borrow().get(key)),
+			other => Err(self.fail(format!("attempt to index a {} value", other.type_name()))),
+		}
+	}
+
+	fn set_index(&self, table: &Value, key: &Value, value: Value) -> Result<(), Error> {
+		match table {
+			Value::Table(t) => t.borrow_mut().set(key, value).map_err(|e| self.fail(e)),
+			other => Err(self.fail(format!("attempt to index a {} value", other.type_name()))),
+		}
+	}
+}
+
+fn binary(op: &str, left: &Value, right: &Value) -> Result<Value, String> {
+	use Value::{Number as N, Str as S};
+	Ok(match (op, left, right) {
+		("==", a, b) => Value::Bool(a.equals(b)),
+		("~=", a, b) => Value::Bool(!a.equals(b)),
+		("+", N(a), N(b)) => N(a + b),
+		("-", N(a), N(b)) => N(a - b),
+		("*", N(a), N(b)) => N(a * b),
+		("/", N(a), N(b)) => N(a / b),
+		("//", N(a), N(b)) => N((a / b).floor()),
+		("%", N(a), N(b)) => N(a - (a / b).floor() * b),
+		("^", N(a), N(b)) => N(a.powf(*b)),
+		("..", N(_) | S(_), N(_) | S(_)) => S(format!("{}{}", left, right).into()),
+		("<" | ">" | "<=" | ">=", a, b) => {
+			let order = match (a, b) {
+				(N(a), N(b)) => a.partial_cmp(b),
+				(S(a), S(b)) => Some(a.cmp(b)),
+				_ => return Err(format!("can't compare a {} value with a {} value", a.type_name(), b.type_name())),
+			};
+			Value::Bool(match op {
+				"<" => order == Some(Ordering::Less),
+				">" => order == Some(Ordering::Greater),
+				"<=" => matches!(order, Some(Ordering::Less | Ordering::Equal)),
+				_ => matches!(order, Some(Ordering::Greater | Ordering::Equal)),
+			})
+		},
+		("..", a, b) => return Err(format!("can't join a {} value to a {} value", a.type_name(), b.type_name())),
+		(op, a, b) => return Err(format!("can't use '{}' on a {} value and a {} value", op, a.type_name(), b.type_name())),
+	})
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	// a host whose one function, `say`, keeps what it's given
+	#[derive(Default)]
+	struct Said(Vec<String>);
+
+	impl Host for Said {
+		fn call(&mut self, _: &str, args: Vec<Value>) -> Result<Vec<Value>, String> {
+			self.0.push(args.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(" "));
+			Ok(vec![])
+		}
+	}
+
+	fn run(text: &str) -> Result<Vec<String>, Error> {
+		let mut interpreter = Interpreter::new(1);
+		interpreter.register("say");
+		let mut said = Said::default();
+		let script = interpreter.load(text)?;
+		interpreter.call(&script, vec![], &mut said)?;
+		Ok(said.0)
+	}
+
+	#[test]
+	fn expressions_work_out_like_lua() {
+		let said = run("say(1 + 2 * 3 ^ 2, -2 ^ 2, 7 // 2, -7 % 3, 'a' .. 1 .. 2.5, 1 < 2 and 'yes' or 'no', not nil, #{1, 2, 3})").unwrap();
+		assert_eq!(said, ["19 -4 3 2 a12.5 yes true 3"]);
+	}
+
+	#[test]
+	fn functions_keep_their_variables_and_give_back_several_values() {
+		let said = run("
+			local function counter()
+				local n = 0
+				return function() n = n + 1 return n end
+			end
+			local a, b = counter(), counter()
+			a() a()
+			say(a(), b())
+			local function two() return 1, 2 end
+			local t = {two()}
+			local x, y, z = two()
+			say(#t, x, y, z, (two()))
+			local point = {x = 3}
+			function point:moved(by) return self.x + by end
+			say(point:moved(4))
+		").unwrap();
+		assert_eq!(said, ["3 1", "2 1 2 nil 1", "7"]);
+	}
+
+	#[test]
+	fn loops_run_and_break() {
+		let said = run("
+			local total = 0
+			for i = 10, 1, -3 do total = total + i end
+			local keys = ''
+			for k, v in pairs({b = 1, a = 2}) do keys = keys .. k .. v end
+			local n = 0
+			while true do n = n + 1 if n == 5 then break end end
+			repeat local m = n n = n + 1 until m >= 7
+			for i, v in ipairs({4, 5, nil, 6}) do total = total + v end
+			say(total, keys, n)
+		").unwrap();
+		assert_eq!(said, ["31 a2b1 8"]);
+	}
+
+	#[test]
+	fn mistakes_say_where_they_are() {
+		assert_eq!(run("say(1)\nlocal x = = 2").unwrap_err().line, 2);
+		assert_eq!(run("local t = nil\n\nsay(t.x)").unwrap_err().line, 3);
+		assert!(run("break").is_err());
+		assert_eq!(run("error('no')").unwrap_err().message, "no");
+	}
+
+	#[test]
+	fn a_script_that_never_stops_is_stopped() {
+		assert!(run("while true do end").unwrap_err().message.contains("steps"));
+		assert!(run("local function f() return f() end f()").unwrap_err().message.contains("deep"));
+	}
+}
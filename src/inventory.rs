@@ -0,0 +1,108 @@
+// What the player carries: how many of each block they've picked up. In survival mode that's
+// also what there is to build with; in creative mode anything can be built.
+
+use macroquad::prelude::*;
+use crate::Voxel;
+use crate::block::{self, BlockId};
+
+// size of an entry on the inventory screen, in pixels
+const ENTRY: (f32, f32) = (150.0, 30.0);
+// size of the color sliders under the entries, in pixels
+const SLIDER: (f32, f32) = (256.0, 16.0);
+
+pub struct Inventory {
+	counts: Vec<u32>,
+}
+
+impl Inventory {
+	pub fn new() -> Inventory {
+		Inventory { counts: vec![0; block::all().len()] }
+	}
+
+	pub fn add(&mut self, block: BlockId) {
+		self.counts[block as usize] += 1;
+	}
+
+	pub fn count(&self, block: BlockId) -> u32 {
+		self.counts[block as usize]
+	}
+
+	// take one of `block`, if there is one
+	pub fn take(&mut self, block: BlockId) -> bool {
+		let count = &mut self.counts[block as usize];
+		if *count == 0 {
+			return false;
+		}
+		*count -= 1;
+		true
+	}
+
+	// take one of the first block held, if any
+	pub fn take_first(&mut self) -> Option<BlockId> {
+		let (block, _) = self.items().next()?;
+		self.counts[block as usize] -= 1;
+		Some(block)
+	}
+
+	// blocks held at least one of, with their counts
+	pub fn items(&self) -> impl Iterator<Item = (BlockId, u32)> + '_ {
+		self.counts.iter().enumerate().filter(|(_, &n)| n > 0).map(|(b, &n)| (b as BlockId, n))
+	}
+
+	// Draw the inventory screen over the middle of the window: what's carried, or in creative
+	// mode every block there is to build with, and sliders for the color to build in. Clicking
+	// a block builds with it in its own color, and dragging the sliders tints it.
+	pub fn draw_screen(&self, creative: bool, selected: &mut Voxel) {
+		let entries: Vec<(BlockId, Option<u32>)> = if creative {
+			(1..block::all().len() as BlockId).filter(|&b| buildable(b)).map(|b| (b, None)).collect()
+		} else {
+			self.items().map(|(b, n)| (b, Some(n))).collect()
+		};
+		let (w, h) = (screen_width(), screen_height());
+		let columns = ((w * 0.8 / ENTRY.0) as usize).max(1);
+		let rows = entries.len().div_ceil(columns).max(1);
+		let sliders = 3.0 * (SLIDER.1 + 8.0);
+		let (left, top) = (w/2.0 - columns as f32 * ENTRY.0 / 2.0, h/2.0 - (rows as f32 * ENTRY.1 + sliders) / 2.0);
+		draw_rectangle(left - 8.0, top - 32.0, columns as f32 * ENTRY.0 + 16.0, rows as f32 * ENTRY.1 + sliders + 48.0, Color::new(0.0, 0.0, 0.0, 0.75));
+		draw_text(if creative { "Blocks" } else { "Inventory" }, left, top - 10.0, 24.0, WHITE);
+		if entries.is_empty() {
+			draw_text("nothing yet", left, top + 20.0, 20.0, GRAY);
+		}
+		let (mx, my) = mouse_position();
+		for (k, &(b, count)) in entries.iter().enumerate() {
+			let (x, y) = (left + (k % columns) as f32 * ENTRY.0, top + (k / columns) as f32 * ENTRY.1);
+			let hovered = mx >= x && mx < x + ENTRY.0 && my >= y && my < y + ENTRY.1;
+			if b == selected.block || hovered {
+				draw_rectangle_lines(x, y, ENTRY.0 - 4.0, ENTRY.1 - 4.0, 2.0, if b == selected.block { YELLOW } else { GRAY });
+			}
+			draw_rectangle(x + 4.0, y + 4.0, ENTRY.1 - 12.0, ENTRY.1 - 12.0, Color::from_vec(block::def(b).color));
+			let label = match count {
+				Some(n) => format!("{} {}", block::def(b).name, n),
+				None => block::def(b).name.to_string(),
+			};
+			draw_text(label, x + ENTRY.1, y + 20.0, 20.0, WHITE);
+			if hovered && is_mouse_button_pressed(MouseButton::Left) {
+				*selected = Voxel::of(b);
+			}
+		}
+
+		let top = top + rows as f32 * ENTRY.1 + 8.0;
+		draw_rectangle(left, top, 3.0 * SLIDER.1 + 16.0, 3.0 * SLIDER.1 + 16.0, Color::from_vec(selected.color));
+		let left = left + 3.0 * SLIDER.1 + 32.0;
+		for (channel, (name, color)) in [("R", RED), ("G", GREEN), ("B", BLUE)].into_iter().enumerate() {
+			let y = top + channel as f32 * (SLIDER.1 + 8.0);
+			draw_text(name, left, y + SLIDER.1 - 2.0, 20.0, WHITE);
+			let x = left + 20.0;
+			draw_rectangle(x, y, SLIDER.0 * selected.color[channel], SLIDER.1, color);
+			draw_rectangle_lines(x, y, SLIDER.0, SLIDER.1, 2.0, GRAY);
+			if is_mouse_button_down(MouseButton::Left) && mx >= x - 8.0 && mx < x + SLIDER.0 + 8.0 && my >= y && my < y + SLIDER.1 {
+				selected.color[channel] = ((mx - x) / SLIDER.0).clamp(0.0, 1.0);
+			}
+		}
+	}
+}
+
+// blocks that make sense to build with: not air, nor ones that only come from elsewhere
+fn buildable(block: BlockId) -> bool {
+	!matches!(block, block::AIR | block::CUSTOM | block::FIRE)
+}
@@ -0,0 +1,48 @@
+// Floating islands generator: clusters of islands with open sky between them. The density
+// noise is periodic in every axis, so islands wrap vertically as well as horizontally.
+
+use glam::*;
+use rayon::prelude::*;
+use crate::{World, Voxel, Progress, ISLAND_CLUSTERS, link_world, paint_column, decoration, ores};
+use crate::biome::BiomeMap;
+use crate::block;
+use crate::noise::{Noise, NoiseParams};
+use crate::rng::Rng;
+
+// how much density the noise has to add before a voxel is solid; higher means smaller islands
+const THRESHOLD: f32 = 0.35;
+
+pub fn build_islands_world(nx: usize, ny: usize, nz: usize, params: &NoiseParams, rng: &mut Rng, progress: &Progress) -> World {
+	let mut world = link_world(nx, ny, nz, progress);
+	let biomes = BiomeMap::generate(nx, nz, rng);
+	let shape = Noise::new(rng);
+	let clusters = Noise::new(rng);
+
+	let mut voxels = vec![Voxel::of(block::AIR); nx*ny*nz];
+	let mut surface = vec![None; nx*nz];
+	progress.begin("islands", nx);
+	voxels.par_chunks_mut(ny*nz).zip(surface.par_chunks_mut(nz)).enumerate().for_each(|(x, (slab, tops))| {
+		let mut solid = vec![false; ny];
+		for (z, top) in tops.iter_mut().enumerate() {
+			for (y, s) in solid.iter_mut().enumerate() {
+				let p = vec3(x as f32 / nx as f32, y as f32 / ny as f32, z as f32 / nz as f32);
+				// sampling the shape at twice the height squashes islands into flatter slabs
+				let squashed = vec3(p.x, (2.0 * p.y).fract(), p.z);
+				let density = shape.fbm(squashed, params) + clusters.fbm(p, &ISLAND_CLUSTERS);
+				*s = density > THRESHOLD;
+			}
+			*top = (0..ny).rev().find(|&y| solid[y]);
+			paint_column(&solid, &biomes.biome(x, z).palette(), |y, b| slab[y*nz + z] = Voxel::of(b));
+		}
+		progress.advance();
+	});
+	world.set_voxels(&voxels);
+
+	progress.begin("decorating", 2);
+	ores::scatter_ores(&mut world, nx, ny, nz, &surface, rng);
+	progress.advance();
+	decoration::decorate(&mut world, nx, ny, nz, &surface, &biomes, rng);
+	progress.advance();
+
+	world
+}
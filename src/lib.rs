@@ -0,0 +1,996 @@
+#[cfg(feature = "graphics")]
+use std::f32::consts::PI;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use glam::*;
+#[cfg(feature = "graphics")]
+use macroquad::prelude::*;
+use rayon::prelude::*;
+use rng::Rng;
+use noise::{Noise, NoiseParams};
+use biome::{BiomeMap, Palette, SUBSURFACE_DEPTH};
+use block::BlockId;
+use streaming::Streamer;
+#[cfg(feature = "graphics")]
+use render::{Renderer, Screen, View};
+use world::{World, Neighbors};
+#[cfg(feature = "graphics")]
+use dirty::Dirty;
+use gravity::{Gravity, GravityField};
+use entity::{Entity, Kind};
+#[cfg(feature = "graphics")]
+use event::Event;
+use particle::Particles;
+
+mod animation;
+mod automaton;
+mod biome;
+mod block;
+mod brush;
+mod circuit;
+#[cfg(feature = "graphics")]
+mod clipboard;
+#[cfg(feature = "graphics")]
+mod cloud;
+mod config;
+mod console;
+mod decoration;
+mod dirty;
+mod ecology;
+#[cfg(feature = "graphics")]
+mod effects;
+mod entity;
+mod erosion;
+#[cfg(feature = "graphics")]
+mod event;
+mod explosion;
+mod falling;
+mod fire;
+#[cfg(feature = "graphics")]
+mod game;
+#[cfg(feature = "graphics")]
+mod gamepad;
+mod gravity;
+#[cfg(feature = "graphics")]
+mod guide;
+mod heightmap;
+#[cfg(feature = "graphics")]
+mod hotbar;
+mod interact;
+#[cfg(feature = "graphics")]
+mod input;
+#[cfg(feature = "graphics")]
+mod interpreter;
+#[cfg(feature = "graphics")]
+mod inventory;
+mod islands;
+#[cfg(feature = "graphics")]
+mod light;
+#[cfg(feature = "graphics")]
+mod locator;
+mod log;
+#[cfg(feature = "graphics")]
+mod map;
+mod maze;
+#[cfg(feature = "graphics")]
+mod menu;
+#[cfg(feature = "graphics")]
+mod mesh;
+#[cfg(feature = "graphics")]
+mod minimap;
+#[cfg(feature = "graphics")]
+mod mob;
+#[cfg(feature = "audio")]
+mod music;
+mod net;
+mod noise;
+#[cfg(feature = "graphics")]
+mod packet;
+mod particle;
+#[cfg(feature = "graphics")]
+mod path;
+mod permission;
+mod player;
+mod prefab;
+mod ores;
+#[cfg(feature = "graphics")]
+mod render;
+mod rng;
+mod save;
+mod schedule;
+#[cfg(feature = "graphics")]
+mod script;
+mod selection;
+mod server;
+#[cfg(feature = "graphics")]
+mod sign;
+#[cfg(feature = "audio")]
+mod sound;
+#[cfg(feature = "graphics")]
+mod spectator;
+#[cfg(feature = "graphics")]
+mod stamp;
+#[cfg(feature = "graphics")]
+mod stats;
+mod streaming;
+#[cfg(feature = "graphics")]
+mod symmetry;
+#[cfg(feature = "graphics")]
+mod sync;
+#[cfg(feature = "graphics")]
+mod touch;
+mod water;
+#[cfg(feature = "graphics")]
+mod waypoint;
+mod weather;
+mod world;
+
+// types
+
+#[derive(Clone, PartialEq)]
+struct Voxel {
+	color: Vec4,
+	transparent: bool,
+	block: BlockId,
+	// what the block is up to, if it has anything to keep track of, like how far flowing water
+	// is from its source
+	state: u8,
+}
+
+impl Voxel {
+	fn new(block: BlockId, color: Vec4) -> Voxel {
+		Voxel { color, transparent: block::def(block).transparent, block, state: 0 }
+	}
+
+	// a voxel of the block's own color
+	fn of(block: BlockId) -> Voxel {
+		Voxel::new(block, block::def(block).color)
+	}
+
+	fn with_state(self, state: u8) -> Voxel {
+		Voxel { state, ..self }
+	}
+}
+
+#[allow(dead_code)]
+struct Position {
+	voxel_id: usize,
+	local_position: Vec3
+}
+
+#[cfg(feature = "graphics")]
+struct Camera {
+	i: usize,
+	position: Vec3,
+	// which way the camera faces, relative to the way gravity stands it up: it looks along x,
+	// with y up and z to its right
+	rotation: Quat,
+	movement_speed: f32,
+	rotation_speed: (f32, f32),
+	fov: (f32, f32),
+	screen: (usize, usize),
+}
+
+#[cfg(feature = "graphics")]
+impl Camera {
+	// the way the camera looks, its up and its right, before gravity stands it up
+	fn basis(&self) -> (Vec3, Vec3, Vec3) {
+		(self.rotation * Vec3::X, self.rotation * Vec3::Y, self.rotation * Vec3::Z)
+	}
+
+	// Turn `yaw` radians to the right, around the way gravity stands the camera up, and `pitch`
+	// up, around its own right, stopping at straight up or down.
+	fn turn(&mut self, yaw: f32, pitch: f32) {
+		let now = (self.rotation * Vec3::X).y.clamp(-1.0, 1.0).asin();
+		let pitch = (now + pitch).clamp(-PI/2.0, PI/2.0) - now;
+		self.rotation = (Quat::from_rotation_y(-yaw) * self.rotation * Quat::from_rotation_z(pitch)).normalize();
+	}
+
+	// roll `angle` radians clockwise, around the way the camera looks
+	fn roll(&mut self, angle: f32) {
+		self.rotation = (self.rotation * Quat::from_rotation_x(angle)).normalize();
+	}
+}
+
+// Settings; more are read from the settings file, see the config module
+// movement is simulated in ticks of fixed length, speeds are per tick
+const TICK: f32 = 1.0 / 60.0;
+// longest frame time simulated, so a stall doesn't run a burst of ticks
+#[cfg(feature = "graphics")]
+const MAX_FRAME_TIME: f32 = 0.25;
+const TERMINAL_VELOCITY: f32 = 1.0;
+// sneaking slows movement by this factor and lowers the camera
+#[cfg(feature = "graphics")]
+const SNEAK_SPEED: f32 = 0.5;
+#[cfg(feature = "graphics")]
+const SNEAK_CAMERA_DROP: f32 = 0.25;
+// sprinting speeds movement up by this factor and widens the field of view
+#[cfg(feature = "graphics")]
+const SPRINT_SPEED: f32 = 1.6;
+#[cfg(feature = "graphics")]
+const SPRINT_FOV: f32 = 1.15;
+// radians per second the camera rolls with the roll keys
+#[cfg(feature = "graphics")]
+const ROLL_SPEED: f32 = 1.5;
+// the spectator camera flies this many times faster than walking
+#[cfg(feature = "graphics")]
+const SPECTATOR_SPEED: f32 = 3.0;
+// holding zoom narrows the field of view by this factor, and mouse turning with it
+#[cfg(feature = "graphics")]
+const ZOOM_FOV: f32 = 0.25;
+// how quickly the view widens and narrows, per second
+#[cfg(feature = "graphics")]
+const FOV_RATE: f32 = 10.0;
+// with the camera in water, movement is slowed by SWIM_SPEED, gravity by WATER_GRAVITY and
+// vertical velocity by WATER_DRAG each tick; the player sinks or swims up to the given speeds
+#[cfg(feature = "graphics")]
+const SWIM_SPEED: f32 = 0.6;
+#[cfg(feature = "graphics")]
+const WATER_GRAVITY: f32 = 0.3;
+#[cfg(feature = "graphics")]
+const WATER_DRAG: f32 = 0.9;
+#[cfg(feature = "graphics")]
+const SWIM_ACCELERATION: f32 = 0.02;
+#[cfg(feature = "graphics")]
+const SINK_VELOCITY: f32 = 0.05;
+#[cfg(feature = "graphics")]
+const SWIM_VELOCITY: f32 = 0.12;
+// pressing jump twice within this many seconds toggles flying
+#[cfg(feature = "graphics")]
+const DOUBLE_TAP_TIME: f64 = 0.3;
+// speed broken blocks pop up at, in voxels per tick
+const ITEM_POP: f32 = 0.1;
+// speed arrows are shot and blocks thrown at, in voxels per tick
+#[cfg(feature = "graphics")]
+const ARROW_SPEED: f32 = 0.8;
+#[cfg(feature = "graphics")]
+const THROW_SPEED: f32 = 0.4;
+// seconds after breaking a block before the next one starts breaking while the button is held
+#[cfg(feature = "graphics")]
+const BREAK_DELAY: f32 = 0.25;
+// fraction of an explosion's sideways push kept each tick
+#[cfg(feature = "graphics")]
+const KNOCKBACK_DRAG: f32 = 0.85;
+// how quickly the camera turns to a new gravity direction, per second
+#[cfg(feature = "graphics")]
+const ORIENTATION_RATE: f32 = 8.0;
+// seconds from dying to respawning
+#[cfg(feature = "graphics")]
+const RESPAWN_DELAY: f64 = 2.0;
+// loaded part of a streamed world, which should be at least twice the view distance across
+const STREAM_WINDOW: [usize; 3] = [128, 128, 128];
+const TOUCH_DISTANCE: usize = 16;
+// where the clipboard is written as a schematic unless --clipboard-file says otherwise
+#[cfg(feature = "graphics")]
+const CLIPBOARD_FILE: &str = "clipboard.prefab";
+// frames drawn by --benchmark
+#[cfg(feature = "graphics")]
+const BENCHMARK_FRAMES: usize = 120;
+// most solid voxels x-ray sees through
+#[cfg(feature = "graphics")]
+const MAX_XRAY: usize = 8;
+// noise for the default terrain and the floating islands unless the settings say otherwise
+const TERRAIN_NOISE: NoiseParams = NoiseParams { amplitude: 0.8, frequency: 2, octaves: 4 };
+const ISLAND_NOISE: NoiseParams = NoiseParams { amplitude: 1.0, frequency: 4, octaves: 3 };
+const ISLAND_CLUSTERS: NoiseParams = NoiseParams { amplitude: 0.5, frequency: 2, octaves: 1 };
+// cube of random cells the life generator starts with: its size and the chance of each cell
+const LIFE_SOUP: (usize, f32) = (24, 0.35);
+const DEFAULT_LIFE_RULE: &str = "amoeba";
+// height of the sea in the default terrain, as a fraction of the world height
+const SEA_LEVEL: f32 = 0.45;
+
+
+// Raycasting algorithm
+fn lattice_intersect(pos: Vec3, v: Vec3) -> (Vec3, [i32; 3], f32) {
+	let t = ((v.signum() + 1.0) / 2.0 - pos) / v;
+	let t_min: f32 = t.min_element();
+	let mut i_min: usize = 0;
+	for i in 0..3 {
+		if t[i] == t_min {
+			i_min = i
+		}
+	}
+	let mut key: [i32; 3] = [0, 0, 0];
+	key[i_min] = v[i_min].signum() as i32;
+	let key2 = vec3(key[0] as f32, key[1] as f32, key[2] as f32);
+	let x_new = pos + t_min*v - key2;
+	(x_new, key, (t_min*v).length())
+}
+
+fn raycast(world: &World, vox_id: usize, basepoint: Vec3, ray: Vec3, max_steps: usize) -> (usize, Vec3, f32) {
+	let (i, x, dist, _, _) = raycast_liquid(world, vox_id, basepoint, ray, max_steps);
+	(i, x, dist)
+}
+
+// like `raycast`, also measuring how far the ray went through liquid before it hit something,
+// and counting the voxels it stepped through
+fn raycast_liquid(world: &World, vox_id: usize, basepoint: Vec3, ray: Vec3, max_steps: usize) -> (usize, Vec3, f32, f32, usize) {
+	let (mut i, mut x) = (vox_id, basepoint);
+	let mut k: [i32; 3];
+	let mut dist = 0.0;
+	let mut wet = 0.0;
+	let mut dt: f32;
+	for steps in 1..=max_steps {
+		(x, k, dt)  = lattice_intersect(x, ray);
+		dist += dt;
+		if world.liquid(i) {
+			wet += dt;
+		}
+		let n = world.neighbors(i);
+		if k[0] == 1 {
+			i = n.up_x as usize;
+		} else if k[0] == -1 {
+			i = n.down_x as usize;
+		} else if k[1] == 1 {
+			i = n.up_y as usize;
+		} else if k[1] == -1 {
+			i = n.down_y as usize;
+		} else if k[2] == 1 {
+			i = n.up_z as usize;
+		} else if k[2] == -1 {
+			i = n.down_z as usize;
+		}
+		if ! world.transparent(i) {
+			return (i, x, dist, wet, steps);
+		}
+	}
+	(i, x, max_steps as f32, wet, max_steps)
+}
+
+// neighbor of voxel i one step along `axis` (0, 1, 2 for x, y, z), upward if `sign` is positive
+fn step(world: &World, i: usize, axis: usize, sign: i32) -> usize {
+	let n = world.neighbors(i);
+	(match (axis, sign > 0) {
+		(0, true) => n.up_x,
+		(0, false) => n.down_x,
+		(1, true) => n.up_y,
+		(1, false) => n.down_y,
+		(_, true) => n.up_z,
+		(_, false) => n.down_z,
+	}) as usize
+}
+
+// Turn voxel i to air, dropping what it was made of as an item if `drop`. TNT is lit instead.
+fn break_block(world: &mut World, entities: &mut Vec<Entity>, particles: &mut Particles, i: usize, drop: bool) {
+	if world.transparent(i) {
+		return;
+	}
+	particles.debris(i, shade(world, i));
+	if world.voxel(i).block == block::TNT {
+		explosion::light(world, entities, i);
+		return;
+	}
+	if drop {
+		let mut item = Entity::new(Kind::Item(world.voxel(i).block), i, Vec3::splat(0.5));
+		item.velocity = ITEM_POP * world.gravity_at(i).up();
+		entities.push(item);
+	}
+	world.set(i, Voxel::of(block::AIR));
+}
+
+// Publish the entities `spawning` adds to a list on the bus, to join the others.
+#[cfg(feature = "graphics")]
+fn publish_spawns(bus: &mut event::Bus, spawning: impl FnOnce(&mut Vec<Entity>)) {
+	let mut spawned = vec![];
+	spawning(&mut spawned);
+	bus.extend(spawned.into_iter().map(Event::Spawn));
+}
+
+// What keeps the world getting on by itself from tick to tick.
+struct Simulation {
+	schedule: schedule::Scheduler,
+	flow: water::Flow,
+	fire: fire::Fire,
+	circuit: circuit::Circuit,
+	ecology: ecology::Ecology,
+}
+
+impl Simulation {
+	// water flowing between two sources becomes a source itself if `infinite`
+	fn new(seed: u64, infinite: bool) -> Simulation {
+		Simulation {
+			schedule: schedule::Scheduler::new(seed),
+			flow: water::Flow::new(infinite),
+			fire: fire::Fire::new(seed),
+			circuit: circuit::Circuit::new(),
+			ecology: ecology::Ecology::new(seed),
+		}
+	}
+}
+
+// A tick of the world getting on by itself once the voxels `changed` have changed: blocks
+// falling, water flowing, fire spreading, cells living and dying, signals passing along, and
+// plants growing and withering around the voxels in `near`, where the players are.
+fn simulate(world: &mut World, entities: &mut Vec<Entity>, changed: &[usize], near: &[usize], simulation: &mut Simulation, automaton: Option<&mut automaton::Automaton>) {
+	let Simulation { schedule, flow, fire, circuit, ecology } = simulation;
+	falling::notice(world, changed, schedule);
+	flow.notice(world, changed, schedule);
+	fire.notice(world, changed, schedule);
+	let due = schedule.tick();
+	let flowing: Vec<usize> = due.iter().filter(|(_, update)| *update == schedule::Update::Flow).map(|&(i, _)| i).collect();
+	flow.flow(world, &flowing);
+	for (i, update) in due {
+		match update {
+			schedule::Update::Flow => {},
+			schedule::Update::Fall => falling::loosen(world, entities, i),
+			schedule::Update::Burn => fire.burn(world, entities, i, schedule),
+		}
+	}
+	if let Some(automaton) = automaton {
+		automaton.notice(world, changed);
+		automaton.tick(world);
+	}
+	circuit.notice(world, changed);
+	circuit.tick(world);
+	let random = schedule.random(world, near);
+	ecology.tick(world, entities, &random);
+}
+
+// Where in the window the image goes, as its top left corner and the side of the square each
+// ray is drawn as: as big as fits, centered, whatever shape the window is now.
+#[cfg(feature = "graphics")]
+fn letterbox(screen: (usize, usize)) -> (Vec2, f32) {
+	let (w, h) = (screen_width(), screen_height());
+	let rect = (w / screen.0 as f32).min(h / screen.1 as f32);
+	(vec2(w - rect * screen.0 as f32, h - rect * screen.1 as f32) / 2.0, rect)
+}
+
+// Share of the way to move toward something approached at `rate` per second this frame. Over
+// any run of frames it adds up to the same as one frame as long as them all, so easing doesn't
+// depend on the frame rate.
+#[cfg(feature = "graphics")]
+fn approach(rate: f32) -> f32 {
+	1.0 - (-rate * get_frame_time()).exp()
+}
+
+// Move a position given relative to voxel i into the voxel it has wandered into, returning
+// that voxel and the position relative to it.
+fn settle(world: &World, i: usize, position: Vec3) -> (usize, Vec3) {
+	let (mut i, mut position) = (i, position);
+	for axis in 0..3 {
+		while position[axis] < 0.0 {
+			i = step(world, i, axis, -1);
+			position[axis] += 1.0;
+		}
+		while position[axis] > 1.0 {
+			i = step(world, i, axis, 1);
+			position[axis] -= 1.0;
+		}
+	}
+	(i, position)
+}
+
+// voxels of the same block vary a little in color; the variation is worked out from the
+// voxel's id when it is drawn, so it costs no memory
+fn shade(world: &World, i: usize) -> Vec4 {
+	let voxel = world.voxel(i);
+	if voxel.block == block::CUSTOM {
+		return voxel.color;
+	}
+	match voxel.block {
+		// fire flickers brighter and dimmer as it burns, its state counting up
+		block::FIRE => {
+			let mut rng = Rng::new(i as u64 ^ ((voxel.state as u64) << 32));
+			let glow = rng.range(0.8, 1.3);
+			return (voxel.color * glow).min(Vec4::ONE).with_w(1.0);
+		},
+		block::WIRE | block::SWITCH | block::LAMP => return circuit::color(voxel),
+		_ => {},
+	}
+	let mut rng = Rng::new(i as u64);
+	voxel.color + vec4(rng.range(-0.025, 0.025), rng.range(-0.025, 0.025), rng.range(-0.025, 0.025), 0.0)
+}
+
+// whether voxel i gives off its own light, so it shows through the dark of distance
+#[cfg(feature = "graphics")]
+fn glows(world: &World, i: usize) -> bool {
+	let voxel = world.voxel(i);
+	block::def(voxel.block).emits || (voxel.block == block::LAMP && voxel.state > 0)
+}
+
+// Coordinates of voxel i in the whole world, which for a streamed world is larger than the
+// window loaded, and the size of the whole world.
+#[cfg(feature = "graphics")]
+fn locate(world: &World, streamer: Option<&Streamer>, i: usize) -> ([usize; 3], [usize; 3]) {
+	match streamer {
+		Some(streamer) => (streamer.global(i), streamer.size()),
+		None => {
+			let [_, ny, nz] = world.size();
+			([i / (ny*nz), i / nz % ny, i % nz], world.size())
+		},
+	}
+}
+
+// voxel reached from i by walking d[0], d[1], d[2] steps along x, y, z
+fn offset(world: &World, i: usize, d: [i32; 3]) -> usize {
+	let mut i = i;
+	for (axis, &n) in d.iter().enumerate() {
+		for _ in 0..n.abs() {
+			i = step(world, i, axis, n.signum());
+		}
+	}
+	i
+}
+
+// World generation
+
+// how far along world generation is, shared with the loading screen, and what went wrong on
+// the way to be shown once the game starts
+#[derive(Default)]
+struct Progress {
+	stage: Mutex<&'static str>,
+	done: AtomicUsize,
+	total: AtomicUsize,
+	warnings: Mutex<Vec<String>>,
+}
+
+impl Progress {
+	fn warn(&self, target: &str, warning: impl std::fmt::Display) {
+		log::warning!(target, "{}", warning);
+		self.warnings.lock().unwrap().push(format!("{}: {}", target, warning));
+	}
+
+	#[cfg(feature = "graphics")]
+	fn take_warnings(&self) -> Vec<String> {
+		std::mem::take(&mut *self.warnings.lock().unwrap())
+	}
+
+	fn begin(&self, stage: &'static str, total: usize) {
+		log::debug!("generation", "{}", stage);
+		*self.stage.lock().unwrap() = stage;
+		self.done.store(0, Ordering::Relaxed);
+		self.total.store(total, Ordering::Relaxed);
+	}
+
+	fn advance(&self) {
+		self.done.fetch_add(1, Ordering::Relaxed);
+	}
+
+	#[cfg(feature = "graphics")]
+	fn stage(&self) -> &'static str {
+		*self.stage.lock().unwrap()
+	}
+
+	#[cfg(feature = "graphics")]
+	fn fraction(&self) -> f32 {
+		let total = self.total.load(Ordering::Relaxed).max(1);
+		self.done.load(Ordering::Relaxed) as f32 / total as f32
+	}
+}
+
+fn furl(i: usize, j: usize, k: usize, ny: usize, nz: usize) -> usize {
+	i*ny*nz + j*nz + k
+}
+
+// initialize world of given size, fully transparent, linked as a 3-torus
+fn link_world(nx: usize, ny: usize, nz: usize, progress: &Progress) -> World {
+	let unlinked = Neighbors {
+		up_x  : 0,
+		down_x: 0,
+		up_y  : 0,
+		down_y: 0,
+		up_z  : 0,
+		down_z: 0
+	};
+
+	// link all the voxels to their neighbors (this defines the topology),
+	// one slab of constant x per task
+	let mut links = vec![unlinked; nx*ny*nz];
+	progress.begin("linking", nx);
+	links.par_chunks_mut(ny*nz).enumerate().for_each(|(i, slab)| {
+		let link = |i: usize, j: usize, k: usize| furl(i, j, k, ny, nz) as u32;
+		for j in 0..ny {
+			for k in 0..nz {
+				slab[j*nz + k] = Neighbors {
+					up_x  : link((i as i32 + 1).rem_euclid(nx as i32) as usize, j, k),
+					down_x: link((i as i32 - 1).rem_euclid(nx as i32) as usize, j, k),
+					up_y  : link(i, (j as i32 + 1).rem_euclid(ny as i32) as usize, k),
+					down_y: link(i, (j as i32 - 1).rem_euclid(ny as i32) as usize, k),
+					up_z  : link(i, j, (k as i32 + 1).rem_euclid(nz as i32) as usize),
+					down_z: link(i, j, (k as i32 - 1).rem_euclid(nz as i32) as usize)
+				};
+			}
+		}
+		progress.advance();
+	});
+
+	World::new([nx, ny, nz], links).expect("a torus only links voxels inside it")
+}
+
+// fill each (x, z) column from y = 0 up to the height given by `column` with its voxel
+fn fill_columns(world: &mut World, nx: usize, ny: usize, nz: usize, mut column: impl FnMut(usize, usize) -> (usize, Voxel)) {
+	for x in 0..nx {
+		for z in 0..nz {
+			let (height, voxel) = column(x, z);
+			for y in 0..height.min(ny) {
+				world.set(furl(x, y, z, ny, nz), voxel.clone());
+			}
+		}
+	}
+}
+
+// pick a block for each height of a column where `solid` is set, from the palette by its depth
+// below the nearest air above it, and hand it to `set` from the top down
+fn paint_column(solid: &[bool], palette: &Palette, mut set: impl FnMut(usize, BlockId)) {
+	let mut depth = 0;
+	for y in (0..solid.len()).rev() {
+		if !solid[y] {
+			depth = 0;
+			continue;
+		}
+		let block = match depth {
+			0 => palette.surface,
+			d if d <= SUBSURFACE_DEPTH => palette.subsurface,
+			_ => palette.stone,
+		};
+		depth += 1;
+		set(y, block);
+	}
+}
+
+// whether the default terrain is solid at p, given as fractions of the world size; density falls
+// off with height so the ground sits around the middle of the world, with hills and overhangs
+fn terrain_solid(noise: &Noise, params: &NoiseParams, roughness: f32, p: Vec3) -> bool {
+	1.0 - 2.0 * p.y + roughness * noise.fbm(p, params) > 0.0
+}
+
+// the default terrain, worn down by erosion first if `erode` is set
+fn build_world(nx: usize, ny: usize, nz: usize, erode: bool, params: &NoiseParams, rng: &mut Rng, progress: &Progress) -> World {
+	let mut world = link_world(nx, ny, nz, progress);
+	let biomes = BiomeMap::generate(nx, nz, rng);
+	let noise = Noise::new(rng);
+	let solid_at = |x: usize, y: f32, z: usize| {
+		terrain_solid(&noise, params, biomes.roughness(x, z), vec3(x as f32 / nx as f32, y / ny as f32, z as f32 / nz as f32))
+	};
+
+	// Erosion works on the height of the ground in each column, so each column is shifted up or
+	// down as a whole to meet the height it's worn to.
+	let mut shift = vec![0.0; nx*nz];
+	if erode {
+		progress.begin("surveying", nx);
+		let mut heights = vec![0.0; nx*nz];
+		heights.par_chunks_mut(nz).enumerate().for_each(|(x, heights)| {
+			for (z, height) in heights.iter_mut().enumerate() {
+				*height = (0..ny).rev().find(|&y| solid_at(x, y as f32, z)).map_or(0.0, |y| y as f32 + 1.0);
+			}
+			progress.advance();
+		});
+		let mut eroded = heights.clone();
+		erosion::erode(&mut eroded, nx, nz, rng, progress);
+		for (k, s) in shift.iter_mut().enumerate() {
+			// columns without any ground stay empty
+			if heights[k] > 0.0 {
+				*s = (heights[k] - eroded[k]).round();
+			}
+		}
+	}
+
+	// each slab of constant x is generated in parallel
+	let mut voxels = vec![Voxel::of(block::AIR); nx*ny*nz];
+	let mut surface = vec![None; nx*nz];
+	progress.begin("terrain", nx);
+	voxels.par_chunks_mut(ny*nz).zip(surface.par_chunks_mut(nz)).enumerate().for_each(|(x, (slab, tops))| {
+		let mut solid = vec![false; ny];
+		for (z, top) in tops.iter_mut().enumerate() {
+			for (y, s) in solid.iter_mut().enumerate() {
+				*s = solid_at(x, y as f32 + shift[x*nz + z], z);
+			}
+			*top = (0..ny).rev().find(|&y| solid[y]);
+			paint_column(&solid, &biomes.biome(x, z).palette(), |y, b| slab[y*nz + z] = Voxel::of(b));
+			// flood the open air between the ground and sea level
+			let ground = top.map_or(0, |t| t + 1);
+			for y in ground..(SEA_LEVEL * ny as f32) as usize {
+				slab[y*nz + z] = Voxel::of(block::WATER);
+			}
+		}
+		progress.advance();
+	});
+	world.set_voxels(&voxels);
+
+	progress.begin("decorating", 2);
+	ores::scatter_ores(&mut world, nx, ny, nz, &surface, rng);
+	progress.advance();
+	decoration::decorate(&mut world, nx, ny, nz, &surface, &biomes, rng);
+	progress.advance();
+
+	world
+}
+
+// Command line
+
+// value following `flag` on the command line, e.g. `--heightmap terrain.png`
+fn arg_value(args: &[String], flag: &str) -> Option<String> {
+	args.iter().position(|a| a == flag).and_then(|i| args.get(i + 1)).cloned()
+}
+
+// the role players joining a hosted game start out in, from `--default-role builder`
+fn default_role(args: &[String], warn: impl FnOnce(String)) -> permission::Role {
+	let role = arg_value(args, "--default-role");
+	match role.as_deref().map(permission::Role::parse) {
+		Some(Some(role)) => role,
+		Some(None) => {
+			warn(format!("there's no role called '{}', so players join as builders", role.unwrap_or_default()));
+			permission::Role::Builder
+		},
+		None => permission::Role::Builder,
+	}
+}
+
+// world dimensions written like `1024x256x1024`
+fn parse_size(s: &str) -> Option<[usize; 3]> {
+	let dims: Vec<usize> = s.split('x').map(|d| d.parse().ok()).collect::<Option<_>>()?;
+	match dims.as_slice() {
+		&[x, y, z] if x > 0 && y > 0 && z > 0 => Some([x, y, z]),
+		_ => None,
+	}
+}
+
+// an axis direction written like `-y` or `+x`
+fn parse_direction(s: &str) -> Option<(usize, i32)> {
+	let (sign, axis) = match s.strip_prefix('-') {
+		Some(axis) => (-1, axis),
+		None => (1, s.strip_prefix('+').unwrap_or(s)),
+	};
+	["x", "y", "z"].iter().position(|&a| a == axis).map(|a| (a, sign))
+}
+
+// gravity from `--gravity 0.01` (its strength, 0 for none) and `--gravity-down -y` (the way
+// things fall)
+fn parse_gravity(args: &[String]) -> Gravity {
+	let mut gravity = Gravity::DEFAULT;
+	if let Some(s) = arg_value(args, "--gravity") {
+		match s.parse() {
+			Ok(strength) => gravity.strength = strength,
+			Err(_) => log::warning!("gravity", "expected a number, got '{}'", s),
+		}
+	}
+	if let Some(s) = arg_value(args, "--gravity-down") {
+		match parse_direction(&s) {
+			Some((axis, sign)) => (gravity.axis, gravity.sign) = (axis, sign),
+			None => log::warning!("gravity", "expected a direction like -y or +x, got '{}'", s),
+		}
+	}
+	gravity
+}
+
+// a gravity field from `--gravity-field surfaces` (down toward the nearest solid voxel, so
+// every surface can be walked on) or `--gravity-flip 48` (reversed from that height on)
+fn parse_gravity_field(args: &[String], world: &World) -> Option<GravityField> {
+	if let Some(s) = arg_value(args, "--gravity-flip") {
+		match s.parse() {
+			Ok(plane) => return Some(GravityField::flipped(world, plane)),
+			Err(_) => log::warning!("gravity", "expected a height, got '{}'", s),
+		}
+	}
+	match arg_value(args, "--gravity-field").as_deref() {
+		Some("surfaces") => Some(GravityField::toward(world, |i| !world.transparent(i))),
+		Some(other) => {
+			log::warning!("gravity", "unknown field '{}'", other);
+			None
+		},
+		None => None,
+	}
+}
+
+// a transparent voxel resting on solid ground with room for the player above it: climb out
+// of the ground or drop onto it, moving on to other columns if one has no ground at all (as
+// between floating islands), too little headroom or is under water
+#[cfg(feature = "graphics")]
+fn find_spawn(world: &World) -> usize {
+	let size = world.size();
+	let gravity = world.gravity();
+	let (axis, down) = (gravity.axis, gravity.sign);
+	let across = [(axis + 1) % 3, (axis + 2) % 3];
+	for column in 0..size[across[0]]*size[across[1]] {
+		let mut p = [0; 3];
+		(p[across[0]], p[across[1]]) = (column / size[across[1]], column % size[across[1]]);
+		let mut i = furl(p[0], p[1], p[2], size[1], size[2]);
+		for _ in 0..size[axis] {
+			if world.transparent(i) {
+				break;
+			}
+			i = step(world, i, axis, -down);
+		}
+		if !world.transparent(i) {
+			continue;
+		}
+		for _ in 0..size[axis] {
+			let below = step(world, i, axis, down);
+			if !world.transparent(below) {
+				if world.transparent(step(world, i, axis, -down)) && !world.liquid(i) {
+					return i;
+				}
+				break;
+			}
+			i = below;
+		}
+	}
+	0
+}
+
+// Pick the world generator from the command line, falling back to the default terrain, shaped
+// by the `terrain` and `islands` noise. Fails if there can't be a world of the size asked for.
+fn generate_world(args: &[String], size: [usize; 3], terrain: &NoiseParams, islands: &NoiseParams, rng: &mut Rng, progress: &Progress) -> Result<(World, Option<Streamer>), String> {
+	if let Some(size) = arg_value(args, "--stream") {
+		match parse_size(&size) {
+			Some(size) => {
+				let (streamer, world) = Streamer::new(size, STREAM_WINDOW, terrain, rng, progress);
+				return Ok((world, Some(streamer)));
+			},
+			None => progress.warn("stream", format!("expected a size like 1024x256x1024, got '{}'", size)),
+		}
+	}
+	World::volume(size)?;
+	Ok((generate_fixed_world(args, size, terrain, islands, rng, progress), None))
+}
+
+// generate a world that is held in memory all at once
+fn generate_fixed_world(args: &[String], size: [usize; 3], terrain: &NoiseParams, islands: &NoiseParams, rng: &mut Rng, progress: &Progress) -> World {
+	let [nx, ny, nz] = size;
+	let erode = args.iter().any(|a| a == "--erode");
+	if let Some(path) = arg_value(args, "--heightmap") {
+		match heightmap::load_image(&path) {
+			Ok(heights) => {
+				let colors = arg_value(args, "--colormap").and_then(|path| {
+					heightmap::load_image(&path).map_err(|e| progress.warn("colormap", e)).ok()
+				});
+				let mut heights = heightmap::heights(&heights, nx, ny, nz);
+				if erode {
+					erosion::erode(&mut heights, nx, nz, rng, progress);
+				}
+				return heightmap::build_world_from_heightmap(nx, ny, nz, &heights, colors.as_ref(), rng, progress);
+			},
+			Err(e) => progress.warn("heightmap", e),
+		}
+	}
+	match arg_value(args, "--generator").as_deref() {
+		Some("maze") => {
+			let corridor = arg_value(args, "--maze-corridor").and_then(|s| s.parse().ok()).unwrap_or(2);
+			let wall = arg_value(args, "--maze-wall").map(|name| block::by_name(&name).unwrap_or_else(|| {
+				progress.warn("maze", format!("unknown block '{}'", name));
+				block::STONE
+			})).unwrap_or(block::STONE);
+			maze::build_maze_world(nx, ny, nz, corridor, wall, rng, progress)
+		},
+		Some("islands") => islands::build_islands_world(nx, ny, nz, islands, rng, progress),
+		Some("life") => automaton::build_soup_world(nx, ny, nz, LIFE_SOUP.0, LIFE_SOUP.1, rng, progress),
+		None | Some("terrain") => build_world(nx, ny, nz, erode, terrain, rng, progress),
+		Some(other) => {
+			progress.warn("generator", format!("unknown generator '{}'", other));
+			build_world(nx, ny, nz, erode, terrain, rng, progress)
+		},
+	}
+}
+
+// What the world is made from besides its seed and size, for a game joining another to check it
+// makes the same one: the options shaping it and the noise the generator uses, with the files
+// they name and the blocks there are stood for by digests, so they can be told apart in a line.
+fn recipe(args: &[String], terrain: &NoiseParams, islands: &NoiseParams) -> String {
+	let mut words = vec![];
+	let flags = ["--stream", "--generator", "--maze-corridor", "--maze-wall", "--life", "--gravity", "--gravity-down", "--gravity-field", "--gravity-flip", "--water"];
+	for flag in flags {
+		if let Some(value) = arg_value(args, flag) {
+			words.push(format!("{} {}", flag, value));
+		}
+	}
+	if args.iter().any(|a| a == "--erode") {
+		words.push("--erode".to_string());
+	}
+	for flag in ["--heightmap", "--colormap"] {
+		if let Some(path) = arg_value(args, flag) {
+			words.push(format!("{} {:016x}", flag, digest(&std::fs::read(path).unwrap_or_default())));
+		}
+	}
+	let noise = match arg_value(args, "--generator").as_deref() {
+		Some("islands") => Some(("islands", islands)),
+		Some("maze" | "life") => None,
+		_ => Some(("terrain", terrain)),
+	};
+	if let Some((name, n)) = noise.filter(|_| arg_value(args, "--heightmap").is_none()) {
+		words.push(format!("{} {} {} {}", name, n.amplitude, n.frequency, n.octaves));
+	}
+	let blocks: String = block::all().iter().map(|b| {
+		format!("{} {:?} {} {} {} {} {} {};", b.name, b.color.to_array(), b.transparent, b.liquid, b.falls, b.flammable, b.hardness, b.emits)
+	}).collect();
+	words.push(format!("blocks {:016x}", digest(blocks.as_bytes())));
+	words.join(" ")
+}
+
+// FNV-1a, which comes out the same in every build of the game, unlike the standard hasher
+fn digest(bytes: &[u8]) -> u64 {
+	bytes.iter().fold(0xcbf29ce484222325, |hash, &b| (hash ^ b as u64).wrapping_mul(0x100000001b3))
+}
+
+// Time drawing frames from the camera while it turns once around, and print how long they took.
+#[cfg(feature = "graphics")]
+fn benchmark(world: &World, camera: &Camera, view_distance: usize, ambient: Vec4) {
+	let frame = world.gravity_at(camera.i).frame();
+	// baked beforehand, as it is in play
+	let light = light::Light::bake(world);
+	let start = std::time::Instant::now();
+	let (mut rays, mut steps) = (0, 0);
+	let mut cache = render::Cache::default();
+	for k in 0..BENCHMARK_FRAMES {
+		let angle = 2.0 * PI * k as f32 / BENCHMARK_FRAMES as f32;
+		let drawn = render::render(&View {
+			world: world.clone(),
+			dirty: Dirty::default(),
+			i: camera.i,
+			position: camera.position,
+			look: frame * vec3(angle.cos(), 0.0, angle.sin()),
+			right: frame * vec3(-angle.sin(), 0.0, angle.cos()),
+			up: frame * Vec3::Y,
+			fov: camera.fov,
+			screen: camera.screen,
+			view_distance,
+			target: usize::MAX,
+			breaking: 0.0,
+			selection: None,
+			xray: 0,
+			ambient,
+			entities: vec![],
+			particles: vec![],
+			clouds: None,
+			light: Some(light.clone()),
+			time: 0.0,
+		}, &mut cache);
+		(rays, steps) = (rays + drawn.rays, steps + drawn.steps);
+	}
+	let elapsed = start.elapsed().as_secs_f64();
+	println!("benchmark: {} frames of {}x{} in {:.2} s, {:.1} ms each, {:.1} steps per ray", BENCHMARK_FRAMES, camera.screen.0, camera.screen.1, elapsed, 1000.0 * elapsed / BENCHMARK_FRAMES as f64, steps as f64 / rays as f64);
+}
+
+// Show why there's no world to play in until the window is closed or Escape is pressed.
+#[cfg(feature = "graphics")]
+async fn fail(reason: &str) {
+	log::error!("world", "couldn't make the world: {}", reason);
+	while !is_key_pressed(KeyCode::Escape) {
+		clear_background(BLACK);
+		let (w, h) = (screen_width(), screen_height());
+		draw_text("Couldn't make the world", w/4.0, h/2.0 - 24.0, 32.0, RED);
+		draw_text(reason, w/4.0, h/2.0 + 8.0, 20.0, WHITE);
+		draw_text("press Escape to quit", w/4.0, h/2.0 + 36.0, 20.0, GRAY);
+		next_frame().await;
+	}
+}
+
+// The cellular automaton, run on the cells of the life generator's world or any other given
+// `--life`, paused to begin with.
+fn life(args: &[String], world: &World) -> Option<automaton::Automaton> {
+	let life_world = arg_value(args, "--generator").as_deref() == Some("life");
+	(life_world || arg_value(args, "--life").is_some()).then(|| {
+		let rule = arg_value(args, "--life").unwrap_or(DEFAULT_LIFE_RULE.to_string());
+		let rule = automaton::Rule::parse(&rule).unwrap_or_else(|e| {
+			log::warning!("life", "{}", e);
+			automaton::Rule::parse(DEFAULT_LIFE_RULE).unwrap()
+		});
+		automaton::Automaton::new(rule, world)
+	})
+}
+
+// Play the game in a window, or host a dedicated server without one given `--server`.
+#[cfg(feature = "graphics")]
+pub fn run() {
+	log::init();
+	let args: Vec<String> = std::env::args().collect();
+	// a dedicated server has no window, so it's never handed to macroquad
+	if args.iter().any(|a| a == "--server") {
+		return server::run(&args);
+	}
+	macroquad::Window::new("voxeltorus", play(args));
+}
+
+// Host a dedicated server, for `voxeltorus-server`.
+pub fn serve() {
+	log::init();
+	let args: Vec<String> = std::env::args().collect();
+	server::run(&args);
+}
+
+#[cfg(feature = "graphics")]
+async fn play(args: Vec<String>) {
+	let Some(mut game) = game::Game::start(args).await else { return };
+	while game.frame() {
+		next_frame().await;
+	}
+}
@@ -0,0 +1,279 @@
+// Baked light: how much sunlight and how much light from glowing blocks reaches each voxel,
+// worked out ahead of time so drawing a lit world costs no more than looking it up. Sunlight
+// comes in at the top of the world, against its gravity (the world's own, not a field's), and
+// shines straight down each column until something solid stops it, dimming through water.
+// From there, and from every glowing block, light spreads through the open voxels around, a
+// level dimmer with every step, in rounds run over every voxel reached at once. Levels go from
+// 0 to MAX, and the face of a solid voxel is lit by the open voxel in front of it.
+//
+// The whole world is baked in the background when the game starts, and drawn unlit until it's
+// done. After that, when voxels change, only the columns of chunks along the way up within
+// MAX voxels of the chunks that changed are baked again, in the background too, since light
+// reaches no further than that from where it starts except straight down.
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use rayon::prelude::*;
+use crate::{World, furl, glows, log};
+use crate::dirty::Dirty;
+use crate::world::CHUNK;
+
+pub const MAX: u8 = 15;
+// levels lost going into a voxel of water, rather than one
+const WATER_DIM: u8 = 3;
+// how bright the darkest voxels are, and how much of its brightness each level down keeps
+const FLOOR: f32 = 0.12;
+const FALLOFF: f32 = 0.82;
+const VOLUME: usize = CHUNK*CHUNK*CHUNK;
+
+#[derive(Clone)]
+pub struct Light {
+	// levels by the world's chunks, laid out like its voxels in them, sunlight in the high four
+	// bits and glowing blocks' light in the low four
+	chunks: Vec<Arc<Vec<u8>>>,
+}
+
+fn pack(sun: u8, blocks: u8) -> u8 {
+	sun << 4 | blocks
+}
+
+fn unpack(level: u8) -> (u8, u8) {
+	(level >> 4, level & 0xf)
+}
+
+// coordinates of chunk c along each axis, in chunks
+fn coordinates(grid: [usize; 3], c: usize) -> [usize; 3] {
+	[c / (grid[1]*grid[2]), c / grid[2] % grid[1], c % grid[2]]
+}
+
+// the voxels in chunk c, leaving out the part of chunks at the far edges hanging over the end
+fn voxels(world: &World, c: usize) -> impl Iterator<Item = usize> {
+	let size = world.size();
+	let [_, ny, nz] = size;
+	let corner = coordinates(world.grid(), c).map(|n| n * CHUNK);
+	let [ex, ey, ez] = [0, 1, 2].map(|k| CHUNK.min(size[k] - corner[k]));
+	(0..ex).flat_map(move |x| (0..ey).flat_map(move |y| (0..ez).map(move |z| furl(corner[0] + x, corner[1] + y, corner[2] + z, ny, nz))))
+}
+
+// whether voxel i is on a side of the chunk it's in, next to another chunk
+fn side(world: &World, i: usize) -> bool {
+	let size = world.size();
+	let [_, ny, nz] = size;
+	let p = [i / (ny*nz), i / nz % ny, i % nz];
+	// the chunks at the far edges end where the world does
+	(0..3).any(|k| p[k].is_multiple_of(CHUNK) || p[k] % CHUNK == CHUNK - 1 || p[k] == size[k] - 1)
+}
+
+impl Light {
+	// bake the whole world
+	pub fn bake(world: &World) -> Light {
+		let [gx, gy, gz] = world.grid();
+		let mut light = Light { chunks: (0..gx*gy*gz).map(|_| Arc::new(vec![0; VOLUME])).collect() };
+		light.rebake(world, &vec![true; gx*gy*gz], &[]);
+		light
+	}
+
+	// sunlight and glowing blocks' light in voxel i
+	pub fn level(&self, world: &World, i: usize) -> (u8, u8) {
+		let (c, k) = world.locate(i);
+		unpack(self.chunks[c][k])
+	}
+
+	// how bright a face lit by voxel i is, from FLOOR to 1
+	pub fn brightness(&self, world: &World, i: usize) -> f32 {
+		let (sun, blocks) = self.level(world, i);
+		FLOOR + (1.0 - FLOOR) * FALLOFF.powi((MAX - sun.max(blocks)) as i32)
+	}
+
+	// bake again around the chunks marked in `dirty`
+	pub fn update(&mut self, world: &World, dirty: &Dirty) {
+		if dirty.is_empty() {
+			return;
+		}
+		if dirty.everything() {
+			*self = Light::bake(world);
+			return;
+		}
+		let size = world.size();
+		let grid = world.grid();
+		let up = world.gravity().axis;
+		let across = [(up + 1) % 3, (up + 2) % 3];
+		let columns: HashSet<[usize; 2]> = dirty.chunks().map(|c| {
+			let cc = coordinates(grid, c);
+			across.map(|k| cc[k])
+		}).collect();
+		// voxels between chunks m and n along axis k, going the shorter way around the world
+		let gap = |k: usize, m: usize, n: usize| {
+			if m == n {
+				return 0;
+			}
+			let end = |c: usize| (c * CHUNK + CHUNK).min(size[k]);
+			let ahead = (m * CHUNK + size[k] - end(n)) % size[k];
+			let behind = (n * CHUNK + size[k] - end(m)) % size[k];
+			ahead.min(behind)
+		};
+		// the columns of chunks near enough to one that changed for its light to reach, and the
+		// ones next to those
+		let near = |cc: [usize; 3]| columns.iter().any(|column| (0..2).all(|n| gap(across[n], cc[across[n]], column[n]) <= MAX as usize));
+		let region: Vec<bool> = (0..self.chunks.len()).into_par_iter().map(|c| near(coordinates(grid, c))).collect();
+		let ring: Vec<usize> = (0..self.chunks.len()).filter(|&c| !region[c] && {
+			let cc = coordinates(grid, c);
+			[(0, 1), (0, -1), (1, 0), (-1, 0), (1, 1), (1, -1), (-1, 1), (-1, -1)].iter().any(|&(du, dv): &(i32, i32)| {
+				let mut next = cc;
+				for (k, d) in across.into_iter().zip([du, dv]) {
+					next[k] = (cc[k] as i32 + d).rem_euclid(grid[k] as i32) as usize;
+				}
+				region[(next[0] * grid[1] + next[1]) * grid[2] + next[2]]
+			})
+		}).collect();
+		self.rebake(world, &region, &ring);
+	}
+
+	// Bake the chunks in `region`, which is made of whole columns of chunks along the way up,
+	// taking in the light already in the chunks of `ring` around it.
+	fn rebake(&mut self, world: &World, region: &[bool], ring: &[usize]) {
+		let size = world.size();
+		let [_, ny, nz] = size;
+		let gravity = world.gravity();
+		let up = gravity.axis;
+		let (a, b) = ((up + 1) % 3, (up + 2) % 3);
+		let grid = world.grid();
+		let height = size[up];
+		// sunlight down each column of voxels, and glowing blocks lit, a column of chunks at a time
+		let tops: Vec<usize> = (0..region.len()).filter(|&c| region[c] && coordinates(grid, c)[up] == 0).collect();
+		let seeded: Vec<(usize, Vec<u8>)> = tops.par_iter().flat_map_iter(|&top| {
+			let corner = coordinates(grid, top).map(|n| n * CHUNK);
+			let mut column = vec![vec![0; VOLUME]; grid[up]];
+			for u in corner[a]..(corner[a] + CHUNK).min(size[a]) {
+				for v in corner[b]..(corner[b] + CHUNK).min(size[b]) {
+					let mut sun = MAX;
+					for depth in 0..height {
+						let mut p = [0; 3];
+						(p[a], p[b]) = (u, v);
+						p[up] = if gravity.sign < 0 { height - 1 - depth } else { depth };
+						let i = furl(p[0], p[1], p[2], ny, nz);
+						if !world.transparent(i) {
+							sun = 0;
+						} else if world.liquid(i) {
+							sun = sun.saturating_sub(WATER_DIM);
+						}
+						let blocks = if glows(world, i) { MAX } else { 0 };
+						let (c, k) = world.locate(i);
+						column[coordinates(grid, c)[up]][k] = pack(sun, blocks);
+					}
+				}
+			}
+			column.into_iter().enumerate().map(move |(n, levels)| {
+				let mut cc = coordinates(grid, top);
+				cc[up] = n;
+				((cc[0] * grid[1] + cc[1]) * grid[2] + cc[2], levels)
+			})
+		}).collect();
+		for (c, levels) in seeded {
+			self.chunks[c] = Arc::new(levels);
+		}
+		// then out from every lit voxel, a step a round, until it's as far as it goes
+		// (only the sides of the chunks in the ring can reach into the region)
+		let inside: Vec<usize> = (0..region.len()).filter(|&c| region[c]).collect();
+		let from = inside.par_iter().flat_map_iter(|&c| voxels(world, c))
+			.chain(ring.par_iter().flat_map_iter(|&c| voxels(world, c).filter(|&i| side(world, i))));
+		let mut lit = self.spread(world, region, from);
+		while !lit.is_empty() {
+			lit = self.spread(world, region, lit.into_par_iter());
+		}
+	}
+
+	// Spread the light in the voxels of `from` a step to the open voxels next to them in `region`,
+	// giving the voxels it brightened.
+	fn spread(&mut self, world: &World, region: &[bool], from: impl ParallelIterator<Item = usize>) -> Vec<usize> {
+		let this = &*self;
+		let reached: Vec<(usize, u8, u8)> = from.flat_map_iter(|i| {
+			let (sun, blocks) = this.level(world, i);
+			// too dim to reach any further
+			let spent = sun <= 1 && blocks <= 1;
+			world.neighbors(i).all().into_iter().filter(move |_| !spent).filter_map(move |j| {
+				let j = j as usize;
+				if !region[world.locate(j).0] || !world.transparent(j) {
+					return None;
+				}
+				let cost = if world.liquid(j) { WATER_DIM } else { 1 };
+				let (s, b) = (sun.saturating_sub(cost), blocks.saturating_sub(cost));
+				let (now_s, now_b) = this.level(world, j);
+				(s > now_s || b > now_b).then_some((j, s, b))
+			})
+		}).collect();
+		let mut brightened = vec![];
+		for (j, s, b) in reached {
+			let (now_s, now_b) = self.level(world, j);
+			if s > now_s || b > now_b {
+				let (c, k) = world.locate(j);
+				Arc::make_mut(&mut self.chunks[c])[k] = pack(s.max(now_s), b.max(now_b));
+				brightened.push(j);
+			}
+		}
+		brightened.sort_unstable();
+		brightened.dedup();
+		brightened
+	}
+}
+
+// The light for a world, baked in the background so play never waits on it: the whole world to
+// begin with, and then again around whatever's changed, the last light finished being used in
+// the meantime.
+pub struct Lighting {
+	light: Option<Light>,
+	// the bake under way, and what's changed since it started
+	baking: Option<JoinHandle<Light>>,
+	since: Dirty,
+}
+
+// bake the whole of `world` as it is now, in the background
+fn bake(world: &World) -> JoinHandle<Light> {
+	let world = world.clone();
+	std::thread::spawn(move || {
+		let started = std::time::Instant::now();
+		let light = Light::bake(&world);
+		log::info!("light", "baked in {:.2} s", started.elapsed().as_secs_f64());
+		light
+	})
+}
+
+impl Lighting {
+	// Start baking `world` as it is now, so what's been marked dirty in it so far is taken care
+	// of and only what's marked after needs handing to `update`.
+	pub fn start(world: &World) -> Lighting {
+		Lighting { light: None, baking: Some(bake(world)), since: Dirty::default() }
+	}
+
+	// keep up with the chunks marked in `dirty`
+	pub fn update(&mut self, world: &World, dirty: &Dirty) {
+		self.since.merge(dirty);
+		if let Some(baking) = self.baking.take_if(|b| b.is_finished()) {
+			match baking.join() {
+				Ok(light) => self.light = Some(light),
+				// whatever it was catching up with is lost, so the whole world is baked again
+				Err(_) => {
+					log::warning!("light", "baking the light crashed, so it's started again");
+					self.baking = Some(bake(world));
+					self.since = Dirty::default();
+				},
+			}
+		}
+		if self.baking.is_some() || self.since.is_empty() {
+			return;
+		}
+		if let Some(light) = &self.light {
+			let (mut light, world, since) = (light.clone(), world.clone(), std::mem::take(&mut self.since));
+			self.baking = Some(std::thread::spawn(move || {
+				light.update(&world, &since);
+				light
+			}));
+		}
+	}
+
+	// the light, once it's been baked
+	pub fn light(&self) -> Option<&Light> {
+		self.light.as_ref()
+	}
+}
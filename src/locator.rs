@@ -0,0 +1,40 @@
+// Where the player is: the coordinates of their voxel, which way they face, and how many times
+// they have gone around the world along each axis since the game started, which tells apart
+// places that share coordinates on the torus.
+
+use macroquad::prelude::*;
+
+#[derive(Default)]
+pub struct Locator {
+	// times around the world along each axis, negative going the other way
+	wraps: [i32; 3],
+}
+
+// the axis `look` points along most, with its sign
+pub fn facing(look: Vec3) -> String {
+	let axis = (0..3).max_by(|&a, &b| look[a].abs().total_cmp(&look[b].abs())).unwrap_or(0);
+	format!("{}{}", if look[axis] < 0.0 { '-' } else { '+' }, ['x', 'y', 'z'][axis])
+}
+
+impl Locator {
+	// Count the edges of a world of `size` voxels crossed moving from coordinates `before` to
+	// `after`, taken to be a short move the nearer way around.
+	pub fn track(&mut self, size: [usize; 3], before: [usize; 3], after: [usize; 3]) {
+		for k in 0..3 {
+			let n = size[k] as i64;
+			let d = (after[k] as i64 - before[k] as i64 + n / 2).rem_euclid(n) - n / 2;
+			let reached = before[k] as i64 + d;
+			if reached >= n {
+				self.wraps[k] += 1;
+			} else if reached < 0 {
+				self.wraps[k] -= 1;
+			}
+		}
+	}
+
+	pub fn describe(&self, coordinates: [usize; 3], look: Vec3) -> String {
+		let [x, y, z] = coordinates;
+		let [wx, wy, wz] = self.wraps;
+		format!("{} {} {}  facing {}  around {} {} {}", x, y, z, facing(look), wx, wy, wz)
+	}
+}
@@ -0,0 +1,102 @@
+// Logging: messages from all over the game, shown in the terminal and written to a log file
+// with the time and how important each one is, to attach to bug reports. Each run starts a new
+// file and keeps the ones from the last few runs, and a file that grows too big is moved aside
+// the same way. VOXELTORUS_LOG sets the least important messages logged: error, warn, info
+// (the default) or debug.
+
+use std::fmt::Arguments;
+use std::fs::{self, File};
+use std::io::Write;
+use std::sync::Mutex;
+use std::time::Instant;
+
+pub const FILE: &str = "voxeltorus.log";
+// earlier log files kept, as voxeltorus.log.1 and so on, the most recent first
+const KEPT: usize = 3;
+// bytes written to a log file before it's moved aside for a new one
+const MAX_SIZE: u64 = 4 << 20;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Level {
+	Error,
+	Warn,
+	Info,
+	Debug,
+}
+
+const LEVELS: [(Level, &str); 4] = [(Level::Error, "error"), (Level::Warn, "warn"), (Level::Info, "info"), (Level::Debug, "debug")];
+
+impl Level {
+	fn name(self) -> &'static str {
+		LEVELS.iter().find(|&&(l, _)| l == self).map_or("?", |(_, n)| n)
+	}
+}
+
+struct Log {
+	file: Option<File>,
+	written: u64,
+	level: Level,
+	start: Instant,
+}
+
+static LOG: Mutex<Option<Log>> = Mutex::new(None);
+
+// move the log files along one, dropping the oldest, and start a new one
+fn rotate() -> Option<File> {
+	for k in (1..KEPT).rev() {
+		let _ = fs::rename(format!("{}.{}", FILE, k), format!("{}.{}", FILE, k + 1));
+	}
+	let _ = fs::rename(FILE, format!("{}.1", FILE));
+	File::create(FILE).map_err(|e| eprintln!("log: can't write {}: {}", FILE, e)).ok()
+}
+
+// Start logging to a new file. Messages from before this are only shown in the terminal.
+pub fn init() {
+	let level = match std::env::var("VOXELTORUS_LOG") {
+		Ok(name) => LEVELS.iter().find(|(_, n)| name.eq_ignore_ascii_case(n)).map(|&(l, _)| l).unwrap_or_else(|| {
+			eprintln!("log: unknown level '{}', expected error, warn, info or debug", name);
+			Level::Info
+		}),
+		Err(_) => Level::Info,
+	};
+	*LOG.lock().unwrap() = Some(Log { file: rotate(), written: 0, level, start: Instant::now() });
+}
+
+// Log a message from part of the game called `target`. Use the macros below rather than this.
+pub fn write(level: Level, target: &str, message: Arguments) {
+	let mut log = LOG.lock().unwrap();
+	if log.as_ref().is_some_and(|log| level > log.level) || (log.is_none() && level > Level::Info) {
+		return;
+	}
+	eprintln!("{}: {}", target, message);
+	let Some(log) = log.as_mut() else { return };
+	if log.written > MAX_SIZE {
+		log.file = rotate();
+		log.written = 0;
+	}
+	let line = format!("{:10.3} {:5} {}: {}\n", log.start.elapsed().as_secs_f64(), level.name(), target, message);
+	if let Some(file) = &mut log.file {
+		if file.write_all(line.as_bytes()).is_ok() {
+			log.written += line.len() as u64;
+		}
+	}
+}
+
+macro_rules! error {
+	($target:expr, $($arg:tt)+) => { $crate::log::write($crate::log::Level::Error, $target, format_args!($($arg)+)) };
+}
+
+macro_rules! warning {
+	($target:expr, $($arg:tt)+) => { $crate::log::write($crate::log::Level::Warn, $target, format_args!($($arg)+)) };
+}
+
+macro_rules! info {
+	($target:expr, $($arg:tt)+) => { $crate::log::write($crate::log::Level::Info, $target, format_args!($($arg)+)) };
+}
+
+macro_rules! debug {
+	($target:expr, $($arg:tt)+) => { $crate::log::write($crate::log::Level::Debug, $target, format_args!($($arg)+)) };
+}
+
+pub(crate) use {error, warning, info, debug};
+
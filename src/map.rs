@@ -0,0 +1,131 @@
+// The map: a full-screen view of one flat slice through the world, at right angles to a chosen
+// axis, stepped through layer by layer. The slice is tiled across the screen, so wrapping at
+// the world's edges shows as a seamless repeat.
+
+use macroquad::prelude::*;
+use crate::World;
+
+const MIN_ZOOM: f32 = 1.0;
+const MAX_ZOOM: f32 = 32.0;
+// screen pixels panned per second with the arrow keys
+const PAN_SPEED: f32 = 400.0;
+const EMPTY: Color = Color::new(0.05, 0.05, 0.08, 1.0);
+
+pub struct Map {
+	pub open: bool,
+	// axis the slice is at right angles to, and its coordinate along it
+	axis: usize,
+	layer: usize,
+	// screen pixels per voxel, and how far the view is moved from centered on the player
+	zoom: f32,
+	pan: Vec2,
+	image: Image,
+	texture: Texture2D,
+}
+
+impl Map {
+	pub fn new() -> Map {
+		let image = Image::gen_image_color(1, 1, EMPTY);
+		let texture = Texture2D::from_image(&image);
+		Map { open: false, axis: 1, layer: 0, zoom: 4.0, pan: Vec2::ZERO, image, texture }
+	}
+
+	// the two axes across the slice, drawn along the screen's x and y
+	fn across(&self) -> (usize, usize) {
+		match self.axis {
+			0 => (2, 1),
+			1 => (0, 2),
+			_ => (0, 1),
+		}
+	}
+
+	// open on the layer holding the player, whose voxel is at `coordinates`
+	pub fn show(&mut self, coordinates: [usize; 3]) {
+		self.open = true;
+		self.layer = coordinates[self.axis];
+		self.pan = Vec2::ZERO;
+	}
+
+	// take this frame's keys: X, Y and Z pick the axis, the wheel or page keys step through
+	// layers, `=` and `-` zoom and the arrow keys pan
+	pub fn update(&mut self, size: [usize; 3], coordinates: [usize; 3]) {
+		for (axis, key) in [KeyCode::X, KeyCode::Y, KeyCode::Z].into_iter().enumerate() {
+			if is_key_pressed(key) && axis != self.axis {
+				self.axis = axis;
+				self.layer = coordinates[axis];
+			}
+		}
+		let n = size[self.axis];
+		let (_, wheel) = mouse_wheel();
+		if is_key_pressed(KeyCode::PageUp) || wheel > 0.0 {
+			self.layer = (self.layer + 1) % n;
+		}
+		if is_key_pressed(KeyCode::PageDown) || wheel < 0.0 {
+			self.layer = (self.layer + n - 1) % n;
+		}
+		if is_key_pressed(KeyCode::Equal) {
+			self.zoom = (self.zoom * 2.0).min(MAX_ZOOM);
+		}
+		if is_key_pressed(KeyCode::Minus) {
+			self.zoom = (self.zoom / 2.0).max(MIN_ZOOM);
+		}
+		let step = PAN_SPEED * get_frame_time();
+		for (key, d) in [(KeyCode::Left, vec2(1.0, 0.0)), (KeyCode::Right, vec2(-1.0, 0.0)), (KeyCode::Up, vec2(0.0, 1.0)), (KeyCode::Down, vec2(0.0, -1.0))] {
+			if is_key_down(key) {
+				self.pan += step * d;
+			}
+		}
+	}
+
+	// Draw the slice over the whole screen, centered on the player at `coordinates` and tiled
+	// out to the edges.
+	pub fn draw(&mut self, world: &World, coordinates: [usize; 3]) {
+		let size = world.size();
+		let [_, ny, nz] = size;
+		let (u, v) = self.across();
+		let (w, h) = (size[u], size[v]);
+		if self.image.width() != w || self.image.height() != h {
+			self.image = Image::gen_image_color(w as u16, h as u16, EMPTY);
+			self.texture = Texture2D::from_image(&self.image);
+			self.texture.set_filter(FilterMode::Nearest);
+		}
+		let mut at = [0; 3];
+		at[self.axis] = self.layer;
+		for x in 0..w {
+			for y in 0..h {
+				at[u] = x;
+				// the screen's y runs down, so higher voxels go at the top
+				at[v] = if v == 1 { h - 1 - y } else { y };
+				let i = at[0]*ny*nz + at[1]*nz + at[2];
+				let color = if world.transparent(i) && !world.liquid(i) { EMPTY } else { Color::from_vec(world.voxel(i).color) };
+				self.image.set_pixel(x as u32, y as u32, color);
+			}
+		}
+		self.texture.update(&self.image);
+
+		let (sw, sh) = (screen_width(), screen_height());
+		clear_background(EMPTY);
+		let tile = vec2(w as f32, h as f32) * self.zoom;
+		let player = vec2(coordinates[u] as f32 + 0.5, if v == 1 { (h - coordinates[v]) as f32 - 0.5 } else { coordinates[v] as f32 + 0.5 }) * self.zoom;
+		let center = vec2(sw, sh) / 2.0 + self.pan;
+		let origin = center - player;
+		let start = vec2(origin.x.rem_euclid(tile.x) - tile.x, origin.y.rem_euclid(tile.y) - tile.y);
+		let mut y = start.y;
+		while y < sh {
+			let mut x = start.x;
+			while x < sw {
+				draw_texture_ex(&self.texture, x, y, WHITE, DrawTextureParams { dest_size: Some(tile), ..Default::default() });
+				x += tile.x;
+			}
+			y += tile.y;
+		}
+		// the player, filled in on their own layer
+		if coordinates[self.axis] == self.layer {
+			draw_circle(center.x, center.y, 4.0, YELLOW);
+		} else {
+			draw_circle_lines(center.x, center.y, 4.0, 1.5, YELLOW);
+		}
+		let axis = ['x', 'y', 'z'][self.axis];
+		draw_text(format!("map  {} = {}  (X Y Z axis, wheel or PgUp/PgDn layer, = - zoom, arrows pan, F3 close)", axis, self.layer), 4.0, sh - 8.0, 20.0, WHITE);
+	}
+}
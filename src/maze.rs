@@ -0,0 +1,69 @@
+// Maze generator: a 3D maze on the torus, carved along a random spanning tree of a coarse
+// lattice of rooms. The lattice wraps like the world does, so corridors run across the seams.
+
+use crate::{World, Voxel, Progress, furl, link_world};
+use crate::block::{self, BlockId};
+use crate::rng::Rng;
+
+pub fn build_maze_world(nx: usize, ny: usize, nz: usize, corridor: usize, wall: BlockId, rng: &mut Rng, progress: &Progress) -> World {
+	let mut world = link_world(nx, ny, nz, progress);
+	let size = [nx, ny, nz];
+	// each room is a corridor-wide cube followed by one voxel of wall; rooms are stretched a
+	// little where needed so they tile every axis exactly and the maze joins up across seams
+	let rooms = size.map(|n| (n / (corridor.max(1) + 1)).max(1));
+	let start = |axis: usize, r: usize| r * size[axis] / rooms[axis];
+	let width = |axis: usize, r: usize| (start(axis, r + 1) - start(axis, r)).saturating_sub(1).max(1);
+
+	world.set_voxels(&vec![Voxel::of(wall); nx*ny*nz]);
+
+	// carve a box given its low corner and extent in voxels, wrapping at the world's edges
+	let carve = |world: &mut World, lo: [usize; 3], extent: [usize; 3]| {
+		for dx in 0..extent[0] {
+			for dy in 0..extent[1] {
+				for dz in 0..extent[2] {
+					let n = furl((lo[0] + dx) % nx, (lo[1] + dy) % ny, (lo[2] + dz) % nz, ny, nz);
+					world.set(n, Voxel::of(block::AIR));
+				}
+			}
+		}
+	};
+	let corner = |room: [usize; 3]| [start(0, room[0]), start(1, room[1]), start(2, room[2])];
+	let extent = |room: [usize; 3]| [width(0, room[0]), width(1, room[1]), width(2, room[2])];
+
+	// randomized depth-first search over the rooms
+	let room_index = |r: [usize; 3]| (r[0] * rooms[1] + r[1]) * rooms[2] + r[2];
+	let mut visited = vec![false; rooms[0] * rooms[1] * rooms[2]];
+	let mut stack = vec![[0usize; 3]];
+	visited[0] = true;
+	carve(&mut world, corner([0; 3]), extent([0; 3]));
+	while let Some(&room) = stack.last() {
+		let mut options = vec![];
+		for axis in 0..3 {
+			for sign in [-1i32, 1] {
+				let mut next = room;
+				next[axis] = (room[axis] as i32 + sign).rem_euclid(rooms[axis] as i32) as usize;
+				if !visited[room_index(next)] {
+					options.push((axis, sign, next));
+				}
+			}
+		}
+		if options.is_empty() {
+			stack.pop();
+			continue;
+		}
+		let (axis, sign, next) = options[(rng.next_u64() % options.len() as u64) as usize];
+		visited[room_index(next)] = true;
+		carve(&mut world, corner(next), extent(next));
+
+		// knock out the wall on the far side of whichever room is lower along the axis
+		let from = if sign > 0 { room } else { next };
+		let mut lo = corner(from);
+		lo[axis] += width(axis, from[axis]);
+		let mut slab = extent(from);
+		slab[axis] = 1;
+		carve(&mut world, lo, slab);
+		stack.push(next);
+	}
+
+	world
+}
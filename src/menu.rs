@@ -0,0 +1,108 @@
+// The pause menu, opened with `Escape`: the game stands still while it's open, and it has
+// sliders for the settings worth changing mid-game and switches for gravity and flying.
+// Settings changed here are written back to the settings file when the menu closes.
+
+use macroquad::prelude::*;
+use crate::config::Config;
+
+// size of a slider, and the space each row of the menu takes, in pixels
+const SLIDER: (f32, f32) = (240.0, 16.0);
+const ROW: f32 = 34.0;
+
+pub struct Menu {
+	pub open: bool,
+	// set when quit is clicked
+	pub quit: bool,
+}
+
+// where one line of the menu goes on screen
+struct Row {
+	left: f32,
+	y: f32,
+}
+
+impl Row {
+	fn label(&self, text: &str) {
+		draw_text(text, self.left, self.y + SLIDER.1 - 2.0, 20.0, WHITE);
+	}
+
+	// a slider for a value between `min` and `max`, returning it moved if it was dragged
+	fn slider(&self, text: &str, value: f32, min: f32, max: f32) -> Option<f32> {
+		self.label(text);
+		let x = self.left + 200.0;
+		draw_rectangle(x, self.y, SLIDER.0 * ((value - min) / (max - min)).clamp(0.0, 1.0), SLIDER.1, GRAY);
+		draw_rectangle_lines(x, self.y, SLIDER.0, SLIDER.1, 2.0, WHITE);
+		let (mx, my) = mouse_position();
+		let over = mx >= x - 8.0 && mx < x + SLIDER.0 + 8.0 && my >= self.y && my < self.y + SLIDER.1;
+		(over && is_mouse_button_down(MouseButton::Left)).then(|| min + (max - min) * ((mx - x) / SLIDER.0).clamp(0.0, 1.0))
+	}
+
+	// a button, returning whether it was clicked
+	fn button(&self, text: &str) -> bool {
+		let (mx, my) = mouse_position();
+		let width = measure_text(text, None, 20, 1.0).width + 16.0;
+		let over = mx >= self.left && mx < self.left + width && my >= self.y - 4.0 && my < self.y + SLIDER.1 + 4.0;
+		draw_rectangle_lines(self.left, self.y - 4.0, width, SLIDER.1 + 8.0, 2.0, if over { YELLOW } else { GRAY });
+		draw_text(text, self.left + 8.0, self.y + SLIDER.1 - 2.0, 20.0, WHITE);
+		over && is_mouse_button_pressed(MouseButton::Left)
+	}
+}
+
+impl Menu {
+	pub fn new() -> Menu {
+		Menu { open: false, quit: false }
+	}
+
+	// Draw the menu over the game. Returns the settings changed with it this frame, if any;
+	// the switches change `gravity` and `flying` directly.
+	pub fn draw(&mut self, config: &Config, gravity: &mut bool, flying: &mut bool) -> Option<Config> {
+		let (w, h) = (screen_width(), screen_height());
+		draw_rectangle(0.0, 0.0, w, h, Color::new(0.0, 0.0, 0.0, 0.6));
+		let left = w/2.0 - 220.0;
+		let top = h/2.0 - 5.5 * ROW;
+		draw_text("Paused", left, top - 12.0, 32.0, WHITE);
+		let row = |k: usize| Row { left, y: top + k as f32 * ROW };
+		let mut changed = config.clone();
+
+		let sensitivity = changed.mouse_sensitivity.0;
+		if let Some(s) = row(0).slider(&format!("sensitivity {:.3}", sensitivity), sensitivity, 0.01, 0.5) {
+			let ratio = changed.mouse_sensitivity.1 / changed.mouse_sensitivity.0;
+			changed.mouse_sensitivity = (s, s * ratio);
+		}
+		if let Some(f) = row(1).slider(&format!("field of view {:.0}", changed.fov), changed.fov, 30.0, 150.0) {
+			changed.fov = f.round();
+		}
+		let distance = changed.view_distance as f32;
+		if let Some(d) = row(2).slider(&format!("view distance {}", changed.view_distance), distance, 16.0, 512.0) {
+			changed.view_distance = d as usize;
+		}
+		// rays down follow from rays across and the window's shape now
+		let across = changed.screen.0 as f32;
+		if let Some(a) = row(3).slider(&format!("resolution {}x{}", changed.screen.0, changed.screen.1), across, 40.0, 800.0) {
+			let a = a as usize;
+			changed.screen = (a, ((a as f32 * h / w) as usize).max(1));
+		}
+		if row(4).button(if *gravity { "gravity: on" } else { "gravity: off" }) {
+			*gravity = !*gravity;
+		}
+		if row(5).button(if *flying { "flying: on" } else { "flying: off" }) {
+			*flying = !*flying;
+		}
+		if row(6).button(if changed.invert_y { "invert y: on" } else { "invert y: off" }) {
+			changed.invert_y = !changed.invert_y;
+		}
+		if row(7).button(if changed.camera_effects { "camera effects: on" } else { "camera effects: off" }) {
+			changed.camera_effects = !changed.camera_effects;
+		}
+		if let Some(v) = row(8).slider(&format!("volume {:.0}%", changed.volume * 100.0), changed.volume, 0.0, 1.0) {
+			changed.volume = (v * 20.0).round() / 20.0;
+		}
+		if row(9).button("resume") {
+			self.open = false;
+		}
+		if row(10).button("quit") {
+			self.quit = true;
+		}
+		(changed != *config).then_some(changed)
+	}
+}
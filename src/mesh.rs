@@ -0,0 +1,266 @@
+// Triangle mesh import: load OBJ/STL models and voxelize them into the world
+
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
+use glam::*;
+use crate::{World, Voxel, step};
+use crate::{block, log};
+
+const DEFAULT_COLOR: Vec4 = vec4(0.6, 0.6, 0.6, 1.0);
+
+struct Triangle {
+	v: [Vec3; 3],
+	color: Vec4,
+}
+
+pub struct Mesh {
+	triangles: Vec<Triangle>,
+}
+
+// A voxelized mesh in its own local lattice, indexed [x][y][z] like `furl`
+pub struct VoxelModel {
+	pub size: [usize; 3],
+	voxels: Vec<Option<Vec4>>,
+}
+
+pub fn load_mesh(path: &str) -> Result<Mesh, String> {
+	let err = |e: String| format!("{}: {}", path, e);
+	let bytes = std::fs::read(path).map_err(|e| err(e.to_string()))?;
+	let mesh = match Path::new(path).extension().and_then(|e| e.to_str()).map(|e| e.to_lowercase()).as_deref() {
+		Some("obj") => parse_obj(&String::from_utf8_lossy(&bytes), Path::new(path)),
+		Some("stl") => parse_stl(&bytes),
+		_ => Err("expected an .obj or .stl file".to_string()),
+	}.map_err(err)?;
+	if mesh.triangles.is_empty() {
+		return Err(err("mesh has no triangles".to_string()));
+	}
+	Ok(mesh)
+}
+
+fn parse_floats(words: &[&str]) -> Result<Vec<f32>, String> {
+	words.iter().map(|w| w.parse::<f32>().map_err(|_| format!("bad number '{}'", w))).collect()
+}
+
+// diffuse colors (`Kd`) of every material in an .mtl file
+fn parse_mtl(text: &str) -> HashMap<String, Vec4> {
+	let mut materials = HashMap::new();
+	let mut name = String::new();
+	for line in text.lines() {
+		let words: Vec<&str> = line.split_whitespace().collect();
+		match words.first() {
+			Some(&"newmtl") if words.len() > 1 => name = words[1].to_string(),
+			Some(&"Kd") => {
+				if let Ok(c) = parse_floats(&words[1..]) {
+					if c.len() >= 3 {
+						materials.insert(name.clone(), vec4(c[0], c[1], c[2], 1.0));
+					}
+				}
+			},
+			_ => {},
+		}
+	}
+	materials
+}
+
+// Wavefront OBJ. Faces take the color of their current material (`usemtl`) if there is one,
+// otherwise the average of their vertex colors (`v x y z r g b`) if present.
+fn parse_obj(text: &str, path: &Path) -> Result<Mesh, String> {
+	let mut vertices: Vec<(Vec3, Option<Vec4>)> = vec![];
+	let mut materials = HashMap::new();
+	let mut material: Option<Vec4> = None;
+	let mut triangles = vec![];
+	for (n, line) in text.lines().enumerate() {
+		let words: Vec<&str> = line.split_whitespace().collect();
+		let err = |e: String| format!("line {}: {}", n + 1, e);
+		match words.first() {
+			Some(&"v") => {
+				let c = parse_floats(&words[1..]).map_err(err)?;
+				if c.len() < 3 {
+					return Err(err("vertex needs 3 coordinates".to_string()));
+				}
+				let color = if c.len() >= 6 { Some(vec4(c[3], c[4], c[5], 1.0)) } else { None };
+				vertices.push((vec3(c[0], c[1], c[2]), color));
+			},
+			Some(&"mtllib") if words.len() > 1 => {
+				let mtl = path.with_file_name(words[1]);
+				match std::fs::read_to_string(&mtl) {
+					Ok(text) => materials.extend(parse_mtl(&text)),
+					Err(e) => log::warning!("mesh", "{}: {}", mtl.display(), e),
+				}
+			},
+			Some(&"usemtl") if words.len() > 1 => material = materials.get(words[1]).copied(),
+			Some(&"f") => {
+				// indices are 1-based, negative ones count back from the latest vertex,
+				// and anything after a slash (texture, normal) is ignored
+				let face = words[1..].iter().map(|w| {
+					let k: i64 = w.split('/').next().unwrap_or("").parse().map_err(|_| err(format!("bad index '{}'", w)))?;
+					let k = if k < 0 { vertices.len() as i64 + k } else { k - 1 };
+					vertices.get(k as usize).copied().ok_or_else(|| err(format!("index {} out of range", w)))
+				}).collect::<Result<Vec<_>, _>>()?;
+				// triangulate polygons as a fan
+				for k in 1..face.len().saturating_sub(1) {
+					let corners = [face[0], face[k], face[k + 1]];
+					let color = material.unwrap_or_else(|| match corners.iter().map(|c| c.1).collect::<Option<Vec<_>>>() {
+						Some(colors) => colors.iter().sum::<Vec4>() / 3.0,
+						None => DEFAULT_COLOR,
+					});
+					triangles.push(Triangle { v: corners.map(|c| c.0), color });
+				}
+			},
+			_ => {},
+		}
+	}
+	Ok(Mesh { triangles })
+}
+
+// STL, either ASCII ("solid ...") or binary
+fn parse_stl(bytes: &[u8]) -> Result<Mesh, String> {
+	let is_ascii = bytes.starts_with(b"solid") && String::from_utf8_lossy(bytes).contains("facet");
+	let mut triangles = vec![];
+	if is_ascii {
+		let text = String::from_utf8_lossy(bytes);
+		let mut corners = vec![];
+		for line in text.lines() {
+			let words: Vec<&str> = line.split_whitespace().collect();
+			if words.first() == Some(&"vertex") {
+				let c = parse_floats(&words[1..])?;
+				if c.len() < 3 {
+					return Err("vertex needs 3 coordinates".to_string());
+				}
+				corners.push(vec3(c[0], c[1], c[2]));
+				if corners.len() == 3 {
+					triangles.push(Triangle { v: [corners[0], corners[1], corners[2]], color: DEFAULT_COLOR });
+					corners.clear();
+				}
+			}
+		}
+	} else {
+		// 80 byte header, triangle count, then 50 bytes per triangle
+		if bytes.len() < 84 {
+			return Err("truncated binary STL".to_string());
+		}
+		let count = u32::from_le_bytes([bytes[80], bytes[81], bytes[82], bytes[83]]) as usize;
+		if bytes.len() < 84 + 50*count {
+			return Err("truncated binary STL".to_string());
+		}
+		let float = |at: usize| f32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+		for t in 0..count {
+			// skip the 12 byte normal
+			let at = 84 + 50*t + 12;
+			let corner = |c: usize| vec3(float(at + 12*c), float(at + 12*c + 4), float(at + 12*c + 8));
+			triangles.push(Triangle { v: [corner(0), corner(1), corner(2)], color: DEFAULT_COLOR });
+		}
+	}
+	Ok(Mesh { triangles })
+}
+
+impl VoxelModel {
+	fn index(&self, x: usize, y: usize, z: usize) -> usize {
+		x*self.size[1]*self.size[2] + y*self.size[2] + z
+	}
+}
+
+// Rasterize a mesh so that its longest side spans `size` voxels. The surface is found by
+// sampling each triangle at under half a voxel spacing; if `solid`, every cell that cannot
+// be reached from outside the bounding box without crossing the surface is filled too.
+pub fn voxelize(mesh: &Mesh, size: usize, solid: bool) -> VoxelModel {
+	let corners = mesh.triangles.iter().flat_map(|t| t.v);
+	let lo = corners.clone().fold(Vec3::splat(f32::MAX), Vec3::min);
+	let hi = corners.fold(Vec3::splat(f32::MIN), Vec3::max);
+	let scale = (size.max(1) as f32 - 1.0).max(0.0) / (hi - lo).max_element().max(f32::EPSILON);
+	let extent = ((hi - lo) * scale).floor();
+	let dims = [extent.x as usize + 1, extent.y as usize + 1, extent.z as usize + 1];
+	let mut model = VoxelModel { size: dims, voxels: vec![None; dims[0]*dims[1]*dims[2]] };
+
+	for t in &mesh.triangles {
+		let [a, b, c] = t.v.map(|v| (v - lo) * scale);
+		let longest = (b - a).length().max((c - a).length()).max((c - b).length());
+		let n = (2.0 * longest).ceil().max(1.0) as usize;
+		for i in 0..=n {
+			for j in 0..=(n - i) {
+				let p = a + (b - a) * (i as f32 / n as f32) + (c - a) * (j as f32 / n as f32);
+				let p = p.round().clamp(Vec3::ZERO, extent);
+				let k = model.index(p.x as usize, p.y as usize, p.z as usize);
+				model.voxels[k] = Some(t.color);
+			}
+		}
+	}
+
+	if solid {
+		// flood the outside from every empty cell on the bounding box
+		let [nx, ny, nz] = dims;
+		let mut outside = vec![false; model.voxels.len()];
+		let mut queue = VecDeque::new();
+		for x in 0..nx {
+			for y in 0..ny {
+				for z in 0..nz {
+					let border = x == 0 || y == 0 || z == 0 || x == nx - 1 || y == ny - 1 || z == nz - 1;
+					let k = model.index(x, y, z);
+					if border && model.voxels[k].is_none() {
+						outside[k] = true;
+						queue.push_back((x, y, z));
+					}
+				}
+			}
+		}
+		while let Some((x, y, z)) = queue.pop_front() {
+			let candidates = [
+				(x.wrapping_sub(1), y, z), (x + 1, y, z),
+				(x, y.wrapping_sub(1), z), (x, y + 1, z),
+				(x, y, z.wrapping_sub(1)), (x, y, z + 1),
+			];
+			for (x, y, z) in candidates {
+				if x < nx && y < ny && z < nz {
+					let k = model.index(x, y, z);
+					if !outside[k] && model.voxels[k].is_none() {
+						outside[k] = true;
+						queue.push_back((x, y, z));
+					}
+				}
+			}
+		}
+		// interior cells take the color of the last surface voxel before them along z
+		for x in 0..nx {
+			for y in 0..ny {
+				let mut color = DEFAULT_COLOR;
+				for z in 0..nz {
+					let k = model.index(x, y, z);
+					match model.voxels[k] {
+						Some(c) => color = c,
+						None if !outside[k] => model.voxels[k] = Some(color),
+						None => {},
+					}
+				}
+			}
+		}
+	}
+	model
+}
+
+// Place a model with the bottom of its bounding box centered on voxel `origin`. Placement
+// walks the neighbor graph, so models wrap around the world like everything else.
+pub fn place_model(world: &mut World, origin: usize, model: &VoxelModel) {
+	let [nx, ny, nz] = model.size;
+	let mut corner = origin;
+	for _ in 0..nx/2 {
+		corner = step(world, corner, 0, -1);
+	}
+	for _ in 0..nz/2 {
+		corner = step(world, corner, 2, -1);
+	}
+	let mut i_x = corner;
+	for x in 0..nx {
+		let mut i_y = i_x;
+		for y in 0..ny {
+			let mut i = i_y;
+			for z in 0..nz {
+				if let Some(color) = model.voxels[model.index(x, y, z)] {
+					world.set(i, Voxel::new(block::CUSTOM, color));
+				}
+				i = step(world, i, 2, 1);
+			}
+			i_y = step(world, i_y, 1, 1);
+		}
+		i_x = step(world, i_x, 0, 1);
+	}
+}
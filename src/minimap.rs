@@ -0,0 +1,87 @@
+// The minimap in the corner of the screen: the world seen from above around the player, looking
+// down from their height, so caves and rooms show up while inside them. Each column is read
+// straight off the voxel ids, wrapping at the world's edges the way the torus does. It's only
+// mapped again when the player moves to another voxel or something changes within its reach.
+
+use macroquad::prelude::*;
+use crate::World;
+use crate::dirty::Dirty;
+
+// columns shown to each side of the player
+const RADIUS: usize = 32;
+// voxels looked down through before a column shows as empty
+const DEPTH: usize = 24;
+// pixels per column on screen
+const SCALE: f32 = 2.0;
+const EMPTY: Color = Color::new(0.05, 0.05, 0.08, 1.0);
+
+pub struct Minimap {
+	image: Image,
+	texture: Texture2D,
+	// the voxel it was last mapped around, and whether anything around it has changed since
+	around: Option<usize>,
+	stale: bool,
+}
+
+impl Minimap {
+	pub fn new() -> Minimap {
+		let side = (2*RADIUS + 1) as u16;
+		let image = Image::gen_image_color(side, side, EMPTY);
+		let texture = Texture2D::from_image(&image);
+		texture.set_filter(FilterMode::Nearest);
+		Minimap { image, texture, around: None, stale: true }
+	}
+
+	// side of the map on screen, in pixels
+	pub fn size(&self) -> f32 {
+		(2*RADIUS + 1) as f32 * SCALE
+	}
+
+	// take note of voxels that changed, whether it's shown or not
+	pub fn notice(&mut self, world: &World, dirty: &Dirty) {
+		let Some(i) = self.around else { return };
+		let reach = [RADIUS, DEPTH, RADIUS];
+		self.stale |= dirty.everything() || dirty.chunks().any(|c| world.chunk_near(c, i, reach));
+	}
+
+	// Map the columns around voxel i, looking down from it, if they might look different from
+	// last time. The first solid voxel in each column is drawn in its color, darker the further
+	// down it is.
+	pub fn update(&mut self, world: &World, i: usize) {
+		if !self.stale && self.around == Some(i) {
+			return;
+		}
+		(self.around, self.stale) = (Some(i), false);
+		let [nx, ny, nz] = world.size();
+		let (x, y, z) = (i / (ny*nz), i / nz % ny, i % nz);
+		for u in 0..2*RADIUS + 1 {
+			for v in 0..2*RADIUS + 1 {
+				let cx = (x + nx + u - RADIUS % nx) % nx;
+				let cz = (z + nz + v - RADIUS % nz) % nz;
+				let column = cx*ny*nz + cz;
+				let found = (0..DEPTH.min(ny)).find_map(|k| {
+					let j = column + (y + ny - k) % ny * nz;
+					(!world.transparent(j)).then(|| (k, world.voxel(j).color))
+				});
+				let color = match found {
+					Some((k, color)) => Color::from_vec(color * (1.0 - 0.7 * k as f32 / DEPTH as f32)).with_alpha(1.0),
+					None => EMPTY,
+				};
+				self.image.set_pixel(u as u32, v as u32, color);
+			}
+		}
+		self.texture.update(&self.image);
+	}
+
+	// draw with the top left corner at (left, top), with the player in the middle facing along
+	// the flat part of `look`
+	pub fn draw(&self, left: f32, top: f32, look: Vec3) {
+		let size = self.size();
+		draw_texture_ex(&self.texture, left, top, WHITE, DrawTextureParams { dest_size: Some(vec2(size, size)), ..Default::default() });
+		draw_rectangle_lines(left, top, size, size, 2.0, GRAY);
+		let center = vec2(left + size / 2.0, top + size / 2.0);
+		let ahead = vec2(look.x, look.z).normalize_or_zero() * 10.0;
+		draw_line(center.x, center.y, center.x + ahead.x, center.y + ahead.y, 2.0, YELLOW);
+		draw_circle(center.x, center.y, 3.0, YELLOW);
+	}
+}
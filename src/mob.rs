@@ -0,0 +1,125 @@
+// Mobs: passive creatures that wander about. Where to go is worked out on the neighbor graph
+// itself with the path module, walking from voxel to voxel the way a mob could, so they find
+// their way around whatever shape the world is linked into.
+
+use std::collections::{HashMap, VecDeque};
+use std::collections::hash_map::Entry;
+use glam::*;
+use crate::{World, offset};
+use crate::entity::{Entity, Kind};
+use crate::path::{self, PathOptions, down};
+use crate::rng::Rng;
+
+// most mobs around the player at once
+const CAP: usize = 8;
+// chance per tick of trying to spawn a mob while under the cap
+const SPAWN_CHANCE: f32 = 0.01;
+// mobs spawn between these distances from the player, in voxels
+const SPAWN_NEAR: f32 = 8.0;
+const SPAWN_FAR: i32 = 24;
+// and disappear beyond this one
+const DESPAWN_DISTANCE: f32 = 48.0;
+// chance per tick an idle mob sets off somewhere
+const WANDER_CHANCE: f32 = 0.01;
+// furthest a mob plans to walk, in steps
+const WANDER_RANGE: usize = 10;
+const WALK_SPEED: f32 = 0.04;
+const JUMP_SPEED: f32 = 0.2;
+
+// a path from `start` to a random voxel up to WANDER_RANGE steps away, found breadth first
+fn plan(world: &World, start: usize, rng: &mut Rng) -> Vec<usize> {
+	let mut came_from = HashMap::from([(start, start)]);
+	let mut queue = VecDeque::from([(start, 0)]);
+	let mut reached = vec![];
+	while let Some((i, d)) = queue.pop_front() {
+		reached.push(i);
+		if d == WANDER_RANGE {
+			continue;
+		}
+		for j in path::moves(world, i, &PathOptions::MOB) {
+			if let Entry::Vacant(e) = came_from.entry(j) {
+				e.insert(i);
+				queue.push_back((j, d + 1));
+			}
+		}
+	}
+	let mut i = reached[(rng.next_u64() % reached.len() as u64) as usize];
+	let mut path = vec![];
+	while i != start {
+		path.push(i);
+		i = came_from[&i];
+	}
+	path.reverse();
+	path
+}
+
+// offset of voxel `to` from voxel `from` if it's one of the 26 around it
+fn nearby(world: &World, from: usize, to: usize) -> Option<Vec3> {
+	for dx in -1..=1 {
+		for dy in -1..=1 {
+			for dz in -1..=1 {
+				if offset(world, from, [dx, dy, dz]) == to {
+					return Some(vec3(dx as f32, dy as f32, dz as f32));
+				}
+			}
+		}
+	}
+	None
+}
+
+// Steer a mob for one tick: follow its path, or now and then pick a new one.
+pub fn think(mob: &mut Entity, world: &World, rng: &mut Rng) {
+	if mob.path.first() == Some(&mob.i) {
+		mob.path.remove(0);
+	}
+	let Some(&next) = mob.path.first() else {
+		if rng.f32() < WANDER_CHANCE && path::standable(world, mob.i, &PathOptions::MOB) {
+			mob.path = plan(world, mob.i, rng);
+		}
+		return;
+	};
+	// pushed off the path somehow: give up on it
+	let Some(toward) = nearby(world, mob.i, next) else {
+		mob.path.clear();
+		return;
+	};
+	let up = world.gravity_at(mob.i).up();
+	let heading = toward + Vec3::splat(0.5) - mob.position;
+	let level = heading - heading.dot(up) * up;
+	let rise = mob.velocity.dot(up);
+	mob.velocity = level.normalize_or_zero() * WALK_SPEED + rise * up;
+	if toward.dot(up) > 0.0 && mob.grounded {
+		mob.velocity += JUMP_SPEED * up;
+	}
+}
+
+// Keep the number of mobs around the player at or below the cap: drop those that have wandered
+// far away, and now and then give a new one to spawn a little way off.
+pub fn populate(entities: &mut Vec<Entity>, world: &World, player: usize, rng: &mut Rng) -> Option<Entity> {
+	entities.retain(|e| {
+		let d = world.displacement(player, e.i);
+		e.kind != Kind::Mob || vec3(d[0] as f32, d[1] as f32, d[2] as f32).length() < DESPAWN_DISTANCE
+	});
+	let mobs = entities.iter().filter(|e| e.kind == Kind::Mob).count();
+	if mobs >= CAP || rng.f32() >= SPAWN_CHANCE {
+		return None;
+	}
+	let mut d = [0; 3].map(|_| (rng.next_u64() % (2 * SPAWN_FAR as u64 + 1)) as i32 - SPAWN_FAR);
+	if vec3(d[0] as f32, d[1] as f32, d[2] as f32).length() < SPAWN_NEAR {
+		return None;
+	}
+	// start a little above the player's level and drop from there onto the ground below
+	let g = world.gravity_at(player);
+	d[g.axis] = -g.sign * d[g.axis].abs() / 4;
+	let mut i = offset(world, player, d);
+	for _ in 0..2 * SPAWN_FAR {
+		if !world.transparent(i) {
+			return None;
+		}
+		if path::standable(world, i, &PathOptions::MOB) {
+			return Some(Entity::new(Kind::Mob, i, Vec3::splat(0.5)));
+		}
+		i = down(world, i);
+	}
+	None
+}
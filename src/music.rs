@@ -0,0 +1,194 @@
+// Ambient sound and music looping under the game: a track to suit where the player is, on the
+// surface, underground or underwater, fading into another when that changes. Tracks are loaded
+// from assets/music, .wav or .ogg files named after the place they're for, like `surface.ogg`,
+// with more for the same place named like `surface_2.ogg`. Skipping moves on to the next track
+// for the place. A place without any gets a made-up one: wind on the surface, a low hum with
+// drips underground and a muffled rumble underwater. Like the sound effects, this is only built
+// with the `audio` feature.
+
+use std::collections::HashMap;
+use macroquad::audio::{self, PlaySoundParams, Sound};
+use macroquad::prelude::*;
+use crate::{World, Rng, raycast};
+use crate::config::Config;
+use crate::sound::{wav, RATE};
+
+pub const FOLDER: &str = "assets/music";
+// seconds a track takes to fade in or out
+const FADE: f32 = 3.0;
+// seconds between looks at where the player is
+const CHECK: f64 = 1.0;
+// voxels looked up for a roof, and how many of the five rays up must find one to be underground
+const ROOF: usize = 24;
+const COVERED: usize = 4;
+// seconds long the made-up tracks are, and how much of the end is faded into the start, so
+// they loop without a click
+const LENGTH: f32 = 12.0;
+const SEAM: f32 = 1.0;
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Place {
+	Surface,
+	Underground,
+	Underwater,
+}
+
+const PLACES: [(Place, &str); 3] = [(Place::Surface, "surface"), (Place::Underground, "underground"), (Place::Underwater, "underwater")];
+
+// where the camera in voxel i at `position` is, for the music
+pub fn place(world: &World, i: usize, position: Vec3) -> Place {
+	if world.liquid(i) {
+		return Place::Underwater;
+	}
+	let up = world.gravity_at(i).up();
+	let (a, b) = up.any_orthonormal_pair();
+	let covered = [up, up + a, up - a, up + b, up - b].iter()
+		.filter(|&&ray| !world.transparent(raycast(world, i, position, ray.normalize(), ROOF).0))
+		.count();
+	if covered >= COVERED { Place::Underground } else { Place::Surface }
+}
+
+// a track playing, fading toward being heard or not
+struct Playing {
+	place: Place,
+	track: usize,
+	volume: f32,
+	heard: bool,
+}
+
+pub struct Music {
+	tracks: HashMap<Place, Vec<(String, Sound)>>,
+	place: Option<Place>,
+	// the track for each place that's next to play there
+	next: HashMap<Place, usize>,
+	playing: Vec<Playing>,
+	checked: f64,
+}
+
+impl Music {
+	// load the tracks in `folder`, making up the rest, telling `warn` about files that can't be read
+	pub async fn load(folder: &str, mut warn: impl FnMut(String)) -> Music {
+		let mut tracks: HashMap<Place, Vec<(String, Sound)>> = HashMap::new();
+		let mut paths: Vec<_> = std::fs::read_dir(folder).into_iter().flatten().flatten().map(|e| e.path()).collect();
+		paths.sort();
+		for path in paths {
+			let (Some(stem), Some("wav" | "ogg")) = (path.file_stem().and_then(|s| s.to_str()), path.extension().and_then(|e| e.to_str())) else {
+				continue;
+			};
+			let Some(&(place, _)) = PLACES.iter().find(|(_, name)| stem.split('_').next() == Some(name)) else {
+				warn(format!("{}: not named after a place: surface, underground or underwater", path.display()));
+				continue;
+			};
+			match audio::load_sound(&path.to_string_lossy()).await {
+				Ok(sound) => tracks.entry(place).or_default().push((stem.to_string(), sound)),
+				Err(e) => warn(format!("{}: {}", path.display(), e)),
+			}
+		}
+		for (place, name) in PLACES {
+			if tracks.contains_key(&place) {
+				continue;
+			}
+			let (left, right) = make_up(place);
+			match audio::load_sound_from_bytes(&wav(&left, &right, RATE)).await {
+				Ok(sound) => { tracks.insert(place, vec![(format!("{} (made up)", name), sound)]); },
+				Err(e) => warn(format!("can't make up a track for {}: {}", name, e)),
+			}
+		}
+		Music { tracks, place: None, next: HashMap::new(), playing: vec![], checked: f64::MIN }
+	}
+
+	// A frame of music for the camera in voxel i at `position`: change track if the place has
+	// changed, and carry on fading.
+	pub fn update(&mut self, world: &World, i: usize, position: Vec3, config: &Config) {
+		if get_time() - self.checked >= CHECK {
+			self.checked = get_time();
+			let place = place(world, i, position);
+			if self.place != Some(place) {
+				self.place = Some(place);
+				self.start(place);
+			}
+		}
+		let step = get_frame_time() / FADE;
+		for playing in &mut self.playing {
+			playing.volume = if playing.heard { (playing.volume + step).min(1.0) } else { (playing.volume - step).max(0.0) };
+			if let Some((_, sound)) = self.tracks.get(&playing.place).and_then(|t| t.get(playing.track)) {
+				audio::set_sound_volume(sound, playing.volume * config.music_volume * config.volume);
+				if playing.volume == 0.0 {
+					audio::stop_sound(sound);
+				}
+			}
+		}
+		self.playing.retain(|p| p.heard || p.volume > 0.0);
+	}
+
+	// move on to the next track for where the player is, returning its name
+	pub fn skip(&mut self) -> Option<&str> {
+		let place = self.place?;
+		*self.next.entry(place).or_default() += 1;
+		self.start(place)
+	}
+
+	// fade into the next track for `place`, returning its name
+	fn start(&mut self, place: Place) -> Option<&str> {
+		let tracks = self.tracks.get(&place)?;
+		let track = self.next.get(&place).copied().unwrap_or(0) % tracks.len();
+		for playing in &mut self.playing {
+			playing.heard = false;
+		}
+		match self.playing.iter_mut().find(|p| p.place == place && p.track == track) {
+			Some(playing) => playing.heard = true,
+			None => {
+				audio::play_sound(&tracks[track].1, PlaySoundParams { looped: true, volume: 0.0 });
+				self.playing.push(Playing { place, track, volume: 0.0, heard: true });
+			},
+		}
+		Some(&tracks[track].0)
+	}
+}
+
+// Samples, from -1 to 1, of the left and right of a track LENGTH long made up for `place`.
+fn make_up(place: Place) -> (Vec<f32>, Vec<f32>) {
+	let n = (LENGTH * RATE as f32) as usize;
+	let seam = (SEAM * RATE as f32) as usize;
+	let mut rng = Rng::new(place as u64);
+	// noise smoothed more the smaller `bright` is, one for each side
+	let mut smooth = [0.0; 2];
+	let mut noise = |rng: &mut Rng, side: usize, bright: f32| {
+		smooth[side] += (rng.range(-1.0, 1.0) - smooth[side]) * bright;
+		smooth[side]
+	};
+	// when drips fall underground
+	let drips: Vec<usize> = (0..6).map(|_| (rng.f32() * n as f32) as usize).collect();
+	let tau = std::f32::consts::TAU;
+	let mut sides = [vec![], vec![]];
+	for k in 0..n + seam {
+		let t = k as f32 / RATE as f32;
+		for (side, samples) in sides.iter_mut().enumerate() {
+			let s = match place {
+				// gusts rising and falling a few times a loop
+				Place::Surface => noise(&mut rng, side, 0.02) * 4.0 * (0.5 + 0.3 * (tau * t * 3.0 / LENGTH + side as f32).sin()),
+				Place::Underground => {
+					let hum = 0.15 * (tau * 55.0 * t).sin() + 0.08 * (tau * 82.5 * t).sin();
+					// half of them on each side, each a blip sliding up and dying away
+					let drip: f32 = drips.iter().skip(side).step_by(2).filter_map(|&at| (k % n).checked_sub(at)).map(|since| {
+						let since = since as f32 / RATE as f32;
+						0.3 * (tau * (1200.0 + 2000.0 * since) * since).sin() * (-since * 20.0).exp()
+					}).sum();
+					hum + drip
+				},
+				Place::Underwater => noise(&mut rng, side, 0.004) * 6.0 + 0.1 * (tau * 40.0 * t).sin(),
+			};
+			samples.push(s.clamp(-1.0, 1.0) * 0.6);
+		}
+	}
+	// fade what runs past the end into the start
+	for samples in &mut sides {
+		for k in 0..seam {
+			let f = k as f32 / seam as f32;
+			samples[k] = samples[k] * f + samples[n + k] * (1.0 - f);
+		}
+		samples.truncate(n);
+	}
+	let [left, right] = sides;
+	(left, right)
+}
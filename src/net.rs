@@ -0,0 +1,1065 @@
+// Multiplayer over TCP, for a few players on a local network. One game hosts with `--host` and
+// the others join it with `--join`. A joining game makes the same world from the host's seed, and
+// is sent the cubes of it edited since, those nearest its player first. A streamed world (see the
+// streaming module) is too big for that, so a game joining one asks for each cube as it loads it,
+// and the host sends it how the cube is now, again those nearest its player first.
+// It only joins if it would make the world from the same recipe as the host, the options and
+// files shaping it besides the seed (see `recipe` in main), refusing to with a different one.
+// From then on each game sends the voxels it changes and where its player is, and the host passes
+// on what each game sends to the others. Messages are lines of text:
+//
+//     hello <version>                            the first thing a joining game sends
+//     world <version> <seed> <x> <y> <z> <recipe>   the first thing the host sends
+//     set <voxel> <count> <block> <state> <r> <g> <b> <a>
+//     chunk <x> <y> <z> [<voxels>]               a cube of the world as it is now
+//     want <x> <y> <z>                           a joining game loaded this cube
+//     at <player> <voxel> <x> <y> <z>            where a player is
+//     ack <move>                                 the host took the moves up to this one
+//     back <move> <voxel> <x> <y> <z>            the host didn't take the move, and has the
+//                                                player here instead
+//     left <player>                              a player left
+//     chat <player> <text>                       a line of chat
+//     notice <text>                              news from the host, like who joined
+//
+// `set` changes a run of voxels numbered on from the first, all to the same, so filling a box
+// takes a line per row rather than one per voxel. A run is at most MAX_RUN voxels long. `chunk`
+// has every voxel of the cube CHUNK voxels on a side at x, y, z, as far as the world goes, as
+// VOXEL_BYTES bytes each, deflated and then written in base64, so a cube of much the same voxels
+// takes a short line. A cube of a streamed world that's still as it was made comes without them.
+// Voxels are numbered in the whole world, like `furl`, even in a streamed world that only has
+// part of it loaded. Games whose versions of the messages differ can't play together, and say so
+// rather than misunderstanding each other. A joining game leaves its own number out of `chat` as
+// well.
+//
+// Each game moves its own player and changes its own world straight away, without waiting to
+// hear back. The host only takes the changes a player's role allows (see the permission module),
+// and those it doesn't are set back in the player's game by sending it the voxels as they are.
+// What a player can reach is judged from where the host lets them be, which doesn't go further
+// from where they were than TOP_SPEED would take them, whatever their game says, nor into a
+// solid voxel. A joining game numbers the moves it sends and keeps those the host hasn't taken
+// yet. When the host sends one back, the game puts its player where the host has them and makes
+// the moves since again from there, so the player ends up where the host will agree they are
+// without the moves made in the meantime being lost.
+// Other players are drawn a little behind where they were last heard of, moving smoothly between
+// the places heard of, so they don't stutter when messages arrive unevenly.
+//
+// The host's player, unless it's a dedicated server, is 0 and the others are numbered as they
+// join. A joining game doesn't know its own number, so it leaves it out when it says where it is,
+// and gives the number of the move instead.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque};
+use std::io::{self, Read, Write};
+use std::net::{TcpListener, TcpStream};
+#[cfg(any(feature = "graphics", test))]
+use std::net::ToSocketAddrs;
+use std::time::{Duration, Instant};
+use glam::*;
+use crate::{World, Voxel, furl, interact, log, player, settle, TERMINAL_VELOCITY, TICK};
+use crate::block::{self, BlockId};
+use crate::console::Command;
+use crate::entity::{Entity, Kind};
+use crate::permission::{Permissions, Role};
+use crate::selection::Region;
+use crate::streaming::{Streamer, CHUNK};
+use crate::world::displacement;
+
+pub const PORT: u16 = 7878;
+// the version of the messages, changed whenever they are
+const VERSION: u32 = 7;
+// how far behind the latest news of them other players are drawn, and how long that's kept
+const DELAY: Duration = Duration::from_millis(100);
+const KEPT: Duration = Duration::from_secs(1);
+// voxels a player can move between two messages without being counted as teleporting
+const LEAP: f32 = 4.0;
+// fastest the host believes a player can move, in voxels a second: falling flat out, with as
+// much again for walking and being knocked about
+const TOP_SPEED: f32 = 2.0 * TERMINAL_VELOCITY / TICK;
+// moves a joining game keeps for the host to take, about as many as a second makes
+const UNACKED: usize = 60;
+// longest line of chat passed on, in characters
+const MAX_CHAT: usize = 200;
+// bytes a voxel takes in a `chunk` line before it's deflated: its block, state and color
+const VOXEL_BYTES: usize = 19;
+// longest line taken from another game, in bytes, room for a cube of voxels that don't deflate
+// at all; one going on longer drops the connection
+const MAX_LINE: usize = 2 * CHUNK*CHUNK*CHUNK * VOXEL_BYTES;
+// most voxels a `set` line changes, so a line can't have the host look at the whole world
+const MAX_RUN: usize = 1024;
+// cubes sent to each joining game a tick
+const CHUNKS_PER_TICK: usize = 4;
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+// how long a joining game waits to connect, and then to hear which world to make
+#[cfg(any(feature = "graphics", test))]
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+// a game at the other end of a connection
+pub struct Peer {
+	// the player it plays, for the host; the host itself is 0
+	id: u32,
+	stream: TcpStream,
+	// bytes received that don't make a whole line yet
+	incoming: Vec<u8>,
+	// bytes waiting for the connection to take them
+	outgoing: Vec<u8>,
+	// whether it's said hello with the same version, for the host
+	greeted: bool,
+	// cubes still to send it, for the host
+	pending: Vec<[usize; 3]>,
+	// why the last change it sent was refused, once it's been told
+	told: Option<&'static str>,
+	// the latest move it sent that's been taken, for the host to say so
+	moved: Option<u32>,
+	gone: bool,
+}
+
+impl Peer {
+	pub fn new(id: u32, stream: TcpStream) -> io::Result<Peer> {
+		stream.set_nonblocking(true)?;
+		stream.set_nodelay(true)?;
+		Ok(Peer { id, stream, incoming: vec![], outgoing: vec![], greeted: false, pending: vec![], told: None, moved: None, gone: false })
+	}
+
+	pub fn send(&mut self, line: &str) {
+		self.outgoing.extend_from_slice(line.as_bytes());
+		self.outgoing.push(b'\n');
+	}
+
+	// write as much of what's waiting as the connection takes without blocking
+	pub fn flush(&mut self) {
+		while !self.outgoing.is_empty() && !self.gone {
+			match self.stream.write(&self.outgoing) {
+				Ok(0) => self.gone = true,
+				Ok(n) => { self.outgoing.drain(..n); },
+				Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+				Err(_) => self.gone = true,
+			}
+		}
+	}
+
+	// the whole lines received since the last call
+	pub fn receive(&mut self) -> Vec<String> {
+		let mut buffer = [0; 4096];
+		while !self.gone {
+			match self.stream.read(&mut buffer) {
+				Ok(0) => self.gone = true,
+				Ok(n) => {
+					self.incoming.extend_from_slice(&buffer[..n]);
+					// the line still coming, so one without an end doesn't pile up
+					let partial = self.incoming.iter().rev().position(|&b| b == b'\n').unwrap_or(self.incoming.len());
+					if partial > MAX_LINE {
+						self.too_long();
+					}
+				},
+				Err(e) if e.kind() == io::ErrorKind::WouldBlock => break,
+				Err(_) => self.gone = true,
+			}
+		}
+		let mut lines = vec![];
+		while let Some(k) = self.incoming.iter().position(|&b| b == b'\n') {
+			if k > MAX_LINE {
+				self.too_long();
+				break;
+			}
+			let line: Vec<u8> = self.incoming.drain(..=k).collect();
+			lines.push(String::from_utf8_lossy(&line).trim().to_string());
+		}
+		lines
+	}
+
+	// drop the connection over a line longer than MAX_LINE, whole or not
+	fn too_long(&mut self) {
+		log::warning!("net", "player {} sent a line longer than {} bytes", self.id, MAX_LINE);
+		self.incoming.clear();
+		self.gone = true;
+	}
+
+	// whether the connection's been closed
+	#[cfg(any(feature = "graphics", test))]
+	pub fn gone(&self) -> bool {
+		self.gone
+	}
+}
+
+pub struct Session {
+	// taking new connections, for the host
+	listener: Option<TcpListener>,
+	peers: Vec<Peer>,
+	seed: u64,
+	// what the world is made from besides the seed, for the host
+	recipe: String,
+	next_id: u32,
+	// every voxel changed since the world was made, by the cube it's in, to send games that join;
+	// a streamed world's are kept by its streamer
+	edited: BTreeMap<[usize; 3], BTreeSet<usize>>,
+	// voxels set last tick from what a peer sent, with who sent them, so they aren't sent back
+	echoes: HashMap<usize, (u32, Voxel)>,
+	// the cubes of a streamed world asked for while they've been loaded, for a joining game
+	asked: HashSet<[usize; 3]>,
+	// where the other players are, by voxel number
+	players: HashMap<u32, (usize, Vec3)>,
+	// where each has been lately and when, the latest last, to draw them moving smoothly
+	tracks: HashMap<u32, VecDeque<(Instant, usize, Vec3)>>,
+	// what the players joining may do, for the host
+	permissions: Permissions,
+	// the number of the last move sent, and those the host hasn't taken yet with where they put
+	// the player, for a joining game
+	sent: u32,
+	unacked: VecDeque<(u32, usize, Vec3)>,
+	// where the player's been put after the host sent a move back, until the game takes it up
+	corrected: Option<(usize, Vec3)>,
+}
+
+impl Session {
+	// host the world made from `seed` on `port`, for other games to join, whose players start
+	// out in `role`
+	pub fn host(port: u16, seed: u64, recipe: &str, role: Role) -> io::Result<Session> {
+		let listener = TcpListener::bind(("0.0.0.0", port))?;
+		listener.set_nonblocking(true)?;
+		Ok(Session { listener: Some(listener), peers: vec![], seed, recipe: recipe.to_string(), next_id: 1, edited: BTreeMap::new(), echoes: HashMap::new(), asked: HashSet::new(), players: HashMap::new(), tracks: HashMap::new(), permissions: Permissions::new(role), sent: 0, unacked: VecDeque::new(), corrected: None })
+	}
+
+	// Join the game hosted at `address`, with or without a port, returning the seed and size of
+	// the world to make to play in it, which this game would make from `recipe`.
+	#[cfg(any(feature = "graphics", test))]
+	pub fn join(address: &str, recipe: &str) -> Result<(Session, u64, [usize; 3]), String> {
+		let address = if address.contains(':') { address.to_string() } else { format!("{}:{}", address, PORT) };
+		let err = |e: io::Error| format!("can't join {}: {}", address, e);
+		let socket = address.to_socket_addrs().map_err(err)?.next().ok_or(format!("can't find {}", address))?;
+		let mut stream = TcpStream::connect_timeout(&socket, TIMEOUT).map_err(err)?;
+		stream.set_read_timeout(Some(TIMEOUT)).map_err(err)?;
+		stream.write_all(format!("hello {}\n", VERSION).as_bytes()).map_err(err)?;
+		// a byte at a time, to leave what comes after the first line for the session
+		let mut line = vec![];
+		let mut byte = [0];
+		while byte[0] != b'\n' {
+			if line.len() > MAX_LINE {
+				return Err(format!("{} isn't hosting a world this game can join", address));
+			}
+			stream.read_exact(&mut byte).map_err(err)?;
+			line.push(byte[0]);
+		}
+		let line = String::from_utf8_lossy(&line);
+		let world = match line.split_whitespace().collect::<Vec<_>>().as_slice() {
+			["world", version, ..] if version.parse() != Ok(VERSION) => {
+				return Err(format!("{} runs another version of the game ({} rather than {}); both need the same one", address, version, VERSION));
+			},
+			["world", _, _, _, _, _, theirs @ ..] if theirs.join(" ") != recipe => {
+				return Err(format!("{} makes its world from '{}', but this game would make it from '{}'; start both with the same options and files", address, theirs.join(" "), recipe));
+			},
+			["world", _, seed, x, y, z, ..] => seed.parse().ok().zip([x, y, z].iter().map(|n| n.parse().ok()).collect::<Option<Vec<usize>>>()),
+			_ => None,
+		};
+		let Some((seed, size)) = world else {
+			return Err(format!("{} isn't hosting a world this game can join", address));
+		};
+		let mut peer = Peer::new(0, stream).map_err(err)?;
+		peer.greeted = true;
+		let session = Session { listener: None, peers: vec![peer], seed, recipe: recipe.to_string(), next_id: 1, edited: BTreeMap::new(), echoes: HashMap::new(), asked: HashSet::new(), players: HashMap::new(), tracks: HashMap::new(), permissions: Permissions::new(Role::Admin), sent: 0, unacked: VecDeque::new(), corrected: None };
+		Ok((session, seed, [size[0], size[1], size[2]]))
+	}
+
+	// every voxel changed since the world was made, in order
+	pub fn edited(&self) -> Vec<usize> {
+		let mut ks: Vec<usize> = self.edited.values().flatten().copied().collect();
+		ks.sort_unstable();
+		ks
+	}
+
+	// whether this game joined another, rather than hosting
+	#[cfg(feature = "graphics")]
+	pub fn joined(&self) -> bool {
+		self.listener.is_none()
+	}
+
+	// send a line of chat to the other players
+	#[cfg(feature = "graphics")]
+	pub fn chat(&mut self, text: &str) {
+		let text: String = text.chars().take(MAX_CHAT).collect();
+		let line = if self.listener.is_some() { format!("chat 0 {}", text) } else { format!("chat {}", text) };
+		for peer in &mut self.peers {
+			peer.send(&line);
+		}
+	}
+
+	// where the player's been put back to since the last call, if the host sent a move back
+	#[cfg(feature = "graphics")]
+	pub fn corrected(&mut self) -> Option<(usize, Vec3)> {
+		self.corrected.take()
+	}
+
+	// The other players, to be drawn among the entities, where they were DELAY ago. Those in a
+	// part of a streamed world that isn't loaded are left out.
+	pub fn players<'a>(&'a self, world: &'a World, streamer: Option<&'a Streamer>) -> impl Iterator<Item = Entity> + 'a {
+		let then = Instant::now() - DELAY;
+		let size = whole(world, streamer);
+		self.tracks.values().filter_map(move |track| {
+			let after = track.iter().position(|&(t, _, _)| t > then).unwrap_or(track.len());
+			let (i, position) = match (after.checked_sub(1).map(|k| track[k]), track.get(after)) {
+				(Some((t0, k0, p0)), Some(&(t1, k1, p1))) => {
+					let i0 = find(streamer, k0)?;
+					let [dx, dy, dz] = displacement(size, k0, k1);
+					let d = vec3(dx as f32, dy as f32, dz as f32) + p1 - p0;
+					if d.length() > LEAP {
+						(i0, p0)
+					} else {
+						let f = (then - t0).as_secs_f32() / (t1 - t0).as_secs_f32().max(f32::EPSILON);
+						settle(world, i0, p0 + f * d)
+					}
+				},
+				(Some((_, k, p)), None) | (None, Some(&(_, k, p))) => (find(streamer, k)?, p),
+				(None, None) => return None,
+			};
+			Some(Entity::new(Kind::Player, i, position))
+		})
+	}
+
+	// hear that player `id` is at `position` in voxel k
+	fn place(&mut self, id: u32, k: usize, position: Vec3) {
+		self.players.insert(id, (k, position));
+		let track = self.tracks.entry(id).or_default();
+		let now = Instant::now();
+		track.push_back((now, k, position));
+		while track.front().is_some_and(|&(t, _, _)| now - t > KEPT) {
+			track.pop_front();
+		}
+	}
+
+	// A tick of play together: send the voxels `changed` and where the player is, as a voxel and
+	// a position in it, if there's a player here, and set the voxels others changed, through
+	// `streamer` if the world's streamed. Returns what there is to tell the player, like who
+	// joined.
+	pub fn tick(&mut self, world: &mut World, mut streamer: Option<&mut Streamer>, changed: &[usize], me: Option<(usize, Vec3)>) -> Vec<String> {
+		let mut said = vec![];
+		let size = whole(world, streamer.as_deref());
+		self.accept(size, &mut said);
+		self.permissions.tick(TICK);
+		// the changes, by who they came from if they're just what a peer sent
+		let mut sources: BTreeMap<Option<u32>, BTreeMap<usize, Voxel>> = BTreeMap::new();
+		for &i in changed {
+			let k = number(streamer.as_deref(), i);
+			if streamer.is_none() {
+				self.edited.entry(chunk(size, k)).or_default().insert(k);
+			}
+			let from = self.echoes.remove(&i).filter(|(_, v)| v == world.voxel(i)).map(|(id, _)| id);
+			sources.entry(from).or_default().insert(k, world.voxel(i).clone());
+		}
+		self.echoes.clear();
+		for (from, changed) in sources {
+			let lines = runs_of(changed);
+			for peer in self.peers.iter_mut().filter(|p| Some(p.id) != from) {
+				for line in &lines {
+					peer.send(line);
+				}
+			}
+		}
+		// a joining game asks for each cube of a streamed world once while it's loaded
+		if let (Some(s), None) = (streamer.as_deref(), &self.listener) {
+			self.asked.retain(|&c| s.holds(c));
+			for c in s.held() {
+				if self.asked.insert(c) {
+					for peer in &mut self.peers {
+						peer.send(&format!("want {} {} {}", c[0], c[1], c[2]));
+					}
+				}
+			}
+		}
+		for peer in self.peers.iter_mut().filter(|p| p.greeted && !p.pending.is_empty()) {
+			// nearest last, to be taken first
+			if let Some(&(k, _)) = self.players.get(&peer.id) {
+				let [nx, ny, nz] = size;
+				peer.pending.sort_by_key(|c| {
+					let [x, y, z] = c.map(|n| n*CHUNK + CHUNK/2);
+					let d = displacement(size, k, furl(x.min(nx - 1), y.min(ny - 1), z.min(nz - 1), ny, nz));
+					std::cmp::Reverse(d.iter().map(|&n| n as i64 * n as i64).sum::<i64>())
+				});
+			}
+			for c in peer.pending.split_off(peer.pending.len().saturating_sub(CHUNKS_PER_TICK)) {
+				let voxels = match streamer.as_deref() {
+					Some(s) => s.cube(world, c),
+					None => Some(cube(world, c)),
+				};
+				peer.send(&match voxels {
+					Some(voxels) => format!("chunk {} {} {} {}", c[0], c[1], c[2], pack(&voxels)),
+					None => format!("chunk {} {} {}", c[0], c[1], c[2]),
+				});
+			}
+		}
+		if let (Some((i, p)), None) = (me, &self.listener) {
+			self.sent += 1;
+			self.unacked.push_back((self.sent, i, p));
+			if self.unacked.len() > UNACKED {
+				self.unacked.pop_front();
+			}
+		}
+		let me = me.map(|(i, p)| format!("{} {} {} {}", number(streamer.as_deref(), i), p.x, p.y, p.z));
+		for peer in &mut self.peers {
+			match (&me, &self.listener) {
+				(Some(me), None) => peer.send(&format!("at {} {}", self.sent, me)),
+				(Some(me), Some(_)) => peer.send(&format!("at 0 {}", me)),
+				(None, _) => {},
+			}
+			if self.listener.is_none() {
+				continue;
+			}
+			let to = peer.id;
+			for (&id, &(j, p)) in self.players.iter().filter(|&(&id, _)| id != to) {
+				peer.send(&format!("at {} {} {} {} {}", id, j, p.x, p.y, p.z));
+			}
+		}
+		// what one game changes, the host changes too, so it's passed on to the others with the
+		// host's own changes next tick
+		let mut received = vec![];
+		for peer in &mut self.peers {
+			for line in peer.receive() {
+				if peer.greeted {
+					received.push((peer.id, line));
+				} else if line.split_whitespace().eq(["hello", &VERSION.to_string()]) {
+					peer.greeted = true;
+				} else {
+					log::warning!("net", "player {} runs another version of the game: '{}'", peer.id, line);
+					peer.gone = true;
+				}
+			}
+		}
+		for (from, line) in received {
+			if let Err(e) = self.handle(world, streamer.as_deref_mut(), from, &line, &mut said) {
+				log::warning!("net", "player {}: {}: '{}'", from, e, line);
+			}
+		}
+		for peer in &mut self.peers {
+			if let Some(n) = peer.moved.take() {
+				peer.send(&format!("ack {}", n));
+			}
+			peer.flush();
+		}
+		for id in self.peers.iter().filter(|p| p.gone).map(|p| p.id).collect::<Vec<_>>() {
+			self.peers.retain(|p| p.id != id);
+			if self.listener.is_none() {
+				self.players.clear();
+				self.tracks.clear();
+				said.push("lost the connection to the host".to_string());
+				continue;
+			}
+			self.players.remove(&id);
+			self.tracks.remove(&id);
+			for peer in &mut self.peers {
+				peer.send(&format!("left {}", id));
+				peer.send(&format!("notice player {} left", id));
+			}
+			said.push(format!("player {} left", id));
+		}
+		said
+	}
+
+	// let in the games that have connected to play in a world of `size`, sending them the world
+	// as it is now
+	fn accept(&mut self, size: [usize; 3], said: &mut Vec<String>) {
+		let Some(listener) = &self.listener else { return };
+		while let Ok((stream, address)) = listener.accept() {
+			let mut peer = match Peer::new(self.next_id, stream) {
+				Ok(peer) => peer,
+				Err(e) => {
+					log::warning!("net", "{}: {}", address, e);
+					continue;
+				},
+			};
+			let [x, y, z] = size;
+			peer.send(&format!("world {} {} {} {} {} {}", VERSION, self.seed, x, y, z, self.recipe));
+			peer.pending = self.edited.keys().copied().collect();
+			peer.send(&format!("notice you're a {}", self.permissions.role(peer.id).name()));
+			said.push(format!("player {} joined from {}", peer.id, address));
+			for other in &mut self.peers {
+				other.send(&format!("notice player {} joined", peer.id));
+			}
+			self.peers.push(peer);
+			self.next_id += 1;
+		}
+	}
+
+	// act on a line from player `from`, adding what there is to tell the player to `said`
+	fn handle(&mut self, world: &mut World, mut streamer: Option<&mut Streamer>, from: u32, line: &str, said: &mut Vec<String>) -> Result<(), String> {
+		let words: Vec<&str> = line.split_whitespace().collect();
+		let size = whole(world, streamer.as_deref());
+		let [x, y, z] = size;
+		let in_world = |k: &str| k.parse().ok().filter(|&k: &usize| k < x*y*z).ok_or("no such voxel");
+		let in_cubes = |c: [&str; 3]| {
+			let c = c.map(|n| n.parse().ok().unwrap_or(usize::MAX));
+			(0..3).all(|a| c[a] < size[a].div_ceil(CHUNK)).then_some(c).ok_or("no such cube")
+		};
+		let number = |n: &str| n.parse().ok().filter(|n: &f32| n.is_finite()).ok_or("bad number");
+		let host = self.listener.is_some();
+		match words.as_slice() {
+			["set", ..] => {
+				let (first, count, voxel) = parse_set(&words, x*y*z)?;
+				let at = self.players.get(&from).map(|&(k, _)| k);
+				let mut refused = vec![];
+				let mut reason = None;
+				// voxels of a streamed world only the host has loaded, passed on to the others now
+				let mut kept = vec![];
+				// a run the player can't change at all is refused whole, without a look at each voxel
+				if let Some(why) = host.then(|| self.permissions.admits(from, count).err()).flatten() {
+					refused.extend(first..first + count);
+					reason = Some(why);
+				} else {
+					for k in first..first + count {
+						if host {
+							if let Err(why) = self.permissions.allows(size, from, at, k) {
+								refused.push(k);
+								reason = Some(why);
+								continue;
+							}
+						}
+						match (find(streamer.as_deref(), k), streamer.as_deref_mut()) {
+							(Some(i), _) => {
+								world.set(i, voxel.clone());
+								self.echoes.insert(i, (from, voxel.clone()));
+							},
+							(None, Some(s)) if host => {
+								s.put(world, k, voxel.clone());
+								kept.push((k, voxel.clone()));
+							},
+							// a cube this game hasn't loaded, which it's sent when it does
+							(None, _) => {},
+						}
+					}
+				}
+				let lines = runs_of(kept);
+				for peer in self.peers.iter_mut().filter(|p| p.id != from) {
+					for line in &lines {
+						peer.send(line);
+					}
+				}
+				if let Some(peer) = self.peers.iter_mut().find(|p| p.id == from) {
+					if streamer.is_some() {
+						// sent again whole, as only the host has all of a streamed world
+						for c in refused.iter().map(|&k| chunk(size, k)) {
+							if !peer.pending.contains(&c) {
+								peer.pending.push(c);
+							}
+						}
+					} else {
+						for line in runs(world, refused) {
+							peer.send(&line);
+						}
+					}
+					if reason.is_some() && reason != peer.told {
+						peer.send(&format!("notice you can't change that: {}", reason.unwrap_or_default()));
+					}
+					peer.told = reason;
+				}
+			},
+			["at", id, k, px, py, pz] if !host => {
+				let id = id.parse().map_err(|_| "bad player")?;
+				let i = in_world(k)?;
+				self.place(id, i, vec3(number(px)?, number(py)?, number(pz)?).clamp(Vec3::ZERO, Vec3::ONE));
+			},
+			["at", n, k, px, py, pz] if host => {
+				let n: u32 = n.parse().map_err(|_| "bad move")?;
+				let k = in_world(k)?;
+				let position = vec3(number(px)?, number(py)?, number(pz)?).clamp(Vec3::ZERO, Vec3::ONE);
+				// A player can't be further from where they were last let be than they could have
+				// got to since, so they can't reach further than that either, nor walk into a wall,
+				// though they can move about in one they were already stuck in. Until they could
+				// have, they stay where they were, and their game is told so. The walls of a
+				// streamed world are only known where the host has it loaded.
+				if let Some(&(t, k0, p0)) = self.tracks.get(&from).and_then(|track| track.back()) {
+					let [dx, dy, dz] = displacement(size, k0, k);
+					let d = vec3(dx as f32, dy as f32, dz as f32) + position - p0;
+					let too_far = d.length() > LEAP + TOP_SPEED * t.elapsed().as_secs_f32();
+					let into_wall = match (find(streamer.as_deref(), k), find(streamer.as_deref(), k0)) {
+						(Some(i), Some(i0)) => player::inside(world, i, position) && !player::inside(world, i0, p0),
+						_ => false,
+					};
+					if too_far || into_wall {
+						if let Some(peer) = self.peers.iter_mut().find(|p| p.id == from) {
+							peer.send(&format!("back {} {} {} {} {}", n, k0, p0.x, p0.y, p0.z));
+						}
+						return Ok(());
+					}
+				}
+				self.place(from, k, position);
+				if let Some(peer) = self.peers.iter_mut().find(|p| p.id == from) {
+					peer.moved = Some(n);
+				}
+			},
+			["ack", n] if !host => {
+				let n: u32 = n.parse().map_err(|_| "bad move")?;
+				self.unacked.retain(|&(m, _, _)| m > n);
+			},
+			["back", n, k, px, py, pz] if !host => {
+				let n = n.parse().map_err(|_| "bad move")?;
+				// somewhere this game hasn't loaded, which it can't be put back in
+				let Some(i) = find(streamer.as_deref(), in_world(k)?) else { return Ok(()) };
+				self.reconcile(world, n, i, vec3(number(px)?, number(py)?, number(pz)?).clamp(Vec3::ZERO, Vec3::ONE));
+			},
+			["want", cx, cy, cz] if host => {
+				let c = in_cubes([cx, cy, cz])?;
+				if let Some(peer) = self.peers.iter_mut().find(|p| p.id == from) {
+					if !peer.pending.contains(&c) {
+						peer.pending.push(c);
+					}
+				}
+			},
+			["chunk", cx, cy, cz, packed @ ..] if !host => {
+				let c = in_cubes([cx, cy, cz])?;
+				let [sx, sy, sz] = extent(size, c);
+				let voxels = match packed {
+					[] => None,
+					[packed] => Some(unpack(packed, sx*sy*sz)?),
+					_ => return Err("can't understand".to_string()),
+				};
+				let changed = match streamer {
+					// unloaded since it was asked for
+					Some(s) if !s.holds(c) => vec![],
+					Some(s) => s.replace(world, c, voxels),
+					None => {
+						let mut changed = vec![];
+						for (n, voxel) in voxels.into_iter().flatten().enumerate() {
+							let i = furl(c[0]*CHUNK + n / (sy*sz), c[1]*CHUNK + n / sz % sy, c[2]*CHUNK + n % sz, y, z);
+							if *world.voxel(i) != voxel {
+								world.set(i, voxel);
+								changed.push(i);
+							}
+						}
+						changed
+					},
+				};
+				for i in changed {
+					self.echoes.insert(i, (from, world.voxel(i).clone()));
+				}
+			},
+			["left", id] => {
+				let id = id.parse().map_err(|_| "bad player")?;
+				self.players.remove(&id);
+				self.tracks.remove(&id);
+			},
+			["chat", ..] if host => {
+				let text: String = line.split_once(' ').map_or("", |(_, text)| text).chars().take(MAX_CHAT).collect();
+				for peer in self.peers.iter_mut().filter(|p| p.id != from) {
+					peer.send(&format!("chat {} {}", from, text));
+				}
+				said.push(format!("player {}: {}", from, text));
+			},
+			["chat", id, ..] => {
+				let id: u32 = id.parse().map_err(|_| "bad player")?;
+				said.push(format!("player {}: {}", id, line.splitn(3, ' ').nth(2).unwrap_or("")));
+			},
+			["notice", ..] => said.push(line.split_once(' ').map_or("", |(_, text)| text).to_string()),
+			_ => return Err("can't understand".to_string()),
+		}
+		Ok(())
+	}
+
+	// Take the host's word that the player was at `position` in voxel i after move n, and make
+	// the moves since again from there, through this game's world.
+	fn reconcile(&mut self, world: &World, n: u32, i: usize, position: Vec3) {
+		let Some(k) = self.unacked.iter().position(|&(m, _, _)| m == n) else {
+			// too long ago to have kept the moves since, so just go where the host says
+			self.unacked.clear();
+			self.corrected = Some((i, position));
+			return;
+		};
+		let (_, mut was_i, mut was_p) = self.unacked[k];
+		self.unacked.drain(..=k);
+		let (mut j, mut q) = (i, position);
+		for (_, at_i, at_p) in &mut self.unacked {
+			let [dx, dy, dz] = world.displacement(was_i, *at_i);
+			let d = vec3(dx as f32, dy as f32, dz as f32) + *at_p - was_p;
+			(was_i, was_p) = (*at_i, *at_p);
+			(j, q) = settle(world, j, player::move_shape(world, j, q, d, &player::PLAYER).0);
+			// kept as made again, so a later move sent back is made again from there too
+			(*at_i, *at_p) = (j, q);
+		}
+		self.corrected = Some((j, q));
+	}
+
+	// Carry out a console command about the players, answering with what to say. Those that
+	// change what players may do are only for the host.
+	pub fn administer(&mut self, world: &World, streamer: Option<&Streamer>, command: Command) -> String {
+		if self.listener.is_none() && !matches!(command, Command::Players) {
+			return "only the host can do that".to_string();
+		}
+		match command {
+			Command::Players if self.players.is_empty() => "nobody else is playing".to_string(),
+			Command::Players => {
+				let mut ids: Vec<u32> = self.players.keys().copied().collect();
+				ids.sort_unstable();
+				let host = self.listener.is_some();
+				ids.iter().map(|&id| if host { format!("player {} ({})", id, self.permissions.role(id).name()) } else { format!("player {}", id) }).collect::<Vec<_>>().join("  ")
+			},
+			Command::Role(id, role) => match self.peers.iter_mut().find(|p| p.id == id) {
+				Some(peer) => {
+					self.permissions.set_role(id, role);
+					peer.send(&format!("notice you're now a {}", role.name()));
+					format!("player {} is now a {}", id, role.name())
+				},
+				None => format!("there's no player {}", id),
+			},
+			Command::Protect(a, b) => {
+				let [_, ny, nz] = world.size();
+				let (a, b) = (furl(a[0], a[1], a[2], ny, nz), furl(b[0], b[1], b[2], ny, nz));
+				// the corners are in the window of a streamed world, and the box in the whole of it
+				let region = match streamer {
+					Some(s) => Region::between_in(s.size(), s.number(a), s.number(b)),
+					None => Region::between(world, a, b),
+				};
+				let [sx, sy, sz] = region.size;
+				self.permissions.protect(region);
+				format!("protected {} voxels", sx*sy*sz)
+			},
+			Command::Unprotect => format!("stopped protecting {} boxes", self.permissions.unprotect()),
+			_ => String::new(),
+		}
+	}
+}
+
+// the whole world, which a streamed one only has part of loaded
+fn whole(world: &World, streamer: Option<&Streamer>) -> [usize; 3] {
+	streamer.map_or(world.size(), Streamer::size)
+}
+
+// the number of voxel i in the whole world
+fn number(streamer: Option<&Streamer>, i: usize) -> usize {
+	streamer.map_or(i, |s| s.number(i))
+}
+
+// the voxel numbered k in the whole world, if it's loaded
+fn find(streamer: Option<&Streamer>, k: usize) -> Option<usize> {
+	streamer.map_or(Some(k), |s| s.find(k))
+}
+
+// the cube voxel k of a world of `size` is in
+fn chunk(size: [usize; 3], k: usize) -> [usize; 3] {
+	let [_, ny, nz] = size;
+	[k / (ny*nz) / CHUNK, k / nz % ny / CHUNK, k % nz / CHUNK]
+}
+
+// how far cube c goes along each axis, which is short of CHUNK at the far side of a world of
+// `size` that isn't a whole number of cubes
+fn extent(size: [usize; 3], c: [usize; 3]) -> [usize; 3] {
+	[0, 1, 2].map(|a| CHUNK.min(size[a] - c[a]*CHUNK))
+}
+
+// the voxels of cube c of a world that's all loaded, indexed [x][y][z] within it
+fn cube(world: &World, c: [usize; 3]) -> Vec<Voxel> {
+	let [sx, sy, sz] = extent(world.size(), c);
+	let [_, ny, nz] = world.size();
+	let mut voxels = Vec::with_capacity(sx*sy*sz);
+	for x in 0..sx {
+		for y in 0..sy {
+			for z in 0..sz {
+				voxels.push(world.voxel(furl(c[0]*CHUNK + x, c[1]*CHUNK + y, c[2]*CHUNK + z, ny, nz)).clone());
+			}
+		}
+	}
+	voxels
+}
+
+// voxels as a `chunk` line has them
+fn pack(voxels: &[Voxel]) -> String {
+	let mut bytes = Vec::with_capacity(voxels.len() * VOXEL_BYTES);
+	for voxel in voxels {
+		bytes.extend(voxel.block.to_le_bytes());
+		bytes.push(voxel.state);
+		for c in voxel.color.to_array() {
+			bytes.extend(c.to_le_bytes());
+		}
+	}
+	base64(&miniz_oxide::deflate::compress_to_vec(&bytes, 6))
+}
+
+// the `count` voxels a `chunk` line has as `packed`
+fn unpack(packed: &str, count: usize) -> Result<Vec<Voxel>, &'static str> {
+	let deflated = unbase64(packed).ok_or("bad voxels")?;
+	let bytes = miniz_oxide::inflate::decompress_to_vec_with_limit(&deflated, count * VOXEL_BYTES).map_err(|_| "bad voxels")?;
+	if bytes.len() != count * VOXEL_BYTES {
+		return Err("bad count");
+	}
+	bytes.chunks_exact(VOXEL_BYTES).map(|b| {
+		let block = BlockId::from_le_bytes([b[0], b[1]]);
+		if block as usize >= block::all().len() {
+			return Err("no such block");
+		}
+		let color: [f32; 4] = std::array::from_fn(|n| f32::from_le_bytes([b[3 + 4*n], b[4 + 4*n], b[5 + 4*n], b[6 + 4*n]]));
+		if !color.iter().all(|c| c.is_finite()) {
+			return Err("bad number");
+		}
+		Ok(Voxel { transparent: interact::transparent(block, b[2]), ..Voxel::new(block, Vec4::from_array(color)).with_state(b[2]) })
+	}).collect()
+}
+
+// bytes as text, six bits a character, without padding
+fn base64(bytes: &[u8]) -> String {
+	let mut text = String::with_capacity(bytes.len().div_ceil(3) * 4);
+	for group in bytes.chunks(3) {
+		let n = group.iter().enumerate().fold(0, |n, (k, &b)| n | (b as u32) << (16 - 8*k));
+		for k in 0..=group.len() {
+			text.push(BASE64[((n >> (18 - 6*k)) & 63) as usize] as char);
+		}
+	}
+	text
+}
+
+fn unbase64(text: &str) -> Option<Vec<u8>> {
+	let mut bytes = Vec::with_capacity(text.len() / 4 * 3 + 2);
+	for group in text.as_bytes().chunks(4) {
+		if group.len() == 1 {
+			return None;
+		}
+		let mut n = 0;
+		for (k, &c) in group.iter().enumerate() {
+			n |= (BASE64.iter().position(|&d| d == c)? as u32) << (18 - 6*k);
+		}
+		for k in 0..group.len() - 1 {
+			bytes.push((n >> (16 - 8*k)) as u8);
+		}
+	}
+	Some(bytes)
+}
+
+// `set` lines for voxels `ks`, in order, with neighbors that are the same joined into runs
+pub fn runs(world: &World, ks: impl IntoIterator<Item = usize>) -> Vec<String> {
+	runs_of(ks.into_iter().map(|k| (k, world.voxel(k).clone())))
+}
+
+// `set` lines for voxels numbered k set to voxel, in order of k, joined into runs like `runs`
+pub fn runs_of(voxels: impl IntoIterator<Item = (usize, Voxel)>) -> Vec<String> {
+	let mut lines = vec![];
+	// the first voxel of the run so far, how many there are and what to
+	let mut run: Option<(usize, usize, Voxel)> = None;
+	for (k, voxel) in voxels {
+		if let Some((first, count, same)) = &mut run {
+			if *first + *count == k && *count < MAX_RUN && *same == voxel {
+				*count += 1;
+				continue;
+			}
+		}
+		lines.extend(run.take().map(|(first, count, voxel)| set(first, count, &voxel)));
+		run = Some((k, 1, voxel));
+	}
+	lines.extend(run.map(|(first, count, voxel)| set(first, count, &voxel)));
+	lines
+}
+
+fn set(first: usize, count: usize, voxel: &Voxel) -> String {
+	let c = voxel.color;
+	format!("set {} {} {} {} {} {} {} {}", first, count, voxel.block, voxel.state, c.x, c.y, c.z, c.w)
+}
+
+// the first voxel a `set` line's `words` change in a world of `volume` voxels, how many, and
+// what to
+pub fn parse_set(words: &[&str], volume: usize) -> Result<(usize, usize, Voxel), &'static str> {
+	let ["set", k, count, b, state, r, g, bl, a] = words else {
+		return Err("can't understand");
+	};
+	let number = |n: &str| n.parse().ok().filter(|n: &f32| n.is_finite()).ok_or("bad number");
+	let first: usize = k.parse().ok().filter(|&k| k < volume).ok_or("no such voxel")?;
+	let count: usize = count.parse().ok().filter(|&n| (1..=MAX_RUN).contains(&n) && n <= volume - first).ok_or("bad count")?;
+	let block: BlockId = b.parse().ok().filter(|&b: &BlockId| (b as usize) < block::all().len()).ok_or("no such block")?;
+	let state = state.parse().map_err(|_| "bad state")?;
+	let color = vec4(number(r)?, number(g)?, number(bl)?, number(a)?);
+	Ok((first, count, Voxel { transparent: interact::transparent(block, state), ..Voxel::new(block, color).with_state(state) }))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{link_world, Progress};
+	use crate::noise::NoiseParams;
+	use crate::rng::Rng;
+
+	fn session() -> Session {
+		Session {
+			listener: None,
+			peers: vec![],
+			seed: 0,
+			recipe: String::new(),
+			next_id: 1,
+			edited: BTreeMap::new(),
+			echoes: HashMap::new(),
+			asked: HashSet::new(),
+			players: HashMap::new(),
+			tracks: HashMap::new(),
+			permissions: Permissions::new(Role::Admin),
+			sent: 0,
+			unacked: VecDeque::new(),
+			corrected: None,
+		}
+	}
+
+	#[test]
+	fn set_refuses_a_count_past_the_world() {
+		let mut world = link_world(4, 4, 4, &Progress::default());
+		let mut said = vec![];
+		let line = format!("set 5 {} 1 0 1 1 1 1", usize::MAX);
+		assert!(session().handle(&mut world, None, 1, &line, &mut said).is_err());
+		let line = format!("set 5 {} 1 0 1 1 1 1", 4*4*4 - 4);
+		assert!(session().handle(&mut world, None, 1, &line, &mut said).is_err());
+		let line = format!("set 5 {} 1 0 1 1 1 1", 4*4*4 - 5);
+		assert!(session().handle(&mut world, None, 1, &line, &mut said).is_ok());
+	}
+
+	#[test]
+	fn set_is_refused_whole_past_a_run_or_for_a_spectator() {
+		let mut world = link_world(4, 4, 4, &Progress::default());
+		let mut said = vec![];
+		let line = format!("set 0 {} {} 0 1 1 1 1", MAX_RUN + 1, block::STONE);
+		assert!(session().handle(&mut world, None, 1, &line, &mut said).is_err());
+		let mut host = Session::host(0, 0, "", Role::Spectator).unwrap();
+		host.handle(&mut world, None, 1, "at 1 0 0.5 0.5 0.5", &mut said).unwrap();
+		let line = format!("set 0 64 {} 0 1 1 1 1", block::STONE);
+		host.handle(&mut world, None, 1, &line, &mut said).unwrap();
+		assert!((0..64).all(|k| world.voxel(k).block == block::AIR));
+		host.permissions.set_role(1, Role::Builder);
+		host.handle(&mut world, None, 1, &line, &mut said).unwrap();
+		assert!((0..64).all(|k| world.voxel(k).block == block::STONE));
+	}
+
+	#[test]
+	fn a_door_sent_open_is_see_through() {
+		let mut world = link_world(4, 4, 4, &Progress::default());
+		let mut said = vec![];
+		for (state, transparent) in [(0, false), (1, true)] {
+			let line = format!("set 5 1 {} {} 1 1 1 1", block::DOOR, state);
+			session().handle(&mut world, None, 1, &line, &mut said).unwrap();
+			assert_eq!(world.transparent(5), transparent);
+		}
+	}
+
+	#[test]
+	fn packed_voxels_come_back_the_same() {
+		for n in 0..8 {
+			let bytes: Vec<u8> = (0..n).map(|k| 250 - k * 30).collect();
+			assert_eq!(unbase64(&base64(&bytes)), Some(bytes));
+		}
+		let door = Voxel { transparent: true, ..Voxel::of(block::DOOR).with_state(1) };
+		let voxels: Vec<Voxel> = (0..500).map(|k| if k % 3 == 0 { door.clone() } else { Voxel::new(block::STONE, vec4(k as f32, 0.5, 0.25, 1.0)) }).collect();
+		assert!(unpack(&pack(&voxels), 500) == Ok(voxels.clone()));
+		assert!(unpack(&pack(&voxels), 501).is_err());
+		assert!(unpack("not base64!", 500).is_err());
+	}
+
+	#[test]
+	fn a_cube_at_the_far_side_goes_as_far_as_the_world() {
+		let mut host = link_world(20, 16, 16, &Progress::default());
+		host.set(furl(18, 3, 4, 16, 16), Voxel::of(block::STONE));
+		assert_eq!(extent(host.size(), [1, 0, 0]), [4, 16, 16]);
+		let line = format!("chunk 1 0 0 {}", pack(&cube(&host, [1, 0, 0])));
+		let mut world = link_world(20, 16, 16, &Progress::default());
+		session().handle(&mut world, None, 0, &line, &mut Vec::new()).unwrap();
+		assert!((0..20*16*16).all(|k| world.voxel(k) == host.voxel(k)));
+	}
+
+	#[test]
+	fn a_streamed_world_is_sent_a_cube_at_a_time() {
+		let stream = || Streamer::new([64, 32, 64], [32, 32, 32], &NoiseParams { amplitude: 0.8, frequency: 2, octaves: 2 }, &mut Rng::new(1), &Progress::default());
+		let (mut streamer, mut world) = stream();
+		for i in [0, 1000, 32*32*32 - 1] {
+			assert_eq!(streamer.find(streamer.number(i)), Some(i));
+		}
+		// a voxel the host hasn't loaded is kept with its cube
+		let mut host = Session::host(0, 0, "", Role::Admin).unwrap();
+		let far = furl(40, 5, 40, 32, 64);
+		assert_eq!(streamer.find(far), None);
+		host.handle(&mut world, Some(&mut streamer), 1, &format!("set {} 1 {} 0 1 1 1 1", far, block::STONE), &mut Vec::new()).unwrap();
+		let voxels = streamer.cube(&world, [2, 0, 2]).unwrap();
+		assert_eq!(voxels[(8*CHUNK + 5)*CHUNK + 8].block, block::STONE);
+		// and a joining game takes a cube it's loaded as sent, or as it was made
+		let (mut theirs, mut there) = stream();
+		let near = furl(3, 20, 5, 32, 64);
+		let mut cube = theirs.cube(&there, [0, 1, 0]).unwrap_or_else(|| vec![Voxel::of(block::AIR); CHUNK*CHUNK*CHUNK]);
+		cube[(3*CHUNK + 4)*CHUNK + 5] = Voxel::of(block::DOOR);
+		let mut joined = session();
+		joined.handle(&mut there, Some(&mut theirs), 0, &format!("chunk 0 1 0 {}", pack(&cube)), &mut Vec::new()).unwrap();
+		let i = theirs.find(near).unwrap();
+		assert_eq!(there.voxel(i).block, block::DOOR);
+		joined.handle(&mut there, Some(&mut theirs), 0, "chunk 0 1 0", &mut Vec::new()).unwrap();
+		assert!(there.voxel(i) == world.voxel(streamer.find(near).unwrap()));
+	}
+
+	#[test]
+	fn a_player_can_only_be_as_far_as_they_could_have_moved() {
+		let mut world = link_world(64, 4, 4, &Progress::default());
+		let mut said = vec![];
+		let mut host = Session::host(0, 0, "", Role::Builder).unwrap();
+		let far = furl(40, 0, 0, 4, 4);
+		host.handle(&mut world, None, 1, "at 1 0 0.5 0.5 0.5", &mut said).unwrap();
+		host.handle(&mut world, None, 1, &format!("at 2 {} 0.5 0.5 0.5", far), &mut said).unwrap();
+		assert_eq!(host.players[&1].0, 0);
+		let track = host.tracks.get_mut(&1).unwrap();
+		track.back_mut().unwrap().0 -= Duration::from_secs(1);
+		host.handle(&mut world, None, 1, &format!("at 3 {} 0.5 0.5 0.5", far), &mut said).unwrap();
+		assert_eq!(host.players[&1].0, far);
+	}
+
+	#[test]
+	fn a_player_cant_walk_into_a_wall() {
+		let mut world = link_world(8, 8, 8, &Progress::default());
+		let mut said = vec![];
+		let mut host = Session::host(0, 0, "", Role::Builder).unwrap();
+		let (here, wall) = (furl(2, 2, 2, 8, 8), furl(2, 2, 3, 8, 8));
+		world.set(wall, Voxel::of(block::STONE));
+		host.handle(&mut world, None, 1, &format!("at 1 {} 0.5 0.5 0.5", here), &mut said).unwrap();
+		host.handle(&mut world, None, 1, &format!("at 2 {} 0.5 0.5 0.5", wall), &mut said).unwrap();
+		assert_eq!(host.players[&1].0, here);
+	}
+
+	#[test]
+	fn moves_since_one_sent_back_are_made_again_from_where_the_host_says() {
+		let mut world = link_world(8, 8, 8, &Progress::default());
+		let mut said = vec![];
+		let mut joined = session();
+		let middle = Vec3::splat(0.5);
+		// moved along z, and then along z again
+		joined.unacked = VecDeque::from([(5, furl(2, 2, 3, 8, 8), middle), (6, furl(2, 2, 4, 8, 8), middle), (7, furl(2, 2, 5, 8, 8), middle)]);
+		joined.handle(&mut world, None, 0, &format!("back 5 {} 0.5 0.5 0.5", furl(6, 2, 2, 8, 8)), &mut said).unwrap();
+		assert_eq!(joined.corrected.take(), Some((furl(6, 2, 4, 8, 8), middle)));
+		joined.handle(&mut world, None, 0, "ack 6", &mut said).unwrap();
+		assert_eq!(joined.unacked.iter().map(|&(n, i, _)| (n, i)).collect::<Vec<_>>(), [(7, furl(6, 2, 4, 8, 8))]);
+	}
+
+	#[test]
+	fn joining_needs_the_same_recipe() {
+		let mut world = link_world(4, 4, 4, &Progress::default());
+		let mut host = Session::host(0, 7, "terrain 0.8 2 4", Role::Builder).unwrap();
+		let address = host.listener.as_ref().unwrap().local_addr().unwrap().to_string();
+		for (recipe, joins) in [("terrain 0.5 2 4", false), ("terrain 0.8 2 4", true)] {
+			let joining = {
+				let (address, recipe) = (address.clone(), recipe.to_string());
+				std::thread::spawn(move || Session::join(&address, &recipe))
+			};
+			while !joining.is_finished() {
+				host.tick(&mut world, None, &[], None);
+			}
+			let joined = joining.join().unwrap();
+			assert_eq!(joined.is_ok(), joins);
+			assert_eq!(joined.ok().map(|(_, seed, size)| (seed, size)), joins.then_some((7, [4, 4, 4])));
+		}
+	}
+
+	#[test]
+	fn a_line_too_long_drops_the_peer() {
+		// still coming, and whole with its newline in the same read as the line before
+		let mut whole = b"chat hi\n".to_vec();
+		whole.extend([b'a'; MAX_LINE + 1]);
+		whole.push(b'\n');
+		for sent in [vec![b'a'; MAX_LINE + 1], whole] {
+			let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+			let mut sender = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+			let mut peer = Peer::new(1, listener.accept().unwrap().0).unwrap();
+			sender.write_all(&sent).unwrap();
+			let start = Instant::now();
+			let mut lines = vec![];
+			while !peer.gone() && start.elapsed() < TIMEOUT {
+				lines.extend(peer.receive());
+			}
+			assert!(peer.gone());
+			assert!(lines.iter().all(|line| line.len() <= MAX_LINE));
+		}
+	}
+}
+
+
+
+
+
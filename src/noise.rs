@@ -0,0 +1,87 @@
+// Periodic gradient (Perlin) noise. Every lattice period divides the world size, so the noise
+// tiles seamlessly across the torus wrap in all three axes.
+
+use glam::*;
+use crate::rng::Rng;
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct NoiseParams {
+	pub amplitude: f32,
+	// lattice cells across the whole world for the first octave, so it must be a whole number
+	pub frequency: u32,
+	pub octaves: u32,
+}
+
+const GRADIENTS: [Vec3; 12] = [
+	vec3(1.0, 1.0, 0.0), vec3(-1.0, 1.0, 0.0), vec3(1.0, -1.0, 0.0), vec3(-1.0, -1.0, 0.0),
+	vec3(1.0, 0.0, 1.0), vec3(-1.0, 0.0, 1.0), vec3(1.0, 0.0, -1.0), vec3(-1.0, 0.0, -1.0),
+	vec3(0.0, 1.0, 1.0), vec3(0.0, -1.0, 1.0), vec3(0.0, 1.0, -1.0), vec3(0.0, -1.0, -1.0),
+];
+
+#[derive(Clone)]
+pub struct Noise {
+	perm: [u8; 256],
+}
+
+fn fade(t: f32) -> f32 {
+	t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+impl Noise {
+	pub fn new(rng: &mut Rng) -> Noise {
+		let mut perm = [0u8; 256];
+		for (i, p) in perm.iter_mut().enumerate() {
+			*p = i as u8;
+		}
+		for i in (1..256).rev() {
+			let j = (rng.next_u64() % (i as u64 + 1)) as usize;
+			perm.swap(i, j);
+		}
+		Noise { perm }
+	}
+
+	fn gradient(&self, x: i32, y: i32, z: i32) -> Vec3 {
+		let h = self.perm[(x & 255) as usize] as i32;
+		let h = self.perm[((h + y) & 255) as usize] as i32;
+		let h = self.perm[((h + z) & 255) as usize];
+		GRADIENTS[h as usize % 12]
+	}
+
+	// noise in roughly [-1, 1] at lattice coordinates p, repeating every `period` cells
+	pub fn periodic(&self, p: Vec3, period: u32) -> f32 {
+		let period = period.max(1) as i32;
+		let cell = p.floor();
+		let f = p - cell;
+		let (cx, cy, cz) = (cell.x as i32, cell.y as i32, cell.z as i32);
+		let corner = |dx: i32, dy: i32, dz: i32| -> f32 {
+			let g = self.gradient(
+				(cx + dx).rem_euclid(period),
+				(cy + dy).rem_euclid(period),
+				(cz + dz).rem_euclid(period),
+			);
+			g.dot(f - vec3(dx as f32, dy as f32, dz as f32))
+		};
+		let (u, v, w) = (fade(f.x), fade(f.y), fade(f.z));
+		let x00 = corner(0, 0, 0) + u * (corner(1, 0, 0) - corner(0, 0, 0));
+		let x10 = corner(0, 1, 0) + u * (corner(1, 1, 0) - corner(0, 1, 0));
+		let x01 = corner(0, 0, 1) + u * (corner(1, 0, 1) - corner(0, 0, 1));
+		let x11 = corner(0, 1, 1) + u * (corner(1, 1, 1) - corner(0, 1, 1));
+		let y0 = x00 + v * (x10 - x00);
+		let y1 = x01 + v * (x11 - x01);
+		y0 + w * (y1 - y0)
+	}
+
+	// fractal sum of octaves at a position given as a fraction of the world size in each axis,
+	// so (0, 0, 0) and (1, 1, 1) are the same point on the torus
+	pub fn fbm(&self, p: Vec3, params: &NoiseParams) -> f32 {
+		let (mut sum, mut amplitude, mut frequency) = (0.0, params.amplitude, params.frequency.max(1));
+		for octave in 0..params.octaves {
+			// shift each octave so they don't share lattice points
+			let offset = Vec3::splat(octave as f32 * 0.37);
+			sum += amplitude * self.periodic(p * frequency as f32 + offset, frequency);
+			amplitude *= 0.5;
+			frequency *= 2;
+		}
+		sum
+	}
+}
@@ -0,0 +1,60 @@
+// Ore veins: a post-pass that grows veins of ore through stone by random walks over the
+// neighbor graph, so veins follow whatever topology the world has
+
+use crate::{World, Voxel, furl, step};
+use crate::block::{self, BlockId};
+use crate::rng::Rng;
+
+struct Ore {
+	block: BlockId,
+	// voxels below the surface before this ore starts to appear
+	min_depth: usize,
+	// relative chance of being picked among the ores allowed at a depth
+	weight: f32,
+	vein_length: usize,
+}
+
+const ORES: [Ore; 5] = [
+	Ore { block: block::COAL, min_depth: 2, weight: 8.0, vein_length: 12 },
+	Ore { block: block::IRON, min_depth: 6, weight: 4.0, vein_length: 8 },
+	Ore { block: block::GOLD, min_depth: 14, weight: 1.5, vein_length: 6 },
+	Ore { block: block::DIAMOND, min_depth: 20, weight: 0.6, vein_length: 4 },
+	Ore { block: block::MAGMA, min_depth: 24, weight: 0.8, vein_length: 10 },
+];
+
+// veins started per voxel of world volume
+const VEIN_DENSITY: f32 = 1.0 / 600.0;
+
+// `surface[x*nz + z]` is the height of the topmost solid voxel of each column, if any
+pub fn scatter_ores(world: &mut World, nx: usize, ny: usize, nz: usize, surface: &[Option<usize>], rng: &mut Rng) {
+	let attempts = (VEIN_DENSITY * (nx*ny*nz) as f32) as usize;
+	for _ in 0..attempts {
+		let x = (rng.next_u64() % nx as u64) as usize;
+		let y = (rng.next_u64() % ny as u64) as usize;
+		let z = (rng.next_u64() % nz as u64) as usize;
+		let Some(top) = surface[x*nz + z] else { continue };
+		if y > top {
+			continue;
+		}
+		let depth = top - y;
+
+		// deeper rock allows rarer ores
+		let allowed: Vec<&Ore> = ORES.iter().filter(|o| depth >= o.min_depth).collect();
+		let total: f32 = allowed.iter().map(|o| o.weight).sum();
+		let mut roll = rng.range(0.0, total);
+		let Some(ore) = allowed.iter().find(|o| {
+			roll -= o.weight;
+			roll < 0.0
+		}) else { continue };
+
+		let mut i = furl(x, y, z, ny, nz);
+		for _ in 0..ore.vein_length {
+			if world.voxel(i).block == block::STONE {
+				world.set(i, Voxel::of(ore.block));
+			}
+			let axis = (rng.next_u64() % 3) as usize;
+			let sign = if rng.f32() < 0.5 { 1 } else { -1 };
+			i = step(world, i, axis, sign);
+		}
+	}
+}
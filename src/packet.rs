@@ -0,0 +1,188 @@
+// Ray packets: LANES rays cast through the world together, each step worked out for all of
+// them at once. The arithmetic is done lane by lane over arrays the compiler turns into SIMD
+// instructions, SSE or NEON, since glam is built without them here; only the lookups of
+// whether each ray's voxel is open or liquid are done a lane at a time. It works for worlds
+// linked as a plain 3-torus, where the voxel a step leads to follows from coordinates, so the
+// renderer casts rays one by one through the links otherwise. Each ray comes out the same as
+// `raycast_liquid` would cast it, except that one going as far as it can without hitting
+// anything gives how far that was, so it can be picked up again from there.
+
+use std::ops::{Add, Div, Mul, Sub};
+use macroquad::prelude::*;
+use crate::{World, furl};
+
+pub const LANES: usize = 4;
+
+// what `raycast_liquid` gives for a ray: the voxel it stopped in, where in it, how far it went,
+// how far of that through liquid, and the voxels it stepped through
+pub type Hit = (usize, Vec3, f32, f32, usize);
+
+// a number for each ray
+#[derive(Clone, Copy)]
+#[repr(align(16))]
+struct Lanes([f32; LANES]);
+
+impl Lanes {
+	fn splat(x: f32) -> Lanes {
+		Lanes([x; LANES])
+	}
+
+	fn map(self, f: impl Fn(f32) -> f32) -> Lanes {
+		Lanes(self.0.map(f))
+	}
+
+	fn zip(self, other: Lanes, f: impl Fn(f32, f32) -> f32) -> Lanes {
+		Lanes(std::array::from_fn(|k| f(self.0[k], other.0[k])))
+	}
+
+	fn min(self, other: Lanes) -> Lanes {
+		self.zip(other, f32::min)
+	}
+
+	fn equal(self, other: Lanes) -> [bool; LANES] {
+		std::array::from_fn(|k| self.0[k] == other.0[k])
+	}
+
+	// `a` in the lanes of `mask`, `b` in the rest
+	fn select(mask: [bool; LANES], a: Lanes, b: Lanes) -> Lanes {
+		Lanes(std::array::from_fn(|k| if mask[k] { a.0[k] } else { b.0[k] }))
+	}
+}
+
+impl Add for Lanes {
+	type Output = Lanes;
+	fn add(self, other: Lanes) -> Lanes {
+		self.zip(other, |a, b| a + b)
+	}
+}
+
+impl Sub for Lanes {
+	type Output = Lanes;
+	fn sub(self, other: Lanes) -> Lanes {
+		self.zip(other, |a, b| a - b)
+	}
+}
+
+impl Mul for Lanes {
+	type Output = Lanes;
+	fn mul(self, other: Lanes) -> Lanes {
+		self.zip(other, |a, b| a * b)
+	}
+}
+
+impl Div for Lanes {
+	type Output = Lanes;
+	fn div(self, other: Lanes) -> Lanes {
+		self.zip(other, |a, b| a / b)
+	}
+}
+
+// Cast each of `rays` from its start, a point in a voxel, at most its `max_steps` voxels, in a
+// world whose `lattice` is true.
+pub fn raycast_packet(world: &World, starts: [(usize, Vec3); LANES], rays: [Vec3; LANES], max_steps: [usize; LANES]) -> [Hit; LANES] {
+	let size = world.size();
+	let [_, ny, nz] = size;
+	let mut voxel = starts.map(|(i, _)| i);
+	let mut cell = voxel.map(|i| [i / (ny*nz), i / nz % ny, i % nz]);
+	let d = [0, 1, 2].map(|k| Lanes(rays.map(|r| r[k])));
+	let sign = d.map(|d| d.map(f32::signum));
+	// where each ray leaves its voxel along each axis, the far side going up and the near going down
+	let exit = sign.map(|s| s.map(|s| (s + 1.0) / 2.0));
+	let length = Lanes(rays.map(|r| r.length()));
+	let mut p = [0, 1, 2].map(|k| Lanes(starts.map(|(_, at)| at[k])));
+	let (mut dist, mut wet) = (Lanes::splat(0.0), Lanes::splat(0.0));
+	let mut hits: [Option<Hit>; LANES] = [None; LANES];
+	let longest = max_steps.into_iter().max().unwrap_or(0);
+	for steps in 1..=longest {
+		let going: [bool; LANES] = std::array::from_fn(|lane| hits[lane].is_none() && steps <= max_steps[lane]);
+		let t = [0, 1, 2].map(|k| (exit[k] - p[k]) / d[k]);
+		// `min_element` the way `lattice_intersect` takes it, skipping NaN
+		let t_min = t[0].min(t[1]).min(t[2]);
+		// the axis each crosses, the last of them on a tie as `lattice_intersect` takes it
+		let (on_y, on_z) = (t[1].equal(t_min), t[2].equal(t_min));
+		let axis: [usize; LANES] = std::array::from_fn(|k| if on_z[k] { 2 } else if on_y[k] { 1 } else { 0 });
+		for (k, p) in p.iter_mut().enumerate() {
+			let crossed = Lanes::select(axis.map(|a| a == k), sign[k], Lanes::splat(0.0));
+			*p = Lanes::select(going, *p + t_min * d[k] - crossed, *p);
+		}
+		let dt = (t_min * length).map(f32::abs);
+		let liquid: [bool; LANES] = std::array::from_fn(|k| going[k] && world.liquid(voxel[k]));
+		wet = Lanes::select(liquid, wet + dt, wet);
+		dist = Lanes::select(going, dist + dt, dist);
+		for lane in (0..LANES).filter(|&lane| going[lane]) {
+			let (k, n) = (axis[lane], size[axis[lane]]);
+			cell[lane][k] = if sign[k].0[lane] > 0.0 { (cell[lane][k] + 1) % n } else { (cell[lane][k] + n - 1) % n };
+			let [x, y, z] = cell[lane];
+			voxel[lane] = furl(x, y, z, ny, nz);
+			if !world.transparent(voxel[lane]) {
+				let at = vec3(p[0].0[lane], p[1].0[lane], p[2].0[lane]);
+				hits[lane] = Some((voxel[lane], at, dist.0[lane], wet.0[lane], steps));
+			}
+		}
+		if (0..LANES).all(|lane| hits[lane].is_some() || steps >= max_steps[lane]) {
+			break;
+		}
+	}
+	std::array::from_fn(|lane| hits[lane].unwrap_or_else(|| {
+		let at = vec3(p[0].0[lane], p[1].0[lane], p[2].0[lane]);
+		(voxel[lane], at, dist.0[lane], wet.0[lane], max_steps[lane])
+	}))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{block, link_world, raycast_liquid, Progress, Voxel};
+	use crate::rng::Rng;
+
+	// a 16-voxel torus, some of it stone and some water
+	fn world(rng: &mut Rng) -> World {
+		let mut world = link_world(16, 16, 16, &Progress::default());
+		for k in 0..16*16*16 {
+			match rng.f32() {
+				r if r < 0.05 => world.set(k, Voxel::of(block::STONE)),
+				r if r < 0.25 => world.set(k, Voxel::of(block::WATER)),
+				_ => {}
+			}
+		}
+		world
+	}
+
+	fn close(a: f32, b: f32) -> bool {
+		(a - b).abs() <= 1e-3 * a.abs().max(1.0)
+	}
+
+	#[test]
+	fn packets_cast_the_same_as_one_ray_at_a_time() {
+		let mut rng = Rng::new(418);
+		let world = world(&mut rng);
+		let straight = [Vec3::X, -Vec3::Y, Vec3::Z, vec3(1.0, -1.0, 0.0)];
+		for packet in 0..500 {
+			let starts = [(); LANES].map(|_| {
+				let k = rng.next_u64() as usize % (16*16*16);
+				(k, vec3(rng.f32(), rng.f32(), rng.f32()))
+			});
+			let rays = std::array::from_fn(|lane| if packet % 10 == 0 {
+				straight[lane]
+			} else {
+				vec3(rng.range(-1.0, 1.0), rng.range(-1.0, 1.0), rng.range(-1.0, 1.0))
+			});
+			let max_steps = [(); LANES].map(|_| 1 + rng.next_u64() as usize % 48);
+			let hits = raycast_packet(&world, starts, rays, max_steps);
+			for lane in 0..LANES {
+				let (i, x, dist, wet, steps) = hits[lane];
+				let (j, y, far, damp, taken) = raycast_liquid(&world, starts[lane].0, starts[lane].1, rays[lane], max_steps[lane]);
+				let what = format!("packet {packet}, lane {lane}");
+				assert_eq!((i, steps), (j, taken), "{what}");
+				assert!((0..3).all(|k| close(x[k], y[k])), "{what}: {x} against {y}");
+				assert!(close(wet, damp), "{what}: {wet} against {damp}");
+				// a ray that hits nothing says how far it went, where one at a time says how far it could
+				if world.transparent(i) {
+					assert_eq!(far, max_steps[lane] as f32, "{what}");
+				} else {
+					assert!(close(dist, far), "{what}: {dist} against {far}");
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,164 @@
+// Particles: specks of debris and puffs of smoke thrown off when blocks break or blow up, and
+// rain and snow falling. They
+// sit in voxels of the graph like entities do but are much lighter: they don't collide with
+// anything, just drift until their time runs out or they end up inside something solid.
+
+use glam::*;
+#[cfg(feature = "graphics")]
+use crate::{World, settle};
+use crate::rng::Rng;
+
+// most particles around at once; the oldest make way for new ones beyond this
+const MAX_PARTICLES: usize = 2000;
+const DEBRIS_PER_BLOCK: usize = 8;
+const DEBRIS_SPEED: f32 = 0.08;
+const DEBRIS_LIFE: (u32, u32) = (20, 40);
+const SMOKE_SPEED: f32 = 0.05;
+const SMOKE_LIFE: (u32, u32) = (40, 90);
+// fraction of its velocity smoke keeps each tick
+const SMOKE_DRAG: f32 = 0.92;
+// voxels a tick rain and snow fall, and how many ticks they last if they don't land first
+#[cfg(feature = "graphics")]
+const RAIN_SPEED: f32 = 0.4;
+#[cfg(feature = "graphics")]
+const RAIN_LIFE: u32 = 40;
+#[cfg(feature = "graphics")]
+const SNOW_SPEED: f32 = 0.04;
+#[cfg(feature = "graphics")]
+const SNOW_DRIFT: f32 = 0.015;
+#[cfg(feature = "graphics")]
+const SNOW_LIFE: u32 = 300;
+
+// made on the server as in the game, but only drawn and moved in the game
+#[cfg_attr(not(feature = "graphics"), allow(dead_code))]
+#[derive(Clone)]
+pub struct Particle {
+	pub i: usize,
+	// position within voxel i
+	pub position: Vec3,
+	// voxels per tick
+	velocity: Vec3,
+	// ticks left, out of the `lifetime` it started with
+	life: u32,
+	lifetime: u32,
+	pub color: Vec4,
+	// half the width of the square it's drawn as, in voxels
+	pub size: f32,
+	// fraction of gravity it feels; smoke feels a little of it upward
+	weight: f32,
+	drag: f32,
+}
+
+impl Particle {
+	// how solid it looks, fading out over its last third
+	#[cfg(feature = "graphics")]
+	pub fn opacity(&self) -> f32 {
+		(3.0 * self.life as f32 / self.lifetime as f32).min(1.0)
+	}
+}
+
+pub struct Particles {
+	pub list: Vec<Particle>,
+	rng: Rng,
+}
+
+impl Particles {
+	pub fn new(seed: u64) -> Particles {
+		Particles { list: vec![], rng: Rng::new(seed) }
+	}
+
+	fn direction(&mut self) -> Vec3 {
+		vec3(self.rng.range(-1.0, 1.0), self.rng.range(-1.0, 1.0), self.rng.range(-1.0, 1.0))
+	}
+
+	fn emit(&mut self, particle: Particle) {
+		if self.list.len() >= MAX_PARTICLES {
+			self.list.remove(0);
+		}
+		self.list.push(particle);
+	}
+
+	// specks of a block of `color` breaking apart in voxel i
+	pub fn debris(&mut self, i: usize, color: Vec4) {
+		for _ in 0..DEBRIS_PER_BLOCK {
+			let position = vec3(self.rng.f32(), self.rng.f32(), self.rng.f32());
+			let life = self.rng.range(DEBRIS_LIFE.0 as f32, DEBRIS_LIFE.1 as f32) as u32;
+			let shade = self.rng.range(0.8, 1.1);
+			let particle = Particle {
+				i,
+				position,
+				velocity: DEBRIS_SPEED * self.direction(),
+				life,
+				lifetime: life,
+				color: (color * shade).with_w(1.0),
+				size: 0.06,
+				weight: 1.0,
+				drag: 1.0,
+			};
+			self.emit(particle);
+		}
+	}
+
+	// a puff of `count` bits of smoke spreading out from the center of voxel i
+	pub fn smoke(&mut self, i: usize, count: usize) {
+		for _ in 0..count {
+			let life = self.rng.range(SMOKE_LIFE.0 as f32, SMOKE_LIFE.1 as f32) as u32;
+			let gray = self.rng.range(0.3, 0.6);
+			let particle = Particle {
+				i,
+				position: Vec3::splat(0.5),
+				velocity: SMOKE_SPEED * self.direction(),
+				life,
+				lifetime: life,
+				color: vec4(gray, gray, gray, 1.0),
+				size: self.rng.range(0.15, 0.3),
+				weight: -0.1,
+				drag: SMOKE_DRAG,
+			};
+			self.emit(particle);
+		}
+	}
+
+	// a drop of rain or flake of snow at `position` in voxel i, falling against `up`
+	#[cfg(feature = "graphics")]
+	pub fn fall(&mut self, i: usize, position: Vec3, up: Vec3, snow: bool) {
+		let particle = if snow {
+			let drift = self.direction();
+			Particle {
+				i,
+				position,
+				velocity: -SNOW_SPEED * up + SNOW_DRIFT * (drift - drift.dot(up) * up),
+				life: SNOW_LIFE,
+				lifetime: SNOW_LIFE,
+				color: vec4(0.95, 0.96, 1.0, 1.0),
+				size: 0.05,
+				weight: 0.0,
+				drag: 1.0,
+			}
+		} else {
+			Particle {
+				i,
+				position,
+				velocity: -RAIN_SPEED * up,
+				life: RAIN_LIFE,
+				lifetime: RAIN_LIFE,
+				color: vec4(0.6, 0.68, 0.8, 0.6),
+				size: 0.03,
+				weight: 0.0,
+				drag: 1.0,
+			}
+		};
+		self.emit(particle);
+	}
+
+	#[cfg(feature = "graphics")]
+	pub fn tick(&mut self, world: &World) {
+		for p in &mut self.list {
+			let gravity = world.gravity_at(p.i);
+			p.velocity = p.velocity * p.drag - p.weight * gravity.strength * gravity.up();
+			(p.i, p.position) = settle(world, p.i, p.position + p.velocity);
+			p.life = p.life.saturating_sub(1);
+		}
+		self.list.retain(|p| p.life > 0 && world.transparent(p.i));
+	}
+}
@@ -0,0 +1,136 @@
+// Pathfinding over the neighbor graph: which voxels something can walk between, and the
+// shortest walk from one voxel to another. Steps follow the links rather than coordinates, so a
+// path takes the short way around the world whenever that's shorter, and gravity decides which
+// way is down for each step.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::collections::hash_map::Entry;
+use crate::{World, step};
+
+// what something walking needs of the voxels it walks through
+#[derive(Clone, Copy)]
+pub struct PathOptions {
+	// voxels of open space it stands in
+	pub height: usize,
+	// deepest drop it will walk off
+	pub max_drop: usize,
+	// most voxels to look at before giving up on finding a path
+	pub limit: usize,
+}
+
+impl PathOptions {
+	pub const MOB: PathOptions = PathOptions { height: 1, max_drop: 2, limit: 4096 };
+	pub const PLAYER: PathOptions = PathOptions { height: 2, max_drop: 3, limit: 100_000 };
+}
+
+// (axis, sign) for each way to walk, across gravity
+fn level_directions(world: &World, i: usize) -> [(usize, i32); 4] {
+	let axis = world.gravity_at(i).axis;
+	let [a, b] = [(axis + 1) % 3, (axis + 2) % 3];
+	[(a, 1), (a, -1), (b, 1), (b, -1)]
+}
+
+pub fn down(world: &World, i: usize) -> usize {
+	let g = world.gravity_at(i);
+	step(world, i, g.axis, g.sign)
+}
+
+pub fn up(world: &World, i: usize) -> usize {
+	let g = world.gravity_at(i);
+	step(world, i, g.axis, -g.sign)
+}
+
+// whether `height` voxels from i upward are open
+fn clear(world: &World, i: usize, height: usize) -> bool {
+	let mut i = i;
+	for _ in 0..height {
+		if !world.transparent(i) {
+			return false;
+		}
+		i = up(world, i);
+	}
+	true
+}
+
+// somewhere to stand with its feet in i: open and dry, with room overhead and ground under it
+pub fn standable(world: &World, i: usize, options: &PathOptions) -> bool {
+	clear(world, i, options.height) && !world.liquid(i) && !world.transparent(down(world, i))
+}
+
+// Voxels one step from standing in i, with how many voxels each climbs or drops: level, up onto
+// a ledge or down a drop.
+fn moves_with_rise(world: &World, i: usize, options: &PathOptions) -> Vec<(usize, usize)> {
+	let mut next = vec![];
+	for (axis, sign) in level_directions(world, i) {
+		let j = step(world, i, axis, sign);
+		if clear(world, j, options.height) {
+			let mut k = j;
+			for drop in 0..=options.max_drop {
+				if standable(world, k, options) {
+					next.push((k, drop));
+					break;
+				}
+				k = down(world, k);
+				if !world.transparent(k) {
+					break;
+				}
+			}
+		} else {
+			let ledge = up(world, j);
+			if standable(world, ledge, options) && clear(world, i, options.height + 1) {
+				next.push((ledge, 1));
+			}
+		}
+	}
+	next
+}
+
+// voxels something standing in i can walk to in one step
+pub fn moves(world: &World, i: usize, options: &PathOptions) -> Vec<usize> {
+	moves_with_rise(world, i, options).into_iter().map(|(j, _)| j).collect()
+}
+
+impl World {
+	// The shortest walk from standing in `from` to standing in `to`, as the voxels stepped into
+	// after `from`, ending with `to`. A step costs one plus the voxels it climbs or drops, which
+	// is never less than how much nearer it brings the walker counted along the axes, so A*
+	// guided by that distance the short way around the world finds the shortest walk. None if
+	// there's no walk within `options.limit` voxels looked at.
+	pub fn find_path(&self, from: usize, to: usize, options: &PathOptions) -> Option<Vec<usize>> {
+		let estimate = |i: usize| self.displacement(i, to).iter().map(|d| d.unsigned_abs() as usize).sum::<usize>();
+		// for each voxel reached, where it was reached from and the cost of getting there
+		let mut came_from = HashMap::from([(from, (from, 0))]);
+		let mut open = BinaryHeap::from([Reverse((estimate(from), 0, from))]);
+		while let Some(Reverse((_, cost, i))) = open.pop() {
+			if i == to {
+				let mut path = vec![];
+				let mut i = to;
+				while i != from {
+					path.push(i);
+					i = came_from[&i].0;
+				}
+				path.reverse();
+				return Some(path);
+			}
+			// already reached more cheaply since this was queued
+			if came_from[&i].1 < cost {
+				continue;
+			}
+			if came_from.len() > options.limit {
+				return None;
+			}
+			for (j, rise) in moves_with_rise(self, i, options) {
+				let through = cost + 1 + rise;
+				match came_from.entry(j) {
+					Entry::Occupied(e) if e.get().1 <= through => continue,
+					Entry::Occupied(mut e) => { e.insert((i, through)); },
+					Entry::Vacant(e) => { e.insert((i, through)); },
+				}
+				open.push(Reverse((through + estimate(j), through, j)));
+			}
+		}
+		None
+	}
+}
+
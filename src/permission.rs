@@ -0,0 +1,178 @@
+// What the players joining a hosted game may do to its world. The host checks every voxel a
+// joining game sends, so nobody can do more than their role allows by changing their own game.
+// Spectators can't change anything. Builders can change voxels within reach of where they are,
+// outside the protected boxes, and only so many a second. Admins can change anything.
+
+use std::collections::HashMap;
+use crate::TOUCH_DISTANCE;
+use crate::world::displacement;
+use crate::selection::Region;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Role {
+	Spectator,
+	Builder,
+	Admin,
+}
+
+pub const ROLES: [(Role, &str); 3] = [(Role::Spectator, "spectator"), (Role::Builder, "builder"), (Role::Admin, "admin")];
+
+impl Role {
+	pub fn parse(name: &str) -> Option<Role> {
+		ROLES.iter().find(|(_, n)| *n == name).map(|&(r, _)| r)
+	}
+
+	pub fn name(self) -> &'static str {
+		ROLES.iter().find(|&&(r, _)| r == self).map_or("?", |(_, n)| n)
+	}
+}
+
+// how far from where they are builders can change voxels, leaving room for the biggest brush
+const REACH: usize = TOUCH_DISTANCE + crate::brush::MAX_RADIUS;
+// voxels a builder can change a second, and all at once after a pause
+const RATE: f32 = 300.0;
+const BURST: f32 = 3000.0;
+
+pub struct Permissions {
+	// the role of players who haven't been given one
+	pub default: Role,
+	roles: HashMap<u32, Role>,
+	protected: Vec<Region>,
+	// voxels each builder can still change straight away, filling back up at RATE
+	allowance: HashMap<u32, f32>,
+}
+
+impl Permissions {
+	pub fn new(default: Role) -> Permissions {
+		Permissions { default, roles: HashMap::new(), protected: vec![], allowance: HashMap::new() }
+	}
+
+	pub fn role(&self, player: u32) -> Role {
+		self.roles.get(&player).copied().unwrap_or(self.default)
+	}
+
+	pub fn set_role(&mut self, player: u32, role: Role) {
+		self.roles.insert(player, role);
+	}
+
+	pub fn protect(&mut self, region: Region) {
+		self.protected.push(region);
+	}
+
+	// stop protecting every box, returning how many there were
+	pub fn unprotect(&mut self) -> usize {
+		std::mem::take(&mut self.protected).len()
+	}
+
+	// a tick passing, for the allowances to fill back up
+	pub fn tick(&mut self, seconds: f32) {
+		for allowance in self.allowance.values_mut() {
+			*allowance = (*allowance + RATE * seconds).min(BURST);
+		}
+	}
+
+	// Whether `player` may change `count` voxels at all, before any of them is looked at:
+	// spectators can't, and neither can builders without that much allowance left.
+	pub fn admits(&self, player: u32, count: usize) -> Result<(), &'static str> {
+		match self.role(player) {
+			Role::Admin => Ok(()),
+			Role::Spectator => Err("spectators can't change the world"),
+			Role::Builder if self.allowance.get(&player).copied().unwrap_or(BURST) < count as f32 => Err("slow down"),
+			Role::Builder => Ok(()),
+		}
+	}
+
+	// Whether `player`, in voxel `at` if it's known, may change voxel k of a world of `size`.
+	// Changing it uses up some of their allowance.
+	pub fn allows(&mut self, size: [usize; 3], player: u32, at: Option<usize>, k: usize) -> Result<(), &'static str> {
+		match self.role(player) {
+			Role::Admin => return Ok(()),
+			Role::Spectator => return Err("spectators can't change the world"),
+			Role::Builder => {},
+		}
+		if self.protected.iter().any(|region| region.contains(size, k)) {
+			return Err("that's protected");
+		}
+		let near = at.is_some_and(|i| displacement(size, i, k).iter().all(|&d| d.unsigned_abs() as usize <= REACH));
+		if !near {
+			return Err("that's out of reach");
+		}
+		let allowance = self.allowance.entry(player).or_insert(BURST);
+		if *allowance < 1.0 {
+			return Err("slow down");
+		}
+		*allowance -= 1.0;
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{World, furl, link_world, Progress};
+
+	// a world long enough along x to be out of reach, and voxel x along it
+	fn world() -> World {
+		link_world(128, 4, 4, &Progress::default())
+	}
+
+	fn along(x: usize) -> usize {
+		furl(x, 0, 0, 4, 4)
+	}
+
+	#[test]
+	fn each_role_may_do_its_own() {
+		let world = world();
+		let mut permissions = Permissions::new(Role::Spectator);
+		permissions.set_role(2, Role::Builder);
+		permissions.set_role(3, Role::Admin);
+		assert!(permissions.allows(world.size(), 1, Some(0), along(1)).is_err());
+		assert!(permissions.admits(1, 1).is_err());
+		assert!(permissions.allows(world.size(), 2, Some(0), along(1)).is_ok());
+		assert!(permissions.allows(world.size(), 2, None, along(1)).is_err());
+		assert!(permissions.allows(world.size(), 3, None, along(64)).is_ok());
+	}
+
+	#[test]
+	fn builders_reach_so_far_and_no_further() {
+		let world = world();
+		let mut permissions = Permissions::new(Role::Builder);
+		assert!(permissions.allows(world.size(), 1, Some(0), along(REACH)).is_ok());
+		assert!(permissions.allows(world.size(), 1, Some(0), along(REACH + 1)).is_err());
+		// the world wraps around, so reach goes back the other way too
+		assert!(permissions.allows(world.size(), 1, Some(0), along(128 - REACH)).is_ok());
+	}
+
+	#[test]
+	fn protected_boxes_keep_builders_out_but_not_admins() {
+		let world = world();
+		let mut permissions = Permissions::new(Role::Builder);
+		permissions.set_role(2, Role::Admin);
+		permissions.protect(Region::between(&world, along(2), along(4)));
+		assert!(permissions.allows(world.size(), 1, Some(0), along(3)).is_err());
+		assert!(permissions.allows(world.size(), 1, Some(0), along(5)).is_ok());
+		assert!(permissions.allows(world.size(), 2, Some(0), along(3)).is_ok());
+		assert_eq!(permissions.unprotect(), 1);
+		assert!(permissions.allows(world.size(), 1, Some(0), along(3)).is_ok());
+	}
+
+	#[test]
+	fn a_builder_runs_out_after_a_burst_and_gets_it_back() {
+		let world = world();
+		let mut permissions = Permissions::new(Role::Builder);
+		for _ in 0..BURST as usize {
+			assert!(permissions.allows(world.size(), 1, Some(0), along(1)).is_ok());
+		}
+		assert!(permissions.allows(world.size(), 1, Some(0), along(1)).is_err());
+		assert!(permissions.admits(1, 1).is_err());
+		// other builders have their own
+		assert!(permissions.allows(world.size(), 2, Some(0), along(1)).is_ok());
+		permissions.tick(1.0);
+		assert!(permissions.admits(1, RATE as usize).is_ok());
+		assert!(permissions.admits(1, RATE as usize + 1).is_err());
+		for _ in 0..RATE as usize {
+			assert!(permissions.allows(world.size(), 1, Some(0), along(1)).is_ok());
+		}
+		assert!(permissions.allows(world.size(), 1, Some(0), along(1)).is_err());
+	}
+}
@@ -0,0 +1,206 @@
+// The player's body: a box around the camera that can't pass through solid voxels. The box is
+// checked against voxels found by walking the neighbor graph from the camera's voxel, so it
+// collides the same way across seams of the world as anywhere else.
+
+use glam::*;
+use crate::{World, offset};
+#[cfg(feature = "graphics")]
+use crate::settle;
+use crate::gravity::Gravity;
+
+// A box standing along gravity, `width` across and `height` tall, held at a point `eye` above
+// its bottom (its end toward the ground). Other things that move through the world use boxes too.
+pub struct Shape {
+	pub width: f32,
+	pub height: f32,
+	pub eye: f32,
+}
+
+pub const PLAYER: Shape = Shape { width: 0.6, height: 1.8, eye: 1.6 };
+#[cfg(feature = "graphics")]
+pub const EYE_HEIGHT: f32 = PLAYER.eye;
+// gap kept between the box and anything it runs into
+const SKIN: f32 = 0.001;
+// tallest ledge climbed without jumping when stepping is on
+#[cfg(feature = "graphics")]
+const STEP_HEIGHT: f32 = 1.0;
+#[cfg(feature = "graphics")]
+pub const MAX_HEALTH: f32 = 20.0;
+// landing slower than this, in voxels per tick, does no harm (about a three voxel fall)
+#[cfg(feature = "graphics")]
+const SAFE_LANDING_SPEED: f32 = 0.26;
+// health lost per unit of landing speed above the safe one
+#[cfg(feature = "graphics")]
+const FALL_DAMAGE: f32 = 40.0;
+// longest move checked at once, so fast falls can't skip over a voxel
+const MAX_STEP: f32 = 0.5;
+
+#[cfg(feature = "graphics")]
+#[derive(Clone, Copy, PartialEq)]
+pub enum Life {
+	Alive,
+	// waiting to respawn since the given time
+	Dead { since: f64 },
+}
+
+// health lost landing at `speed` voxels per tick
+#[cfg(feature = "graphics")]
+pub fn fall_damage(speed: f32) -> f32 {
+	(speed - SAFE_LANDING_SPEED).max(0.0) * FALL_DAMAGE
+}
+
+// corners of the box held at `position`
+fn bounds(position: Vec3, gravity: &Gravity, shape: &Shape) -> (Vec3, Vec3) {
+	let (mut below, mut above) = (Vec3::splat(shape.width / 2.0), Vec3::splat(shape.width / 2.0));
+	let (feet, head) = (shape.eye, shape.height - shape.eye);
+	(below[gravity.axis], above[gravity.axis]) = if gravity.sign < 0 { (feet, head) } else { (head, feet) };
+	(position - below, position + above)
+}
+
+// the two axes across gravity
+fn level_axes(gravity: &Gravity) -> [usize; 2] {
+	[(gravity.axis + 1) % 3, (gravity.axis + 2) % 3]
+}
+
+// Voxels overlapping the box with their offsets from voxel i (the position is relative to i).
+fn overlaps(world: &World, i: usize, position: Vec3, shape: &Shape) -> Vec<([i32; 3], usize)> {
+	let (lo, hi) = bounds(position, &world.gravity_at(i), shape);
+	cells(world, i, lo, hi)
+}
+
+// voxels overlapping the box from `lo` to `hi`, relative to voxel i
+fn cells(world: &World, i: usize, lo: Vec3, hi: Vec3) -> Vec<([i32; 3], usize)> {
+	let (lo, hi) = (lo.floor(), (hi.ceil() - 1.0).max(lo.floor()));
+	let mut cells = vec![];
+	for x in lo.x as i32..=hi.x as i32 {
+		for y in lo.y as i32..=hi.y as i32 {
+			for z in lo.z as i32..=hi.z as i32 {
+				cells.push(([x, y, z], offset(world, i, [x, y, z])));
+			}
+		}
+	}
+	cells
+}
+
+fn solid_overlaps(world: &World, i: usize, position: Vec3, shape: &Shape) -> Vec<[i32; 3]> {
+	overlaps(world, i, position, shape).into_iter().filter(|&(_, j)| !world.transparent(j)).map(|(c, _)| c).collect()
+}
+
+// whether the box of the camera at `position` in voxel i is in anything solid
+pub fn inside(world: &World, i: usize, position: Vec3) -> bool {
+	!solid_overlaps(world, i, position, &PLAYER).is_empty()
+}
+
+// whether anything solid is right under the box
+#[cfg(feature = "graphics")]
+fn supported(world: &World, i: usize, position: Vec3) -> bool {
+	let gravity = world.gravity_at(i);
+	let (mut lo, mut hi) = bounds(position, &gravity, &PLAYER);
+	let a = gravity.axis;
+	let floor = if gravity.sign < 0 { lo[a] - 2.0 * SKIN } else { hi[a] + 2.0 * SKIN };
+	(lo[a], hi[a]) = (floor, floor);
+	cells(world, i, lo, hi).iter().any(|&(_, j)| !world.transparent(j))
+}
+
+// the voxel under the middle of the box of the camera at `position` in voxel i
+#[cfg(feature = "graphics")]
+pub fn ground(world: &World, i: usize, position: Vec3) -> usize {
+	let up = world.gravity_at(i).up();
+	settle(world, i, position - (PLAYER.eye + 2.0 * SKIN) * up).0
+}
+
+// whether voxel j is inside the box of the camera at `position` in voxel i, so that making it
+// solid would trap the player
+#[cfg(feature = "graphics")]
+pub fn occupies(world: &World, i: usize, position: Vec3, j: usize) -> bool {
+	overlaps(world, i, position, &PLAYER).iter().any(|&(_, k)| k == j)
+}
+
+// Move the camera at `position` in voxel i by `delta`, stopping against solid voxels. Returns
+// the new position, still relative to i, and whether the move was blocked along each axis.
+#[cfg(feature = "graphics")]
+pub fn move_body(world: &World, i: usize, position: Vec3, delta: Vec3) -> (Vec3, [bool; 3]) {
+	move_shape(world, i, position, delta, &PLAYER)
+}
+
+// Move a box of any shape like `move_body`, one axis at a time starting along gravity.
+pub fn move_shape(world: &World, i: usize, position: Vec3, delta: Vec3, shape: &Shape) -> (Vec3, [bool; 3]) {
+	let gravity = world.gravity_at(i);
+	let [a, b] = level_axes(&gravity);
+	let mut position = position;
+	let mut blocked = [false; 3];
+	for axis in [gravity.axis, a, b] {
+		if delta[axis] == 0.0 {
+			continue;
+		}
+		let steps = (delta[axis].abs() / MAX_STEP).ceil().max(1.0);
+		for _ in 0..steps as usize {
+			position[axis] += delta[axis] / steps;
+			let cells = solid_overlaps(world, i, position, shape);
+			if cells.is_empty() {
+				continue;
+			}
+			// back off to the face of the nearest voxel hit
+			let (lo, hi) = bounds(position, &gravity, shape);
+			let (lo, hi) = (position - lo, hi - position);
+			position[axis] = if delta[axis] > 0.0 {
+				cells.iter().map(|c| c[axis]).min().unwrap() as f32 - hi[axis] - SKIN
+			} else {
+				cells.iter().map(|c| c[axis]).max().unwrap() as f32 + 1.0 + lo[axis] + SKIN
+			};
+			blocked[axis] = true;
+			break;
+		}
+	}
+	(position, blocked)
+}
+
+// Like `move_body` for a player standing on the ground, but climbing onto ledges up to
+// STEP_HEIGHT high instead of stopping against them.
+#[cfg(feature = "graphics")]
+pub fn move_body_stepping(world: &World, i: usize, position: Vec3, delta: Vec3) -> (Vec3, [bool; 3]) {
+	let gravity = world.gravity_at(i);
+	let [a, b] = level_axes(&gravity);
+	let (moved, blocked) = move_body(world, i, position, delta);
+	if !(blocked[a] || blocked[b]) {
+		return (moved, blocked);
+	}
+	let up = gravity.up();
+	let (raised, rise) = move_body(world, i, position, STEP_HEIGHT * up);
+	if rise[gravity.axis] {
+		return (moved, blocked);
+	}
+	let (across, mut side) = move_body(world, i, raised, delta - delta.dot(up) * up);
+	let (landed, fall) = move_body(world, i, across, -STEP_HEIGHT * up);
+	let progress = |p: Vec3| {
+		let d = p - position;
+		(d - d.dot(up) * up).length()
+	};
+	if progress(landed) > progress(moved) + SKIN {
+		side[gravity.axis] = fall[gravity.axis];
+		(landed, side)
+	} else {
+		(moved, blocked)
+	}
+}
+
+// Like `move_body` for a sneaking player standing on the ground: moves across gravity that would
+// leave nothing under the box are dropped, so the player can't walk off an edge.
+#[cfg(feature = "graphics")]
+pub fn move_body_sneaking(world: &World, i: usize, position: Vec3, delta: Vec3) -> (Vec3, [bool; 3]) {
+	let [a, b] = level_axes(&world.gravity_at(i));
+	let (moved, blocked) = move_body(world, i, position, delta);
+	if supported(world, i, moved) {
+		return (moved, blocked);
+	}
+	let back = |axes: &[usize]| {
+		let mut p = moved;
+		for &a in axes {
+			p[a] = position[a];
+		}
+		p
+	};
+	// keep as much of the move as still has ground under it, sliding along the edge
+	let kept = [back(&[a]), back(&[b]), back(&[a, b])].into_iter().find(|&p| supported(world, i, p)).unwrap_or(back(&[a, b]));
+	(kept, blocked)
+}
@@ -0,0 +1,144 @@
+// Prefabs: multi-voxel structures from a small text schematic format, placed by walking the
+// neighbor graph so they wrap around the world's edges.
+//
+// A schematic gives its size, a palette of one-character block names, then one `layer` per y
+// from the bottom up. Each layer has one row per z and one character per x; the first row is
+// the front. `_` leaves the voxel as it was, `#` starts a comment.
+//
+//     size 3 1 2
+//     palette
+//     P planks
+//     layer
+//     PPP
+//     P_P
+
+use glam::*;
+use crate::{World, Voxel, step, offset};
+use crate::block::{self, BlockId};
+
+pub const HOUSE: &str = include_str!("../assets/prefabs/house.prefab");
+pub const RUIN: &str = include_str!("../assets/prefabs/ruin.prefab");
+
+#[derive(Clone)]
+pub struct Prefab {
+	pub size: [usize; 3],
+	// indexed [x][y][z] like `furl`; None leaves the world unchanged
+	blocks: Vec<Option<BlockId>>,
+}
+
+impl Prefab {
+	fn index(&self, x: usize, y: usize, z: usize) -> usize {
+		x*self.size[1]*self.size[2] + y*self.size[2] + z
+	}
+
+	#[cfg(feature = "graphics")]
+	pub fn load(path: &str) -> Result<Prefab, String> {
+		let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+		Prefab::parse(&text).map_err(|e| format!("{}: {}", path, e))
+	}
+
+	pub fn parse(text: &str) -> Result<Prefab, String> {
+		let mut lines = text.lines()
+			.enumerate()
+			.map(|(n, l)| (n + 1, l.split('#').next().unwrap_or("").trim()))
+			.filter(|(_, l)| !l.is_empty());
+
+		let (n, line) = lines.next().ok_or("empty schematic")?;
+		let words: Vec<&str> = line.split_whitespace().collect();
+		let size: Vec<usize> = match words.as_slice() {
+			["size", dims @ ..] if dims.len() == 3 => dims.iter().map(|d| d.parse().map_err(|_| format!("line {}: bad size", n))).collect::<Result<_, _>>()?,
+			_ => return Err(format!("line {}: expected 'size x y z'", n)),
+		};
+		let size = [size[0], size[1], size[2]];
+		let mut prefab = Prefab { size, blocks: vec![None; size[0]*size[1]*size[2]] };
+
+		match lines.next() {
+			Some((_, "palette")) => {},
+			Some((n, _)) => return Err(format!("line {}: expected 'palette'", n)),
+			None => return Err("missing palette".to_string()),
+		}
+		let mut palette: Vec<(char, BlockId)> = vec![];
+		let mut line = lines.next();
+		while let Some((n, l)) = line {
+			if l == "layer" {
+				break;
+			}
+			let mut parts = l.split_whitespace();
+			let (key, name) = (parts.next().and_then(|k| k.chars().next()), parts.next());
+			match (key, name.and_then(block::by_name)) {
+				(Some(key), Some(id)) => palette.push((key, id)),
+				_ => return Err(format!("line {}: expected a character and a block name", n)),
+			}
+			line = lines.next();
+		}
+
+		for y in 0..size[1] {
+			match line {
+				Some((_, "layer")) => {},
+				Some((n, _)) => return Err(format!("line {}: expected 'layer'", n)),
+				None => return Err(format!("missing layer {}", y)),
+			}
+			for z in 0..size[2] {
+				let (n, row) = lines.next().ok_or(format!("layer {} is missing rows", y))?;
+				let row: Vec<char> = row.chars().collect();
+				if row.len() != size[0] {
+					return Err(format!("line {}: expected {} characters", n, size[0]));
+				}
+				for (x, c) in row.into_iter().enumerate() {
+					let k = prefab.index(x, y, z);
+					prefab.blocks[k] = match c {
+						'_' => None,
+						c => Some(palette.iter().find(|p| p.0 == c).ok_or(format!("line {}: '{}' is not in the palette", n, c))?.1),
+					};
+				}
+			}
+			line = lines.next();
+		}
+		Ok(prefab)
+	}
+
+	// turned a quarter turn about +y, taking the +x direction to +z
+	pub fn rotated(&self) -> Prefab {
+		let [sx, sy, sz] = self.size;
+		let mut turned = Prefab { size: [sz, sy, sx], blocks: vec![None; self.blocks.len()] };
+		for x in 0..sx {
+			for y in 0..sy {
+				for z in 0..sz {
+					let k = turned.index(sz - 1 - z, y, x);
+					turned.blocks[k] = self.blocks[self.index(x, y, z)];
+				}
+			}
+		}
+		turned
+	}
+
+	// turned so that its front faces back along the horizontal direction `look`
+	#[cfg(feature = "graphics")]
+	pub fn facing(&self, look: Vec3) -> Prefab {
+		let turns = if look.x.abs() > look.z.abs() {
+			if look.x > 0.0 { 3 } else { 1 }
+		} else if look.z > 0.0 { 0 } else { 2 };
+		(0..turns).fold(self.clone(), |p, _| p.rotated())
+	}
+
+	// place with the bottom of the footprint centered on voxel `origin`
+	pub fn place(&self, world: &mut World, origin: usize) {
+		let [sx, sy, sz] = self.size;
+		let corner = offset(world, origin, [-(sx as i32 / 2), 0, -(sz as i32 / 2)]);
+		let mut i_x = corner;
+		for x in 0..sx {
+			let mut i_y = i_x;
+			for y in 0..sy {
+				let mut i = i_y;
+				for z in 0..sz {
+					if let Some(b) = self.blocks[self.index(x, y, z)] {
+						world.set(i, Voxel::of(b));
+					}
+					i = step(world, i, 2, 1);
+				}
+				i_y = step(world, i_y, 1, 1);
+			}
+			i_x = step(world, i_x, 0, 1);
+		}
+	}
+}
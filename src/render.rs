@@ -0,0 +1,632 @@
+// The ray renderer runs on its own thread. The main thread hands it a snapshot of the world
+// with the camera whenever it is free and keeps showing the last finished frame in the meantime,
+// so a slow frame never holds up input or editing.
+
+use std::sync::mpsc::{self, Receiver, Sender};
+use macroquad::prelude::*;
+use rayon::prelude::*;
+use crate::{World, furl, raycast_liquid, shade, glows, step};
+use crate::{animation, block, weather};
+use crate::cloud::Layer;
+use crate::dirty::Dirty;
+use crate::entity::Entity;
+use crate::light::Light;
+use crate::packet::{Hit, LANES, raycast_packet};
+use crate::rng::Rng;
+use crate::particle::Particle;
+use crate::selection::Region;
+use crate::world::CHUNK;
+
+// fraction of light getting through each voxel of water is about exp(-WATER_ABSORPTION)
+const WATER_ABSORPTION: f32 = 0.15;
+// squares across a face that crack one by one while it's being broken, and how dark they go
+const CRACK_GRID: f32 = 6.0;
+const CRACK_SHADE: f32 = 0.45;
+// how much of the darkening with distance applies to voxels that give off light
+const GLOW_FADE: f32 = 0.25;
+// color selected voxels are tinted toward, and how far
+const SELECTION_COLOR: Vec4 = vec4(0.45, 0.75, 1.0, 1.0);
+const SELECTION_TINT: f32 = 0.35;
+// pixels along each side of the squares the screen is drawn in, one task each
+const TILE: usize = 16;
+// furthest the camera can move between frames, in voxels, for the last frame's rays to say
+// how far this frame's can skip, and how far short of where they went clear of anything they do
+const RESUME_MOVE: f32 = 0.25;
+const RESUME_MARGIN: f32 = 2.0;
+// voxels every ray steps through from the camera before skipping ahead, which also keeps what
+// it skips far enough off that the camera moving shifts it by only a few pixels
+const NEAR_STEPS: usize = 16;
+// least a ray goes in NEAR_STEPS voxels, stepping along the diagonal
+const NEAR: f32 = (NEAR_STEPS - 3) as f32 / 1.7321;
+// least worth skipping, and most pixels to look around for the last frame's rays
+const RESUME_MIN: f32 = 8.0;
+const RESUME_REACH: usize = 8;
+
+// color and distance of each pixel, indexed [x][y]
+pub type Screen = Vec<Vec<(Vec4, f32)>>;
+
+// a finished frame, with how much work it took
+pub struct Frame {
+	pub screen: Screen,
+	pub rays: usize,
+	// voxels stepped through by all the rays together
+	pub steps: usize,
+}
+
+// everything needed to draw one frame
+pub struct View {
+	pub world: World,
+	// chunks with voxels set since the last view handed to the renderer
+	pub dirty: Dirty,
+	pub i: usize,
+	pub position: Vec3,
+	pub look: Vec3,
+	pub right: Vec3,
+	pub up: Vec3,
+	pub fov: (f32, f32),
+	pub screen: (usize, usize),
+	pub view_distance: usize,
+	// voxel the player is pointing at, drawn highlighted
+	pub target: usize,
+	// how far along breaking the target is, from 0 to 1, shown by cracks spreading over it
+	pub breaking: f32,
+	// box of voxels selected for editing, drawn tinted
+	pub selection: Option<Region>,
+	// solid voxels each ray passes through before stopping, to see inside things
+	pub xray: usize,
+	// color things fade into with distance
+	pub ambient: Vec4,
+	pub entities: Vec<Entity>,
+	pub particles: Vec<Particle>,
+	pub clouds: Option<Layer>,
+	// the world's baked light, if it's lit
+	pub light: Option<Light>,
+	// seconds the world has run, for animated blocks
+	pub time: f32,
+}
+
+// where the rays kept in a `Cache` were cast from
+struct Eye {
+	i: usize,
+	position: Vec3,
+	look: Vec3,
+	right: Vec3,
+	up: Vec3,
+	fov: (f32, f32),
+	screen: (usize, usize),
+}
+
+// How far the last frame's rays went through open air, kept between frames. While the camera
+// only moves a little, a ray needn't step through all the air on its way to things far off:
+// once it's clear of the camera, it skips to a little short of where the rays last frame around
+// where it points all went clear of everything. Only worlds that are a plain lattice, where the
+// voxel it skips to follows from coordinates. Rays that went through chunks with voxels set
+// since are forgotten, and the rest kept.
+#[derive(Default)]
+pub struct Cache {
+	eye: Option<Eye>,
+	// indexed [x][y] like the screen
+	clear: Vec<Vec<f32>>,
+}
+
+// what's kept from the last frame, worked out for skipping ahead this frame
+struct Resume<'a> {
+	eye: &'a Eye,
+	// how far the rays within reach of each pixel all went clear
+	clear: Vec<Vec<f32>>,
+	moved: f32,
+}
+
+impl Eye {
+	fn of(view: &View) -> Eye {
+		Eye { i: view.i, position: view.position, look: view.look, right: view.right, up: view.up, fov: view.fov, screen: view.screen }
+	}
+
+	fn ray(&self, i: usize, j: usize) -> Vec3 {
+		ray_through(self.look, self.right, self.up, self.fov, self.screen, i, j)
+	}
+}
+
+impl Cache {
+	// forget the rays that went clear through any of the chunks marked in `dirty` in `world`
+	fn forget(&mut self, world: &World, dirty: &Dirty) {
+		if dirty.is_empty() {
+			return;
+		}
+		let Some(eye) = self.eye.as_ref().filter(|_| !dirty.everything()) else {
+			self.eye = None;
+			return;
+		};
+		let [_, ny, nz] = world.size();
+		let camera = Vec3::from([eye.i / (ny*nz), eye.i / nz % ny, eye.i % nz].map(|n| n as f32)) + eye.position;
+		self.clear.par_iter_mut().enumerate().for_each(|(x, column)| {
+			for (y, clear) in column.iter_mut().enumerate() {
+				if *clear > 0.0 && crosses(world, dirty, camera, eye.ray(x, y), *clear) {
+					*clear = 0.0;
+				}
+			}
+		});
+	}
+
+	// what's kept, if it can be used for `view`
+	fn resume(&self, view: &View) -> Option<Resume<'_>> {
+		let eye = self.eye.as_ref()?;
+		if !view.world.lattice() || eye.screen != view.screen {
+			return None;
+		}
+		let moved = view.world.direction(eye.i, eye.position, view.i, view.position).length();
+		if moved > RESUME_MOVE {
+			return None;
+		}
+		// pixels that anything past NEAR can have shifted by, as the last frame saw it, with
+		// some to spare for pixels being narrower toward the edges of the screen
+		let reach = |size: usize, fov: f32| 1 + (1.5 * moved / NEAR * size as f32 / fov).ceil() as usize;
+		let (rx, ry) = (reach(eye.screen.0, eye.fov.0), reach(eye.screen.1, eye.fov.1));
+		if rx.max(ry) > RESUME_REACH {
+			return None;
+		}
+		// the least within reach, across then down, with nothing known past the edges
+		let (w, h) = eye.screen;
+		let least = |r: usize, n: usize, k: usize, at: &dyn Fn(usize) -> f32| {
+			if k < r || k + r >= n { 0.0 } else { (k - r..=k + r).map(at).fold(f32::INFINITY, f32::min) }
+		};
+		let across: Vec<Vec<f32>> = (0..w).into_par_iter().map(|x| (0..h).map(|y| least(rx, w, x, &|u| self.clear[u][y])).collect()).collect();
+		let clear = (0..w).into_par_iter().map(|x| (0..h).map(|y| least(ry, h, y, &|v| across[x][v])).collect()).collect();
+		Some(Resume { eye, clear, moved })
+	}
+}
+
+// Whether `ray`, going `length` from `start` in world coordinates, passes through any chunk
+// marked in `dirty`, going from chunk to chunk.
+fn crosses(world: &World, dirty: &Dirty, start: Vec3, ray: Vec3, length: f32) -> bool {
+	let (size, grid) = (world.size(), world.grid());
+	let ray = ray.normalize();
+	let (mut p, mut gone) = (start, 0.0);
+	loop {
+		let cell = [0, 1, 2].map(|k| (p[k].floor() as i64).rem_euclid(size[k] as i64) as usize);
+		let chunk = cell.map(|n| n / CHUNK);
+		if dirty.contains((chunk[0]*grid[1] + chunk[1])*grid[2] + chunk[2]) {
+			return true;
+		}
+		// how far to where it leaves the chunk, which can be cut short at the far edges of the
+		// world, and a little over so it's into the next
+		let out = [0, 1, 2].map(|k| {
+			let corner = chunk[k] * CHUNK;
+			let into = cell[k] - corner;
+			let inside = into as f32 + p[k] - p[k].floor();
+			let extent = CHUNK.min(size[k] - corner) as f32;
+			if ray[k] > 0.0 { (extent - inside) / ray[k] } else if ray[k] < 0.0 { -inside / ray[k] } else { f32::INFINITY }
+		});
+		let t = out[0].min(out[1]).min(out[2]) + 1e-3;
+		gone += t;
+		if gone >= length {
+			return false;
+		}
+		p += t * ray;
+	}
+}
+
+impl Resume<'_> {
+	// how far along `ray` from the camera it can skip to
+	fn ahead(&self, ray: Vec3) -> f32 {
+		let eye = self.eye;
+		let depth = ray.dot(eye.look);
+		if depth <= 0.0 {
+			return 0.0;
+		}
+		// the pixel it would have been last frame, as `project` finds it
+		let x = ((ray.dot(eye.right) / depth).tan() / eye.fov.0 + 0.5) * eye.screen.0 as f32;
+		let y = ((-ray.dot(eye.up) / depth).tan() / eye.fov.1 + 0.5) * eye.screen.1 as f32;
+		let (x, y) = (x.round(), y.round());
+		if x < 0.0 || y < 0.0 || x >= eye.screen.0 as f32 || y >= eye.screen.1 as f32 {
+			return 0.0;
+		}
+		self.clear[x as usize][y as usize] - self.moved - RESUME_MARGIN
+	}
+}
+
+pub struct Renderer {
+	views: Sender<View>,
+	frames: Receiver<Frame>,
+	busy: bool,
+}
+
+impl Renderer {
+	pub fn spawn() -> Renderer {
+		let (views, view_rx) = mpsc::channel::<View>();
+		let (frame_tx, frames) = mpsc::channel();
+		std::thread::spawn(move || {
+			let mut cache = Cache::default();
+			for view in view_rx {
+				if frame_tx.send(render(&view, &mut cache)).is_err() {
+					break;
+				}
+			}
+		});
+		Renderer { views, frames, busy: false }
+	}
+
+	// Start drawing the view made by `view` unless the last one is still being drawn, in which
+	// case no snapshot is taken.
+	pub fn submit(&mut self, view: impl FnOnce() -> View) {
+		if !self.busy {
+			self.busy = self.views.send(view()).is_ok();
+		}
+	}
+
+	// the frame finished since the last call, if any
+	pub fn finished(&mut self) -> Option<Frame> {
+		let frame = self.frames.try_recv().ok();
+		if frame.is_some() {
+			self.busy = false;
+		}
+		frame
+	}
+}
+
+// the pixels of a screen indexed [x][y] cut into tiles, each a column of pixels for every x
+// across it, with where it starts
+fn tiles<T>(screen: &mut [Vec<T>], rows: usize) -> Vec<(usize, usize, Vec<&mut [T]>)> {
+	let mut tiles = vec![];
+	for (u, strip) in screen.chunks_mut(TILE).enumerate() {
+		let mut columns: Vec<_> = strip.iter_mut().map(|column| column.chunks_mut(TILE)).collect();
+		for v in 0..rows.div_ceil(TILE) {
+			let tile: Vec<&mut [T]> = columns.iter_mut().filter_map(|c| c.next()).collect();
+			tiles.push((u*TILE, v*TILE, tile));
+		}
+	}
+	tiles
+}
+
+pub fn render(view: &View, cache: &mut Cache) -> Frame {
+	let mut screen = vec![vec![(vec4(0.0, 0.0, 0.0, 0.0), 0.0); view.screen.1]; view.screen.0];
+	let mut clear = vec![vec![0.0; view.screen.1]; view.screen.0];
+	cache.forget(&view.world, &view.dirty);
+	let resume = cache.resume(view);
+	// the tiles drawn in parallel, the pixels in each one after another, a packet down each
+	// column at a time with the last one filled out with the last pixel
+	let tiles = tiles(&mut screen, view.screen.1).into_iter().zip(tiles(&mut clear, view.screen.1));
+	let steps = tiles.collect::<Vec<_>>().into_par_iter().map(|((x, y, mut tile), (_, _, mut clear))| {
+		let mut steps = 0;
+		for (di, (column, clear)) in tile.iter_mut().zip(clear.iter_mut()).enumerate() {
+			let last = y + column.len() - 1;
+			for (n, (pixels, clear)) in column.chunks_mut(LANES).zip(clear.chunks_mut(LANES)).enumerate() {
+				let rays = std::array::from_fn(|lane| pixel_ray(view, x + di, (y + n*LANES + lane).min(last)));
+				let (hits, went) = cast(view, rays, resume.as_ref());
+				for (((pixel, clear), ray), ((rayhit_i, hit, distance, wet, s), went)) in pixels.iter_mut().zip(clear).zip(rays).zip(hits.into_iter().zip(went)) {
+					*pixel = (color(view, ray, rayhit_i, hit, distance, wet), distance);
+					*clear = went;
+					steps += s;
+				}
+			}
+		}
+		steps
+	}).sum();
+	drop(resume);
+	cache.eye = Some(Eye::of(view));
+	cache.clear = clear;
+	draw_entities(view, &mut screen);
+	draw_particles(view, &mut screen);
+	draw_clouds(view, &mut screen);
+	Frame { screen, rays: view.screen.0 * view.screen.1, steps }
+}
+
+// the color of a pixel whose `ray` stopped at `hit` in voxel i, `distance` away after going
+// `wet` of it through water
+fn color(view: &View, ray: Vec3, rayhit_i: usize, hit: Vec3, distance: f32, wet: f32) -> Vec4 {
+	let water = block::def(block::WATER);
+	let mut fade = 1.7321 * distance / (view.view_distance as f32);
+	if glows(&view.world, rayhit_i) {
+		fade *= GLOW_FADE;
+	}
+	if rayhit_i == view.target {
+		fade = 0.5*(fade + 1.0);
+	}
+	let color = animation::animate(&view.world, rayhit_i, hit, shade(&view.world, rayhit_i), view.time);
+	let mut color = weather::snowed(&view.world, rayhit_i, hit, color);
+	if let Some(light) = view.light.as_ref().filter(|_| !glows(&view.world, rayhit_i)) {
+		let lit = light.brightness(&view.world, front(&view.world, rayhit_i, hit, ray));
+		color = (color * lit).with_w(color.w);
+	}
+	if rayhit_i == view.target && cracked(hit, view.breaking) {
+		color *= CRACK_SHADE;
+	}
+	if view.selection.is_some_and(|r| r.contains(view.world.size(), rayhit_i)) {
+		color = color.lerp(SELECTION_COLOR, SELECTION_TINT);
+	}
+	let water = water.animation.color(water.color, animation::phase(rayhit_i, hit), view.time);
+	let color = color.with_w(1.0).lerp(water, 1.0 - (-WATER_ABSORPTION * wet).exp());
+	fade*view.ambient + (1.0 - fade)*color
+}
+
+// Cast a packet of rays from the camera, each going on through the first `view.xray` solid
+// voxels it meets, and how far each went through open air before its first. They're cast
+// together where the world is a plain lattice, skipping ahead with what's kept from the last
+// frame in `resume` where there is any.
+fn cast(view: &View, rays: [Vec3; LANES], resume: Option<&Resume>) -> ([Hit; LANES], [f32; LANES]) {
+	let first = if !view.world.lattice() {
+		rays.map(|ray| raycast_liquid(&view.world, view.i, view.position, ray, view.view_distance))
+	} else if let Some(resume) = resume {
+		// the first NEAR_STEPS voxels from the camera, then the rest of the way for the rays that
+		// haven't hit anything, from further on if there's far enough to skip
+		let near = view.view_distance.min(NEAR_STEPS);
+		let first = raycast_packet(&view.world, [(view.i, view.position); LANES], rays, [near; LANES]);
+		let size = view.world.size();
+		let [_, ny, nz] = size;
+		let camera = Vec3::from([view.i / (ny*nz), view.i / nz % ny, view.i % nz].map(|n| n as f32)) + view.position;
+		let mut starts = first.map(|(i, at, ..)| (i, at));
+		let mut max_steps = [0; LANES];
+		// how far along each starts
+		let mut from = first.map(|(_, _, distance, ..)| distance);
+		for lane in 0..LANES {
+			let (i, _, distance, wet, steps) = first[lane];
+			if !view.world.transparent(i) {
+				continue;
+			}
+			max_steps[lane] = view.view_distance - steps;
+			let ahead = resume.ahead(rays[lane]);
+			if wet > 0.0 || distance < NEAR || ahead < distance + RESUME_MIN {
+				continue;
+			}
+			let at = camera + ahead * rays[lane].normalize();
+			let cell = at.floor();
+			let [x, y, z] = [0, 1, 2].map(|k| (cell[k] as i64).rem_euclid(size[k] as i64) as usize);
+			let j = furl(x, y, z, ny, nz);
+			// one voxel for each face crossed on the way from the camera
+			let crossed = (cell - camera.floor()).abs().element_sum() as usize;
+			if view.world.transparent(j) && crossed < view.view_distance {
+				starts[lane] = (j, at - cell);
+				max_steps[lane] = view.view_distance - crossed;
+				from[lane] = ahead;
+			}
+		}
+		let rest = raycast_packet(&view.world, starts, rays, max_steps);
+		std::array::from_fn(|lane| {
+			if max_steps[lane] == 0 {
+				return first[lane];
+			}
+			let (_, _, _, wet, steps) = first[lane];
+			let (i, hit, distance, w, s) = rest[lane];
+			(i, hit, from[lane] + distance, wet + w, steps + s)
+		})
+	} else {
+		raycast_packet(&view.world, [(view.i, view.position); LANES], rays, [view.view_distance; LANES])
+	};
+	// how far each went before anything but air, for the next frame to skip
+	let went = first.map(|(_, _, distance, wet, _)| if wet > 0.0 { 0.0 } else { distance });
+	// rays that didn't hit anything come out as far off as `raycast_liquid` has them
+	let first = first.map(|(i, hit, distance, wet, steps)| {
+		(i, hit, if view.world.transparent(i) { view.view_distance as f32 } else { distance }, wet, steps)
+	});
+	let hits = std::array::from_fn(|lane| {
+		let (mut i, mut hit, mut distance, mut wet, mut steps) = first[lane];
+		for _ in 0..view.xray {
+			let left = view.view_distance.saturating_sub(distance as usize);
+			if left == 0 {
+				break;
+			}
+			let (j, h, d, w, s) = raycast_liquid(&view.world, i, hit, rays[lane], left);
+			(i, hit, distance, wet, steps) = (j, h, distance + d, wet + w, steps + s);
+		}
+		(i, hit, distance, wet, steps)
+	});
+	(hits, went)
+}
+
+// The voxel in front of the face of voxel i that `ray` hit at `hit`, which is what lights it, or
+// voxel i itself if the ray stopped in the open.
+fn front(world: &World, i: usize, hit: Vec3, ray: Vec3) -> usize {
+	if world.transparent(i) {
+		return i;
+	}
+	for axis in 0..3 {
+		if ray[axis] > 0.0 && hit[axis] < 1e-3 {
+			return step(world, i, axis, -1);
+		}
+		if ray[axis] < 0.0 && hit[axis] > 1.0 - 1e-3 {
+			return step(world, i, axis, 1);
+		}
+	}
+	i
+}
+
+// Whether the point `hit` on the face of a voxel is cracked when breaking it is `progress` of
+// the way along. The face is split into a grid and each square cracks at its own point.
+fn cracked(hit: Vec3, progress: f32) -> bool {
+	if progress <= 0.0 {
+		return false;
+	}
+	let square = (hit * CRACK_GRID).floor().clamp(Vec3::ZERO, Vec3::splat(CRACK_GRID - 1.0));
+	let mut rng = Rng::new((square.x as u64) << 16 | (square.y as u64) << 8 | square.z as u64);
+	rng.f32() < progress
+}
+
+// direction of the ray through pixel (i, j)
+fn pixel_ray(view: &View, i: usize, j: usize) -> Vec3 {
+	ray_through(view.look, view.right, view.up, view.fov, view.screen, i, j)
+}
+
+// the ray through pixel (i, j) of `screen` pixels spanning `fov`, from a camera looking along
+// `look`, with `right` and `up` across and up the screen
+fn ray_through(look: Vec3, right: Vec3, up: Vec3, fov: (f32, f32), screen: (usize, usize), i: usize, j: usize) -> Vec3 {
+	let right_coeff = (((i as f32) / (screen.0 as f32) - 0.5) * fov.0).atan();
+	let up_coeff = (((j as f32) / (screen.1 as f32) - 0.5) * fov.1).atan();
+	look + right_coeff*right - up_coeff*up
+}
+
+// pixel column and row that a point at `p` from the camera, in front of it, lands on
+fn project(view: &View, p: Vec3) -> (f32, f32) {
+	let depth = p.dot(view.look);
+	let right_coeff = p.dot(view.right) / depth;
+	let up_coeff = -p.dot(view.up) / depth;
+	((right_coeff.tan() / view.fov.0 + 0.5) * view.screen.0 as f32, (up_coeff.tan() / view.fov.1 + 0.5) * view.screen.1 as f32)
+}
+
+// Draw each entity as a disc facing the camera over the pixels whose ray reaches it before the
+// voxel the ray hit, using the distances the raycaster left in the screen.
+fn draw_entities(view: &View, screen: &mut Screen) {
+	for entity in &view.entities {
+		let d = view.world.displacement(view.i, entity.i);
+		let p = vec3(d[0] as f32, d[1] as f32, d[2] as f32) + entity.position - view.position;
+		let r = entity.radius();
+		if p.dot(view.look) <= r || p.length() > view.view_distance as f32 {
+			continue;
+		}
+		let (left, top) = project(view, p - r*view.right + r*view.up);
+		let (right, bottom) = project(view, p + r*view.right - r*view.up);
+		let columns = (left.floor().max(0.0) as usize)..(right.ceil().max(0.0) as usize).min(view.screen.0);
+		let rows = (top.floor().max(0.0) as usize)..(bottom.ceil().max(0.0) as usize).min(view.screen.1);
+		for i in columns {
+			for j in rows.clone() {
+				let ray = pixel_ray(view, i, j).normalize();
+				// distance along the ray to where it passes the disc
+				let along = p.dot(ray);
+				if (p - along*ray).length_squared() > r*r || along > screen[i][j].1 {
+					continue;
+				}
+				let fade = 1.7321 * along / (view.view_distance as f32);
+				screen[i][j] = (fade*view.ambient + (1.0 - fade)*entity.color(), along);
+			}
+		}
+	}
+}
+
+// Draw each particle as a small square facing the camera, blended over the pixels it covers
+// that are further away.
+fn draw_particles(view: &View, screen: &mut Screen) {
+	for particle in &view.particles {
+		let d = view.world.displacement(view.i, particle.i);
+		let p = vec3(d[0] as f32, d[1] as f32, d[2] as f32) + particle.position - view.position;
+		let r = particle.size;
+		let distance = p.length();
+		if p.dot(view.look) <= r || distance > view.view_distance as f32 {
+			continue;
+		}
+		let (left, top) = project(view, p - r*view.right + r*view.up);
+		let (right, bottom) = project(view, p + r*view.right - r*view.up);
+		let columns = (left.floor().max(0.0) as usize)..(right.ceil().max(0.0) as usize).min(view.screen.0);
+		let rows = (top.floor().max(0.0) as usize)..(bottom.ceil().max(0.0) as usize).min(view.screen.1);
+		let fade = 1.7321 * distance / (view.view_distance as f32);
+		let color = fade*view.ambient + (1.0 - fade)*particle.color;
+		for i in columns {
+			for j in rows.clone() {
+				if distance < screen[i][j].1 {
+					screen[i][j].0 = screen[i][j].0.lerp(color, particle.opacity());
+				}
+			}
+		}
+	}
+}
+
+// Draw the clouds over the pixels whose ray crosses their layer before what it hit, the
+// furthest crossing first so nearer clouds cover it.
+fn draw_clouds(view: &View, screen: &mut Screen) {
+	let Some(layer) = &view.clouds else { return };
+	// beyond this they've faded into the distance
+	let furthest = view.view_distance as f32 / 1.7321;
+	screen.par_iter_mut().enumerate().for_each(|(i, screen_i)| {
+		for (j, (color, distance)) in screen_i.iter_mut().enumerate() {
+			let ray = pixel_ray(view, i, j).normalize();
+			for &(along, density) in layer.crossings(ray, distance.min(furthest)).iter().rev() {
+				let fade = 1.7321 * along / (view.view_distance as f32);
+				*color = color.lerp(fade*view.ambient + (1.0 - fade)*layer.color, density);
+			}
+		}
+	});
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{Progress, Voxel, link_world};
+
+	fn view(world: &World, dirty: Dirty) -> View {
+		let [_, ny, nz] = world.size();
+		View {
+			world: world.clone(),
+			dirty,
+			i: furl(8, 32, 32, ny, nz),
+			position: Vec3::splat(0.5),
+			look: Vec3::X,
+			right: Vec3::Z,
+			up: Vec3::Y,
+			fov: (1.0, 1.0),
+			screen: (16, 16),
+			view_distance: 64,
+			target: usize::MAX,
+			breaking: 0.0,
+			selection: None,
+			xray: 0,
+			ambient: Vec4::ZERO,
+			entities: vec![],
+			particles: vec![],
+			clouds: None,
+			light: None,
+			time: 0.0,
+		}
+	}
+
+	#[test]
+	fn an_edit_off_the_rays_keeps_how_far_they_went_clear() {
+		let mut world = link_world(64, 64, 64, &Progress::default());
+		let [_, ny, nz] = world.size();
+		for y in 0..ny {
+			for z in 0..nz {
+				world.set(furl(40, y, z, ny, nz), Voxel::of(block::STONE));
+			}
+		}
+		let mut cache = Cache::default();
+		let dirty = world.take_dirty();
+		render(&view(&world, dirty), &mut cache);
+		let kept = cache.clear.clone();
+		assert!(kept[8][8] > 30.0);
+		// behind the camera, out of sight
+		world.set(furl(2, 32, 60, ny, nz), Voxel::of(block::STONE));
+		let dirty = world.take_dirty();
+		cache.forget(&world, &dirty);
+		assert!(cache.clear == kept);
+		// in the way of the middle of the screen
+		world.set(furl(24, 32, 32, ny, nz), Voxel::of(block::STONE));
+		let dirty = world.take_dirty();
+		cache.forget(&world, &dirty);
+		assert_eq!(cache.clear[8][8], 0.0);
+		assert_eq!(cache.clear[0][0], kept[0][0]);
+	}
+
+	// a patch of stone at x = 32 around the middle of the screen, past what's stepped through
+	// before skipping ahead but short of the wall that's kept as how far the rays went clear
+	fn patch(world: &mut World) {
+		let [_, ny, nz] = world.size();
+		for y in 28..37 {
+			for z in 28..37 {
+				world.set(furl(32, y, z, ny, nz), Voxel::of(block::STONE));
+			}
+		}
+	}
+
+	#[test]
+	fn an_edit_on_a_ray_is_seen_the_next_frame() {
+		let mut world = link_world(64, 64, 64, &Progress::default());
+		let [_, ny, nz] = world.size();
+		for y in 0..ny {
+			for z in 0..nz {
+				world.set(furl(40, y, z, ny, nz), Voxel::of(block::STONE));
+			}
+		}
+		let (mut told, mut untold) = (Cache::default(), Cache::default());
+		let dirty = world.take_dirty();
+		render(&view(&world, dirty.clone()), &mut told);
+		render(&view(&world, dirty), &mut untold);
+		let far = told.clear[8][8];
+		assert!(far > 30.0);
+		patch(&mut world);
+		let dirty = world.take_dirty();
+		// the ray through the middle is forgotten, and goes as far as the patch
+		let frame = render(&view(&world, dirty), &mut told);
+		assert!(frame.screen[8][8].1 < 25.0);
+		assert!(told.clear[8][8] < 25.0);
+		// one not told of the edit skips to where it went clear last frame, past the patch
+		let frame = render(&view(&world, Dirty::default()), &mut untold);
+		assert!(frame.screen[8][8].1 > 30.0);
+	}
+}
@@ -0,0 +1,37 @@
+// Seedable pseudorandom numbers (SplitMix64), so a seed always reproduces the same world
+
+pub struct Rng {
+	state: u64,
+}
+
+impl Rng {
+	pub fn new(seed: u64) -> Rng {
+		Rng { state: seed }
+	}
+
+	pub fn next_u64(&mut self) -> u64 {
+		self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+		let mut z = self.state;
+		z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+		z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+		z ^ (z >> 31)
+	}
+
+	// uniform in [0, 1)
+	pub fn f32(&mut self) -> f32 {
+		(self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+	}
+
+	// uniform in [a, b)
+	pub fn range(&mut self, a: f32, b: f32) -> f32 {
+		a + (b - a) * self.f32()
+	}
+}
+
+// a seed for when none was given on the command line
+pub fn random_seed() -> u64 {
+	std::time::SystemTime::now()
+		.duration_since(std::time::UNIX_EPOCH)
+		.map(|d| d.as_nanos() as u64)
+		.unwrap_or(0)
+}
@@ -0,0 +1,132 @@
+// Worlds kept between runs of the dedicated server. A world is made again from its seed, so all
+// that's kept is which world it is and the voxels edited since it was made, as the lines games
+// playing together send each other, numbered in the whole world if it's streamed:
+//
+//     world <version> <seed> <x> <y> <z> <recipe>
+//     set <voxel> <count> <block> <state> <r> <g> <b> <a>
+//     ...
+//
+// The version is the version of the file, changed whenever it or the `set` lines are. A world is
+// written to a file alongside first and then put in place of the old one, so one cut off halfway
+// never takes the place of a whole one.
+
+use crate::{World, Voxel};
+use crate::net::{parse_set, runs_of};
+use crate::streaming::Streamer;
+
+// the version of the file
+const VERSION: u32 = 1;
+
+// a world as it was kept
+pub struct Saved {
+	pub seed: u64,
+	pub size: [usize; 3],
+	// what it was made from besides the seed (see `recipe` in lib)
+	pub recipe: String,
+	// runs of voxels edited since, as the first, how many, and what to
+	pub edits: Vec<(usize, usize, Voxel)>,
+}
+
+impl Saved {
+	// set the voxels edited in `world`, made again from the seed, size and recipe, through
+	// `streamer` if it's streamed
+	pub fn restore(&self, world: &mut World, mut streamer: Option<&mut Streamer>) {
+		for (first, count, voxel) in &self.edits {
+			for k in *first..first + count {
+				match streamer.as_deref_mut() {
+					Some(s) => s.put(world, k, voxel.clone()),
+					None => world.set(k, voxel.clone()),
+				}
+			}
+		}
+	}
+}
+
+// Keep the world of `size` made from `seed` and `recipe` in the file at `path`, with the voxels
+// `edited` since it was made, by number in order.
+pub fn write(path: &str, seed: u64, recipe: &str, size: [usize; 3], edited: impl IntoIterator<Item = (usize, Voxel)>) -> Result<(), String> {
+	let [x, y, z] = size;
+	let mut text = format!("world {} {} {} {} {} {}\n", VERSION, seed, x, y, z, recipe);
+	for line in runs_of(edited) {
+		text.push_str(&line);
+		text.push('\n');
+	}
+	let err = |e: std::io::Error| format!("{}: {}", path, e);
+	let partial = format!("{}.partial", path);
+	std::fs::write(&partial, text).map_err(err)?;
+	std::fs::rename(&partial, path).map_err(err)
+}
+
+// The world kept in the file at `path`, or none if there's no file there yet.
+pub fn read(path: &str) -> Result<Option<Saved>, String> {
+	let text = match std::fs::read_to_string(path) {
+		Ok(text) => text,
+		Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+		Err(e) => return Err(format!("{}: {}", path, e)),
+	};
+	let mut lines = text.lines();
+	let header: Vec<&str> = lines.next().unwrap_or("").split_whitespace().collect();
+	let (seed, size, recipe) = match header.as_slice() {
+		["world", version, ..] if version.parse() != Ok(VERSION) => {
+			return Err(format!("{}: kept by another version of the game ({} rather than {})", path, version, VERSION));
+		},
+		["world", _, seed, x, y, z, recipe @ ..] => {
+			let number = |n: &str| n.parse().ok().filter(|&n: &usize| n > 0);
+			match (seed.parse().ok(), number(x), number(y), number(z)) {
+				(Some(seed), Some(x), Some(y), Some(z)) => (seed, [x, y, z], recipe.join(" ")),
+				_ => return Err(format!("{}: isn't a kept world", path)),
+			}
+		},
+		_ => return Err(format!("{}: isn't a kept world", path)),
+	};
+	let volume = size.iter().product();
+	let mut edits = vec![];
+	for (n, line) in lines.enumerate().filter(|(_, line)| !line.trim().is_empty()) {
+		let words: Vec<&str> = line.split_whitespace().collect();
+		edits.push(parse_set(&words, volume).map_err(|e| format!("{} line {}: {}", path, n + 2, e))?);
+	}
+	Ok(Some(Saved { seed, size, recipe, edits }))
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{block, link_world, Progress};
+
+	fn path(name: &str) -> String {
+		std::env::temp_dir().join(format!("voxeltorus-{}-{}.world", name, std::process::id())).to_string_lossy().into_owned()
+	}
+
+	#[test]
+	fn a_kept_world_comes_back_the_same() {
+		let mut world = link_world(8, 8, 8, &Progress::default());
+		// an open door, which is see-through
+		let door = Voxel { transparent: true, ..Voxel::of(block::DOOR).with_state(1) };
+		for k in [3, 4, 5, 40] {
+			world.set(k, Voxel::of(block::STONE));
+		}
+		world.set(100, door.clone());
+		let path = path("kept");
+		write(&path, 42, "--generator maze", world.size(), [3, 4, 5, 40, 100].map(|k| (k, world.voxel(k).clone()))).unwrap();
+		let saved = read(&path).unwrap().unwrap();
+		std::fs::remove_file(&path).unwrap();
+		assert_eq!((saved.seed, saved.size, saved.recipe.as_str()), (42, [8, 8, 8], "--generator maze"));
+		// one run for the three in a row
+		assert_eq!(saved.edits.len(), 3);
+		let mut again = link_world(8, 8, 8, &Progress::default());
+		saved.restore(&mut again, None);
+		assert!((0..512).all(|k| again.voxel(k) == world.voxel(k)));
+		assert!(again.transparent(100));
+	}
+
+	#[test]
+	fn no_file_is_no_world_and_a_bad_one_is_an_error() {
+		assert!(read(&path("missing")).unwrap().is_none());
+		let path = path("bad");
+		for text in ["hello\n", "world 1 42 8 8 8\nset 600 1 1 0 1 1 1 1\n", "world 99 42 8 8 8\n"] {
+			std::fs::write(&path, text).unwrap();
+			assert!(read(&path).is_err(), "{}", text);
+		}
+		std::fs::remove_file(&path).unwrap();
+	}
+}
@@ -0,0 +1,111 @@
+// The scheduler, which keeps what the world does by itself from costing more the bigger the
+// world or the busier it gets. Two kinds of work go through it:
+//
+// Random ticks: each tick a few voxels picked at random from each chunk near a player are
+// handed out to be looked at, so grass, saplings and leaves near someone change at a steady
+// pace while the rest of the world, which no one is there to see, costs nothing.
+//
+// Scheduled updates: water flowing, blocks falling and fire burning ask to be looked at again
+// some ticks later, and are kept in a queue in the order they come due. No more than
+// MAX_UPDATES are worked through a tick; the rest wait for the next, so a flood or a forest
+// fire slows down rather than stalling the game.
+
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+use crate::{World, furl};
+use crate::rng::Rng;
+use crate::world::CHUNK;
+
+// chunks, the ones the world's stored in, around a player random ticks reach, and voxels picked
+// from each chunk each tick
+const RANGE: i32 = 4;
+const BUDGET: usize = 1;
+// most scheduled updates worked through a tick
+const MAX_UPDATES: usize = 8192;
+
+// what a scheduled update does to its voxel
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub enum Update {
+	Flow,
+	Fall,
+	Burn,
+}
+
+pub struct Scheduler {
+	ticks: u64,
+	// updates by the tick they're due and the order they were asked for in
+	queue: BinaryHeap<Reverse<(u64, u64, usize, Update)>>,
+	// the updates in the queue, so none is asked for twice
+	queued: HashSet<(usize, Update)>,
+	asked: u64,
+	rng: Rng,
+}
+
+impl Scheduler {
+	pub fn new(seed: u64) -> Scheduler {
+		Scheduler { ticks: 0, queue: BinaryHeap::new(), queued: HashSet::new(), asked: 0, rng: Rng::new(seed ^ 0x5c4ed) }
+	}
+
+	// do `update` to voxel i `delay` ticks from now, or this tick for none, unless it's already
+	// waiting to be done
+	pub fn schedule(&mut self, i: usize, update: Update, delay: u64) {
+		if self.queued.insert((i, update)) {
+			self.queue.push(Reverse((self.ticks + delay, self.asked, i, update)));
+			self.asked += 1;
+		}
+	}
+
+	// the updates that have come due this tick, at most MAX_UPDATES of them, moving on to the next
+	pub fn tick(&mut self) -> Vec<(usize, Update)> {
+		let mut due = vec![];
+		while due.len() < MAX_UPDATES {
+			match self.queue.peek() {
+				Some(&Reverse((tick, _, i, update))) if tick <= self.ticks => {
+					self.queue.pop();
+					self.queued.remove(&(i, update));
+					due.push((i, update));
+				},
+				_ => break,
+			}
+		}
+		self.ticks += 1;
+		due
+	}
+
+	// voxels to random tick this tick, BUDGET from each chunk within RANGE of any of the voxels
+	// in `near`
+	pub fn random(&mut self, world: &World, near: &[usize]) -> Vec<usize> {
+		let size = world.size();
+		let [_, ny, nz] = size;
+		let chunks = size.map(|n| n.div_ceil(CHUNK) as i32);
+		let mut picked = vec![];
+		for &i in near {
+			// the chunk it's in, from its coordinates, so it only holds for the torus worlds are
+			// linked as
+			let c = [i / (ny*nz), i / nz % ny, i % nz].map(|n| (n / CHUNK) as i32);
+			for dx in -RANGE..=RANGE {
+				for dy in -RANGE..=RANGE {
+					for dz in -RANGE..=RANGE {
+						let d = [dx, dy, dz];
+						picked.push([0, 1, 2].map(|k| (c[k] + d[k]).rem_euclid(chunks[k]) as usize));
+					}
+				}
+			}
+		}
+		// in order, so the same chunks give the same voxels from the same seed
+		picked.sort_unstable();
+		picked.dedup();
+		let mut voxels = Vec::with_capacity(picked.len() * BUDGET);
+		for chunk in picked {
+			// the chunks at the far edges are cut short where the world's size isn't a whole
+			// number of them
+			let start = chunk.map(|c| c * CHUNK);
+			let extent = [0, 1, 2].map(|k| CHUNK.min(size[k] - start[k]));
+			for _ in 0..BUDGET {
+				let [x, y, z] = [0, 1, 2].map(|k| start[k] + (self.rng.next_u64() % extent[k] as u64) as usize);
+				voxels.push(furl(x, y, z, ny, nz));
+			}
+		}
+		voxels
+	}
+}
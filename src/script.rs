@@ -0,0 +1,298 @@
+// Scripts: files in the scripts folder ending in .lua, written in the part of Lua the
+// interpreter module runs, for making things, games and machines in a world without changing
+// the game. Each runs once the world is made, in order of their names, and can hand functions
+// to `on_tick` and `on_change` to be called back later. What they're given to work with:
+//
+//   world.get(x, y, z)                   the name of the block at x y z
+//   world.set(x, y, z, block)            change the voxel at x y z to a block, by name
+//   world.size()                         how many voxels the world is along x, y and z
+//   world.raycast(x, y, z, dx, dy, dz, distance)
+//                                        the x, y, z and block of the first solid voxel a ray
+//                                        from x y z going dx dy dz hits within distance (or
+//                                        RAYCAST_DISTANCE), or nil if it hits none
+//   player.position()                    where the player is, as x, y, z
+//   player.look()                        which way the player's looking, as x, y, z
+//   run(command)                         run a console command, as if it had been typed
+//   print(...)                           show something in the console
+//   on_tick(f)                           call f(tick) every tick
+//   on_change(f)                         call f(x, y, z, block) for every voxel that changes
+//
+// Coordinates wrap around the world, and can be fractions, which count as the voxel they're
+// in. A script that goes wrong says so in the console and is stopped.
+
+use macroquad::prelude::*;
+use crate::{World, furl, log, raycast};
+use crate::block;
+use crate::event::Event;
+use crate::interpreter::{Host, Interpreter, Value};
+
+pub const FOLDER: &str = "scripts";
+
+// how far `world.raycast` looks when it isn't told
+const RAYCAST_DISTANCE: f64 = 64.0;
+
+// the functions scripts are given, besides Lua's own
+const FUNCTIONS: [&str; 10] = ["world.get", "world.set", "world.size", "world.raycast", "player.position", "player.look", "run", "print", "on_tick", "on_change"];
+
+// where the player is for the scripts: in voxel i, at `position` within it, looking along `look`
+pub struct Player {
+	pub i: usize,
+	pub position: Vec3,
+	pub look: Vec3,
+}
+
+#[derive(Default)]
+pub struct Scripts {
+	scripts: Vec<Script>,
+}
+
+struct Script {
+	name: String,
+	interpreter: Interpreter,
+	// the whole script, run by `start`
+	chunk: Value,
+	on_tick: Vec<Value>,
+	on_change: Vec<Value>,
+	// stopped after going wrong
+	failed: bool,
+}
+
+impl Scripts {
+	// Read every script in `folder`, in order of their names, handing those that can't be read
+	// to `warn`. `math.random` starts from `seed` in each. No folder means no scripts.
+	pub fn load(folder: &str, seed: u64, mut warn: impl FnMut(String)) -> Scripts {
+		let mut scripts = Scripts::default();
+		let Ok(entries) = std::fs::read_dir(folder) else { return scripts };
+		let mut paths: Vec<_> = entries.filter_map(|e| e.ok()).map(|e| e.path()).filter(|p| p.extension().is_some_and(|x| x == "lua")).collect();
+		paths.sort();
+		for path in paths {
+			let name = path.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+			match std::fs::read_to_string(&path).map_err(|e| e.to_string()).and_then(|text| scripts.add(&name, &text, seed).map_err(|e| e.to_string())) {
+				Ok(()) => {},
+				Err(e) => warn(format!("{}: {}", path.display(), e)),
+			}
+		}
+		scripts
+	}
+
+	fn add(&mut self, name: &str, text: &str, seed: u64) -> Result<(), crate::interpreter::Error> {
+		let mut interpreter = Interpreter::new(seed);
+		for function in FUNCTIONS {
+			interpreter.register(function);
+		}
+		let chunk = interpreter.load(text)?;
+		self.scripts.push(Script { name: name.to_string(), interpreter, chunk, on_tick: vec![], on_change: vec![], failed: false });
+		Ok(())
+	}
+
+	// run each script once the world is made, with what they do for the game to take up
+	pub fn start(&mut self, world: &mut World, player: &Player) -> Vec<Event> {
+		let mut events = vec![];
+		for script in &mut self.scripts {
+			let chunk = script.chunk.clone();
+			script.call(&chunk, vec![], world, player, &mut events);
+		}
+		events
+	}
+
+	// Call the scripts back for tick number `tick`, in which the voxels `changed` changed, with
+	// what they do for the game to take up.
+	pub fn tick(&mut self, world: &mut World, tick: u64, changed: &[usize], player: &Player) -> Vec<Event> {
+		let mut events = vec![];
+		// a voxel set more than once in the tick only counts once
+		let mut changed = changed.to_vec();
+		changed.sort_unstable();
+		changed.dedup();
+		let [_, ny, nz] = world.size();
+		for script in &mut self.scripts {
+			for f in script.on_tick.clone() {
+				script.call(&f, vec![Value::Number(tick as f64)], world, player, &mut events);
+			}
+			for f in script.on_change.clone() {
+				for &i in &changed {
+					let name = block::def(world.voxel(i).block).name;
+					let args = vec![Value::from((i / (ny*nz)) as f64), Value::from((i / nz % ny) as f64), Value::from((i % nz) as f64), Value::from(name)];
+					script.call(&f, args, world, player, &mut events);
+				}
+			}
+		}
+		events
+	}
+}
+
+impl Script {
+	// call one of the script's functions, stopping the script if it goes wrong
+	fn call(&mut self, f: &Value, args: Vec<Value>, world: &mut World, player: &Player, events: &mut Vec<Event>) {
+		if self.failed {
+			return;
+		}
+		let mut context = Context { world, player, events, on_tick: vec![], on_change: vec![] };
+		let result = self.interpreter.call(f, args, &mut context);
+		let (on_tick, on_change) = (context.on_tick, context.on_change);
+		self.on_tick.extend(on_tick);
+		self.on_change.extend(on_change);
+		if let Err(e) = result {
+			log::warning!("scripts", "{}: {}", self.name, e);
+			events.push(Event::Say(format!("{}: {} (stopped)", self.name, e)));
+			self.failed = true;
+		}
+	}
+}
+
+// what a script's calls to the game's functions work on
+struct Context<'a> {
+	world: &'a mut World,
+	player: &'a Player,
+	events: &'a mut Vec<Event>,
+	// callbacks handed over during the call
+	on_tick: Vec<Value>,
+	on_change: Vec<Value>,
+}
+
+impl Context<'_> {
+	// the voxel at coordinates x y z, wrapping around the world
+	fn voxel(&self, x: f64, y: f64, z: f64) -> usize {
+		let [nx, ny, nz] = self.world.size();
+		let wrap = |a: f64, n: usize| (a.floor() as i64).rem_euclid(n as i64) as usize;
+		furl(wrap(x, nx), wrap(y, ny), wrap(z, nz), ny, nz)
+	}
+
+	// the coordinates of voxel i, offset by `position` within it
+	fn coordinates(&self, i: usize, position: Vec3) -> Vec<Value> {
+		let [_, ny, nz] = self.world.size();
+		let at = [i / (ny*nz), i / nz % ny, i % nz];
+		(0..3).map(|k| Value::Number(at[k] as f64 + position[k] as f64)).collect()
+	}
+}
+
+impl Host for Context<'_> {
+	fn call(&mut self, name: &str, args: Vec<Value>) -> Result<Vec<Value>, String> {
+		let number = |k: usize| args.get(k).and_then(Value::number).ok_or(format!("{} expects a number as argument {}", name, k + 1));
+		let function = |k: usize| match args.get(k) {
+			Some(f @ Value::Function(_)) => Ok(f.clone()),
+			_ => Err(format!("{} expects a function", name)),
+		};
+		Ok(match name {
+			"world.get" => {
+				let i = self.voxel(number(0)?, number(1)?, number(2)?);
+				vec![Value::from(block::def(self.world.voxel(i).block).name)]
+			},
+			"world.set" => {
+				let i = self.voxel(number(0)?, number(1)?, number(2)?);
+				let name = args.get(3).map(Value::to_string).unwrap_or_default();
+				let b = block::by_name(&name).ok_or(format!("there's no block called '{}'", name))?;
+				self.world.set(i, crate::Voxel::of(b));
+				vec![]
+			},
+			"world.size" => self.world.size().iter().map(|&n| Value::Number(n as f64)).collect(),
+			"world.raycast" => {
+				let from = vec3(number(0)? as f32, number(1)? as f32, number(2)? as f32);
+				let ray = vec3(number(3)? as f32, number(4)? as f32, number(5)? as f32).normalize_or_zero();
+				let distance = if args.len() > 6 { number(6)? } else { RAYCAST_DISTANCE };
+				if ray == Vec3::ZERO {
+					return Err("world.raycast needs a direction".to_string());
+				}
+				let start = self.voxel(from.x as f64, from.y as f64, from.z as f64);
+				// a ray crosses at most three faces a voxel's width along it
+				let steps = (distance.max(0.0) * 3.0).ceil() as usize;
+				let (i, _, dist) = raycast(self.world, start, from - from.floor(), ray, steps);
+				if self.world.transparent(i) || dist as f64 > distance {
+					vec![Value::Nil]
+				} else {
+					let mut hit = self.coordinates(i, Vec3::ZERO);
+					hit.push(Value::from(block::def(self.world.voxel(i).block).name));
+					hit
+				}
+			},
+			"player.position" => self.coordinates(self.player.i, self.player.position),
+			"player.look" => self.player.look.to_array().iter().map(|&a| Value::Number(a as f64)).collect(),
+			"run" => {
+				let command = args.first().map(Value::to_string).ok_or("run expects a command")?;
+				self.events.push(Event::Command(relative(&command, self.world, self.player.i)));
+				vec![]
+			},
+			"print" => {
+				self.events.push(Event::Say(args.iter().map(Value::to_string).collect::<Vec<_>>().join(" ")));
+				vec![]
+			},
+			"on_tick" => {
+				self.on_tick.push(function(0)?);
+				vec![]
+			},
+			"on_change" => {
+				self.on_change.push(function(0)?);
+				vec![]
+			},
+			_ => return Err(format!("there's no function called '{}'", name)),
+		})
+	}
+}
+
+// A command with its relative coordinates counted from voxel i. Coordinates come in threes after
+// the command's name, so which axis each is along follows from where it is in the line.
+pub fn relative(line: &str, world: &World, i: usize) -> String {
+	let size = world.size();
+	let [_, ny, nz] = size;
+	let at = [i / (ny*nz), i / nz % ny, i % nz];
+	line.split_whitespace().enumerate().map(|(k, word)| {
+		let Some(offset) = word.strip_prefix('~') else { return word.to_string() };
+		let offset = if offset.is_empty() { Some(0) } else { offset.parse::<i64>().ok() };
+		let Some(offset) = offset else { return word.to_string() };
+		let axis = (k + 2) % 3;
+		(at[axis] as i64 + offset).rem_euclid(size[axis] as i64).to_string()
+	}).collect::<Vec<_>>().join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{Progress, Voxel, link_world};
+
+	fn player(world: &World) -> Player {
+		let [_, ny, nz] = world.size();
+		Player { i: furl(0, 5, 0, ny, nz), position: Vec3::splat(0.5), look: Vec3::X }
+	}
+
+	#[test]
+	fn scripts_change_the_world_and_hear_about_changes() {
+		let mut world = link_world(8, 8, 8, &Progress::default());
+		let player = player(&world);
+		let mut scripts = Scripts::default();
+		scripts.add("pillars.lua", "
+			world.set(1, 2, 3, 'stone')
+			local x, y, z = player.position()
+			run('setblock ~ ~1 ~ dirt')
+			print(world.get(9, 2, -5), x, y)
+			on_change(function(x, y, z, block)
+				if block == 'stone' and world.get(x, y - 1, z) == 'air' then world.set(x, y - 1, z, 'dirt') end
+			end)
+		", 1).unwrap();
+		let events = scripts.start(&mut world, &player);
+		assert!(matches!(&events[..], [Event::Command(c), Event::Say(s)] if c == "setblock 0 6 0 dirt" && s == "stone 0.5 5.5"));
+		let changed = world.take_changes();
+		scripts.tick(&mut world, 1, &changed, &player);
+		assert!(*world.voxel(furl(1, 1, 3, 8, 8)) == Voxel::of(block::DIRT));
+	}
+
+	#[test]
+	fn rays_stop_at_the_first_solid_voxel() {
+		let mut world = link_world(8, 8, 8, &Progress::default());
+		world.set(furl(5, 2, 2, 8, 8), Voxel::of(block::STONE));
+		let player = player(&world);
+		let mut scripts = Scripts::default();
+		scripts.add("ray.lua", "local x, y, z, block = world.raycast(1.5, 2.5, 2.5, 1, 0, 0) print(x, y, z, block, world.raycast(1.5, 2.5, 2.5, 1, 0, 0, 2))", 1).unwrap();
+		let events = scripts.start(&mut world, &player);
+		assert!(matches!(&events[..], [Event::Say(s)] if s == "5 2 2 stone nil"));
+	}
+
+	#[test]
+	fn a_script_that_goes_wrong_is_stopped() {
+		let mut world = link_world(8, 8, 8, &Progress::default());
+		let player = player(&world);
+		let mut scripts = Scripts::default();
+		scripts.add("broken.lua", "on_tick(function(t) world.set(0, 0, 0, 'nothing') end)", 1).unwrap();
+		scripts.start(&mut world, &player);
+		assert!(matches!(&scripts.tick(&mut world, 1, &[], &player)[..], [Event::Say(_)]));
+		assert!(scripts.tick(&mut world, 2, &[], &player).is_empty());
+	}
+}
@@ -0,0 +1,118 @@
+// Region selection: a box of voxels between two corners marked by the player, and tools that
+// edit the whole box at once. The box is walked over the neighbor graph from its lowest
+// corner, so it can reach across the world's edges like anything else.
+
+use crate::{World, furl, offset};
+use crate::world::displacement;
+#[cfg(feature = "graphics")]
+use crate::{Voxel, step};
+#[cfg(feature = "graphics")]
+use crate::block::{self, BlockId};
+
+// a box of voxels: the corner with the lowest coordinates and how many voxels it spans
+#[derive(Clone, Copy)]
+pub struct Region {
+	pub origin: usize,
+	pub size: [usize; 3],
+}
+
+impl Region {
+	// the box with voxels a and b at opposite corners, whichever way around the torus is shorter
+	pub fn between(world: &World, a: usize, b: usize) -> Region {
+		let d = world.displacement(a, b);
+		Region {
+			origin: offset(world, a, d.map(|n| n.min(0))),
+			size: d.map(|n| n.unsigned_abs() as usize + 1),
+		}
+	}
+
+	// Like `between`, in a 3-torus of `size` rather than a world, like the whole of a streamed
+	// world that's only loaded in part.
+	pub fn between_in(size: [usize; 3], a: usize, b: usize) -> Region {
+		let d = displacement(size, a, b);
+		let [_, ny, nz] = size;
+		let corner = [a / (ny*nz), a / nz % ny, a % nz];
+		let [x, y, z] = [0, 1, 2].map(|k| (corner[k] as i64 + d[k].min(0) as i64).rem_euclid(size[k] as i64) as usize);
+		Region {
+			origin: furl(x, y, z, ny, nz),
+			size: d.map(|n| n.unsigned_abs() as usize + 1),
+		}
+	}
+
+	// Every voxel in the box, indexed [x][y][z] within it like `furl`.
+	#[cfg(feature = "graphics")]
+	pub fn voxels(&self, world: &World) -> Vec<usize> {
+		let [sx, sy, sz] = self.size;
+		let mut found = Vec::with_capacity(sx*sy*sz);
+		let mut i_x = self.origin;
+		for _ in 0..sx {
+			let mut i_y = i_x;
+			for _ in 0..sy {
+				let mut i = i_y;
+				for _ in 0..sz {
+					found.push(i);
+					i = step(world, i, 2, 1);
+				}
+				i_y = step(world, i_y, 1, 1);
+			}
+			i_x = step(world, i_x, 0, 1);
+		}
+		found
+	}
+
+	// Whether voxel i of a world of `size` is in the box. This reads positions off the ids, so
+	// like `World::displacement` it only holds for the torus worlds are linked as.
+	pub fn contains(&self, size: [usize; 3], i: usize) -> bool {
+		let [_, ny, nz] = size;
+		let coordinates = |i: usize| [i / (ny*nz), i / nz % ny, i % nz];
+		let (a, b) = (coordinates(self.origin), coordinates(i));
+		(0..3).all(|k| (b[k] + size[k] - a[k]) % size[k] < self.size[k])
+	}
+}
+
+// Two corners marked one at a time; the box between them is selected once both are.
+#[cfg(feature = "graphics")]
+#[derive(Default)]
+pub struct Selection {
+	pub corners: [Option<usize>; 2],
+}
+
+#[cfg(feature = "graphics")]
+impl Selection {
+	pub fn region(&self, world: &World) -> Option<Region> {
+		match self.corners {
+			[Some(a), Some(b)] => Some(Region::between(world, a, b)),
+			_ => None,
+		}
+	}
+}
+
+#[cfg(feature = "graphics")]
+pub fn fill(world: &mut World, region: &Region, voxel: &Voxel) {
+	for i in region.voxels(world) {
+		world.set(i, voxel.clone());
+	}
+}
+
+// swap every voxel of block `from` for `to`
+#[cfg(feature = "graphics")]
+pub fn replace(world: &mut World, region: &Region, from: BlockId, to: &Voxel) {
+	for i in region.voxels(world) {
+		if world.voxel(i).block == from {
+			world.set(i, to.clone());
+		}
+	}
+}
+
+// clear everything inside the box, leaving its faces as they were
+#[cfg(feature = "graphics")]
+pub fn hollow(world: &mut World, region: &Region) {
+	let [sx, sy, sz] = region.size;
+	let inside = |n: usize, s: usize| n > 0 && n + 1 < s;
+	for (k, i) in region.voxels(world).into_iter().enumerate() {
+		let (x, y, z) = (k / (sy*sz), k / sz % sy, k % sz);
+		if inside(x, sx) && inside(y, sy) && inside(z, sz) {
+			world.set(i, Voxel::of(block::AIR));
+		}
+	}
+}
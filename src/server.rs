@@ -0,0 +1,181 @@
+// The dedicated server, started with `--server`: it makes the world and hosts it for games to
+// join, running the world's own goings-on tick by tick without a window or a player of its own.
+// The console commands about players can be typed into its terminal, along with `save` and `stop`.
+// It keeps its world in a file (see the save module) every SAVE_EVERY and when it's stopped,
+// whether by `stop` or by Ctrl-C or `kill`, and picks up from there when it's started again.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use glam::*;
+use crate::{World, Voxel, Progress, Rng, log, block, config, console, explosion, net, rng, save};
+use crate::{arg_value, break_block, default_role, generate_world, life, parse_gravity, parse_gravity_field, recipe, simulate, Simulation, TICK};
+use crate::entity::{Entity, Impact, Kind};
+use crate::particle::Particles;
+use crate::streaming::Streamer;
+
+// where the world is kept unless `--save` says otherwise, and how often
+const SAVE_FILE: &str = "voxeltorus.world";
+const SAVE_EVERY: Duration = Duration::from_secs(300);
+
+// set when the process is asked to stop, so the world is kept before it does
+static STOPPING: AtomicBool = AtomicBool::new(false);
+
+pub fn run(args: &[String]) {
+	block::load_packs(block::PACK_FOLDER, |e| log::warning!("blocks", "{}", e));
+	let mut config = config::Config::load(config::FILE);
+	config.apply_args(args);
+	let made_from = recipe(args, &config.terrain_noise, &config.island_noise);
+	let kept_in = arg_value(args, "--save").unwrap_or(SAVE_FILE.to_string());
+	let saved = match save::read(&kept_in) {
+		Ok(saved) => saved,
+		Err(e) => return log::error!("save", "{}", e),
+	};
+	// a kept world is made again the way it was made the first time
+	if let Some(saved) = saved.as_ref().filter(|saved| saved.recipe != made_from) {
+		return log::error!("save", "{} was made from '{}', but this server makes its world from '{}'; start it with the same options and files, or keep this world elsewhere with --save", kept_in, saved.recipe, made_from);
+	}
+	let seed = match &saved {
+		Some(saved) => saved.seed,
+		None => arg_value(args, "--seed").and_then(|s| s.parse().ok()).unwrap_or_else(rng::random_seed),
+	};
+	log::info!("world", "seed {}", seed);
+	let mut rng = Rng::new(seed);
+	let started = Instant::now();
+	let size = saved.as_ref().map_or(config.world_size, |saved| saved.size);
+	let (mut world, mut streamer) = match generate_world(args, size, &config.terrain_noise, &config.island_noise, &mut rng, &Progress::default()) {
+		Ok(generated) => generated,
+		Err(e) => return log::error!("world", "{}", e),
+	};
+	world.take_changes();
+	world.set_gravity(parse_gravity(args));
+	if let Some(field) = parse_gravity_field(args, &world) {
+		if streamer.is_some() {
+			log::warning!("gravity", "fields don't work with streamed worlds");
+		} else {
+			world.set_gravity_field(field);
+		}
+	}
+	log::info!("world", "made {}x{}x{} in {:.2} s", world.size()[0], world.size()[1], world.size()[2], started.elapsed().as_secs_f64());
+	// the edits kept are changes like any others, sent to the games that join
+	if let Some(saved) = &saved {
+		saved.restore(&mut world, streamer.as_mut());
+		log::info!("save", "picked up the world kept in {}", kept_in);
+	}
+	let port = arg_value(args, "--port").and_then(|p| p.parse().ok()).unwrap_or(net::PORT);
+	let mut session = match net::Session::host(port, seed, &made_from, default_role(args, |e| log::warning!("net", "{}", e))) {
+		Ok(session) => session,
+		Err(e) => return log::error!("net", "can't host on port {}: {}", port, e),
+	};
+	log::info!("net", "hosting on port {}", port);
+	let keep = |world: &World, streamer: Option<&Streamer>, session: &net::Session| {
+		// a streamed world's edits are kept by its streamer, the rest by the session
+		let (size, edited) = match streamer {
+			Some(s) => (s.size(), s.edits(world)),
+			None => (world.size(), session.edited().into_iter().map(|k| (k, world.voxel(k).clone())).collect()),
+		};
+		match save::write(&kept_in, seed, &made_from, size, edited) {
+			Ok(()) => log::info!("save", "kept the world in {}", kept_in),
+			Err(e) => log::error!("save", "can't keep the world: {}", e),
+		}
+	};
+	catch_signals();
+	let (typed, commands) = std::sync::mpsc::channel();
+	std::thread::spawn(move || {
+		for line in std::io::stdin().lines().map_while(Result::ok) {
+			if typed.send(line).is_err() {
+				break;
+			}
+		}
+	});
+
+	let mut entities: Vec<Entity> = vec![];
+	let mut particles = Particles::new(seed);
+	let mut simulation = Simulation::new(seed, arg_value(args, "--water").as_deref() != Some("finite"));
+	// with nobody here to start it, the automaton runs from the beginning
+	let mut automaton = life(args, &world);
+	if let Some(automaton) = &mut automaton {
+		automaton.running = true;
+	}
+	let mut kept_at = Instant::now();
+	let tick = Duration::from_secs_f32(TICK);
+	let mut next = Instant::now();
+	loop {
+		let mut impacts = vec![];
+		for entity in &mut entities {
+			impacts.extend(entity.tick(&world));
+		}
+		entities.retain(|entity| !entity.spent);
+		let changed = world.take_changes();
+		for text in session.tick(&mut world, streamer.as_mut(), &changed, None) {
+			log::info!("net", "{}", text);
+		}
+		let near: Vec<usize> = session.players(&world, streamer.as_ref()).map(|e| e.i).collect();
+		simulate(&mut world, &mut entities, &changed, &near, &mut simulation, automaton.as_mut());
+		let mut stopping = STOPPING.load(Ordering::Relaxed);
+		for line in commands.try_iter() {
+			if line.trim() == "stop" {
+				stopping = true;
+				continue;
+			}
+			match console::parse(&line, world.size()) {
+				Ok(command @ (console::Command::Players | console::Command::Role(..) | console::Command::Protect(..) | console::Command::Unprotect)) => {
+					log::info!("server", "{}", session.administer(&world, streamer.as_ref(), command));
+				},
+				Ok(console::Command::Save) => {
+					keep(&world, streamer.as_ref(), &session);
+					kept_at = Instant::now();
+				},
+				Ok(_) => log::warning!("server", "only players, role, protect, unprotect, save and stop can be used here"),
+				Err(e) if e.is_empty() => {},
+				Err(e) => log::warning!("server", "{}", e),
+			}
+		}
+		for impact in impacts {
+			carry_out(&mut world, &mut entities, &mut particles, &mut rng, impact);
+		}
+		if stopping {
+			keep(&world, streamer.as_ref(), &session);
+			return log::info!("server", "stopped");
+		}
+		if kept_at.elapsed() >= SAVE_EVERY {
+			keep(&world, streamer.as_ref(), &session);
+			kept_at = Instant::now();
+		}
+		next += tick;
+		// a server that's fallen behind skips the ticks it missed rather than running them back
+		// to back to catch up
+		next = next.max(Instant::now() - tick);
+		std::thread::sleep(next.saturating_duration_since(Instant::now()));
+	}
+}
+
+// what an entity did to the world, with no player of the server's own to push around
+fn carry_out(world: &mut World, entities: &mut Vec<Entity>, particles: &mut Particles, rng: &mut Rng, impact: Impact) {
+	match impact {
+		Impact::Smash(i) => break_block(world, entities, particles, i, true),
+		Impact::Explode(i) => {
+			let mut spawned = vec![];
+			explosion::explode(world, entities, &mut spawned, particles, i, rng);
+			entities.extend(spawned);
+		},
+		Impact::Land(i, block) if world.transparent(i) => world.set(i, Voxel::of(block)),
+		Impact::Land(i, block) => entities.push(Entity::new(Kind::Item(block), i, Vec3::splat(0.5))),
+	}
+}
+
+// have Ctrl-C and `kill` set STOPPING rather than end the process there and then
+#[cfg(unix)]
+fn catch_signals() {
+	extern "C" fn stop(_: libc::c_int) {
+		STOPPING.store(true, Ordering::Relaxed);
+	}
+	for signal in [libc::SIGINT, libc::SIGTERM] {
+		// all the handler does is store to an atomic, which is safe to do in one
+		unsafe {
+			libc::signal(signal, stop as extern "C" fn(libc::c_int) as libc::sighandler_t);
+		}
+	}
+}
+
+#[cfg(not(unix))]
+fn catch_signals() {}
@@ -0,0 +1,52 @@
+// Signs: blocks with a line of text written on them, kept in the world next to the voxel and
+// shown with the block's name when the sign is looked at. The text is typed into an overlay
+// that opens when a sign is placed or used; `Enter` writes it and `Escape` leaves it as it was.
+
+use macroquad::prelude::*;
+use crate::World;
+use crate::block;
+
+// longest text a sign takes, in characters
+const MAX_LENGTH: usize = 40;
+
+pub struct Editor {
+	// the sign being written
+	i: usize,
+	text: String,
+}
+
+impl Editor {
+	pub fn new(world: &World, i: usize) -> Editor {
+		Editor { i, text: world.text(i).unwrap_or("").to_string() }
+	}
+
+	// Take this frame's typing. Returns whether the editor is done, having written the text
+	// to the sign if it was confirmed.
+	pub fn update(&mut self, world: &mut World) -> bool {
+		while let Some(c) = get_char_pressed() {
+			if !c.is_control() && self.text.chars().count() < MAX_LENGTH {
+				self.text.push(c);
+			}
+		}
+		if is_key_pressed(KeyCode::Backspace) {
+			self.text.pop();
+		}
+		if is_key_pressed(KeyCode::Enter) {
+			// unless the sign was broken in the meantime
+			if world.voxel(self.i).block == block::SIGN {
+				world.set_text(self.i, self.text.trim());
+			}
+			return true;
+		}
+		is_key_pressed(KeyCode::Escape)
+	}
+
+	pub fn draw(&self) {
+		let (w, h) = (screen_width(), screen_height());
+		draw_rectangle(w/2.0 - 220.0, h/2.0 - 40.0, 440.0, 70.0, Color::new(0.0, 0.0, 0.0, 0.75));
+		draw_text("write on the sign, Enter to finish", w/2.0 - 210.0, h/2.0 - 18.0, 20.0, GRAY);
+		// a blinking cursor after the text
+		let cursor = if ((get_time() * 2.0) as u64).is_multiple_of(2) { "_" } else { "" };
+		draw_text(format!("{}{}", self.text, cursor), w/2.0 - 210.0, h/2.0 + 14.0, 24.0, WHITE);
+	}
+}
@@ -0,0 +1,313 @@
+// Sound effects: footsteps, blocks breaking and being placed, explosions, jumping and landing.
+// Whatever makes one happen publishes a cue on the event bus, with the voxel it came from unless
+// it's the player's own, and they're all played at the end of the frame.
+//
+// Sounds from somewhere fade with the distance to it, the shorter way around the world, and are
+// gone beyond HEARING. They're muffled when there's anything solid on the straight line to the
+// camera, unless the settings turn that off. macroquad can't pan a sound, so each is made in
+// stereo from a number of sides ahead of time and the one nearest the side it comes from is
+// played. That takes its samples, so it's done for the sounds made up (see below) and .wav files,
+// which are read here, but not .ogg files, which are heard the same on both sides.
+//
+// Each sound is loaded from assets/sounds if there's a file for it there, a .wav or .ogg named
+// after the cue and the block, like `step_grass.wav`, or after the cue alone, like `step.wav`,
+// for every block without its own. Otherwise one's made up to suit the block: harder blocks
+// sound higher and sharper, and liquids splash. This is only built with the `audio` feature.
+
+use std::collections::HashMap;
+use macroquad::audio::{self, PlaySoundParams, Sound};
+use macroquad::prelude::*;
+use crate::{World, Rng};
+use crate::block::{self, BlockId};
+use crate::config::Config;
+use crate::event::Cue;
+
+pub const FOLDER: &str = "assets/sounds";
+// samples a second of the sounds made up
+pub const RATE: u32 = 22050;
+// sides sounds are made heard from, spread from left to right
+const PANS: usize = 5;
+// voxels away a sound is last heard at, and how loud a muffled sound is
+const HEARING: f32 = 48.0;
+const MUFFLED: f32 = 0.35;
+
+// where sounds are heard from: the camera's voxel, its position in it and its right
+pub struct Ear {
+	pub i: usize,
+	pub position: Vec3,
+	pub right: Vec3,
+}
+
+// what a sound is, for its volume setting
+#[derive(Clone, Copy, PartialEq)]
+pub enum Category {
+	Footsteps,
+	Blocks,
+	Body,
+}
+
+impl Cue {
+	pub fn category(self) -> Category {
+		match self {
+			Cue::Step(_) => Category::Footsteps,
+			Cue::Break(_) | Cue::Place(_) | Cue::Explode => Category::Blocks,
+			Cue::Jump | Cue::Land(_) => Category::Body,
+		}
+	}
+
+	fn name(self) -> &'static str {
+		match self {
+			Cue::Step(_) => "step",
+			Cue::Break(_) => "break",
+			Cue::Place(_) => "place",
+			Cue::Explode => "explode",
+			Cue::Jump => "jump",
+			Cue::Land(_) => "land",
+		}
+	}
+
+	fn block(self) -> Option<BlockId> {
+		match self {
+			Cue::Step(b) | Cue::Break(b) | Cue::Place(b) | Cue::Land(b) => Some(b),
+			Cue::Explode | Cue::Jump => None,
+		}
+	}
+
+	// every cue there's a sound for
+	fn all() -> Vec<Cue> {
+		let mut cues = vec![Cue::Explode, Cue::Jump];
+		for b in 0..block::all().len() as BlockId {
+			cues.extend([Cue::Step(b), Cue::Break(b), Cue::Place(b), Cue::Land(b)]);
+		}
+		cues
+	}
+}
+
+pub struct Sounds {
+	// by cue name and block, heard from each of the PANS sides unless they're from .ogg files
+	sounds: HashMap<(&'static str, Option<BlockId>), Vec<Sound>>,
+}
+
+impl Sounds {
+	// load the sounds in `folder`, making up the rest, telling `warn` about files that can't be read
+	pub async fn load(folder: &str, mut warn: impl FnMut(String)) -> Sounds {
+		let mut files: HashMap<String, Vec<Sound>> = HashMap::new();
+		for entry in std::fs::read_dir(folder).into_iter().flatten().flatten() {
+			let path = entry.path();
+			let (Some(stem), Some(extension @ ("wav" | "ogg"))) = (path.file_stem().and_then(|s| s.to_str()), path.extension().and_then(|e| e.to_str())) else {
+				continue;
+			};
+			let name = path.display().to_string();
+			// .wav files are read here, to pan them; anything else is left to macroquad
+			let read = match extension {
+				"wav" => match std::fs::read(&path).map_err(|e| e.to_string()).and_then(|bytes| samples(&bytes)) {
+					Ok(read) => Some(read),
+					Err(e) => { warn(format!("{}: {}, so it's heard the same on both sides", name, e)); None },
+				},
+				_ => None,
+			};
+			let pans = match read {
+				Some((samples, rate)) => pan(&samples, rate, &name, &mut warn).await,
+				None => match audio::load_sound(&path.to_string_lossy()).await {
+					Ok(sound) => vec![sound],
+					Err(e) => { warn(format!("{}: {}", name, e)); continue; },
+				},
+			};
+			files.insert(stem.to_string(), pans);
+		}
+		let mut sounds = HashMap::new();
+		for cue in Cue::all() {
+			let named = cue.block().map(|b| format!("{}_{}", cue.name(), file_name(block::def(b).name)));
+			let pans = match named.and_then(|name| files.get(&name)).or(files.get(cue.name())) {
+				Some(pans) => pans.clone(),
+				None => pan(&make_up(cue), RATE, &format!("a made-up {} sound", cue.name()), &mut warn).await,
+			};
+			sounds.insert((cue.name(), cue.block()), pans);
+		}
+		Sounds { sounds }
+	}
+
+	// Play the sound for `cue` at the volume the settings give its category, coming from voxel
+	// `from` if it's given, or otherwise from right where it's heard.
+	pub fn play(&self, cue: Cue, from: Option<usize>, world: &World, ear: &Ear, config: &Config) {
+		let (heard, pan) = match from {
+			Some(j) => {
+				let line = world.direction(ear.i, ear.position, j, Vec3::splat(0.5));
+				let fade = (1.0 - line.length() / HEARING).max(0.0).powi(2);
+				let muffled = config.occlusion && !world.visible(ear.i, ear.position, j, Vec3::splat(0.5));
+				(fade * if muffled { MUFFLED } else { 1.0 }, line.normalize_or_zero().dot(ear.right))
+			},
+			None => (1.0, 0.0),
+		};
+		let volume = heard * config.volume * match cue.category() {
+			Category::Footsteps => config.footstep_volume,
+			Category::Blocks => config.block_volume,
+			Category::Body => config.body_volume,
+		};
+		if volume <= 0.0 {
+			return;
+		}
+		let Some(pans) = self.sounds.get(&(cue.name(), cue.block())) else { return };
+		let k = ((pan + 1.0) / 2.0 * (pans.len() as f32 - 1.0)).round() as usize;
+		if let Some(sound) = pans.get(k) {
+			audio::play_sound(sound, PlaySoundParams { looped: false, volume });
+		}
+	}
+}
+
+// `samples`, from -1 to 1, at `rate` a second, as heard from each of the PANS sides, telling
+// `warn` about any that can't be made, calling the sound `name`
+async fn pan(samples: &[f32], rate: u32, name: &str, warn: &mut impl FnMut(String)) -> Vec<Sound> {
+	let mut pans = vec![];
+	for k in 0..PANS {
+		// as loud all the way across
+		let angle = k as f32 / (PANS - 1) as f32 * std::f32::consts::FRAC_PI_2;
+		let (left, right): (Vec<f32>, Vec<f32>) = samples.iter().map(|s| (s * angle.cos(), s * angle.sin())).unzip();
+		match audio::load_sound_from_bytes(&wav(&left, &right, rate)).await {
+			Ok(sound) => pans.push(sound),
+			Err(e) => warn(format!("can't make {}: {}", name, e)),
+		}
+	}
+	pans
+}
+
+// a block's name as it's written in a file name, like `pine_needles`
+fn file_name(name: &str) -> String {
+	name.to_lowercase().replace(' ', "_")
+}
+
+// Samples, from -1 to 1, of a sound to suit `cue`: a burst of noise, smoothed more the softer
+// the block, with a tone under it that slides from one pitch to another, dying away.
+fn make_up(cue: Cue) -> Vec<f32> {
+	let def = cue.block().map(block::def);
+	let hardness = def.map_or(0.5, |d| d.hardness.min(2.0) / 2.0);
+	let liquid = def.is_some_and(|d| d.liquid);
+	// seconds long, how much of the noise gets through the smoothing, pitches and how loud the tone is
+	let (seconds, bright, (from, to), tone) = match cue {
+		Cue::Step(_) if liquid => (0.25, 0.15, (300.0, 150.0), 0.1),
+		Cue::Step(_) => (0.08, 0.1 + 0.5 * hardness, (80.0 + 200.0 * hardness, 60.0), 0.3),
+		Cue::Break(_) => (0.2, 0.2 + 0.6 * hardness, (150.0 + 400.0 * hardness, 80.0), 0.3),
+		Cue::Place(_) => (0.06, 0.3 + 0.5 * hardness, (200.0 + 500.0 * hardness, 150.0 + 300.0 * hardness), 0.5),
+		Cue::Explode => (0.9, 0.3, (60.0, 25.0), 0.4),
+		Cue::Jump => (0.12, 0.1, (200.0, 400.0), 0.2),
+		Cue::Land(_) if liquid => (0.35, 0.2, (250.0, 100.0), 0.1),
+		Cue::Land(_) => (0.15, 0.05 + 0.3 * hardness, (70.0 + 100.0 * hardness, 40.0), 0.6),
+	};
+	let mut rng = Rng::new(from as u64);
+	let n = (seconds * RATE as f32) as usize;
+	let (mut smooth, mut phase) = (0.0, 0.0);
+	(0..n).map(|k| {
+		let t = k as f32 / n as f32;
+		smooth += (rng.range(-1.0, 1.0) - smooth) * bright;
+		phase += (from + (to - from) * t) / RATE as f32 * std::f32::consts::TAU;
+		// rising fast and dying away
+		let envelope = (t * 50.0).min(1.0) * (1.0 - t).powi(2);
+		envelope * ((1.0 - tone) * smooth * 2.0 + tone * phase.sin()).clamp(-1.0, 1.0)
+	}).collect()
+}
+
+// a WAV file of 16 bit stereo samples, from -1 to 1, at `rate` a second
+pub fn wav(left: &[f32], right: &[f32], rate: u32) -> Vec<u8> {
+	let data = left.len().min(right.len()) as u32 * 4;
+	let mut bytes = Vec::with_capacity(44 + data as usize);
+	bytes.extend_from_slice(b"RIFF");
+	bytes.extend_from_slice(&(36 + data).to_le_bytes());
+	bytes.extend_from_slice(b"WAVEfmt ");
+	bytes.extend_from_slice(&16u32.to_le_bytes());
+	// plain samples, two channels
+	bytes.extend_from_slice(&1u16.to_le_bytes());
+	bytes.extend_from_slice(&2u16.to_le_bytes());
+	bytes.extend_from_slice(&rate.to_le_bytes());
+	bytes.extend_from_slice(&(rate * 4).to_le_bytes());
+	bytes.extend_from_slice(&4u16.to_le_bytes());
+	bytes.extend_from_slice(&16u16.to_le_bytes());
+	bytes.extend_from_slice(b"data");
+	bytes.extend_from_slice(&data.to_le_bytes());
+	for (l, r) in left.iter().zip(right) {
+		bytes.extend_from_slice(&((l * i16::MAX as f32) as i16).to_le_bytes());
+		bytes.extend_from_slice(&((r * i16::MAX as f32) as i16).to_le_bytes());
+	}
+	bytes
+}
+
+// The samples in a WAV file, from -1 to 1 with the channels mixed into one, and how many there
+// are a second. Files of 8, 16, 24 or 32 bit whole numbers or 32 bit floats can be read.
+pub fn samples(bytes: &[u8]) -> Result<(Vec<f32>, u32), String> {
+	if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+		return Err("isn't a WAV file".to_string());
+	}
+	let u16_at = |at: usize| u16::from_le_bytes([bytes[at], bytes[at + 1]]);
+	let u32_at = |at: usize| u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]]);
+	// format, channels, samples a second and bits a sample
+	let mut format = None;
+	let mut at = 12;
+	while at + 8 <= bytes.len() {
+		let size = u32_at(at + 4) as usize;
+		let body = at + 8..(at + 8 + size).min(bytes.len());
+		match &bytes[at..at + 4] {
+			b"fmt " if body.len() >= 16 => {
+				let mut kind = u16_at(body.start);
+				// an extended format, which gives the kind in the first two bytes of a GUID further on
+				if kind == 0xfffe && body.len() >= 26 {
+					kind = u16_at(body.start + 24);
+				}
+				format = Some((kind, u16_at(body.start + 2) as usize, u32_at(body.start + 4), u16_at(body.start + 14) as usize));
+			},
+			b"data" => {
+				let Some((kind, channels, rate, bits)) = format else {
+					return Err("has its samples before their format".to_string());
+				};
+				// whole numbers or floats
+				let float = match (kind, bits) {
+					(1, 8 | 16 | 24 | 32) => false,
+					(3, 32) => true,
+					_ => return Err(format!("has samples of a kind that can't be read (format {}, {} bits)", kind, bits)),
+				};
+				if channels == 0 || rate == 0 {
+					return Err("has no channels".to_string());
+				}
+				let width = bits / 8;
+				let sample = |b: &[u8]| match (float, width) {
+					(true, _) => f32::from_le_bytes([b[0], b[1], b[2], b[3]]),
+					(false, 1) => (b[0] as f32 - 128.0) / 128.0,
+					(false, 2) => i16::from_le_bytes([b[0], b[1]]) as f32 / 32768.0,
+					(false, 3) => i32::from_le_bytes([0, b[0], b[1], b[2]]) as f32 / 2147483648.0,
+					(false, _) => i32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f32 / 2147483648.0,
+				};
+				let frames = bytes[body].chunks_exact(width * channels);
+				let mixed = frames.map(|frame| frame.chunks_exact(width).map(sample).sum::<f32>() / channels as f32);
+				return Ok((mixed.map(|s| s.clamp(-1.0, 1.0)).collect(), rate));
+			},
+			_ => {},
+		}
+		// chunks are padded to an even length
+		at += 8 + size + size % 2;
+	}
+	Err("has no samples".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn a_wav_file_is_read_back_mixed_into_one_channel() {
+		let left = [0.5, -0.25, 1.0, 0.0];
+		let right = [0.5, 0.25, 0.0, -1.0];
+		let (mixed, rate) = samples(&wav(&left, &right, 11025)).unwrap();
+		assert_eq!(rate, 11025);
+		assert_eq!(mixed.len(), 4);
+		for (got, want) in mixed.iter().zip([0.5, 0.0, 0.5, -0.5]) {
+			assert!((got - want).abs() < 0.001, "{} rather than {}", got, want);
+		}
+	}
+
+	#[test]
+	fn what_isnt_a_wav_file_of_samples_is_an_error() {
+		assert!(samples(b"OggS").is_err());
+		let mut odd = wav(&[0.5], &[0.5], RATE);
+		// made out to be 12 bit, which can't be read
+		odd[34] = 12;
+		assert!(samples(&odd).is_err());
+	}
+}
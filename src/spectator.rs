@@ -0,0 +1,32 @@
+// The spectator camera: a viewpoint that flies freely through everything, with no gravity,
+// while the player's body stays where it was left. It starts at the body's eyes and goes back
+// to them when spectating ends.
+
+use macroquad::prelude::*;
+use crate::{settle, World};
+
+pub struct Spectator {
+	i: usize,
+	position: Vec3,
+	// where it was a tick ago, relative to voxel i, to draw it partway between ticks
+	previous: Vec3,
+}
+
+impl Spectator {
+	pub fn new(i: usize, position: Vec3) -> Spectator {
+		Spectator { i, position, previous: position }
+	}
+
+	// fly `delta` in one tick, straight through solid voxels
+	pub fn tick(&mut self, world: &World, delta: Vec3) {
+		let (i, position) = settle(world, self.i, self.position + delta);
+		// where it was, relative to the voxel it's in now
+		self.previous = position - delta;
+		(self.i, self.position) = (i, position);
+	}
+
+	// the voxel and position to view from, `t` of the way from the last tick to this one
+	pub fn view(&self, world: &World, t: f32) -> (usize, Vec3) {
+		settle(world, self.i, self.previous.lerp(self.position, t))
+	}
+}
@@ -0,0 +1,44 @@
+// Image voxelizer: stamp a picture into the world as a one voxel thick wall
+
+use glam::*;
+use image::RgbaImage;
+use crate::{World, Voxel, step};
+use crate::block;
+use crate::heightmap::pixel;
+
+const COLOR_LEVELS: f32 = 16.0;
+
+// snap each channel to one of COLOR_LEVELS evenly spaced values
+fn quantize(c: Vec4) -> Vec4 {
+	let q = |x: f32| (x * (COLOR_LEVELS - 1.0)).round() / (COLOR_LEVELS - 1.0);
+	vec4(q(c.x), q(c.y), q(c.z), 1.0)
+}
+
+// Stamp `image` so that its bottom row is centered on voxel `origin`, facing the camera.
+// The wall runs along whichever horizontal axis is closest to `right` and upward along +y.
+// Placement walks the neighbor graph, so walls wrap and pass through portals like the rays do.
+// Pixels that are mostly transparent are left empty.
+pub fn stamp_image(world: &mut World, origin: usize, right: Vec3, image: &RgbaImage) {
+	let (axis, sign) = if right.x.abs() > right.z.abs() {
+		(0, right.x.signum() as i32)
+	} else {
+		(2, right.z.signum() as i32)
+	};
+	let (w, h) = (image.width(), image.height());
+
+	let mut column = origin;
+	for _ in 0..w/2 {
+		column = step(world, column, axis, -sign);
+	}
+	for px in 0..w {
+		let mut i = column;
+		for py in (0..h).rev() {
+			let c = pixel(image, px, py);
+			if c.w >= 0.5 {
+				world.set(i, Voxel::new(block::CUSTOM, quantize(c)));
+			}
+			i = step(world, i, 1, 1);
+		}
+		column = step(world, column, axis, sign);
+	}
+}
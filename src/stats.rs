@@ -0,0 +1,86 @@
+// The statistics panel, shown with `F9`: what the world is made of, the memory it takes, and
+// how much work the renderer did for the last frame it finished. The world is counted again
+// every so often while the panel is open, if it's changed, since counting it takes a pass over
+// every chunk.
+
+use macroquad::prelude::*;
+use crate::World;
+use crate::block;
+use crate::dirty::Dirty;
+
+// seconds between counts of the world
+const REFRESH: f64 = 1.0;
+// most kinds of block listed, the most common first
+const LISTED: usize = 12;
+const LINE: f32 = 20.0;
+
+pub struct Stats {
+	pub open: bool,
+	// when the world was last counted, and what was found
+	counted: Option<f64>,
+	// whether the world has changed since
+	stale: bool,
+	blocks: Vec<usize>,
+	solid: usize,
+	memory: usize,
+	// rays cast for the last finished frame, and voxels they stepped through together
+	rays: usize,
+	steps: usize,
+}
+
+impl Stats {
+	pub fn new() -> Stats {
+		Stats { open: false, counted: None, stale: true, blocks: vec![], solid: 0, memory: 0, rays: 0, steps: 0 }
+	}
+
+	// take the counts for a frame the renderer finished
+	pub fn frame(&mut self, rays: usize, steps: usize) {
+		(self.rays, self.steps) = (rays, steps);
+	}
+
+	// show or hide the panel, counting the world straight away when it opens
+	pub fn toggle(&mut self) {
+		self.open = !self.open;
+		self.counted = None;
+	}
+
+	// take note of voxels that changed, whether it's open or not
+	pub fn notice(&mut self, dirty: &Dirty) {
+		self.stale |= !dirty.is_empty();
+	}
+
+	// count the world again if the panel is open and the last count is old and out of date
+	pub fn update(&mut self, world: &World) {
+		let now = get_time();
+		if !self.open || self.counted.is_some_and(|t| !self.stale || now - t < REFRESH) {
+			return;
+		}
+		(self.counted, self.stale) = (Some(now), false);
+		self.blocks = world.count_blocks();
+		self.solid = world.count_matching(|v| !v.transparent);
+		self.memory = world.memory();
+	}
+
+	pub fn draw(&self) {
+		if !self.open {
+			return;
+		}
+		let total: usize = self.blocks.iter().sum();
+		let mut kinds: Vec<(usize, usize)> = self.blocks.iter().copied().enumerate().filter(|&(_, n)| n > 0).collect();
+		kinds.sort_by_key(|&(_, n)| std::cmp::Reverse(n));
+		let mut lines = vec![
+			format!("voxels {}: {} solid, {} transparent", total, self.solid, total - self.solid),
+			format!("world data {:.1} MiB", self.memory as f64 / (1024.0 * 1024.0)),
+			format!("rays last frame {}, {:.1} steps each", self.rays, self.steps as f32 / self.rays.max(1) as f32),
+		];
+		for &(b, n) in kinds.iter().take(LISTED) {
+			lines.push(format!("{} {} ({:.1}%)", block::def(b as u16).name, n, 100.0 * n as f32 / total.max(1) as f32));
+		}
+		let (left, top) = (4.0, 70.0);
+		let width = lines.iter().map(|l| measure_text(l, None, 18, 1.0).width).fold(0.0, f32::max) + 12.0;
+		draw_rectangle(left, top, width, lines.len() as f32 * LINE + 8.0, Color::new(0.0, 0.0, 0.0, 0.6));
+		for (k, line) in lines.iter().enumerate() {
+			draw_text(line, left + 6.0, top + (k + 1) as f32 * LINE, 18.0, WHITE);
+		}
+	}
+}
@@ -0,0 +1,361 @@
+// Streaming worlds: worlds too large to keep in memory are played through a window of chunks
+// around the player. The window is an ordinary linked world that wraps like a torus, and each
+// of its chunk slots holds whichever chunk of the full world belongs there relative to the
+// player. Crossing a chunk boundary reloads only the slots that fell behind, on the far side
+// of the window. Chunks are generated from the seed when they come into range, and chunks that
+// were edited are kept when they leave it, along with any text written on their voxels, so
+// they come back the way they were left.
+//
+// Only the default terrain streams: decorations and ore veins reach across chunks, and so does
+// the sea, which fills down to the ground of the whole column, so they are left out.
+//
+// Played together, voxels go by their number in the whole world, numbered like `furl`, rather
+// than by where they are in the window, which is different in every game. The host keeps every
+// chunk edited, whether it's loaded or not, and a joining game asks it for each chunk as it's
+// loaded (see the net module).
+
+use std::collections::HashMap;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use glam::*;
+use rayon::prelude::*;
+use crate::{World, Voxel, Progress, furl, link_world, paint_column, terrain_solid};
+use crate::biome::{self, Biome, Climate, SUBSURFACE_DEPTH};
+use crate::block;
+use crate::noise::{Noise, NoiseParams};
+use crate::rng::Rng;
+
+pub const CHUNK: usize = 16;
+
+type ChunkPos = [usize; 3];
+
+// a chunk's voxels, indexed [x][y][z] within it, and the texts written on them
+#[derive(Clone)]
+struct Contents {
+	voxels: Vec<Voxel>,
+	texts: Vec<(usize, String)>,
+}
+
+fn fingerprint(contents: &Contents) -> u64 {
+	let mut hasher = DefaultHasher::new();
+	contents.texts.hash(&mut hasher);
+	for v in &contents.voxels {
+		v.block.hash(&mut hasher);
+		v.transparent.hash(&mut hasher);
+		v.state.hash(&mut hasher);
+		v.color.to_array().map(f32::to_bits).hash(&mut hasher);
+	}
+	hasher.finish()
+}
+
+struct ChunkGenerator {
+	// the full world, in voxels
+	size: [usize; 3],
+	climate: Climate,
+	terrain: Noise,
+	params: NoiseParams,
+}
+
+impl ChunkGenerator {
+	// voxels of a chunk, indexed [x][y][z] within it
+	fn generate(&self, chunk: ChunkPos) -> Vec<Voxel> {
+		let [nx, ny, nz] = self.size;
+		let mut voxels = vec![Voxel::of(block::AIR); CHUNK*CHUNK*CHUNK];
+		// look a few voxels past the top of the chunk so surface and subsurface layers that
+		// start in the chunk above still come out right
+		let mut solid = vec![false; CHUNK + SUBSURFACE_DEPTH + 1];
+		for x in 0..CHUNK {
+			for z in 0..CHUNK {
+				let (gx, gz) = (chunk[0]*CHUNK + x, chunk[2]*CHUNK + z);
+				let (u, w) = (gx as f32 / nx as f32, gz as f32 / nz as f32);
+				let t = self.climate.temperature(u, w);
+				for (k, s) in solid.iter_mut().enumerate() {
+					let gy = (chunk[1]*CHUNK + k) % ny;
+					*s = terrain_solid(&self.terrain, &self.params, biome::roughness(t), vec3(u, gy as f32 / ny as f32, w));
+				}
+				paint_column(&solid, &Biome::from_temperature(t).palette(), |k, b| {
+					if k < CHUNK {
+						voxels[(x*CHUNK + k)*CHUNK + z] = Voxel::of(b);
+					}
+				});
+			}
+		}
+		voxels
+	}
+}
+
+pub struct Streamer {
+	// the full world, in chunks
+	chunks: [usize; 3],
+	// the window, in chunks
+	window: [usize; 3],
+	// chunk of the full world held in each window slot, and the slot holding each chunk
+	slots: Vec<ChunkPos>,
+	held: HashMap<ChunkPos, usize>,
+	// fingerprint of each slot's voxels as loaded, to tell whether they were edited since
+	loaded: Vec<u64>,
+	edits: HashMap<ChunkPos, Contents>,
+	generator: ChunkGenerator,
+}
+
+impl Streamer {
+	// Set up streaming of a world of `size` voxels through a window of `window` voxels, both
+	// rounded up to whole chunks, and load the window around the middle of the world's height.
+	// The terrain is shaped by the noise `params`.
+	pub fn new(size: [usize; 3], window: [usize; 3], params: &NoiseParams, rng: &mut Rng, progress: &Progress) -> (Streamer, World) {
+		let chunks = size.map(|n| n.div_ceil(CHUNK).max(1));
+		let window = [0, 1, 2].map(|a| window[a].div_ceil(CHUNK).clamp(1, chunks[a]));
+		let generator = ChunkGenerator {
+			size: chunks.map(|c| c * CHUNK),
+			climate: Climate::new(rng),
+			terrain: Noise::new(rng),
+			params: *params,
+		};
+		let mut world = link_world(window[0]*CHUNK, window[1]*CHUNK, window[2]*CHUNK, progress);
+		let mut streamer = Streamer {
+			chunks,
+			window,
+			slots: vec![[usize::MAX; 3]; window[0]*window[1]*window[2]],
+			held: HashMap::new(),
+			loaded: vec![0; window[0]*window[1]*window[2]],
+			edits: HashMap::new(),
+			generator,
+		};
+		progress.begin("chunks", 1);
+		streamer.recenter(&mut world, [0, 0, 0], [0, chunks[1] / 2, 0]);
+		progress.advance();
+		(streamer, world)
+	}
+
+	// Rays that go further than half the window would come back around it into chunks from
+	// the wrong side, so they are cut off there.
+	#[cfg(feature = "graphics")]
+	pub fn view_distance(&self) -> usize {
+		self.window.iter().min().unwrap() * CHUNK / 2
+	}
+
+	// the full world, in voxels
+	pub fn size(&self) -> [usize; 3] {
+		self.chunks.map(|c| c * CHUNK)
+	}
+
+	// coordinates in the full world of window voxel i
+	pub fn global(&self, i: usize) -> [usize; 3] {
+		let [wy, wz] = [self.window[1]*CHUNK, self.window[2]*CHUNK];
+		let local = [i / (wy*wz), i / wz % wy, i % wz];
+		let chunk = self.slots[self.slot_index(local.map(|n| n / CHUNK))];
+		[0, 1, 2].map(|a| chunk[a]*CHUNK + local[a] % CHUNK)
+	}
+
+	// the number of window voxel i in the full world
+	pub fn number(&self, i: usize) -> usize {
+		let [x, y, z] = self.global(i);
+		let [_, ny, nz] = self.size();
+		furl(x, y, z, ny, nz)
+	}
+
+	// the window voxel holding voxel k of the full world, if its chunk is loaded
+	pub fn find(&self, k: usize) -> Option<usize> {
+		let (chunk, [x, y, z]) = self.split(k);
+		let slot = self.slot_at(*self.held.get(&chunk)?);
+		Some(self.voxel_index(slot, x, y, z))
+	}
+
+	// the chunks loaded
+	pub fn held(&self) -> impl Iterator<Item = ChunkPos> + '_ {
+		self.held.keys().copied()
+	}
+
+	pub fn holds(&self, chunk: ChunkPos) -> bool {
+		self.held.contains_key(&chunk)
+	}
+
+	// The voxels of `chunk` as they are now, indexed [x][y][z] within it, if they've been changed
+	// from how they're generated; none if they haven't.
+	pub fn cube(&self, world: &World, chunk: ChunkPos) -> Option<Vec<Voxel>> {
+		if let Some(&k) = self.held.get(&chunk) {
+			let contents = self.read_slot(world, self.slot_at(k));
+			if fingerprint(&contents) != self.loaded[k] {
+				return Some(contents.voxels);
+			}
+		}
+		self.edits.get(&chunk).map(|contents| contents.voxels.clone())
+	}
+
+	// Set voxel k of the full world, in the window if its chunk is loaded and among the edited
+	// chunks kept if it isn't.
+	pub fn put(&mut self, world: &mut World, k: usize, voxel: Voxel) {
+		if let Some(i) = self.find(k) {
+			return world.set(i, voxel);
+		}
+		let (chunk, [x, y, z]) = self.split(k);
+		let generator = &self.generator;
+		let contents = self.edits.entry(chunk).or_insert_with(|| Contents { voxels: generator.generate(chunk), texts: vec![] });
+		contents.voxels[(x*CHUNK + y)*CHUNK + z] = voxel;
+	}
+
+	// Take `voxels` as what `chunk` holds, or the chunk as it's generated if there are none, and
+	// return the window voxels that changed if it's loaded.
+	pub fn replace(&mut self, world: &mut World, chunk: ChunkPos, voxels: Option<Vec<Voxel>>) -> Vec<usize> {
+		let voxels = match voxels {
+			Some(voxels) => {
+				self.edits.insert(chunk, Contents { voxels: voxels.clone(), texts: vec![] });
+				voxels
+			},
+			None if self.edits.remove(&chunk).is_some() || self.held.contains_key(&chunk) => self.generator.generate(chunk),
+			None => return vec![],
+		};
+		let Some(&k) = self.held.get(&chunk) else { return vec![] };
+		let slot = self.slot_at(k);
+		let mut changed = vec![];
+		for x in 0..CHUNK {
+			for y in 0..CHUNK {
+				for z in 0..CHUNK {
+					let (i, voxel) = (self.voxel_index(slot, x, y, z), &voxels[(x*CHUNK + y)*CHUNK + z]);
+					if world.voxel(i) != voxel {
+						world.set(i, voxel.clone());
+						changed.push(i);
+					}
+				}
+			}
+		}
+		changed
+	}
+
+	// Every voxel of the full world changed from how it's generated, by its number, in order.
+	pub fn edits(&self, world: &World) -> Vec<(usize, Voxel)> {
+		let [_, ny, nz] = self.size();
+		let mut chunks: Vec<ChunkPos> = self.edits.keys().chain(self.held.keys()).copied().collect();
+		chunks.sort_unstable();
+		chunks.dedup();
+		let mut edits = vec![];
+		for chunk in chunks {
+			let Some(voxels) = self.cube(world, chunk) else { continue };
+			for (n, (now, made)) in voxels.into_iter().zip(self.generator.generate(chunk)).enumerate() {
+				if now != made {
+					let [x, y, z] = [n / (CHUNK*CHUNK), n / CHUNK % CHUNK, n % CHUNK];
+					edits.push((furl(chunk[0]*CHUNK + x, chunk[1]*CHUNK + y, chunk[2]*CHUNK + z, ny, nz), now));
+				}
+			}
+		}
+		edits.sort_unstable_by_key(|&(k, _)| k);
+		edits
+	}
+
+	// the chunk voxel k of the full world is in, and where it is in the chunk
+	fn split(&self, k: usize) -> (ChunkPos, [usize; 3]) {
+		let [_, ny, nz] = self.size();
+		let at = [k / (ny*nz), k / nz % ny, k % nz];
+		(at.map(|n| n / CHUNK), at.map(|n| n % CHUNK))
+	}
+
+	fn slot_index(&self, slot: [usize; 3]) -> usize {
+		(slot[0] * self.window[1] + slot[1]) * self.window[2] + slot[2]
+	}
+
+	fn slot_at(&self, k: usize) -> [usize; 3] {
+		[k / (self.window[1]*self.window[2]), k / self.window[2] % self.window[1], k % self.window[2]]
+	}
+
+	fn voxel_index(&self, slot: [usize; 3], x: usize, y: usize, z: usize) -> usize {
+		let [wy, wz] = [self.window[1]*CHUNK, self.window[2]*CHUNK];
+		furl(slot[0]*CHUNK + x, slot[1]*CHUNK + y, slot[2]*CHUNK + z, wy, wz)
+	}
+
+	// Keep the window centered on the chunk holding window voxel `i`, usually the camera's.
+	#[cfg(feature = "graphics")]
+	pub fn update(&mut self, world: &mut World, i: usize) {
+		let [wy, wz] = [self.window[1]*CHUNK, self.window[2]*CHUNK];
+		let slot = [i / (wy*wz) / CHUNK, i / wz % wy / CHUNK, i % wz / CHUNK];
+		let chunk = self.slots[self.slot_index(slot)];
+		self.recenter(world, slot, chunk);
+	}
+
+	// load every slot with the chunk that belongs there when `slot` holds `chunk`
+	fn recenter(&mut self, world: &mut World, slot: [usize; 3], chunk: ChunkPos) {
+		let mut stale = vec![];
+		for sx in 0..self.window[0] {
+			for sy in 0..self.window[1] {
+				for sz in 0..self.window[2] {
+					let s = [sx, sy, sz];
+					// offset from the center slot, wrapped into the nearer half of the window
+					let wanted = [0, 1, 2].map(|a| {
+						let w = self.window[a] as i64;
+						let d = (s[a] as i64 - slot[a] as i64 + w / 2).rem_euclid(w) - w / 2;
+						(chunk[a] as i64 + d).rem_euclid(self.chunks[a] as i64) as usize
+					});
+					if self.slots[self.slot_index(s)] != wanted {
+						stale.push((s, wanted));
+					}
+				}
+			}
+		}
+		if stale.is_empty() {
+			return;
+		}
+
+		// keep the chunks on their way out if they changed while loaded
+		let evicted: Vec<(ChunkPos, Contents)> = stale.par_iter().filter_map(|&(s, _)| {
+			let k = self.slot_index(s);
+			if self.slots[k][0] == usize::MAX {
+				return None;
+			}
+			let current = self.read_slot(world, s);
+			(fingerprint(&current) != self.loaded[k]).then_some((self.slots[k], current))
+		}).collect();
+		self.edits.extend(evicted);
+
+		let loaded: Vec<Contents> = stale.par_iter().map(|&(_, wanted)| {
+			match self.edits.get(&wanted) {
+				Some(contents) => contents.clone(),
+				None => Contents { voxels: self.generator.generate(wanted), texts: vec![] },
+			}
+		}).collect();
+		for &(s, _) in &stale {
+			self.held.remove(&self.slots[self.slot_index(s)]);
+		}
+		// chunks coming into the window aren't edits
+		let so_far = world.changes_so_far();
+		for ((s, wanted), contents) in stale.into_iter().zip(loaded) {
+			self.write_slot(world, s, &contents);
+			let k = self.slot_index(s);
+			self.slots[k] = wanted;
+			self.held.insert(wanted, k);
+			self.loaded[k] = fingerprint(&contents);
+		}
+		world.forget_changes(so_far);
+	}
+
+	fn read_slot(&self, world: &World, slot: [usize; 3]) -> Contents {
+		let mut contents = Contents { voxels: Vec::with_capacity(CHUNK*CHUNK*CHUNK), texts: vec![] };
+		for x in 0..CHUNK {
+			for y in 0..CHUNK {
+				for z in 0..CHUNK {
+					let i = self.voxel_index(slot, x, y, z);
+					if let Some(text) = world.text(i) {
+						contents.texts.push((contents.voxels.len(), text.to_string()));
+					}
+					contents.voxels.push(world.voxel(i).clone());
+				}
+			}
+		}
+		contents
+	}
+
+	fn write_slot(&self, world: &mut World, slot: [usize; 3], contents: &Contents) {
+		for x in 0..CHUNK {
+			for y in 0..CHUNK {
+				for z in 0..CHUNK {
+					let i = self.voxel_index(slot, x, y, z);
+					world.set(i, contents.voxels[(x*CHUNK + y)*CHUNK + z].clone());
+					// a sign left here by the chunk before could be in the same place
+					world.set_text(i, "");
+				}
+			}
+		}
+		for (k, text) in &contents.texts {
+			let (x, y, z) = (k / (CHUNK*CHUNK), k / CHUNK % CHUNK, k % CHUNK);
+			world.set_text(self.voxel_index(slot, x, y, z), text);
+		}
+	}
+}
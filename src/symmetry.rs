@@ -0,0 +1,60 @@
+// Symmetric building: while it's on, every block placed or broken is also placed or broken at
+// its mirror images across planes through a chosen center voxel, and at copies repeated evenly
+// around the world along x and z. Offsets from the center are read off the ids and walked over
+// the graph, so like `World::displacement` this holds for the torus worlds are linked as.
+
+use crate::{World, offset};
+
+pub struct Symmetry {
+	// voxel the mirror planes pass through the middle of, while symmetry is on
+	pub center: Option<usize>,
+	// which axes are mirrored, across the plane through the center at right angles to them
+	mirror: [bool; 3],
+	// copies along each of x and z, spaced evenly around the world
+	repeat: usize,
+}
+
+impl Symmetry {
+	// Mirrored axes as letters, like "xz", and how many times to repeat around the world.
+	pub fn new(axes: &str, repeat: usize) -> Symmetry {
+		let mirror = ['x', 'y', 'z'].map(|a| axes.contains(a));
+		Symmetry { center: None, mirror, repeat: repeat.max(1) }
+	}
+
+	pub fn describe(&self) -> String {
+		let axes: String = ['x', 'y', 'z'].iter().zip(self.mirror).filter(|(_, m)| *m).map(|(a, _)| *a).collect();
+		let mut text = if axes.is_empty() { "symmetry".to_string() } else { format!("mirrored in {}", axes) };
+		if self.repeat > 1 {
+			text += &format!(", repeated {} times", self.repeat);
+		}
+		text
+	}
+
+	// Voxel i and all its images, without repeats.
+	pub fn images(&self, world: &World, i: usize) -> Vec<usize> {
+		let Some(center) = self.center else { return vec![i] };
+		let d = world.displacement(center, i);
+		let size = world.size();
+		let mut found = vec![];
+		for flips in 0..8 {
+			if (0..3).any(|k| flips & (1 << k) != 0 && !self.mirror[k]) {
+				continue;
+			}
+			let e: [i32; 3] = [0, 1, 2].map(|k| if flips & (1 << k) != 0 { -d[k] } else { d[k] });
+			for rx in 0..self.repeat {
+				for rz in 0..self.repeat {
+					let shift = [(rx * size[0] / self.repeat) as i32, 0, (rz * size[2] / self.repeat) as i32];
+					// walking the shorter way around
+					let walk = [0, 1, 2].map(|k| {
+						let n = size[k] as i32;
+						(e[k] + shift[k] + n / 2).rem_euclid(n) - n / 2
+					});
+					found.push(offset(world, center, walk));
+				}
+			}
+		}
+		found.sort_unstable();
+		found.dedup();
+		found
+	}
+}
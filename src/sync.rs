@@ -0,0 +1,448 @@
+// Syncing with other games as equals rather than joining a host, for building together over a
+// connection that comes and goes. One game listens with `--sync-listen` (on `--port`) and the
+// others connect to it with `--sync <address>`, all started with the same `--seed` and world
+// size. They only sync if they'd make the world from the same recipe (see `recipe` in the lib).
+// Each keeps playing its own world while the connection's down, and when it's back they catch
+// each other up.
+//
+// Every change to a voxel is stamped with a clock that's always ahead of every stamp the game has
+// seen, and the number of the game that made it to break ties. A voxel is whatever its change
+// with the largest stamp made it, whichever order the changes arrive in, so once the games have
+// heard of each other's changes they all have the same world. Each game also numbers its own
+// changes and keeps count of how many of every game's it's seen, so on connecting each side sends
+// only the changes the other hasn't seen, in the order they were made. Changes made after that
+// are only sent once the catching up has been, so a game never hears of a change before earlier
+// ones by the same game, and the count it's seen is all it needs to know what it's missing.
+// Messages are lines of text:
+//
+//     sync <version> <seed> <x> <y> <z> <game> <recipe>   the first thing each side sends
+//     seen <game>:<count> ...                             how many of each game's changes it's seen
+//     op <voxel> <clock> <game> <count> <block> <state> <r> <g> <b> <a>
+//
+// A game passes on the changes it takes to the others it's connected to, so a game connected to
+// by several keeps them all in step. What the world does by itself, like water flowing, is
+// changed by each game and synced like any other change.
+
+use std::collections::HashMap;
+use std::io;
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+use glam::*;
+use crate::{World, Voxel, interact, log, rng};
+use crate::block::{self, BlockId};
+use crate::net::Peer;
+
+pub const PORT: u16 = 7879;
+// the version of the messages, changed whenever they are
+const VERSION: u32 = 2;
+// how long to wait between tries to connect, and for each try
+const RETRY: Duration = Duration::from_secs(3);
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+// when a change was made, compared by the clock first and then the game that made it
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+struct Stamp {
+	clock: u64,
+	game: u64,
+}
+
+#[derive(Clone)]
+struct Change {
+	stamp: Stamp,
+	// which of its game's changes it was, counting from 1
+	count: u64,
+	voxel: Voxel,
+}
+
+// the other end of a connection, and which game it is once it's said
+struct Link {
+	peer: Peer,
+	game: Option<u64>,
+	// whether it's been sent the changes it hadn't seen, and so can be sent new ones as they're made
+	caught_up: bool,
+	// whether its world differs, to be disconnected
+	refused: bool,
+}
+
+pub struct Sync {
+	// this game's number, different for every game
+	game: u64,
+	seed: u64,
+	// what the world is made from besides the seed
+	recipe: String,
+	// taking new connections, for the listening game
+	listener: Option<TcpListener>,
+	// where to connect to, for the others, and the try under way
+	address: Option<String>,
+	connecting: Option<JoinHandle<io::Result<TcpStream>>>,
+	retry: Instant,
+	links: Vec<Link>,
+	next_id: u32,
+	// ahead of every stamp seen
+	clock: u64,
+	// the changes this game's made
+	count: u64,
+	// the latest change to every voxel changed since the world was made
+	latest: HashMap<usize, Change>,
+	// how many of each game's changes this game has seen
+	seen: HashMap<u64, u64>,
+	// voxels set last tick from what another game sent, so they aren't taken as this game's own
+	echoes: HashMap<usize, Voxel>,
+}
+
+impl Sync {
+	// listen on `port` for games syncing the world made from `seed` and `recipe`
+	pub fn listen(port: u16, seed: u64, recipe: &str) -> io::Result<Sync> {
+		let listener = TcpListener::bind(("0.0.0.0", port))?;
+		listener.set_nonblocking(true)?;
+		Ok(Sync { listener: Some(listener), ..Sync::new(seed, recipe, None) })
+	}
+
+	// sync with the game listening at `address`, with or without a port, trying again whenever
+	// the connection's lost
+	pub fn connect(address: &str, seed: u64, recipe: &str) -> Sync {
+		let address = if address.contains(':') { address.to_string() } else { format!("{}:{}", address, PORT) };
+		Sync::new(seed, recipe, Some(address))
+	}
+
+	fn new(seed: u64, recipe: &str, address: Option<String>) -> Sync {
+		Sync {
+			game: rng::random_seed(),
+			seed,
+			recipe: recipe.to_string(),
+			listener: None,
+			address,
+			connecting: None,
+			retry: Instant::now(),
+			links: vec![],
+			next_id: 0,
+			clock: 0,
+			count: 0,
+			latest: HashMap::new(),
+			seen: HashMap::new(),
+			echoes: HashMap::new(),
+		}
+	}
+
+	// A tick of syncing: make the voxels `changed` this game's changes and send them, and take
+	// the changes other games sent. Returns what there is to tell the player, like losing the
+	// connection.
+	pub fn tick(&mut self, world: &mut World, changed: &[usize]) -> Vec<String> {
+		let mut said = vec![];
+		self.accept(world, &mut said);
+		for &k in changed {
+			let voxel = world.voxel(k).clone();
+			if self.echoes.remove(&k).is_some_and(|v| v == voxel) {
+				continue;
+			}
+			self.clock += 1;
+			self.count += 1;
+			let change = Change { stamp: Stamp { clock: self.clock, game: self.game }, count: self.count, voxel };
+			let line = op(k, &change);
+			for link in self.links.iter_mut().filter(|l| l.caught_up) {
+				link.peer.send(&line);
+			}
+			self.latest.insert(k, change);
+		}
+		self.echoes.clear();
+		self.seen.insert(self.game, self.count);
+		let mut received = vec![];
+		for (n, link) in self.links.iter_mut().enumerate() {
+			received.extend(link.peer.receive().into_iter().map(|line| (n, line)));
+		}
+		for (n, line) in received {
+			if let Err(e) = self.handle(world, n, &line, &mut said) {
+				log::warning!("sync", "{}: '{}'", e, line);
+			}
+		}
+		for link in &mut self.links {
+			link.peer.flush();
+		}
+		let lost = self.links.iter().filter(|l| l.peer.gone() && !l.refused).count();
+		self.links.retain(|l| !l.peer.gone() && !l.refused);
+		if lost > 0 {
+			said.push(match self.address {
+				Some(_) => "lost the sync connection; carrying on alone until it's back".to_string(),
+				None => format!("{} synced games went away", lost),
+			});
+		}
+		said
+	}
+
+	// let in the games that have connected, or connect to the listening game if it's time
+	fn accept(&mut self, world: &World, said: &mut Vec<String>) {
+		let mut streams = vec![];
+		if let Some(listener) = &self.listener {
+			while let Ok((stream, address)) = listener.accept() {
+				log::info!("sync", "{} connected", address);
+				streams.push(stream);
+			}
+		}
+		if let Some(address) = &self.address {
+			if self.connecting.as_ref().is_some_and(|c| c.is_finished()) {
+				match self.connecting.take().map(|c| c.join()) {
+					Some(Ok(Ok(stream))) => {
+						said.push(format!("syncing with {}", address));
+						streams.push(stream);
+					},
+					Some(Ok(Err(e))) => log::info!("sync", "can't reach {}: {}", address, e),
+					_ => {},
+				}
+			}
+			if self.links.is_empty() && self.connecting.is_none() && Instant::now() >= self.retry {
+				let address = address.clone();
+				self.retry = Instant::now() + RETRY;
+				self.connecting = Some(std::thread::spawn(move || {
+					let socket = address.to_socket_addrs()?.next().ok_or(io::ErrorKind::NotFound)?;
+					TcpStream::connect_timeout(&socket, TIMEOUT)
+				}));
+			}
+		}
+		for stream in streams {
+			let mut peer = match Peer::new(self.next_id, stream) {
+				Ok(peer) => peer,
+				Err(e) => {
+					log::warning!("sync", "{}", e);
+					continue;
+				},
+			};
+			self.next_id += 1;
+			let [x, y, z] = world.size();
+			peer.send(&format!("sync {} {} {} {} {} {} {}", VERSION, self.seed, x, y, z, self.game, self.recipe));
+			let seen: Vec<String> = self.seen.iter().map(|(game, count)| format!("{}:{}", game, count)).collect();
+			peer.send(&format!("seen {}", seen.join(" ")));
+			self.links.push(Link { peer, game: None, caught_up: false, refused: false });
+		}
+	}
+
+	// act on a line from the nth link, adding what there is to tell the player to `said`
+	fn handle(&mut self, world: &mut World, n: usize, line: &str, said: &mut Vec<String>) -> Result<(), String> {
+		let words: Vec<&str> = line.split_whitespace().collect();
+		let [x, y, z] = world.size();
+		let link = &mut self.links[n];
+		if link.refused {
+			return Ok(());
+		}
+		if link.game.is_none() {
+			let game = match words.as_slice() {
+				["sync", version, ..] if version.parse() != Ok(VERSION) => Err(format!("the other game runs another version of the game ({} rather than {}); both need the same one", version, VERSION)),
+				["sync", _, seed, ..] if seed.parse() != Ok(self.seed) => Err(format!("the other game's world is made from seed {}; start with --seed {} to sync with it", seed, seed)),
+				["sync", _, _, sx, sy, sz, _, ..] if ![sx, sy, sz].iter().map(|n| n.parse().ok()).eq([x, y, z].map(Some)) => Err(format!("the other game's world is {}x{}x{}; both need the same size", sx, sy, sz)),
+				["sync", _, _, _, _, _, _, theirs @ ..] if theirs.join(" ") != self.recipe => Err(format!("the other game makes its world from '{}', but this one makes it from '{}'; start both with the same options and files", theirs.join(" "), self.recipe)),
+				["sync", _, _, _, _, _, game, ..] => game.parse().map_err(|_| "bad game".to_string()),
+				_ => Err("the other end isn't a game to sync with".to_string()),
+			};
+			match game {
+				Ok(game) => link.game = Some(game),
+				Err(e) if self.listener.is_some() => {
+					said.push("turned away a game that can't sync with this one".to_string());
+					log::warning!("sync", "{}", e);
+					link.refused = true;
+				},
+				Err(e) => {
+					said.push(e);
+					// the games differ, so there's no point trying again
+					self.address = None;
+					link.refused = true;
+				},
+			}
+			return Ok(());
+		}
+		match words.as_slice() {
+			["seen", counts @ ..] => {
+				let mut theirs = HashMap::new();
+				for count in counts {
+					let (game, count) = count.split_once(':').ok_or("bad count")?;
+					theirs.insert(game.parse::<u64>().map_err(|_| "bad game")?, count.parse::<u64>().map_err(|_| "bad count")?);
+				}
+				for line in self.unseen(&theirs) {
+					self.links[n].peer.send(&line);
+				}
+				self.links[n].caught_up = true;
+			},
+			["op", ..] => {
+				let (k, change) = parse_op(&words, x*y*z)?;
+				let line = op(k, &change);
+				if self.merge(world, k, change) {
+					for (m, other) in self.links.iter_mut().enumerate() {
+						if m != n && other.caught_up {
+							other.peer.send(&line);
+						}
+					}
+				}
+			},
+			_ => return Err("can't understand".to_string()),
+		}
+		Ok(())
+	}
+
+	// Take another game's change to voxel k, unless the voxel's latest change is later. Returns
+	// whether it was taken.
+	fn merge(&mut self, world: &mut World, k: usize, change: Change) -> bool {
+		self.clock = self.clock.max(change.stamp.clock);
+		let seen = self.seen.entry(change.stamp.game).or_default();
+		*seen = (*seen).max(change.count);
+		if self.latest.get(&k).is_some_and(|c| c.stamp >= change.stamp) {
+			return false;
+		}
+		world.set(k, change.voxel.clone());
+		self.echoes.insert(k, change.voxel.clone());
+		self.latest.insert(k, change);
+		true
+	}
+
+	// `op` lines for the changes a game that's seen `theirs` of each game's hasn't, in the order
+	// they were made, so catching up cut short leaves no gaps
+	fn unseen(&self, theirs: &HashMap<u64, u64>) -> Vec<String> {
+		let mut unseen: Vec<(&usize, &Change)> = self.latest.iter().filter(|(_, c)| c.count > theirs.get(&c.stamp.game).copied().unwrap_or(0)).collect();
+		unseen.sort_by_key(|(_, c)| (c.stamp.game, c.count));
+		unseen.into_iter().map(|(&k, change)| op(k, change)).collect()
+	}
+}
+
+fn op(k: usize, change: &Change) -> String {
+	let (v, c) = (&change.voxel, change.voxel.color);
+	format!("op {} {} {} {} {} {} {} {} {} {}", k, change.stamp.clock, change.stamp.game, change.count, v.block, v.state, c.x, c.y, c.z, c.w)
+}
+
+// the voxel an `op` line's words change, in a world of `volume` voxels, and how
+fn parse_op(words: &[&str], volume: usize) -> Result<(usize, Change), &'static str> {
+	let number = |n: &str| n.parse().ok().filter(|n: &f32| n.is_finite()).ok_or("bad number");
+	let ["op", k, clock, game, count, b, state, r, g, bl, a] = words else { return Err("can't understand") };
+	let k: usize = k.parse().ok().filter(|&k| k < volume).ok_or("no such voxel")?;
+	let stamp = Stamp { clock: clock.parse().map_err(|_| "bad clock")?, game: game.parse().map_err(|_| "bad game")? };
+	let count: u64 = count.parse().map_err(|_| "bad count")?;
+	let block: BlockId = b.parse().ok().filter(|&b: &BlockId| (b as usize) < block::all().len()).ok_or("no such block")?;
+	let state = state.parse().map_err(|_| "bad state")?;
+	let color = vec4(number(r)?, number(g)?, number(bl)?, number(a)?);
+	let voxel = Voxel { transparent: interact::transparent(block, state), ..Voxel::new(block, color).with_state(state) };
+	Ok((k, Change { stamp, count, voxel }))
+}
+
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{link_world, Progress};
+
+	// a game numbered `game`, and its world
+	fn game(game: u64) -> (Sync, World) {
+		(Sync { game, ..Sync::new(0, "", None) }, link_world(4, 4, 4, &Progress::default()))
+	}
+
+	// make the voxels `ks` `block` in a game's world, as a player would
+	fn edit(sync: &mut Sync, world: &mut World, ks: &[usize], block: BlockId) {
+		for &k in ks {
+			world.set(k, Voxel::of(block));
+		}
+		let changed = world.take_changes();
+		sync.tick(world, &changed);
+	}
+
+	// what `from` has to catch `to` up on, as lines
+	fn catch_up(from: &Sync, to: &Sync) -> Vec<String> {
+		from.unseen(&to.seen)
+	}
+
+	fn deliver(to: &mut Sync, world: &mut World, lines: &[String]) {
+		for line in lines {
+			let words: Vec<&str> = line.split_whitespace().collect();
+			let (k, change) = parse_op(&words, 64).unwrap();
+			to.merge(world, k, change);
+		}
+		let changed = world.take_changes();
+		to.tick(world, &changed);
+	}
+
+	fn same(a: &World, b: &World) -> bool {
+		(0..64).all(|k| a.voxel(k) == b.voxel(k))
+	}
+
+	#[test]
+	fn changes_arriving_out_of_order_and_twice_end_the_same() {
+		let (mut a, mut world_a) = game(1);
+		let (mut b, mut world_b) = game(2);
+		edit(&mut a, &mut world_a, &[0, 1, 2], block::STONE);
+		edit(&mut b, &mut world_b, &[2, 3], block::DIRT);
+		edit(&mut a, &mut world_a, &[3], block::SAND);
+		let mut to_b = catch_up(&a, &b);
+		let mut to_a = catch_up(&b, &a);
+		to_b.reverse();
+		to_b.extend(to_b.clone());
+		to_a.extend(to_a.clone());
+		to_a.reverse();
+		deliver(&mut b, &mut world_b, &to_b);
+		deliver(&mut a, &mut world_a, &to_a);
+		assert!(same(&world_a, &world_b));
+		// where they met, a's clock was ahead, having made more changes
+		assert_eq!([2, 3].map(|k| world_a.voxel(k).block), [block::STONE, block::SAND]);
+		assert!(catch_up(&a, &b).is_empty() && catch_up(&b, &a).is_empty());
+	}
+
+	#[test]
+	fn catching_up_cut_short_is_finished_on_reconnecting() {
+		let (mut a, mut world_a) = game(1);
+		let (mut b, mut world_b) = game(2);
+		for k in 0..10 {
+			edit(&mut a, &mut world_a, &[k], block::STONE);
+		}
+		let to_b = catch_up(&a, &b);
+		deliver(&mut b, &mut world_b, &to_b[..4]);
+		assert_eq!(b.seen[&1], 4);
+		// apart, both carry on, over some of the same voxels
+		edit(&mut a, &mut world_a, &[20, 8], block::SAND);
+		edit(&mut b, &mut world_b, &[8, 9, 30], block::DIRT);
+		let (to_b, to_a) = (catch_up(&a, &b), catch_up(&b, &a));
+		assert_eq!(to_b.len(), 10 - 4 + 2 - 1, "a's change to 8 before the sand isn't its latest");
+		deliver(&mut b, &mut world_b, &to_b);
+		deliver(&mut a, &mut world_a, &to_a);
+		assert!(same(&world_a, &world_b));
+		// b only knew of a's first four, so its clock was behind a's on 8 and 9
+		assert_eq!([8, 9, 20, 30].map(|k| world_a.voxel(k).block), [block::SAND, block::STONE, block::SAND, block::DIRT]);
+	}
+
+	#[test]
+	fn games_connected_sync_what_was_made_before_and_after() {
+		let (mut a, mut world_a) = game(1);
+		a.listener = Some(TcpListener::bind("127.0.0.1:0").unwrap());
+		a.listener.as_ref().unwrap().set_nonblocking(true).unwrap();
+		let address = a.listener.as_ref().unwrap().local_addr().unwrap().to_string();
+		let (mut b, mut world_b) = game(2);
+		b.address = Some(address);
+		edit(&mut a, &mut world_a, &[0, 1, 2, 3], block::STONE);
+		let start = Instant::now();
+		while !b.links.iter().any(|l| l.caught_up) && start.elapsed() < TIMEOUT {
+			edit(&mut a, &mut world_a, &[], block::AIR);
+			edit(&mut b, &mut world_b, &[], block::AIR);
+		}
+		// made as soon as they're connected, so it's sent after the catching up
+		edit(&mut a, &mut world_a, &[5], block::SAND);
+		edit(&mut b, &mut world_b, &[6], block::DIRT);
+		while !same(&world_a, &world_b) && start.elapsed() < TIMEOUT {
+			edit(&mut a, &mut world_a, &[], block::AIR);
+			edit(&mut b, &mut world_b, &[], block::AIR);
+		}
+		assert!(same(&world_a, &world_b));
+		assert_eq!(world_b.voxel(5).block, block::SAND);
+		assert_eq!(world_a.voxel(6).block, block::DIRT);
+	}
+
+	#[test]
+	fn games_made_from_different_recipes_dont_sync() {
+		let (mut a, mut world_a) = game(1);
+		a.listener = Some(TcpListener::bind("127.0.0.1:0").unwrap());
+		a.listener.as_ref().unwrap().set_nonblocking(true).unwrap();
+		let address = a.listener.as_ref().unwrap().local_addr().unwrap().to_string();
+		let (mut b, mut world_b) = game(2);
+		b.address = Some(address);
+		b.recipe = "generator maze".to_string();
+		let start = Instant::now();
+		while b.address.is_some() && start.elapsed() < TIMEOUT {
+			edit(&mut a, &mut world_a, &[], block::AIR);
+			edit(&mut b, &mut world_b, &[], block::AIR);
+		}
+		assert!(b.address.is_none());
+		assert!(a.links.iter().all(|l| l.game.is_none() || l.refused));
+	}
+}
@@ -0,0 +1,178 @@
+// On-screen controls for touch screens, shown from the first time the screen is touched. A
+// touch on the left third of the screen is a joystick to walk with, wherever it starts.
+// Anywhere else, dragging looks around, a tap breaks the block looked at and holding still
+// places one. Buttons on screen jump, pause and open the inventory.
+
+use std::collections::HashMap;
+use macroquad::prelude::*;
+use crate::input::Action;
+
+// pixels from the middle of the joystick to its edge
+const STICK_RADIUS: f32 = 60.0;
+// pixels a touch can wander and still count as a tap or being held still
+const TAP_MOVE: f32 = 12.0;
+// seconds a touch is held still before it places a block
+const LONG_PRESS: f64 = 0.5;
+// radians turned per pixel dragged
+const LOOK_SPEED: f32 = 0.006;
+const BUTTON_RADIUS: f32 = 32.0;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Button {
+	Jump,
+	Pause,
+	Inventory,
+}
+
+const BUTTONS: [(Button, &str); 3] = [(Button::Jump, "jump"), (Button::Pause, "pause"), (Button::Inventory, "items")];
+
+// what a finger on the screen is doing
+enum Role {
+	// the joystick, centered where the touch started
+	Stick { center: Vec2, at: Vec2 },
+	Look { start: Vec2, last: Vec2, since: f64, still: bool, placed: bool },
+	Button(Button),
+}
+
+pub struct Touch {
+	// whether the screen has ever been touched
+	pub active: bool,
+	roles: HashMap<u64, Role>,
+	// buttons held this frame and the one before
+	held: Vec<Button>,
+	before: Vec<Button>,
+	// pixels dragged to look this frame
+	dragged: Vec2,
+	tapped: bool,
+	long_pressed: bool,
+}
+
+fn button_center(button: Button) -> Vec2 {
+	let (w, h) = (screen_width(), screen_height());
+	match button {
+		Button::Jump => vec2(w - 2.5 * BUTTON_RADIUS, h - 5.0 * BUTTON_RADIUS),
+		Button::Pause => vec2(w / 2.0 - 1.5 * BUTTON_RADIUS, 1.5 * BUTTON_RADIUS),
+		Button::Inventory => vec2(w / 2.0 + 1.5 * BUTTON_RADIUS, 1.5 * BUTTON_RADIUS),
+	}
+}
+
+impl Touch {
+	pub fn new() -> Touch {
+		Touch {
+			active: false,
+			roles: HashMap::new(),
+			held: vec![],
+			before: vec![],
+			dragged: Vec2::ZERO,
+			tapped: false,
+			long_pressed: false,
+		}
+	}
+
+	// take this frame's touches
+	pub fn update(&mut self) {
+		let touches = touches();
+		self.active |= !touches.is_empty();
+		self.before = std::mem::take(&mut self.held);
+		self.dragged = Vec2::ZERO;
+		self.tapped = false;
+		self.long_pressed = false;
+		self.roles.retain(|id, _| touches.iter().any(|t| t.id == *id));
+		for touch in touches {
+			let at = touch.position;
+			if touch.phase == TouchPhase::Started {
+				let role = match BUTTONS.iter().find(|&&(b, _)| button_center(b).distance(at) < BUTTON_RADIUS) {
+					Some(&(button, _)) => Role::Button(button),
+					None if at.x < screen_width() / 3.0 => Role::Stick { center: at, at },
+					None => Role::Look { start: at, last: at, since: get_time(), still: true, placed: false },
+				};
+				self.roles.insert(touch.id, role);
+			}
+			let ended = matches!(touch.phase, TouchPhase::Ended | TouchPhase::Cancelled);
+			match self.roles.get_mut(&touch.id) {
+				Some(Role::Stick { at: stick, .. }) => *stick = at,
+				Some(Role::Look { start, last, since, still, placed }) => {
+					self.dragged += at - *last;
+					*last = at;
+					*still &= start.distance(at) <= TAP_MOVE;
+					if *still && !*placed && get_time() - *since >= LONG_PRESS {
+						*placed = true;
+						self.long_pressed = true;
+					}
+					self.tapped |= ended && *still && !*placed;
+				},
+				Some(Role::Button(button)) if !ended => self.held.push(*button),
+				_ => {},
+			}
+			if ended {
+				self.roles.remove(&touch.id);
+			}
+		}
+	}
+
+	// which way the joystick is pushed, with y forward, up to 1 at its edge
+	pub fn walk(&self) -> Vec2 {
+		self.roles.values().find_map(|role| match role {
+			Role::Stick { center, at } => Some(((*at - *center) / STICK_RADIUS * vec2(1.0, -1.0)).clamp_length_max(1.0)),
+			_ => None,
+		}).unwrap_or(Vec2::ZERO)
+	}
+
+	// radians to turn across and up for this frame's dragging
+	pub fn look(&self) -> Vec2 {
+		self.dragged * vec2(LOOK_SPEED, -LOOK_SPEED)
+	}
+
+	// whether the screen was tapped this frame, which breaks the block looked at outright
+	pub fn tapped(&self) -> bool {
+		self.tapped
+	}
+
+	fn button(action: Action) -> Option<Button> {
+		match action {
+			Action::Jump => Some(Button::Jump),
+			Action::Pause => Some(Button::Pause),
+			Action::Inventory => Some(Button::Inventory),
+			_ => None,
+		}
+	}
+
+	pub fn down(&self, action: Action) -> bool {
+		match action {
+			Action::Break => self.tapped,
+			_ => Touch::button(action).is_some_and(|b| self.held.contains(&b)),
+		}
+	}
+
+	pub fn pressed(&self, action: Action) -> bool {
+		match action {
+			Action::Break => self.tapped,
+			Action::Place => self.long_pressed,
+			_ => Touch::button(action).is_some_and(|b| self.held.contains(&b) && !self.before.contains(&b)),
+		}
+	}
+
+	// draw the joystick and buttons, once the screen has been touched
+	pub fn draw(&self) {
+		if !self.active {
+			return;
+		}
+		let (center, at) = self.roles.values().find_map(|role| match role {
+			Role::Stick { center, at } => Some((*center, *at)),
+			_ => None,
+		}).unwrap_or_else(|| {
+			let rest = vec2(2.0 * STICK_RADIUS, screen_height() - 2.5 * STICK_RADIUS);
+			(rest, rest)
+		});
+		let knob = center + (at - center).clamp_length_max(STICK_RADIUS);
+		draw_circle_lines(center.x, center.y, STICK_RADIUS, 2.0, Color::new(1.0, 1.0, 1.0, 0.5));
+		draw_circle(knob.x, knob.y, STICK_RADIUS / 3.0, Color::new(1.0, 1.0, 1.0, 0.4));
+		for (button, label) in BUTTONS {
+			let c = button_center(button);
+			let alpha = if self.held.contains(&button) { 0.5 } else { 0.25 };
+			draw_circle(c.x, c.y, BUTTON_RADIUS, Color::new(1.0, 1.0, 1.0, alpha));
+			let width = measure_text(label, None, 18, 1.0).width;
+			draw_text(label, c.x - width / 2.0, c.y + 5.0, 18.0, WHITE);
+		}
+	}
+}
@@ -0,0 +1,100 @@
+// Flowing water. A water voxel's state is how far it is from a source: 0 for a source, which
+// stays put, and 1 up to MAX_DISTANCE for water that has flowed out of one. Water falls along
+// gravity and spreads across it wherever it rests on ground or on still water, thinning out
+// as it goes, and drains away again once nothing feeds it.
+//
+// Only voxels that changed, and their neighbors, are looked at, scheduled a few ticks after
+// they changed, so the still water of a whole sea costs nothing.
+
+use crate::{World, Voxel, step};
+use crate::block;
+use crate::schedule::{Scheduler, Update};
+
+// furthest water flows sideways from a source
+const MAX_DISTANCE: u8 = 7;
+// ticks between a voxel changing and the water around it moving on
+const FLOW_INTERVAL: u64 = 5;
+
+pub struct Flow {
+	// whether water flowing between two sources becomes a source itself
+	infinite: bool,
+}
+
+// distance of the water in voxel i from its source, if there is any
+fn distance(world: &World, i: usize) -> Option<u8> {
+	world.liquid(i).then(|| world.voxel(i).state)
+}
+
+// whether water in voxel i spreads sideways, resting on something solid or on still water,
+// rather than only falling
+fn spreads(world: &World, i: usize) -> bool {
+	let gravity = world.gravity_at(i);
+	let below = step(world, i, gravity.axis, gravity.sign);
+	!world.transparent(below) || distance(world, below) == Some(0)
+}
+
+impl Flow {
+	pub fn new(infinite: bool) -> Flow {
+		Flow { infinite }
+	}
+
+	// look again at voxels that changed and their neighbors, FLOW_INTERVAL ticks from now
+	pub fn notice(&self, world: &World, changed: &[usize], schedule: &mut Scheduler) {
+		for &i in changed {
+			schedule.schedule(i, Update::Flow, FLOW_INTERVAL);
+			for axis in 0..3 {
+				for sign in [-1, 1] {
+					schedule.schedule(step(world, i, axis, sign), Update::Flow, FLOW_INTERVAL);
+				}
+			}
+		}
+	}
+
+	// move the water on in the voxels in `due`
+	pub fn flow(&self, world: &mut World, due: &[usize]) {
+		// work out every change before making any, so the flow doesn't depend on the order
+		let updates: Vec<(usize, Option<u8>)> = due.iter().filter_map(|&i| {
+			let wanted = self.wanted(world, i)?;
+			(wanted != distance(world, i)).then_some((i, wanted))
+		}).collect();
+		for (i, wanted) in updates {
+			world.set(i, match wanted {
+				Some(d) => Voxel::of(block::WATER).with_state(d),
+				None => Voxel::of(block::AIR),
+			});
+		}
+	}
+
+	// what voxel i should hold next, if it's air or flowing water: how far from a source the
+	// water there would be, or None for air
+	fn wanted(&self, world: &World, i: usize) -> Option<Option<u8>> {
+		let current = distance(world, i);
+		if current == Some(0) || (current.is_none() && world.voxel(i).block != block::AIR) {
+			return None;
+		}
+		let gravity = world.gravity_at(i);
+		let above = step(world, i, gravity.axis, -gravity.sign);
+		if distance(world, above).is_some() {
+			return Some(Some(1));
+		}
+		let below = step(world, i, gravity.axis, gravity.sign);
+		let across = [(gravity.axis + 1) % 3, (gravity.axis + 2) % 3]
+			.into_iter()
+			.flat_map(|axis| [step(world, i, axis, -1), step(world, i, axis, 1)]);
+		let mut sources = 0;
+		let mut nearest = None;
+		for j in across {
+			let Some(d) = distance(world, j) else { continue };
+			if d == 0 {
+				sources += 1;
+			}
+			if d < MAX_DISTANCE && spreads(world, j) {
+				nearest = Some(nearest.map_or(d + 1, |n: u8| n.min(d + 1)));
+			}
+		}
+		if self.infinite && sources >= 2 && (!world.transparent(below) || distance(world, below) == Some(0)) {
+			return Some(Some(0));
+		}
+		Some(nearest)
+	}
+}
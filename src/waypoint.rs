@@ -0,0 +1,43 @@
+// Waypoints: named places the player has marked, to go back to later from the console. A place
+// is a voxel and the position in it, which pins it down in any topology, and which way the
+// camera was facing, roll and all.
+
+use macroquad::prelude::*;
+
+#[derive(Clone, Copy)]
+pub struct Waypoint {
+	pub i: usize,
+	pub position: Vec3,
+	pub rotation: Quat,
+}
+
+#[derive(Default)]
+pub struct Waypoints {
+	// in the order they were first marked
+	marked: Vec<(String, Waypoint)>,
+}
+
+impl Waypoints {
+	// mark a place, moving the waypoint if the name is taken
+	pub fn mark(&mut self, name: &str, waypoint: Waypoint) {
+		match self.marked.iter_mut().find(|(n, _)| n == name) {
+			Some((_, w)) => *w = waypoint,
+			None => self.marked.push((name.to_string(), waypoint)),
+		}
+	}
+
+	pub fn get(&self, name: &str) -> Option<Waypoint> {
+		self.marked.iter().find(|(n, _)| n == name).map(|&(_, w)| w)
+	}
+
+	// forget a waypoint, returning whether there was one
+	pub fn unmark(&mut self, name: &str) -> bool {
+		let before = self.marked.len();
+		self.marked.retain(|(n, _)| n != name);
+		self.marked.len() != before
+	}
+
+	pub fn names(&self) -> impl Iterator<Item = &str> {
+		self.marked.iter().map(|(n, _)| n.as_str())
+	}
+}
@@ -0,0 +1,250 @@
+// Weather: clear skies, rain, snow and storms, each lasting a while before the weather turns.
+// Rain and snow fall as particles around the camera wherever nothing's over them, and the
+// heavier they are the greyer the sky and the closer the distance closes in, storms darkest of
+// all. While it snows, snow builds up on the tops of blocks open to the sky, counted in their
+// state so the block itself is unchanged, and melts away again once it's stopped.
+
+#[cfg(feature = "graphics")]
+use glam::*;
+#[cfg(feature = "graphics")]
+use crate::{World, Voxel, furl, raycast, step};
+#[cfg(feature = "graphics")]
+use crate::block;
+#[cfg(feature = "graphics")]
+use crate::particle::Particles;
+#[cfg(feature = "graphics")]
+use crate::rng::Rng;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Kind {
+	Clear,
+	Rain,
+	Snow,
+	Storm,
+}
+
+pub const KINDS: [(Kind, &str); 4] = [(Kind::Clear, "clear"), (Kind::Rain, "rain"), (Kind::Snow, "snow"), (Kind::Storm, "storm")];
+
+impl Kind {
+	pub fn parse(name: &str) -> Option<Kind> {
+		KINDS.iter().find(|(_, n)| *n == name).map(|&(k, _)| k)
+	}
+
+	#[cfg(feature = "graphics")]
+	pub fn name(self) -> &'static str {
+		KINDS.iter().find(|&&(k, _)| k == self).map_or("?", |(_, n)| n)
+	}
+
+	// how heavy it gets, from 0 to 1
+	#[cfg(feature = "graphics")]
+	fn heaviness(self) -> f32 {
+		match self {
+			Kind::Clear => 0.0,
+			Kind::Rain | Kind::Snow => 0.6,
+			Kind::Storm => 1.0,
+		}
+	}
+
+	// the fewest and most ticks it lasts
+	#[cfg(feature = "graphics")]
+	fn lasts(self) -> (f32, f32) {
+		match self {
+			Kind::Clear => (7200.0, 21600.0),
+			Kind::Rain | Kind::Snow => (3600.0, 10800.0),
+			Kind::Storm => (1800.0, 5400.0),
+		}
+	}
+}
+
+// how much heavier or lighter it gets a tick, so the weather takes ten seconds or so to set in
+#[cfg(feature = "graphics")]
+const EASE: f32 = 0.001;
+// drops of rain and flakes of snow a tick at the heaviest
+#[cfg(feature = "graphics")]
+const DROPS: f32 = 24.0;
+#[cfg(feature = "graphics")]
+const FLAKES: f32 = 5.0;
+// how far around the camera they start, across and up
+#[cfg(feature = "graphics")]
+const SPREAD: f32 = 12.0;
+#[cfg(feature = "graphics")]
+const HEIGHT: (f32, f32) = (4.0, 10.0);
+// voxels looked up to find the sky
+#[cfg(feature = "graphics")]
+const ROOF: usize = 24;
+// the color the sky turns, how far it turns at the heaviest, and how much darker storms are
+#[cfg(feature = "graphics")]
+const OVERCAST: Vec4 = vec4(0.45, 0.47, 0.5, 1.0);
+#[cfg(feature = "graphics")]
+const GREY: f32 = 0.4;
+#[cfg(feature = "graphics")]
+const STORM_DARK: f32 = 0.5;
+// how much of the view distance is lost at the heaviest
+#[cfg(feature = "graphics")]
+const FOG: f32 = 0.4;
+// voxels around the camera looked at for snow to settle on or melt from each tick, how far
+// away, and the deepest it gets, in steps of state
+#[cfg(feature = "graphics")]
+const SNOW_SAMPLES: usize = 400;
+#[cfg(feature = "graphics")]
+const SNOW_RANGE: f32 = 32.0;
+#[cfg(feature = "graphics")]
+pub const SNOW_DEPTH: u8 = 8;
+// blocks whose state is already theirs to keep, which snow can't settle on
+#[cfg(feature = "graphics")]
+const KEEPS_STATE: [block::BlockId; 8] = [block::FIRE, block::WIRE, block::SWITCH, block::LAMP, block::DOOR, block::SIGN, block::TNT, block::CELL];
+
+#[cfg(feature = "graphics")]
+pub struct Weather {
+	pub kind: Kind,
+	// how heavy it is now, easing toward the kind's heaviness
+	heaviness: f32,
+	// whether what falls is snow, kept while it clears
+	snowing: bool,
+	// ticks until the weather turns
+	left: u32,
+	rng: Rng,
+}
+
+#[cfg(feature = "graphics")]
+impl Weather {
+	pub fn new(seed: u64) -> Weather {
+		let mut weather = Weather { kind: Kind::Clear, heaviness: 0.0, snowing: false, left: 0, rng: Rng::new(seed) };
+		weather.set(Kind::Clear);
+		weather
+	}
+
+	pub fn set(&mut self, kind: Kind) {
+		self.kind = kind;
+		let (least, most) = kind.lasts();
+		self.left = self.rng.range(least, most) as u32;
+		match kind {
+			Kind::Snow => self.snowing = true,
+			Kind::Rain | Kind::Storm => self.snowing = false,
+			Kind::Clear => {},
+		}
+	}
+
+	// what the weather turns to after `kind`
+	fn next(&mut self) -> Kind {
+		let roll = self.rng.f32();
+		match self.kind {
+			Kind::Clear if roll < 0.5 => Kind::Rain,
+			Kind::Clear => Kind::Snow,
+			Kind::Rain if roll < 0.3 => Kind::Storm,
+			Kind::Storm => Kind::Rain,
+			_ => Kind::Clear,
+		}
+	}
+
+	// A tick of weather around the camera in voxel i at `position`: rain or snow falling, and if
+	// `settles`, snow settling or melting.
+	pub fn tick(&mut self, world: &mut World, particles: &mut Particles, i: usize, position: Vec3, settles: bool) {
+		self.left = self.left.saturating_sub(1);
+		if self.left == 0 {
+			let next = self.next();
+			self.set(next);
+		}
+		let target = self.kind.heaviness();
+		self.heaviness += (target - self.heaviness).clamp(-EASE, EASE);
+		let up = world.gravity_at(i).up();
+		let (a, b) = up.any_orthonormal_pair();
+		let count = self.heaviness * if self.snowing { FLAKES } else { DROPS };
+		// the fraction of one left over falls some ticks and not others
+		let count = count as usize + (self.rng.f32() < count.fract()) as usize;
+		for _ in 0..count {
+			let d = position + self.rng.range(-SPREAD, SPREAD) * a + self.rng.range(-SPREAD, SPREAD) * b + self.rng.range(HEIGHT.0, HEIGHT.1) * up;
+			let j = around(world, i, d.floor().to_array().map(|n| n as i32));
+			let at = d - d.floor();
+			if world.transparent(j) && open(world, j, at, up) {
+				particles.fall(j, at, up, self.snowing);
+			}
+		}
+		if settles {
+			self.settle(world, i);
+		}
+	}
+
+	// snow settling on or melting from voxels around voxel i
+	fn settle(&mut self, world: &mut World, i: usize) {
+		let snowing = self.snowing && self.heaviness > 0.0;
+		for _ in 0..SNOW_SAMPLES {
+			let d = [(); 3].map(|_| self.rng.range(-SNOW_RANGE, SNOW_RANGE) as i32);
+			let j = around(world, i, d);
+			let voxel = world.voxel(j);
+			if !holds_snow(voxel) || (!snowing && voxel.state == 0) {
+				continue;
+			}
+			let gravity = world.gravity_at(j);
+			let above = step(world, j, gravity.axis, -gravity.sign);
+			let state = if snowing && world.transparent(above) {
+				if voxel.state >= SNOW_DEPTH || self.rng.f32() >= self.heaviness || !open(world, above, Vec3::splat(0.5), gravity.up()) {
+					continue;
+				}
+				voxel.state + 1
+			} else if voxel.state > 0 {
+				// melting, or covered up
+				voxel.state - 1
+			} else {
+				continue;
+			};
+			let settled = voxel.clone().with_state(state);
+			world.set(j, settled);
+		}
+	}
+
+	// how heavy the rain or snow is, from 0 to 1
+	pub fn heaviness(&self) -> f32 {
+		self.heaviness
+	}
+
+	// the color of the distance, from the settings' `ambient`, greyed and darkened by the weather
+	pub fn sky(&self, ambient: Vec4) -> Vec4 {
+		let storm = ((self.heaviness - Kind::Rain.heaviness()) / (Kind::Storm.heaviness() - Kind::Rain.heaviness())).max(0.0);
+		(ambient.lerp(OVERCAST, GREY * self.heaviness) * (1.0 - STORM_DARK * storm)).with_w(1.0)
+	}
+
+	// how far can be seen in the weather, from the settings' `view_distance`
+	pub fn view_distance(&self, view_distance: usize) -> usize {
+		((view_distance as f32 * (1.0 - FOG * self.heaviness)) as usize).max(1)
+	}
+}
+
+// the voxel `d` from voxel i, worked out from their coordinates, so it only holds for the torus
+// worlds are linked as
+#[cfg(feature = "graphics")]
+fn around(world: &World, i: usize, d: [i32; 3]) -> usize {
+	let size = world.size();
+	let [_, ny, nz] = size;
+	let c = [i / (ny*nz), i / nz % ny, i % nz];
+	let [x, y, z] = [0, 1, 2].map(|k| (c[k] as i32 + d[k]).rem_euclid(size[k] as i32) as usize);
+	furl(x, y, z, ny, nz)
+}
+
+// whether nothing's over `position` in voxel i, up to ROOF voxels up along `up`
+#[cfg(feature = "graphics")]
+fn open(world: &World, i: usize, position: Vec3, up: Vec3) -> bool {
+	world.transparent(raycast(world, i, position, up, ROOF).0)
+}
+
+// whether snow can settle on a voxel
+#[cfg(feature = "graphics")]
+fn holds_snow(voxel: &Voxel) -> bool {
+	let def = block::def(voxel.block);
+	!voxel.transparent && !def.liquid && !def.emits && !KEEPS_STATE.contains(&voxel.block)
+}
+
+// `color`, the color of voxel i seen at `hit` on its surface, whitened by any snow on its top
+#[cfg(feature = "graphics")]
+pub fn snowed(world: &World, i: usize, hit: Vec3, color: Vec4) -> Vec4 {
+	let voxel = world.voxel(i);
+	if voxel.state == 0 || !holds_snow(voxel) {
+		return color;
+	}
+	let gravity = world.gravity_at(i);
+	let top = if gravity.sign < 0 { 1.0 } else { 0.0 };
+	if (hit[gravity.axis] - top).abs() > 1e-3 {
+		return color;
+	}
+	color.lerp(block::def(block::SNOW).color, voxel.state as f32 / SNOW_DEPTH as f32)
+}
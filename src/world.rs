@@ -0,0 +1,774 @@
+// The world: a graph of voxels, each linked to its six neighbors.
+//
+// Voxel data is stored compressed in chunks of 16³ voxels, each holding a palette of the
+// distinct voxels it contains and a packed palette index per voxel, so a chunk of one or two
+// kinds of block takes a bit or less per voxel. An entry no voxel uses any more is taken over by
+// the next new one, and the palette is cut down once those left would fit narrower indices
+// twice over, so a voxel flipping back and forth doesn't repack the chunk every time. Whether
+// each voxel is transparent or liquid is mirrored in bitsets so that ray traversal only touches
+// the palette at the voxel it hits.
+//
+// Cloning a world is cheap: the links, the bitset and each chunk are shared between the clones
+// and copied only when one of them is edited, so snapshots can be handed to other threads.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use glam::*;
+use rayon::prelude::*;
+use crate::{Voxel, step, raycast};
+use crate::gravity::{Gravity, GravityField};
+use crate::block;
+use crate::dirty::Dirty;
+
+pub const CHUNK: usize = 16;
+const CHUNK_VOLUME: usize = CHUNK*CHUNK*CHUNK;
+
+#[derive(Clone)]
+pub struct Neighbors {
+	pub up_x  : u32,
+	pub down_x: u32,
+	pub up_y  : u32,
+	pub down_y: u32,
+	pub up_z  : u32,
+	pub down_z: u32,
+}
+
+// names of the links, in the order `Neighbors::all` gives them; each is opposite the next or last
+#[cfg(feature = "graphics")]
+const LINK_NAMES: [&str; 6] = ["up x", "down x", "up y", "down y", "up z", "down z"];
+
+impl Neighbors {
+	pub fn all(&self) -> [u32; 6] {
+		[self.up_x, self.down_x, self.up_y, self.down_y, self.up_z, self.down_z]
+	}
+}
+
+// most voxels a world can have, since links hold voxel ids in 32 bits
+const MAX_VOXELS: u64 = 1 << 32;
+
+// most of each kind of problem `World::validate` lists, though it counts them all
+#[cfg(feature = "graphics")]
+const EXAMPLES: usize = 8;
+
+// What `World::validate` found. Links are given as the voxel they leave and which link it is.
+#[cfg(feature = "graphics")]
+#[derive(Default)]
+pub struct Validation {
+	// links to voxels that don't exist
+	pub out_of_bounds: usize,
+	// links whose far end has no link back at all
+	pub one_way: usize,
+	// links whose far end links back, but not along the opposite link; expected where the
+	// world is glued to itself turned over, but not on a torus
+	pub turned: usize,
+	pub examples: Vec<(usize, &'static str, &'static str)>,
+	// voxels in each piece of the graph that can't be reached from the others, biggest first
+	pub pieces: Vec<usize>,
+}
+
+#[cfg(feature = "graphics")]
+impl Validation {
+	// whether every link is in bounds and two-way and the graph is in one piece
+	pub fn ok(&self) -> bool {
+		self.out_of_bounds == 0 && self.one_way == 0 && self.pieces.len() <= 1
+	}
+
+	fn note(&mut self, i: usize, link: usize, problem: &'static str) {
+		if self.examples.len() < EXAMPLES {
+			self.examples.push((i, LINK_NAMES[link], problem));
+		}
+	}
+
+	// a line for each kind of problem found, and one for each example
+	pub fn lines(&self) -> Vec<String> {
+		let mut lines = vec![];
+		if self.ok() && self.turned == 0 {
+			lines.push("links are all two-way and the world is in one piece".to_string());
+		}
+		for (count, what) in [(self.out_of_bounds, "lead out of the world"), (self.one_way, "are one-way"), (self.turned, "come back along another link")] {
+			if count > 0 {
+				lines.push(format!("{} links {}", count, what));
+			}
+		}
+		if self.pieces.len() > 1 {
+			let sizes: Vec<String> = self.pieces.iter().take(EXAMPLES).map(|n| n.to_string()).collect();
+			lines.push(format!("the world is in {} pieces, of {} voxels{}", self.pieces.len(), sizes.join(", "), if self.pieces.len() > EXAMPLES { "..." } else { "" }));
+		}
+		for (i, link, problem) in &self.examples {
+			lines.push(format!("  voxel {} {}: {}", i, link, problem));
+		}
+		lines
+	}
+}
+
+// bits per packed index for a palette of n entries
+fn bits_for(n: usize) -> u32 {
+	match n {
+		0 | 1 => 0,
+		n => (usize::BITS - (n - 1).leading_zeros()).next_power_of_two(),
+	}
+}
+
+#[derive(Clone)]
+struct Chunk {
+	palette: Vec<Voxel>,
+	// voxels using each palette entry; one none use is taken over by the next new voxel
+	counts: Vec<u16>,
+	// bits per packed index: 0 while the chunk is uniform, then 1, 2, 4, 8 or 16
+	bits: u32,
+	indices: Vec<u64>,
+}
+
+impl Chunk {
+	fn uniform(voxel: Voxel) -> Chunk {
+		Chunk { palette: vec![voxel], counts: vec![CHUNK_VOLUME as u16], bits: 0, indices: vec![] }
+	}
+
+	fn index(&self, k: usize) -> usize {
+		if self.bits == 0 {
+			return 0;
+		}
+		let per_word = 64 / self.bits as usize;
+		let word = self.indices[k / per_word];
+		((word >> ((k % per_word) as u32 * self.bits)) & ((1 << self.bits) - 1)) as usize
+	}
+
+	fn set_index(&mut self, k: usize, p: usize) {
+		let per_word = 64 / self.bits as usize;
+		let shift = (k % per_word) as u32 * self.bits;
+		let mask = ((1u64 << self.bits) - 1) << shift;
+		let word = &mut self.indices[k / per_word];
+		*word = (*word & !mask) | ((p as u64) << shift);
+	}
+
+	fn get(&self, k: usize) -> &Voxel {
+		&self.palette[self.index(k)]
+	}
+
+	fn set(&mut self, k: usize, voxel: Voxel) {
+		let old = self.index(k);
+		if self.palette[old] == voxel {
+			return;
+		}
+		let p = match self.palette.iter().position(|v| *v == voxel) {
+			Some(p) => p,
+			None => match self.counts.iter().position(|&n| n == 0) {
+				Some(p) => {
+					self.palette[p] = voxel;
+					p
+				},
+				None => {
+					self.palette.push(voxel);
+					self.counts.push(0);
+					self.palette.len() - 1
+				},
+			},
+		};
+		if p >= 1 << self.bits {
+			self.repack((self.bits * 2).max(1));
+		}
+		if self.bits > 0 {
+			self.set_index(k, p);
+		}
+		self.counts[old] -= 1;
+		self.counts[p] += 1;
+		// with the last of a voxel gone the rest may fit narrower indices, with room to grow
+		// without widening them straight back
+		if self.counts[old] == 0 && bits_for(2 * self.counts.iter().filter(|&&n| n > 0).count()) < self.bits {
+			self.compact();
+		}
+	}
+
+	// widen the packed indices to `bits` each
+	fn repack(&mut self, bits: u32) {
+		let old: Vec<usize> = (0..CHUNK_VOLUME).map(|k| self.index(k)).collect();
+		self.bits = bits;
+		self.indices = vec![0; CHUNK_VOLUME.div_ceil(64 / bits as usize)];
+		for (k, p) in old.into_iter().enumerate() {
+			self.set_index(k, p);
+		}
+	}
+
+	// drop the palette entries no voxel uses, narrowing the indices to fit those left
+	fn compact(&mut self) {
+		let old: Vec<usize> = (0..CHUNK_VOLUME).map(|k| self.index(k)).collect();
+		// where each entry that's kept moves to
+		let mut to = vec![0; self.palette.len()];
+		let mut kept = 0;
+		for (p, to) in to.iter_mut().enumerate() {
+			if self.counts[p] > 0 {
+				self.palette.swap(kept, p);
+				self.counts.swap(kept, p);
+				*to = kept;
+				kept += 1;
+			}
+		}
+		self.palette.truncate(kept);
+		self.counts.truncate(kept);
+		self.bits = bits_for(kept);
+		if self.bits == 0 {
+			self.indices = vec![];
+			return;
+		}
+		self.indices = vec![0; CHUNK_VOLUME.div_ceil(64 / self.bits as usize)];
+		for (k, p) in old.into_iter().enumerate() {
+			self.set_index(k, to[p]);
+		}
+	}
+
+	fn from_voxels(voxels: &[Voxel]) -> Chunk {
+		let mut chunk = Chunk::uniform(voxels[0].clone());
+		for (k, v) in voxels.iter().enumerate() {
+			// indices start out pointing at the first palette entry
+			if *v != chunk.palette[0] {
+				chunk.set(k, v.clone());
+			}
+		}
+		chunk
+	}
+
+	#[cfg(feature = "graphics")]
+	fn memory(&self) -> usize {
+		std::mem::size_of::<Chunk>() + self.palette.len() * (std::mem::size_of::<Voxel>() + 2) + self.indices.len() * 8
+	}
+}
+
+#[derive(Clone)]
+pub struct World {
+	size: [usize; 3],
+	// chunks along each axis
+	grid: [usize; 3],
+	links: Arc<Vec<Neighbors>>,
+	// whether the links are those of a plain 3-torus, so a step can be worked out from a
+	// voxel's coordinates instead of looked up, which only the game's renderer does
+	#[cfg_attr(not(feature = "graphics"), allow(dead_code))]
+	lattice: bool,
+	chunks: Vec<Arc<Chunk>>,
+	opaque: Arc<Vec<u64>>,
+	liquid: Arc<Vec<u64>>,
+	gravity: Gravity,
+	field: Option<Arc<GravityField>>,
+	// voxels set one by one since the last `take_changes`
+	changes: Vec<usize>,
+	// chunks with voxels set since the last `take_dirty`, for what's kept worked out from them
+	dirty: Dirty,
+	// text written on voxels, like signs, until their block changes
+	texts: Arc<HashMap<usize, String>>,
+}
+
+// the links voxel i would have in a plain 3-torus of `size`, in the order `Neighbors::all` gives
+fn torus_links(size: [usize; 3], i: usize) -> [u32; 6] {
+	let [_, ny, nz] = size;
+	let c = [i / (ny*nz), i / nz % ny, i % nz];
+	let mut links = [0; 6];
+	for axis in 0..3 {
+		for (n, d) in [1, size[axis] - 1].into_iter().enumerate() {
+			let mut p = c;
+			p[axis] = (p[axis] + d) % size[axis];
+			links[2*axis + n] = ((p[0] * ny + p[1]) * nz + p[2]) as u32;
+		}
+	}
+	links
+}
+
+// Offset in voxels from voxel `from` to voxel `to` of a 3-torus of `size`, the shorter way around
+// each axis.
+pub fn displacement(size: [usize; 3], from: usize, to: usize) -> [i32; 3] {
+	let [_, ny, nz] = size;
+	let coordinates = |i: usize| [i / (ny*nz), i / nz % ny, i % nz];
+	let (a, b) = (coordinates(from), coordinates(to));
+	[0, 1, 2].map(|k| {
+		let n = size[k] as i32;
+		(b[k] as i32 - a[k] as i32 + n / 2).rem_euclid(n) - n / 2
+	})
+}
+
+fn bit(bits: &[u64], i: usize) -> bool {
+	bits[i / 64] & (1 << (i % 64)) != 0
+}
+
+fn set_bit(bits: &mut [u64], i: usize, on: bool) {
+	if on {
+		bits[i / 64] |= 1 << (i % 64);
+	} else {
+		bits[i / 64] &= !(1 << (i % 64));
+	}
+}
+
+// a bitset with bit i set where `f(voxels[i])` holds
+fn pack_bits(bits: &mut [u64], voxels: &[Voxel], f: impl Fn(&Voxel) -> bool + Sync) {
+	bits.par_iter_mut().zip(voxels.par_chunks(64)).for_each(|(word, voxels)| {
+		*word = 0;
+		for (b, v) in voxels.iter().enumerate() {
+			if f(v) {
+				*word |= 1 << b;
+			}
+		}
+	});
+}
+
+fn is_liquid(voxel: &Voxel) -> bool {
+	block::def(voxel.block).liquid
+}
+
+// Voxels reached from a center by steps over the graph, nearest first, while their offset from
+// it, counted along each axis as the steps are taken, stays inside a shape. Following the steps
+// rather than ids keeps shapes whole across seams and around the world. See `World::walk`.
+pub struct Walk<'a, F> {
+	world: &'a World,
+	inside: F,
+	seen: HashSet<usize>,
+	queue: VecDeque<(usize, Vec3)>,
+}
+
+impl<F: Fn(Vec3) -> bool> Iterator for Walk<'_, F> {
+	type Item = (usize, Vec3);
+
+	fn next(&mut self) -> Option<(usize, Vec3)> {
+		let (i, d) = self.queue.pop_front()?;
+		for axis in 0..3 {
+			for sign in [-1, 1] {
+				let mut e = d;
+				e[axis] += sign as f32;
+				let j = step(self.world, i, axis, sign);
+				if (self.inside)(e) && self.seen.insert(j) {
+					self.queue.push_back((j, e));
+				}
+			}
+		}
+		Some((i, d))
+	}
+}
+
+impl World {
+	// voxels in a world of `size`, or why there can't be a world that size
+	pub fn volume(size: [usize; 3]) -> Result<usize, String> {
+		let [nx, ny, nz] = size;
+		match size.iter().try_fold(1usize, |n, &s| n.checked_mul(s)) {
+			Some(0) => Err(format!("a world of {}x{}x{} voxels has no voxels at all", nx, ny, nz)),
+			Some(n) if n as u64 <= MAX_VOXELS => Ok(n),
+			_ => Err(format!("a world of {}x{}x{} voxels is too big; the most it can have is {}", nx, ny, nz, MAX_VOXELS)),
+		}
+	}
+
+	// A world of `size` voxels, all air, with voxel i linked by `links[i]`. Voxel ids are laid
+	// out like `furl`, which is what places them in chunks; the links alone decide the topology.
+	// Fails unless there's a link for each voxel and they all lead to voxels in the world, so
+	// nothing walking the graph afterwards can step out of it.
+	pub fn new(size: [usize; 3], links: Vec<Neighbors>) -> Result<World, String> {
+		let n = World::volume(size)?;
+		if links.len() != n {
+			return Err(format!("there are links for {} voxels, but the world has {}", links.len(), n));
+		}
+		let outside = links.par_iter().enumerate().find_map_any(|(i, l)| l.all().into_iter().find(|&j| j as usize >= n).map(|j| (i, j)));
+		if let Some((i, j)) = outside {
+			return Err(format!("voxel {} links to voxel {}, outside the world", i, j));
+		}
+		let lattice = links.par_iter().enumerate().all(|(i, l)| l.all() == torus_links(size, i));
+		let grid = size.map(|n| n.div_ceil(CHUNK));
+		let air = Voxel::of(block::AIR);
+		Ok(World {
+			size,
+			grid,
+			lattice,
+			chunks: (0..grid[0]*grid[1]*grid[2]).map(|_| Arc::new(Chunk::uniform(air.clone()))).collect(),
+			opaque: Arc::new(vec![0; links.len().div_ceil(64)]),
+			liquid: Arc::new(vec![0; links.len().div_ceil(64)]),
+			links: Arc::new(links),
+			gravity: Gravity::DEFAULT,
+			field: None,
+			changes: vec![],
+			dirty: Dirty::default(),
+			texts: Arc::new(HashMap::new()),
+		})
+	}
+
+	pub fn size(&self) -> [usize; 3] {
+		self.size
+	}
+
+	// chunks along each axis
+	#[cfg(feature = "graphics")]
+	pub fn grid(&self) -> [usize; 3] {
+		self.grid
+	}
+
+	// whether voxels are linked as a plain 3-torus, each to the ones next to it by coordinates
+	#[cfg(feature = "graphics")]
+	pub fn lattice(&self) -> bool {
+		self.lattice
+	}
+
+	pub fn gravity(&self) -> Gravity {
+		self.gravity
+	}
+
+	pub fn set_gravity(&mut self, gravity: Gravity) {
+		// only a turn matters to what's worked out from the voxels, not a change of strength
+		if (gravity.axis, gravity.sign) != (self.gravity.axis, self.gravity.sign) {
+			self.dirty.mark_everything();
+		}
+		self.gravity = gravity;
+	}
+
+	// gravity at voxel i: the world's own, unless a field turns it another way there
+	pub fn gravity_at(&self, i: usize) -> Gravity {
+		match &self.field {
+			Some(field) => field.at(i, self.gravity),
+			None => self.gravity,
+		}
+	}
+
+	pub fn set_gravity_field(&mut self, field: GravityField) {
+		self.field = Some(Arc::new(field));
+		self.dirty.mark_everything();
+	}
+
+	pub fn neighbors(&self, i: usize) -> &Neighbors {
+		debug_assert!(i < self.links.len(), "voxel {} isn't in a world of {}", i, self.links.len());
+		&self.links[i]
+	}
+
+	// Check the links that make the world's shape: that each leads to a voxel that exists, that
+	// the voxel it leads to links back, along the opposite link as on a torus or at least along
+	// some link, and that every voxel can be reached from every other.
+	#[cfg(feature = "graphics")]
+	pub fn validate(&self) -> Validation {
+		let n = self.links.len();
+		let mut found = Validation::default();
+		for (i, neighbors) in self.links.iter().enumerate() {
+			for (link, &j) in neighbors.all().iter().enumerate() {
+				let j = j as usize;
+				if j >= n {
+					found.out_of_bounds += 1;
+					found.note(i, link, "leads out of the world");
+					continue;
+				}
+				let back = self.links[j].all();
+				if back[link ^ 1] as usize == i {
+					continue;
+				}
+				if back.contains(&(i as u32)) {
+					found.turned += 1;
+					found.note(i, link, "comes back along another link");
+				} else {
+					found.one_way += 1;
+					found.note(i, link, "is one-way");
+				}
+			}
+		}
+		// links are followed both ways, so one-way links don't split what they join
+		let mut into: Vec<Vec<u32>> = vec![];
+		if found.one_way > 0 {
+			into = vec![vec![]; n];
+			for (i, neighbors) in self.links.iter().enumerate() {
+				for j in neighbors.all() {
+					if (j as usize) < n {
+						into[j as usize].push(i as u32);
+					}
+				}
+			}
+		}
+		let mut seen = vec![false; n];
+		for start in 0..n {
+			if seen[start] {
+				continue;
+			}
+			seen[start] = true;
+			let mut stack = vec![start];
+			let mut size = 0;
+			while let Some(i) = stack.pop() {
+				size += 1;
+				for j in self.links[i].all().into_iter().chain(into.get(i).into_iter().flatten().copied()) {
+					let j = j as usize;
+					if j < n && !seen[j] {
+						seen[j] = true;
+						stack.push(j);
+					}
+				}
+			}
+			found.pieces.push(size);
+		}
+		found.pieces.sort_by_key(|&size| std::cmp::Reverse(size));
+		found
+	}
+
+	pub fn transparent(&self, i: usize) -> bool {
+		!bit(&self.opaque, i)
+	}
+
+	pub fn liquid(&self, i: usize) -> bool {
+		bit(&self.liquid, i)
+	}
+
+	// Offset in voxels from voxel `from` to voxel `to`, the shorter way around each axis. This
+	// reads it off the ids, so it only holds for the torus worlds are linked as.
+	pub fn displacement(&self, from: usize, to: usize) -> [i32; 3] {
+		displacement(self.size, from, to)
+	}
+
+	// chunk holding voxel i and its position within the chunk
+	pub fn locate(&self, i: usize) -> (usize, usize) {
+		let [_, ny, nz] = self.size;
+		let (x, y, z) = (i / (ny*nz), i / nz % ny, i % nz);
+		let chunk = ((x / CHUNK) * self.grid[1] + y / CHUNK) * self.grid[2] + z / CHUNK;
+		let local = ((x % CHUNK) * CHUNK + y % CHUNK) * CHUNK + z % CHUNK;
+		(chunk, local)
+	}
+
+	pub fn voxel(&self, i: usize) -> &Voxel {
+		let (chunk, local) = self.locate(i);
+		self.chunks[chunk].get(local)
+	}
+
+	pub fn set(&mut self, i: usize, voxel: Voxel) {
+		let (chunk, local) = self.locate(i);
+		if *self.chunks[chunk].get(local) == voxel {
+			return;
+		}
+		if self.transparent(i) != voxel.transparent {
+			set_bit(Arc::make_mut(&mut self.opaque).as_mut_slice(), i, !voxel.transparent);
+		}
+		if self.liquid(i) != is_liquid(&voxel) {
+			set_bit(Arc::make_mut(&mut self.liquid).as_mut_slice(), i, is_liquid(&voxel));
+		}
+		if self.voxel(i).block != voxel.block && self.texts.contains_key(&i) {
+			Arc::make_mut(&mut self.texts).remove(&i);
+		}
+		Arc::make_mut(&mut self.chunks[chunk]).set(local, voxel);
+		self.changes.push(i);
+		self.dirty.mark(chunk);
+	}
+
+	pub fn text(&self, i: usize) -> Option<&str> {
+		self.texts.get(&i).map(String::as_str)
+	}
+
+	// write text on voxel i; empty text wipes it
+	pub fn set_text(&mut self, i: usize, text: &str) {
+		if self.text(i).unwrap_or("") == text {
+			return;
+		}
+		let texts = Arc::make_mut(&mut self.texts);
+		if text.is_empty() {
+			texts.remove(&i);
+		} else {
+			texts.insert(i, text.to_string());
+		}
+	}
+
+	// voxels that have changed since the last call, for things that react to edits
+	pub fn take_changes(&mut self) -> Vec<usize> {
+		std::mem::take(&mut self.changes)
+	}
+
+	// how many voxels have been set since the last `take_changes`, to forget those set after
+	pub fn changes_so_far(&self) -> usize {
+		self.changes.len()
+	}
+
+	// forget the voxels set since there were `so_far`, as if they hadn't changed
+	pub fn forget_changes(&mut self, so_far: usize) {
+		self.changes.truncate(so_far);
+	}
+
+	// the chunks with voxels set in them since the last call, for things kept worked out from the
+	// voxels
+	#[cfg(feature = "graphics")]
+	pub fn take_dirty(&mut self) -> Dirty {
+		std::mem::take(&mut self.dirty)
+	}
+
+	// Whether chunk c comes within `reach` voxels of voxel i along each axis, going either way
+	// around the world.
+	#[cfg(feature = "graphics")]
+	pub fn chunk_near(&self, c: usize, i: usize, reach: [usize; 3]) -> bool {
+		let grid = self.grid;
+		let [_, ny, nz] = self.size;
+		let corner = [c / (grid[1]*grid[2]), c / grid[2] % grid[1], c % grid[2]].map(|n| n * CHUNK);
+		let p = [i / (ny*nz), i / nz % ny, i % nz];
+		(0..3).all(|k| {
+			let n = self.size[k];
+			let extent = CHUNK.min(n - corner[k]);
+			// how far into the chunk it is, or how far ahead of it or behind it
+			let into = (p[k] + n - corner[k]) % n;
+			let ahead = (corner[k] + n - p[k]) % n;
+			let behind = (p[k] + n - (corner[k] + extent - 1)) % n;
+			into < extent || ahead <= reach[k] || behind <= reach[k]
+		})
+	}
+
+	// replace every voxel at once from a buffer indexed like the voxel ids
+	pub fn set_voxels(&mut self, voxels: &[Voxel]) {
+		self.dirty.mark_everything();
+		let [nx, ny, nz] = self.size;
+		let grid = self.grid;
+		self.chunks = (0..self.chunks.len()).into_par_iter().map(|c| {
+			let (cx, cy, cz) = (c / (grid[1]*grid[2]), c / grid[2] % grid[1], c % grid[2]);
+			let mut local = vec![Voxel::of(block::AIR); CHUNK_VOLUME];
+			for x in 0..CHUNK.min(nx - cx*CHUNK) {
+				for y in 0..CHUNK.min(ny - cy*CHUNK) {
+					for z in 0..CHUNK.min(nz - cz*CHUNK) {
+						let i = ((cx*CHUNK + x) * ny + cy*CHUNK + y) * nz + cz*CHUNK + z;
+						local[(x*CHUNK + y)*CHUNK + z] = voxels[i].clone();
+					}
+				}
+			}
+			Arc::new(Chunk::from_voxels(&local))
+		}).collect();
+		pack_bits(Arc::make_mut(&mut self.opaque).as_mut_slice(), voxels, |v| !v.transparent);
+		pack_bits(Arc::make_mut(&mut self.liquid).as_mut_slice(), voxels, is_liquid);
+	}
+
+	// The straight line from `from_pos` in voxel `from` to `to_pos` in voxel `to`, the shorter
+	// way around each axis. Like `displacement` it only holds for the torus worlds are linked as.
+	pub fn direction(&self, from: usize, from_pos: Vec3, to: usize, to_pos: Vec3) -> Vec3 {
+		let [dx, dy, dz] = self.displacement(from, to);
+		vec3(dx as f32, dy as f32, dz as f32) + to_pos - from_pos
+	}
+
+	// Whether nothing solid stands on the straight line between two places, found by casting a
+	// ray along `direction`. A solid voxel at the far end doesn't count, so a block is visible
+	// when its face can be seen.
+	pub fn visible(&self, from: usize, from_pos: Vec3, to: usize, to_pos: Vec3) -> bool {
+		let line = self.direction(from, from_pos, to, to_pos);
+		let length = line.length();
+		if length == 0.0 {
+			return true;
+		}
+		// one step per face crossed, and a spare for landing on an edge
+		let steps = line.abs().element_sum().ceil() as usize + 1;
+		let (hit, _, distance) = raycast(self, from, from_pos, line / length, steps);
+		hit == to || distance >= length
+	}
+
+	// Each voxel reached from `center` over the graph while its offset stays `inside`, with that
+	// offset, nearest first. The walk goes a voxel at a time, so stopping early saves the rest.
+	pub fn walk<F: Fn(Vec3) -> bool>(&self, center: usize, inside: F) -> Walk<'_, F> {
+		Walk { world: self, inside, seen: HashSet::from([center]), queue: VecDeque::from([(center, Vec3::ZERO)]) }
+	}
+
+	// each voxel within `radius` of voxel `center`, counted over the graph, with its offset
+	pub fn iter_region(&self, center: usize, radius: f32) -> Walk<'_, impl Fn(Vec3) -> bool> {
+		self.walk(center, move |d| d.length() <= radius)
+	}
+
+	// How many voxels in the whole world `predicate` holds for.
+	#[cfg(feature = "graphics")]
+	pub fn count_matching(&self, predicate: impl Fn(&Voxel) -> bool + Sync) -> usize {
+		self.tally(1, |v| predicate(v).then_some(0))[0]
+	}
+
+	// how many voxels of each block there are in the whole world, indexed by block id
+	#[cfg(feature = "graphics")]
+	pub fn count_blocks(&self) -> Vec<usize> {
+		self.tally(block::all().len(), |v| Some(v.block as usize))
+	}
+
+	// Count the voxels in the whole world into `bins` by which bin `bin` puts each in, if any.
+	// It's asked once per kind of voxel in each chunk rather than once per voxel, so counting is
+	// quick however big the world is.
+	#[cfg(feature = "graphics")]
+	fn tally(&self, bins: usize, bin: impl Fn(&Voxel) -> Option<usize> + Sync) -> Vec<usize> {
+		let size = self.size;
+		let grid = self.grid;
+		self.chunks.par_iter().enumerate().map(|(c, chunk)| {
+			let mut counts = vec![0; bins];
+			let corner = [c / (grid[1]*grid[2]), c / grid[2] % grid[1], c % grid[2]].map(|n| n * CHUNK);
+			// chunks at the far edges can hang over the end of the world
+			let [ex, ey, ez] = [0, 1, 2].map(|k| CHUNK.min(size[k] - corner[k]));
+			let binned: Vec<Option<usize>> = chunk.palette.iter().map(&bin).collect();
+			if binned.iter().all(|&b| b == binned[0]) {
+				if let Some(b) = binned[0] {
+					counts[b] += ex*ey*ez;
+				}
+				return counts;
+			}
+			for x in 0..ex {
+				for y in 0..ey {
+					for z in 0..ez {
+						if let Some(b) = binned[chunk.index((x*CHUNK + y)*CHUNK + z)] {
+							counts[b] += 1;
+						}
+					}
+				}
+			}
+			counts
+		}).reduce(|| vec![0; bins], |a, b| a.iter().zip(&b).map(|(a, b)| a + b).collect())
+	}
+
+	// bytes used by voxel data and links
+	#[cfg(feature = "graphics")]
+	pub fn memory(&self) -> usize {
+		self.links.len() * std::mem::size_of::<Neighbors>()
+			+ (self.opaque.len() + self.liquid.len()) * 8
+			+ self.chunks.iter().map(|c| c.memory()).sum::<usize>()
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn palette_shrinks_as_voxels_go() {
+		let mut chunk = Chunk::uniform(Voxel::of(block::AIR));
+		for k in 0..CHUNK_VOLUME {
+			chunk.set(k, Voxel::new(block::STONE, vec4(k as f32, 0.0, 0.0, 1.0)));
+		}
+		assert_eq!(chunk.bits, 16);
+		for k in (0..CHUNK_VOLUME).step_by(2) {
+			chunk.set(k, Voxel::of(block::DIRT));
+		}
+		assert_eq!(chunk.bits, 16);
+		for k in (1..CHUNK_VOLUME).step_by(2) {
+			chunk.set(k, Voxel::of(block::AIR));
+		}
+		assert_eq!((chunk.palette.len(), chunk.bits), (2, 1));
+		assert!(*chunk.get(6) == Voxel::of(block::DIRT) && *chunk.get(7) == Voxel::of(block::AIR));
+	}
+
+	#[test]
+	fn a_voxel_flipping_back_and_forth_keeps_the_palette() {
+		let mut chunk = Chunk::uniform(Voxel::of(block::AIR));
+		for _ in 0..3 {
+			chunk.set(7, Voxel::of(block::STONE));
+			assert_eq!((chunk.palette.len(), chunk.bits), (2, 1));
+			chunk.set(7, Voxel::of(block::AIR));
+			assert_eq!((chunk.palette.len(), chunk.bits), (2, 1));
+		}
+	}
+
+	#[test]
+	fn a_new_voxel_takes_over_an_entry_none_use() {
+		let mut chunk = Chunk::uniform(Voxel::of(block::AIR));
+		chunk.set(0, Voxel::of(block::STONE));
+		chunk.set(1, Voxel::of(block::DIRT));
+		chunk.set(0, Voxel::of(block::AIR));
+		assert_eq!((chunk.palette.len(), chunk.bits), (3, 2));
+		chunk.set(2, Voxel::of(block::SAND));
+		assert_eq!((chunk.palette.len(), chunk.bits), (3, 2));
+		assert!(chunk.palette[1] == Voxel::of(block::SAND));
+		let blocks: Vec<_> = (0..4).map(|k| chunk.get(k).block).collect();
+		assert_eq!(blocks, [block::AIR, block::DIRT, block::SAND, block::AIR]);
+	}
+
+	#[test]
+	fn indices_widen_and_narrow_across_a_width() {
+		let mut chunk = Chunk::uniform(Voxel::of(block::AIR));
+		let kinds = [block::STONE, block::DIRT, block::SAND, block::WOOD];
+		for (k, &b) in kinds.iter().enumerate() {
+			chunk.set(k, Voxel::of(b));
+		}
+		// five kinds don't fit two bits
+		assert_eq!((chunk.palette.len(), chunk.bits), (5, 4));
+		chunk.set(0, Voxel::of(block::AIR));
+		chunk.set(1, Voxel::of(block::AIR));
+		// three left would fit two bits, but not twice over
+		assert_eq!(chunk.bits, 4);
+		chunk.set(2, Voxel::of(block::AIR));
+		assert_eq!((chunk.palette.len(), chunk.bits), (2, 1));
+		let blocks: Vec<_> = (0..5).map(|k| chunk.get(k).block).collect();
+		assert_eq!(blocks, [block::AIR, block::AIR, block::AIR, block::WOOD, block::AIR]);
+	}
+}